@@ -1,6 +1,13 @@
 use std::sync::{Arc, RwLock};
 
-use libclockrobustus::{alarm::Alarm, check_database_directory};
+use libclockrobustus::{
+    alarm::{Alarm, AlarmSort, DuplicatePolicy},
+    check_database_directory,
+    control::{self, Request, Response},
+    history::{RingEvent, RingStats},
+    import::ImportSource,
+};
+use tauri::Window;
 
 static mut CONN: Option<Arc<RwLock<sqlite::Connection>>> = None;
 
@@ -15,7 +22,7 @@ fn db_check_init() {
     }
 }
 
-fn db_accessor<F, T>(mut callback: F) -> Option<T>
+pub(crate) fn db_accessor<F, T>(mut callback: F) -> Option<T>
 where
     F: FnMut(&sqlite::Connection) -> T,
 {
@@ -34,21 +41,184 @@ where
     }
 }
 
+/// Alarms as told by the daemon (see [Request::ListAlarms]), none if it is unreachable.
 #[tauri::command]
-pub fn get_alarms() -> Vec<Alarm> {
-    db_accessor(|conn| Alarm::all(conn).expect("Unable to retrieve alarms")).unwrap_or(vec![])
+pub fn get_alarms(
+    sort: Option<AlarmSort>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Vec<Alarm> {
+    control::list_alarms(sort.unwrap_or_default(), limit, offset).unwrap_or_default()
 }
 
+/// Number of alarms as told by the daemon (see [Request::CountAlarms]), 0 if it is unreachable.
 #[tauri::command]
-pub fn upsert_alarm(alarm: Alarm) {
-    db_accessor(move |conn| {
-        alarm.save(conn).expect("Unable to save alarm");
-    });
+pub fn count_alarms() -> usize {
+    control::count(&Request::CountAlarms).unwrap_or(0)
 }
 
+/// Has the daemon save the alarm (see [Request::UpsertAlarm]). Given a policy, an alarm ringing
+/// at the same time on the same days as a stored one is rejected (with an error message) or
+/// merged into it.
+#[tauri::command]
+pub fn upsert_alarm(alarm: Alarm, duplicates: Option<DuplicatePolicy>) -> Result<(), String> {
+    let request = Request::UpsertAlarm {
+        alarm: Box::new(alarm),
+        duplicates,
+    };
+
+    match control::send(&request) {
+        Ok(Response::Done) => Ok(()),
+        Ok(Response::Error { message }) => Err(message),
+        Ok(_) => Err("Unexpected reply from the daemon".to_string()),
+        Err(error) => Err(error.0.to_string()),
+    }
+}
+
+/// Has the daemon save a copy of the alarm as a new one, optionally ringing `shift_minutes`
+/// later (earlier if negative).
+#[tauri::command]
+pub fn duplicate_alarm(alarm: Alarm, shift_minutes: Option<i64>) -> Result<(), String> {
+    let copy = alarm.duplicate().shifted(shift_minutes.unwrap_or(0));
+
+    upsert_alarm(copy, None)
+}
+
+/// Enables or disables the alarms of the given ids in one go. Resolves to the number of alarms
+/// changed, then a single CONFIG_CHANGED event is sent.
+#[tauri::command]
+pub fn set_alarms_enabled(window: Window, ids: Vec<i64>, enabled: bool) -> usize {
+    let changed = control::count(&Request::SetAlarmsEnabled { ids, enabled }).unwrap_or(0);
+
+    config_changed(&window);
+    changed
+}
+
+/// Enables or disables every alarm of a group in one go. Resolves to the number of alarms
+/// changed, then a single CONFIG_CHANGED event is sent.
+#[tauri::command]
+pub fn set_group_enabled(window: Window, group: String, enabled: bool) -> usize {
+    let changed = control::count(&Request::SetGroupEnabled { group, enabled }).unwrap_or(0);
+
+    config_changed(&window);
+    changed
+}
+
+/// Unix timestamp (seconds) the alarm rings at next (skipped occurrences passed over, jitter
+/// applied), null if it never rings.
+#[tauri::command]
+pub fn next_alarm_ring(alarm: Alarm) -> Option<i64> {
+    control::next_ring(alarm).ok().flatten()
+}
+
+/// Skips the next occurrence of the alarm (eg: a public holiday), resolving to its date
+/// (YYYY-MM-DD, in the alarm timezone), null if the alarm never rings.
+#[tauri::command]
+pub fn skip_next_alarm(window: Window, alarm: Alarm) -> Option<String> {
+    let skipped = alarm
+        .id
+        .and_then(|id| control::skip_next(id).ok().flatten())
+        .map(|date| date.to_string());
+
+    config_changed(&window);
+    skipped
+}
+
+/// Tags of the alarm, alphabetically.
+#[tauri::command]
+pub fn get_alarm_tags(alarm: Alarm) -> Vec<String> {
+    alarm
+        .id
+        .and_then(|id| control::alarm_tags(id).ok())
+        .unwrap_or_default()
+}
+
+/// Replaces the tags of a saved alarm.
+#[tauri::command]
+pub fn set_alarm_tags(window: Window, alarm: Alarm, tags: Vec<String>) -> Result<(), String> {
+    let id = alarm.id.ok_or("Impossible to tag an unsaved alarm")?;
+    control::set_alarm_tags(id, tags).map_err(|error| error.0.to_string())?;
+
+    config_changed(&window);
+    Ok(())
+}
+
+/// Alarms matching every word of the query in their label, group or tags.
+#[tauri::command]
+pub fn search_alarms(query: String) -> Vec<Alarm> {
+    control::search_alarms(&query).unwrap_or_default()
+}
+
+fn config_changed(window: &Window) {
+    window
+        .emit("CONFIG_CHANGED", ())
+        .expect("Unable to send CONFIG_CHANGED event to window");
+}
+
+/// Has the daemon remove the alarm (see [Request::DeleteAlarm]), which can be brought back with
+/// [restore_alarm] until purged.
 #[tauri::command]
 pub fn delete_alarm(alarm: Alarm) {
-    db_accessor(move |conn| {
-        alarm.remove(conn).expect("Unable to delete alarm");
-    });
+    if let Some(id) = alarm.id {
+        control::delete_alarm(id).expect("Unable to delete alarm");
+    }
+}
+
+/// Has the daemon undo the removal of an alarm (see [Request::RestoreAlarm]).
+#[tauri::command]
+pub fn restore_alarm(window: Window, alarm: Alarm) -> Result<(), String> {
+    let id = alarm.id.ok_or("Impossible to restore an unsaved alarm")?;
+    control::restore_alarm(id).map_err(|error| error.0.to_string())?;
+
+    config_changed(&window);
+    Ok(())
+}
+
+/// Latest alarm history events, newest first, optionally for a single alarm (50 by default).
+#[tauri::command]
+pub fn get_alarm_history(alarm_id: Option<i64>, limit: Option<usize>) -> Vec<RingEvent> {
+    control::history(alarm_id, limit.unwrap_or(50)).unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_ring_stats() -> Option<RingStats> {
+    control::ring_stats().ok()
+}
+
+#[tauri::command]
+pub fn export_history_csv() -> String {
+    control::export(&Request::ExportHistory).unwrap_or_default()
+}
+
+/// Imports the alarms of another app from the content of its export file. Resolves to the
+/// number of imported alarms, null if the export cannot be read or the daemon refused.
+#[tauri::command]
+pub fn import_alarms(source: ImportSource, content: String) -> Option<usize> {
+    let alarms = source.parse(&content).ok()?;
+
+    control::count(&Request::ImportAlarms { alarms }).ok()
+}
+
+/// Backup of every alarm, as JSON (see [Alarm::export_all]).
+#[tauri::command]
+pub fn export_alarms() -> String {
+    control::export(&Request::ExportAlarms).unwrap_or_default()
+}
+
+/// Restores a backup made by `export_alarms`, alarms still stored being overwritten. Resolves to
+/// the number of restored alarms, null if the backup cannot be read or the daemon refused.
+#[tauri::command]
+pub fn restore_alarms(window: Window, content: String) -> Option<usize> {
+    let restored = control::count(&Request::RestoreBackup { content }).ok();
+
+    if restored.is_some() {
+        config_changed(&window);
+    }
+    restored
+}
+
+/// Alarms as an iCalendar document (see [libclockrobustus::ical::export]), for calendar apps.
+#[tauri::command]
+pub fn export_ical() -> String {
+    control::export(&Request::ExportIcal).unwrap_or_default()
 }
@@ -0,0 +1,23 @@
+use libclockrobustus::bedtime::Bedtime;
+
+use crate::alarms::db_accessor;
+
+#[tauri::command]
+pub fn get_bedtimes() -> Vec<Bedtime> {
+    db_accessor(|conn| Bedtime::all(conn).expect("Unable to retrieve bedtimes")).unwrap_or(vec![])
+}
+
+/// Saves the bedtime, rejecting an invalid time with an error message.
+#[tauri::command]
+pub fn upsert_bedtime(bedtime: Bedtime) -> Result<(), String> {
+    db_accessor(move |conn| bedtime.save(conn))
+        .unwrap_or(Ok(()))
+        .map_err(|error| error.0.to_string())
+}
+
+#[tauri::command]
+pub fn delete_bedtime(bedtime: Bedtime) {
+    db_accessor(move |conn| {
+        bedtime.remove(conn).expect("Unable to delete bedtime");
+    });
+}
@@ -0,0 +1,23 @@
+use libclockrobustus::countdown::Countdown;
+
+use crate::alarms::db_accessor;
+
+#[tauri::command]
+pub fn get_countdowns() -> Vec<Countdown> {
+    db_accessor(|conn| Countdown::all(conn).expect("Unable to retrieve countdowns"))
+        .unwrap_or(vec![])
+}
+
+#[tauri::command]
+pub fn upsert_countdown(countdown: Countdown) {
+    db_accessor(move |conn| {
+        countdown.save(conn).expect("Unable to save countdown");
+    });
+}
+
+#[tauri::command]
+pub fn delete_countdown(countdown: Countdown) {
+    db_accessor(move |conn| {
+        countdown.remove(conn).expect("Unable to delete countdown");
+    });
+}
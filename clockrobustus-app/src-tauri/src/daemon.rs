@@ -0,0 +1,43 @@
+use libclockrobustus::{
+    control::{self, Health},
+    queue::{self, RoundTrip},
+};
+
+/// Echo requests sent by [measure_latency].
+const LATENCY_SAMPLES: usize = 10;
+
+/// Pings the daemon. Resolves to null if the daemon is dead, to its component-level health
+/// flags otherwise.
+#[tauri::command]
+pub fn daemon_health() -> Option<Health> {
+    control::health()
+}
+
+/// Asks the daemon to ring the given alarm in `delay` seconds, so the user can preview it.
+/// Resolves to false if the daemon is unreachable or refused.
+#[tauri::command]
+pub fn preview_alarm(id: i64, delay: u64) -> bool {
+    control::preview(id, delay).is_ok()
+}
+
+/// Asks the daemon to ring the given alarm again in `minutes` (its own snooze delay if not
+/// given). Resolves to the Unix timestamp (seconds) it rings again at, null if the daemon is
+/// unreachable or refused.
+#[tauri::command]
+pub fn snooze_alarm(id: i64, minutes: Option<u8>) -> Option<i64> {
+    control::snooze(id, minutes).ok()
+}
+
+/// Tells the daemon the ringing alarm has been dismissed. Resolves to false if the daemon is
+/// unreachable or refused (eg: the alarm is not ringing anymore).
+#[tauri::command]
+pub fn dismiss_alarm(id: i64) -> bool {
+    control::dismiss(id).is_ok()
+}
+
+/// Measures the round-trip time to the daemon (for the debug panel). Resolves to null if the
+/// daemon is unreachable.
+#[tauri::command]
+pub fn measure_latency() -> Option<RoundTrip> {
+    queue::measure_latency(LATENCY_SAMPLES).ok()
+}
@@ -1,32 +1,123 @@
-use libclockrobustus::{message::Message, queue::listen};
-use std::{
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
-    thread,
-};
-use tauri::Window;
-/// Handler to retrieve events from zmq and to dispatch them to the frontend
-#[tauri::command]
-pub fn clock_events(window: Window) {
-    // Spawning a thread to ensure the invoke method does not block !
-    thread::spawn(move || {
-        let running = Arc::new(AtomicBool::new(true));
-        let rc = running.clone();
-        // Stop handler
-        let stop_handler = window.once("STOP", move |_| rc.store(false, Ordering::SeqCst));
+use libclockrobustus::bus::EventBus;
+use std::sync::Arc;
+use tauri::{State, Window};
+
+use crate::timekeeping;
 
-        listen(running, |message| match message {
-            Message::Alarm(alarm) => window
+/// Handler to retrieve events from the shared [EventBus] and to dispatch them to the frontend
+#[tauri::command]
+pub fn clock_events(window: Window, bus: State<'_, Arc<EventBus>>) {
+    let alarm_window = window.clone();
+    let clock_window = window.clone();
+    let countdown_window = window.clone();
+    let pomodoro_window = window.clone();
+    let interval_window = window.clone();
+    let next_alarm_window = window.clone();
+    let bedtime_window = window.clone();
+    let reminder_window = window.clone();
+    let world_clock_window = window.clone();
+    let sun_window = window.clone();
+    let sync_status_window = window.clone();
+    let clock_jump_window = window.clone();
+    let speak_window = window.clone();
+    let heartbeat_window = window.clone();
+    let missed_window = window.clone();
+    let subscriptions = [
+        bus.on_alarm(move |alarm| {
+            alarm_window
                 .emit("ALARM", alarm)
-                .expect("Unable to send ALARM event to window"),
-            Message::Clock(clock_message) => window
+                .expect("Unable to send ALARM event to window")
+        }),
+        bus.on_clock(move |clock_message| {
+            clock_window
                 .emit("CLOCK", clock_message)
-                .expect("Unable to send CLOCK event to window"),
-        })
-        .expect("Unable to listen on client side");
+                .expect("Unable to send CLOCK event to window");
+            timekeeping::emit_running(&clock_window);
+        }),
+        bus.on_countdown(move |countdown| {
+            countdown_window
+                .emit("COUNTDOWN", countdown)
+                .expect("Unable to send COUNTDOWN event to window")
+        }),
+        bus.on_pomodoro(move |pomodoro| {
+            pomodoro_window
+                .emit("POMODORO", pomodoro)
+                .expect("Unable to send POMODORO event to window")
+        }),
+        bus.on_interval(move |interval| {
+            interval_window
+                .emit("INTERVAL", interval)
+                .expect("Unable to send INTERVAL event to window")
+        }),
+        bus.on_next_alarm(move |next_alarm| {
+            next_alarm_window
+                .emit("NEXT_ALARM", next_alarm)
+                .expect("Unable to send NEXT_ALARM event to window")
+        }),
+        bus.on_bedtime(move |bedtime| {
+            bedtime_window
+                .emit("BEDTIME", bedtime)
+                .expect("Unable to send BEDTIME event to window")
+        }),
+        bus.on_reminder(move |reminder| {
+            reminder_window
+                .emit("REMINDER", reminder)
+                .expect("Unable to send REMINDER event to window")
+        }),
+        bus.on_world_clock(move |world_clock| {
+            world_clock_window
+                .emit("WORLD_CLOCK", world_clock)
+                .expect("Unable to send WORLD_CLOCK event to window")
+        }),
+        bus.on_sun(move |sun| {
+            sun_window
+                .emit("SUN", sun)
+                .expect("Unable to send SUN event to window")
+        }),
+        bus.on_sync_status(move |sync_status| {
+            sync_status_window
+                .emit("SYNC_STATUS", sync_status)
+                .expect("Unable to send SYNC_STATUS event to window")
+        }),
+        bus.on_clock_jump(move |clock_jump| {
+            clock_jump_window
+                .emit("CLOCK_JUMP", clock_jump)
+                .expect("Unable to send CLOCK_JUMP event to window")
+        }),
+        bus.on_speak(move |speak| {
+            speak_window
+                .emit("SPEAK", speak)
+                .expect("Unable to send SPEAK event to window")
+        }),
+        bus.on_heartbeat(move |heartbeat| {
+            heartbeat_window
+                .emit("HEARTBEAT", heartbeat)
+                .expect("Unable to send HEARTBEAT event to window")
+        }),
+        bus.on_missed(move |missed| {
+            missed_window
+                .emit("MISSED", missed)
+                .expect("Unable to send MISSED event to window")
+        }),
+    ];
+    let bus = bus.inner().clone();
 
-        window.unlisten(stop_handler);
+    // Stop handler
+    window.once("STOP", move |_| {
+        for subscription in subscriptions {
+            bus.unsubscribe(subscription);
+        }
     });
 }
+
+/// Stops receiving CLOCK events (eg: while the window is hidden), alarms still come through.
+#[tauri::command]
+pub fn pause_clock(bus: State<'_, Arc<EventBus>>) {
+    bus.set_clock_paused(true);
+}
+
+/// Receives CLOCK events again after [pause_clock].
+#[tauri::command]
+pub fn resume_clock(bus: State<'_, Arc<EventBus>>) {
+    bus.set_clock_paused(false);
+}
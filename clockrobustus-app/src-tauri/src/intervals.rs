@@ -0,0 +1,24 @@
+use libclockrobustus::interval::IntervalTimer;
+
+use crate::alarms::db_accessor;
+
+#[tauri::command]
+pub fn get_interval_timers() -> Vec<IntervalTimer> {
+    db_accessor(|conn| IntervalTimer::all(conn).expect("Unable to retrieve interval timers"))
+        .unwrap_or(vec![])
+}
+
+/// Saves the interval timer, rejecting a zero interval with an error message.
+#[tauri::command]
+pub fn upsert_interval_timer(timer: IntervalTimer) -> Result<(), String> {
+    db_accessor(move |conn| timer.save(conn))
+        .unwrap_or(Ok(()))
+        .map_err(|error| error.0.to_string())
+}
+
+#[tauri::command]
+pub fn delete_interval_timer(timer: IntervalTimer) {
+    db_accessor(move |conn| {
+        timer.remove(conn).expect("Unable to delete interval timer");
+    });
+}
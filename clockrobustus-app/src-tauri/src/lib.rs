@@ -1,2 +1,12 @@
 pub mod alarms;
+pub mod bedtimes;
+pub mod countdowns;
+pub mod daemon;
 pub mod events;
+pub mod intervals;
+pub mod presets;
+pub mod reminders;
+pub mod settings;
+pub mod timekeeping;
+pub mod timezones;
+pub mod world_clocks;
@@ -1,14 +1,81 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
-use clockrobustus::{alarms, events};
+use clockrobustus::{
+    alarms, bedtimes, countdowns, daemon, events, intervals, presets, reminders, settings,
+    timekeeping, timezones, world_clocks,
+};
+use libclockrobustus::bus::EventBus;
 
 fn main() {
+    // A single connection to the daemon, shared by every part of the app
+    let bus = EventBus::new();
+    bus.start();
+
     tauri::Builder::default()
+        .manage(bus)
         .invoke_handler(tauri::generate_handler![
             events::clock_events,
+            events::pause_clock,
+            events::resume_clock,
             alarms::get_alarms,
+            alarms::count_alarms,
             alarms::upsert_alarm,
+            alarms::duplicate_alarm,
+            alarms::set_alarms_enabled,
+            alarms::set_group_enabled,
+            alarms::skip_next_alarm,
+            alarms::get_alarm_tags,
+            alarms::set_alarm_tags,
+            alarms::search_alarms,
+            alarms::next_alarm_ring,
             alarms::delete_alarm,
+            alarms::restore_alarm,
+            alarms::get_alarm_history,
+            alarms::get_ring_stats,
+            alarms::export_history_csv,
+            alarms::import_alarms,
+            alarms::export_alarms,
+            alarms::restore_alarms,
+            alarms::export_ical,
+            countdowns::get_countdowns,
+            countdowns::upsert_countdown,
+            countdowns::delete_countdown,
+            intervals::get_interval_timers,
+            intervals::upsert_interval_timer,
+            intervals::delete_interval_timer,
+            presets::get_timer_presets,
+            presets::upsert_timer_preset,
+            presets::delete_timer_preset,
+            bedtimes::get_bedtimes,
+            bedtimes::upsert_bedtime,
+            bedtimes::delete_bedtime,
+            reminders::get_reminders,
+            reminders::upsert_reminder,
+            reminders::delete_reminder,
+            world_clocks::get_world_clocks,
+            world_clocks::upsert_world_clock,
+            world_clocks::delete_world_clock,
+            settings::get_settings,
+            settings::save_settings,
+            daemon::daemon_health,
+            daemon::preview_alarm,
+            daemon::snooze_alarm,
+            daemon::dismiss_alarm,
+            daemon::measure_latency,
+            timekeeping::timer_start,
+            timekeeping::timer_pause,
+            timekeeping::timer_reset,
+            timekeeping::timer_status,
+            timekeeping::stopwatch_start,
+            timekeeping::stopwatch_pause,
+            timekeeping::stopwatch_reset,
+            timekeeping::stopwatch_lap,
+            timekeeping::stopwatch_status,
+            timekeeping::pomodoro_start,
+            timekeeping::pomodoro_stop,
+            timekeeping::pomodoro_skip,
+            timekeeping::pomodoro_status,
+            timezones::convert_time,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
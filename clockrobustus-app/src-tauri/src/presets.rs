@@ -0,0 +1,24 @@
+use libclockrobustus::preset::TimerPreset;
+
+use crate::alarms::db_accessor;
+
+#[tauri::command]
+pub fn get_timer_presets() -> Vec<TimerPreset> {
+    db_accessor(|conn| TimerPreset::all(conn).expect("Unable to retrieve timer presets"))
+        .unwrap_or(vec![])
+}
+
+/// Saves the timer preset, rejecting a blank name or a zero duration with an error message.
+#[tauri::command]
+pub fn upsert_timer_preset(preset: TimerPreset) -> Result<(), String> {
+    db_accessor(move |conn| preset.save(conn))
+        .unwrap_or(Ok(()))
+        .map_err(|error| error.0.to_string())
+}
+
+#[tauri::command]
+pub fn delete_timer_preset(preset: TimerPreset) {
+    db_accessor(move |conn| {
+        preset.remove(conn).expect("Unable to delete timer preset");
+    });
+}
@@ -0,0 +1,23 @@
+use libclockrobustus::reminder::Reminder;
+
+use crate::alarms::db_accessor;
+
+#[tauri::command]
+pub fn get_reminders() -> Vec<Reminder> {
+    db_accessor(|conn| Reminder::all(conn).expect("Unable to retrieve reminders")).unwrap_or(vec![])
+}
+
+/// Saves the reminder, rejecting a blank or too long text with an error message.
+#[tauri::command]
+pub fn upsert_reminder(reminder: Reminder) -> Result<(), String> {
+    db_accessor(move |conn| reminder.save(conn))
+        .unwrap_or(Ok(()))
+        .map_err(|error| error.0.to_string())
+}
+
+#[tauri::command]
+pub fn delete_reminder(reminder: Reminder) {
+    db_accessor(move |conn| {
+        reminder.remove(conn).expect("Unable to delete reminder");
+    });
+}
@@ -0,0 +1,16 @@
+use libclockrobustus::settings::Settings;
+
+use crate::alarms::db_accessor;
+
+#[tauri::command]
+pub fn get_settings() -> Settings {
+    db_accessor(|conn| Settings::load(conn).expect("Unable to retrieve settings"))
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn save_settings(settings: Settings) {
+    db_accessor(move |conn| {
+        settings.save(conn).expect("Unable to save settings");
+    });
+}
@@ -0,0 +1,123 @@
+use libclockrobustus::{
+    control::{self, Request},
+    pomodoro::{PomodoroMessage, PomodoroSettings},
+    timer::{StopwatchState, TimerState},
+};
+use tauri::Window;
+
+/// Sends a timer request to the daemon and forwards the resulting state as a TIMER event.
+fn timer(window: &Window, request: Request) -> Option<TimerState> {
+    let state = control::timer(&request).ok()?;
+
+    window
+        .emit("TIMER", state)
+        .expect("Unable to send TIMER event to window");
+
+    Some(state)
+}
+
+/// Sends a stopwatch request to the daemon and forwards the resulting state as a STOPWATCH
+/// event.
+fn stopwatch(window: &Window, request: Request) -> Option<StopwatchState> {
+    let state = control::stopwatch(&request).ok()?;
+
+    window
+        .emit("STOPWATCH", state.clone())
+        .expect("Unable to send STOPWATCH event to window");
+
+    Some(state)
+}
+
+/// Sends a pomodoro request to the daemon. The phase change comes back as a POMODORO event.
+fn pomodoro(request: Request) -> Option<PomodoroMessage> {
+    control::pomodoro(&request).ok()
+}
+
+/// Pushes the timer and stopwatch states while they run, so the frontend never keeps time
+/// itself. Called on each clock tick.
+pub fn emit_running(window: &Window) {
+    if let Ok(state) = control::timer(&Request::TimerStatus) {
+        if state.running {
+            window
+                .emit("TIMER", state)
+                .expect("Unable to send TIMER event to window");
+        }
+    }
+
+    if let Ok(state) = control::stopwatch(&Request::StopwatchStatus) {
+        if state.running {
+            window
+                .emit("STOPWATCH", state)
+                .expect("Unable to send STOPWATCH event to window");
+        }
+    }
+}
+
+/// Starts a countdown of `duration_ms`, or resumes the paused one. Resolves to null if the
+/// daemon is unreachable.
+#[tauri::command]
+pub fn timer_start(window: Window, duration_ms: Option<u64>) -> Option<TimerState> {
+    timer(&window, Request::TimerStart { duration_ms })
+}
+
+#[tauri::command]
+pub fn timer_pause(window: Window) -> Option<TimerState> {
+    timer(&window, Request::TimerPause)
+}
+
+#[tauri::command]
+pub fn timer_reset(window: Window) -> Option<TimerState> {
+    timer(&window, Request::TimerReset)
+}
+
+#[tauri::command]
+pub fn timer_status(window: Window) -> Option<TimerState> {
+    timer(&window, Request::TimerStatus)
+}
+
+#[tauri::command]
+pub fn stopwatch_start(window: Window) -> Option<StopwatchState> {
+    stopwatch(&window, Request::StopwatchStart)
+}
+
+#[tauri::command]
+pub fn stopwatch_pause(window: Window) -> Option<StopwatchState> {
+    stopwatch(&window, Request::StopwatchPause)
+}
+
+#[tauri::command]
+pub fn stopwatch_reset(window: Window) -> Option<StopwatchState> {
+    stopwatch(&window, Request::StopwatchReset)
+}
+
+#[tauri::command]
+pub fn stopwatch_lap(window: Window) -> Option<StopwatchState> {
+    stopwatch(&window, Request::StopwatchLap)
+}
+
+#[tauri::command]
+pub fn stopwatch_status(window: Window) -> Option<StopwatchState> {
+    stopwatch(&window, Request::StopwatchStatus)
+}
+
+/// Starts a new pomodoro cycle, with new settings if given. Resolves to null if the daemon is
+/// unreachable or refuses the settings.
+#[tauri::command]
+pub fn pomodoro_start(settings: Option<PomodoroSettings>) -> Option<PomodoroMessage> {
+    pomodoro(Request::PomodoroStart { settings })
+}
+
+#[tauri::command]
+pub fn pomodoro_stop() -> Option<PomodoroMessage> {
+    pomodoro(Request::PomodoroStop)
+}
+
+#[tauri::command]
+pub fn pomodoro_skip() -> Option<PomodoroMessage> {
+    pomodoro(Request::PomodoroSkip)
+}
+
+#[tauri::command]
+pub fn pomodoro_status() -> Option<PomodoroMessage> {
+    pomodoro(Request::PomodoroStatus)
+}
@@ -0,0 +1,10 @@
+use chrono::NaiveDateTime;
+use libclockrobustus::clock;
+
+/// Converts a wall clock time between two zones (IANA names, or `local` for this machine),
+/// eg: what time is 9am in Tokyo here. [None] for an unknown zone or a time skipped by a DST
+/// change.
+#[tauri::command]
+pub fn convert_time(time: NaiveDateTime, from_tz: String, to_tz: String) -> Option<NaiveDateTime> {
+    clock::convert(time, &from_tz, &to_tz).ok()
+}
@@ -0,0 +1,26 @@
+use libclockrobustus::world_clock::WorldClock;
+
+use crate::alarms::db_accessor;
+
+#[tauri::command]
+pub fn get_world_clocks() -> Vec<WorldClock> {
+    db_accessor(|conn| WorldClock::all(conn).expect("Unable to retrieve world clocks"))
+        .unwrap_or(vec![])
+}
+
+/// Saves the world clock, rejecting a blank or too long label with an error message.
+#[tauri::command]
+pub fn upsert_world_clock(world_clock: WorldClock) -> Result<(), String> {
+    db_accessor(move |conn| world_clock.save(conn))
+        .unwrap_or(Ok(()))
+        .map_err(|error| error.0.to_string())
+}
+
+#[tauri::command]
+pub fn delete_world_clock(world_clock: WorldClock) {
+    db_accessor(move |conn| {
+        world_clock
+            .remove(conn)
+            .expect("Unable to delete world clock");
+    });
+}
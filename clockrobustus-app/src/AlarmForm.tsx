@@ -1,5 +1,5 @@
-import { Delete, Save } from "@mui/icons-material";
-import { Box, Divider, IconButton } from "@mui/material";
+import { ContentCopy, Delete, EventBusy, PlayArrow, Save } from "@mui/icons-material";
+import { Box, Divider, IconButton, Switch, TextField, Typography } from "@mui/material";
 import { invoke } from "@tauri-apps/api";
 import { appWindow } from "@tauri-apps/api/window";
 import { useEffect, useState } from "react";
@@ -14,9 +14,78 @@ export default function AlarmForm(props: AlarmFormProps) {
     setAlarm(props.alarm);
   });
 
+  // Unix timestamp (seconds) of the next ring, null if the alarm never rings
+  const [nextRing, setNextRing] = useState<number | null>(null);
+
+  useEffect(() => {
+    if (props.alarm.id) {
+      invoke<number | null>('next_alarm_ring', { alarm: props.alarm }).then(setNextRing);
+    }
+  }, [props.alarm]);
+
+  // Tags of a saved alarm, comma separated in the form
+  const [tags, setTags] = useState<string[]>([]);
+
+  useEffect(() => {
+    if (props.alarm.id) {
+      invoke<string[]>('get_alarm_tags', { alarm: props.alarm }).then(setTags);
+    }
+  }, [props.alarm]);
+
+  let ringsIn;
+
+  if (alarm.id && nextRing !== null) {
+    const minutes = Math.max(0, Math.round((nextRing * 1000 - Date.now()) / 60000));
+    const days = Math.floor(minutes / 1440);
+
+    ringsIn = <Typography variant="caption">
+      rings in {days > 0 ? `${days}d ` : ''}{Math.floor(minutes % 1440 / 60)}h {minutes % 60}m
+    </Typography>;
+  }
+
   let deleteButton;
+  let previewButton;
+  let duplicateButton;
+  let skipButton;
 
   if (alarm.id) {
+    previewButton = <IconButton
+      aria-label="preview alarm"
+      onClick={async () => {
+        await invoke('preview_alarm', {
+          id: alarm.id,
+          delay: 3,
+        });
+      }}
+    >
+      <PlayArrow />
+    </IconButton>;
+
+    duplicateButton = <IconButton
+      aria-label="duplicate alarm"
+      onClick={async () => {
+        await invoke('duplicate_alarm', {
+          alarm,
+          shiftMinutes: 0,
+        });
+        appWindow.emit('REFRESH_ALARMS', {});
+      }}
+    >
+      <ContentCopy />
+    </IconButton>;
+
+    // Silences the next occurrence only (eg: a public holiday)
+    skipButton = <IconButton
+      aria-label="skip next occurrence"
+      onClick={async () => {
+        await invoke('skip_next_alarm', {
+          alarm,
+        });
+      }}
+    >
+      <EventBusy />
+    </IconButton>;
+
     deleteButton = <IconButton 
       aria-label="delete alarm"
       onClick={async () => {
@@ -24,6 +93,8 @@ export default function AlarmForm(props: AlarmFormProps) {
           alarm,
         });
         appWindow.emit('REFRESH_ALARMS', {});
+        // Offers to undo the deletion
+        appWindow.emit('ALARM_DELETED', alarm);
       }}
     >
       <Delete />
@@ -32,10 +103,16 @@ export default function AlarmForm(props: AlarmFormProps) {
     deleteButton = <IconButton
       aria-label="save alarm"
       onClick={async () => {
-        await invoke('upsert_alarm', {
-          alarm,
-        });
-        appWindow.emit('REFRESH_ALARMS', {});
+        try {
+          // Another alarm ringing at the same time on the same days is most likely a mistake
+          await invoke('upsert_alarm', {
+            alarm,
+            duplicates: 'reject',
+          });
+          appWindow.emit('REFRESH_ALARMS', {});
+        } catch (error) {
+          alert(error);
+        }
       }}
     >
       <Save />
@@ -90,6 +167,70 @@ export default function AlarmForm(props: AlarmFormProps) {
             }
           }}
         />
+        <NumericSelect 
+          id={`alarm-id-jitter-${alarm.id || 0}`}
+          size={31}
+          label="Jitter (± min)"
+          value={alarm.jitterMinutes || 0}
+          change={async (event) => {
+            alarm.jitterMinutes = event;
+            if (alarm.id) {
+              await invoke('upsert_alarm', {
+                alarm
+              });
+              appWindow.emit('REFRESH_ALARMS', {});
+            }
+          }}
+        />
+        <NumericSelect 
+          id={`alarm-id-snooze-${alarm.id || 0}`}
+          size={31}
+          label="Snooze (min)"
+          value={alarm.snoozeMinutes ?? 9}
+          change={async (event) => {
+            alarm.snoozeMinutes = event;
+            if (alarm.id) {
+              await invoke('upsert_alarm', {
+                alarm
+              });
+              appWindow.emit('REFRESH_ALARMS', {});
+            }
+          }}
+        />
+        <NumericSelect
+          id={`alarm-id-ring-${alarm.id || 0}`}
+          size={61}
+          label="Ring (min)"
+          value={alarm.ringMinutes ?? 10}
+          change={async (event) => {
+            alarm.ringMinutes = event;
+            if (alarm.id) {
+              await invoke('upsert_alarm', {
+                alarm
+              });
+              appWindow.emit('REFRESH_ALARMS', {});
+            }
+          }}
+        />
+        <TextField
+          id={`alarm-id-max-snoozes-${alarm.id || 0}`}
+          type="number"
+          label="Max snoozes"
+          placeholder="unlimited"
+          inputProps={{ min: 0, max: 255 }}
+          defaultValue={alarm.maxSnoozes ?? ''}
+          onBlur={async (event) => {
+            // Cleared: unlimited snoozes
+            alarm.maxSnoozes = event.target.value === '' ? undefined : Number(event.target.value);
+
+            if (alarm.id) {
+              await invoke('upsert_alarm', {
+                alarm,
+              });
+              appWindow.emit('REFRESH_ALARMS', {});
+            }
+          }}
+        />
       </Box>
       <Box sx={{ minWidth: 200, display: 'inline-flex'}}>
         <DayChooser 
@@ -105,6 +246,127 @@ export default function AlarmForm(props: AlarmFormProps) {
             }
           }}
         />
+        <TextField
+          id={`alarm-id-date-${alarm.id || 0}`}
+          type="date"
+          label="Once on"
+          InputLabelProps={{ shrink: true }}
+          value={alarm.date || ''}
+          onChange={async (event) => {
+            // Cleared: back to a weekly alarm
+            alarm.date = event.target.value || undefined;
+
+            if (alarm.id) {
+              await invoke('upsert_alarm', {
+                alarm,
+              });
+              appWindow.emit('REFRESH_ALARMS', {});
+            }
+          }}
+        />
+        <TextField
+          id={`alarm-id-valid-from-${alarm.id || 0}`}
+          type="date"
+          label="From"
+          InputLabelProps={{ shrink: true }}
+          value={alarm.validFrom || ''}
+          onChange={async (event) => {
+            alarm.validFrom = event.target.value || undefined;
+
+            if (alarm.id) {
+              await invoke('upsert_alarm', {
+                alarm,
+              });
+              appWindow.emit('REFRESH_ALARMS', {});
+            }
+          }}
+        />
+        <TextField
+          id={`alarm-id-valid-until-${alarm.id || 0}`}
+          type="date"
+          label="Until"
+          InputLabelProps={{ shrink: true }}
+          value={alarm.validUntil || ''}
+          onChange={async (event) => {
+            alarm.validUntil = event.target.value || undefined;
+
+            if (alarm.id) {
+              await invoke('upsert_alarm', {
+                alarm,
+              });
+              appWindow.emit('REFRESH_ALARMS', {});
+            }
+          }}
+        />
+        <TextField
+          id={`alarm-id-timezone-${alarm.id || 0}`}
+          label="Timezone"
+          placeholder="local"
+          defaultValue={alarm.timezone === 'local' ? '' : alarm.timezone}
+          onBlur={async (event) => {
+            alarm.timezone = event.target.value.trim() || 'local';
+
+            if (alarm.id) {
+              await invoke('upsert_alarm', {
+                alarm,
+              });
+              appWindow.emit('REFRESH_ALARMS', {});
+            }
+          }}
+        />
+        <TextField
+          id={`alarm-id-recurrence-${alarm.id || 0}`}
+          label="Recurrence"
+          placeholder="FREQ=MONTHLY;BYDAY=1MO"
+          defaultValue={alarm.recurrence || ''}
+          onBlur={async (event) => {
+            // Cleared: back to the active days
+            alarm.recurrence = event.target.value.trim() || undefined;
+
+            if (alarm.id) {
+              await invoke('upsert_alarm', {
+                alarm,
+              });
+              appWindow.emit('REFRESH_ALARMS', {});
+            }
+          }}
+        />
+        <TextField
+          id={`alarm-id-tags-${alarm.id || 0}`}
+          key={tags.join(',')}
+          label="Tags"
+          placeholder="work, morning"
+          disabled={!alarm.id}
+          defaultValue={tags.join(', ')}
+          onBlur={async (event) => {
+            try {
+              await invoke('set_alarm_tags', {
+                alarm,
+                tags: event.target.value.split(','),
+              });
+            } catch (error) {
+              alert(error);
+            }
+          }}
+        />
+        <Switch
+          inputProps={{ 'aria-label': 'alarm enabled' }}
+          checked={alarm.enabled ?? true}
+          onChange={async (event) => {
+            alarm.enabled = event.target.checked;
+
+            if (alarm.id) {
+              await invoke('upsert_alarm', {
+                alarm,
+              });
+              appWindow.emit('REFRESH_ALARMS', {});
+            }
+          }}
+        />
+        {ringsIn}
+        {previewButton}
+        {duplicateButton}
+        {skipButton}
         {deleteButton}
       </Box>
       <br />
@@ -1,8 +1,8 @@
 import { AccessAlarm, Done } from "@mui/icons-material";
-import { Box, Dialog, DialogActions, DialogContent, DialogTitle, Divider, IconButton } from "@mui/material";
+import { Box, Button, Dialog, DialogActions, DialogContent, DialogTitle, Divider, IconButton, Snackbar, TextField } from "@mui/material";
 import { invoke } from "@tauri-apps/api/tauri";
 import { listen } from "@tauri-apps/api/event";
-import { useState } from "react";
+import { useRef, useState } from "react";
 import { Alarm, dummyAlarm } from "./interfaces";
 import "./Alarms.css";
 import AlarmForm from "./AlarmForm";
@@ -11,17 +11,54 @@ export default function Alarms() {
   const emptyAlarms: Array<Alarm> = [];
   const [open, setOpen] = useState(false);
   const [alarms, setAlarms] = useState(emptyAlarms);
+  const [query, setQuery] = useState('');
+  // Read by the event listeners, registered once
+  const queryRef = useRef('');
+  // Last deleted alarm, while its deletion can be undone
+  const [deleted, setDeleted] = useState<Alarm | null>(null);
   let listener;
 
+  const fetchAlarms = async (): Promise<[Alarm]> => {
+    return queryRef.current.trim()
+      ? await invoke('search_alarms', { query: queryRef.current })
+      : await invoke('get_alarms');
+  };
+
+  const search = async (text: string) => {
+    setQuery(text);
+    queryRef.current = text;
+    try {
+      setAlarms(await fetchAlarms());
+    } catch (error) {
+      alert(error);
+    }
+  };
+
+  const undoDelete = async () => {
+    const alarm = deleted;
+
+    setDeleted(null);
+    try {
+      await invoke('restore_alarm', { alarm });
+    } catch (error) {
+      alert(error);
+    }
+  };
+
   const openDialog = async () => {
     try {
-      const retrievedAlarms: [Alarm] = await invoke('get_alarms');
+      const retrievedAlarms: [Alarm] = await fetchAlarms();
       setAlarms(retrievedAlarms);
-      listener = listen('REFRESH_ALARMS', async (_e) => {
-        const refreshedAlarms: [Alarm] = await invoke('get_alarms');
+      const refresh = async (_e) => {
+        const refreshedAlarms: [Alarm] = await fetchAlarms();
         
         setAlarms(refreshedAlarms);
-      });
+      };
+      listener = [
+        listen('REFRESH_ALARMS', refresh),
+        listen('CONFIG_CHANGED', refresh),
+        listen<Alarm>('ALARM_DELETED', (e) => setDeleted(e.payload)),
+      ];
       setOpen(true);
     } catch (error) {
       alert(error);
@@ -39,6 +76,14 @@ export default function Alarms() {
           <br />
           <AlarmForm alarm={dummyAlarm} />
           <br />
+          <TextField
+            label="Search (label, group, tags)"
+            size="small"
+            fullWidth
+            value={query}
+            onChange={(e) => search(e.target.value)}
+          />
+          <br />
           <br />
           {
             alarms.map((alarm) => {
@@ -54,6 +99,17 @@ export default function Alarms() {
             <Done />
           </IconButton>
         </DialogActions>
+        <Snackbar
+          open={deleted !== null}
+          autoHideDuration={8000}
+          onClose={() => setDeleted(null)}
+          message="Alarm deleted"
+          action={
+            <Button color="secondary" size="small" onClick={undoDelete}>
+              Undo
+            </Button>
+          }
+        />
       </Dialog>
       <IconButton aria-label="manage alarms" color="secondary" onClick={openDialog}>
         <AccessAlarm />
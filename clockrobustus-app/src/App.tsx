@@ -1,9 +1,30 @@
 import React, { useState } from "react";
+import { Snackbar } from "@mui/material";
 import { invoke } from "@tauri-apps/api/tauri";
 import { appWindow } from "@tauri-apps/api/window";
 import "./App.css";
-import { ClockMessage, dummyAlarm, dummyClockMessage } from "./interfaces";
+import {
+  Alarm,
+  ClockJumpMessage,
+  ClockMessage,
+  Countdown,
+  CountdownMessage,
+  dummyAlarm,
+  dummyClockMessage,
+  HeartbeatMessage,
+  Liveness,
+  MissedMessages,
+  MoonPhase,
+  ReminderMessage,
+  Settings,
+  SpeakLocale,
+  SpeakMessage,
+  SunMessage,
+  SyncStatusMessage,
+  WorldClockMessage,
+} from "./interfaces";
 import Alarms from "./Alarms";
+import History from "./History";
 import NumericSelect from "./NumericSelect";
 import AlarmForm from "./AlarmForm";
 
@@ -14,25 +35,192 @@ function formatDigits(d: number): string {
   });
 }
 
+const WEEKDAYS = ['Mon', 'Tue', 'Wed', 'Thu', 'Fri', 'Sat', 'Sun'];
+
+const MOON_PHASES: Record<MoonPhase, string> = {
+  newMoon: '🌑',
+  waxingCrescent: '🌒',
+  firstQuarter: '🌓',
+  waxingGibbous: '🌔',
+  fullMoon: '🌕',
+  waningGibbous: '🌖',
+  lastQuarter: '🌗',
+  waningCrescent: '🌘',
+};
+
+function formatRemaining(countdown: CountdownMessage): string {
+  if (countdown.days > 0) {
+    return `${countdown.days} day${countdown.days > 1 ? 's' : ''}`;
+  }
+  return `${countdown.hours}h ${formatDigits(countdown.minutes)}m`;
+}
+
+// How long the stale data indicator stays after messages were dropped
+const STALE_INDICATOR_MS = 10000;
+
+// Speech synthesis voices of the speaking clock locales
+const SPEECH_LANGS: Record<SpeakLocale, string> = {
+  en: 'en-US',
+  fr: 'fr-FR',
+};
+
+// Same thresholds as the daemon (see libclockrobustus::heartbeat)
+const HEARTBEAT_INTERVAL_S = 5;
+const MISSED_HEARTBEATS_DOWN = 3;
+
+function liveness(lastHeartbeat: number | null, now: number): Liveness {
+  if (lastHeartbeat === null) {
+    return 'down';
+  }
+  const elapsed = (now - lastHeartbeat) / 1000;
+  if (elapsed <= HEARTBEAT_INTERVAL_S * 2) {
+    return 'up';
+  }
+  return elapsed < HEARTBEAT_INTERVAL_S * MISSED_HEARTBEATS_DOWN ? 'late' : 'down';
+}
+
+const LIVENESS_TITLES: Record<Liveness, string> = {
+  up: 'Connected to the daemon',
+  late: 'Connection to the daemon unreliable',
+  down: 'Daemon down',
+};
+
+function formatTimestamp(timestamp: number): string {
+  const date = new Date(timestamp * 1000);
+  return `${formatDigits(date.getHours())}:${formatDigits(date.getMinutes())}`;
+}
+
 function App() {
   const [clock, setClock] = useState(dummyClockMessage);
   const [invoked, setInvoked] = useState(false);
   const [faceStyle, setFaceStyle] = useState({
     backgroundColor: 'inherit',
   });
+  const [ringing, setRinging] = useState<Alarm | null>(null);
+  const [snoozedUntil, setSnoozedUntil] = useState<Date | null>(null);
+  const [countdowns, setCountdowns] = useState<Record<number, CountdownMessage>>({});
+  const [countdownLabels, setCountdownLabels] = useState<Record<number, string>>({});
+  const [reminder, setReminder] = useState<ReminderMessage | null>(null);
+  const [settings, setSettings] = useState<Settings>({ hourFormat: 'h24' });
+  const [worldClocks, setWorldClocks] = useState<Record<number, WorldClockMessage>>({});
+  const [sun, setSun] = useState<SunMessage | null>(null);
+  const [syncStatus, setSyncStatus] = useState<SyncStatusMessage | null>(null);
+  const [stale, setStale] = useState(false);
+  const [jump, setJump] = useState<ClockJumpMessage | null>(null);
+  const [heartbeat, setHeartbeat] = useState<HeartbeatMessage | null>(null);
+  const [lastHeartbeat, setLastHeartbeat] = useState<number | null>(null);
+  const [now, setNow] = useState(Date.now());
+
+  // Nothing else re-renders once the daemon is gone
+  React.useEffect(() => {
+    const timer = setInterval(() => setNow(Date.now()), 1000);
+    return () => clearInterval(timer);
+  }, []);
 
   React.useEffect(() => {
     (async function() {
       await appWindow.listen(
         'CLOCK',
         (evt) => {
-          setClock(evt.payload as ClockMessage);
+          const clockMessage = evt.payload as ClockMessage;
+          setClock(clockMessage);
+          // Styles may key on the theme hint shared by every client
+          document.body.dataset.theme = clockMessage.theme;
+        }
+      );
+
+      await appWindow.listen(
+        'COUNTDOWN',
+        async (evt) => {
+          const countdown = evt.payload as CountdownMessage;
+          setCountdowns((previous) => ({ ...previous, [countdown.id]: countdown }));
+          // Labels are not carried by the messages, new countdowns are looked up
+          if (!(countdown.id in countdownLabels)) {
+            const saved = await invoke('get_countdowns') as Array<Countdown>;
+            setCountdownLabels(Object.fromEntries(saved.map((c) => [c.id, c.label])));
+          }
+        }
+      );
+
+      // One small dial per world clock, updated every tick
+      await appWindow.listen(
+        'WORLD_CLOCK',
+        (evt) => {
+          const worldClock = evt.payload as WorldClockMessage;
+          setWorldClocks((previous) => ({ ...previous, [worldClock.id]: worldClock }));
+        }
+      );
+
+      // Published once a day, only when the daemon knows its location
+      await appWindow.listen(
+        'SUN',
+        (evt) => setSun(evt.payload as SunMessage)
+      );
+
+      // Only published when the daemon is given an NTP server
+      await appWindow.listen(
+        'SYNC_STATUS',
+        (evt) => setSyncStatus(evt.payload as SyncStatusMessage)
+      );
+
+      // The system clock changed, told for a while
+      await appWindow.listen(
+        'CLOCK_JUMP',
+        (evt) => {
+          setJump(evt.payload as ClockJumpMessage);
+          setTimeout(() => setJump(null), STALE_INDICATOR_MS);
+        }
+      );
+
+      // Only published when the daemon is given a speaking clock schedule
+      await appWindow.listen(
+        'SPEAK',
+        (evt) => {
+          const speak = evt.payload as SpeakMessage;
+          const utterance = new SpeechSynthesisUtterance(speak.text);
+          utterance.lang = SPEECH_LANGS[speak.locale];
+          window.speechSynthesis.speak(utterance);
+        }
+      );
+
+      // Tells a daemon down (no more heartbeats) from a network hiccup (a few missed)
+      await appWindow.listen(
+        'HEARTBEAT',
+        (evt) => {
+          setHeartbeat(evt.payload as HeartbeatMessage);
+          setLastHeartbeat(Date.now());
+        }
+      );
+
+      // Dropped messages, what is displayed may be behind for a while
+      await appWindow.listen(
+        'MISSED',
+        (evt) => {
+          const missed = evt.payload as MissedMessages;
+          console.warn(`${missed.count} ${missed.topic} messages missed`);
+          setStale(true);
+          setTimeout(() => setStale(false), STALE_INDICATOR_MS);
         }
       );
 
+      // Reminders are notified, nothing rings
+      await appWindow.listen(
+        'REMINDER',
+        (evt) => setReminder(evt.payload as ReminderMessage)
+      );
+
       await appWindow.listen(
         'ALARM',
-        (_evt) => {
+        (evt) => {
+          const alarm = evt.payload as Alarm;
+          // Snooze announcements only tell when the alarm rings again
+          if (alarm.snoozedUntil) {
+            setRinging(null);
+            setSnoozedUntil(new Date(alarm.snoozedUntil * 1000));
+            return;
+          }
+          setRinging(alarm);
+          setSnoozedUntil(null);
           // When receiving an alarm event, blink the faces' background color and
           // stop after 30s
           const interval = setInterval(() => {
@@ -50,18 +238,25 @@ function App() {
               backgroundColor: 'inherit',
             });
             clearInterval(interval);
+            setRinging(null);
           }, 30000);
         }
       );
       
       if (!invoked) {
+        setSettings(await invoke('get_settings') as Settings);
         await invoke('clock_events', {
           window: appWindow,
         });
+        // No need to receive clock updates nobody can see
+        document.addEventListener('visibilitychange', async () => {
+          await invoke(document.hidden ? 'pause_clock' : 'resume_clock');
+        });
         setInvoked(true);
       }
     })();
   });
+  const connection = liveness(lastHeartbeat, now);
   return (
     <div className="container">
       <div className="face" style={faceStyle} >
@@ -78,12 +273,131 @@ function App() {
           style={{ transform: `rotate(${clock.secondsAngle}rad)`}}>
         </div>
         <Alarms />
-        <p className="digital">
-          {formatDigits(clock.hours)}
+        <History />
+        {/* Clicking the time switches between 24-hour and 12-hour display */}
+        <p
+          className="digital"
+          onClick={async () => {
+            const next: Settings = {
+              ...settings,
+              hourFormat: settings.hourFormat === 'h24' ? 'h12' : 'h24',
+            };
+            setSettings(next);
+            await invoke('save_settings', { settings: next });
+          }}
+        >
+          {formatDigits(settings.hourFormat === 'h12' ? clock.hours12 : clock.hours)}
           :{formatDigits(clock.minutes)}
           :{formatDigits(clock.seconds)}
+          {settings.hourFormat === 'h12' && (clock.pm ? ' PM' : ' AM')}
+          {stale && <span className="stale" title="Messages from the daemon were lost"> ⚠</span>}
+          <span
+            className={`connection ${connection}`}
+            title={heartbeat
+              ? `${LIVENESS_TITLES[connection]} (version ${heartbeat.version}, `
+                + `${heartbeat.alarmCount} alarms)`
+              : LIVENESS_TITLES[connection]}
+          > ●</span>
+        </p>
+        {/* The daemon's date, so it never disagrees with the hands */}
+        <p className="date">
+          {WEEKDAYS[clock.weekday]} {clock.year}-{formatDigits(clock.month)}
+          -{formatDigits(clock.day)} · Week {clock.isoWeek} · Day {clock.dayOfYear}
+          {clock.dst && ' · DST'}
         </p>
+        {sun && (
+          <p className="sun">
+            {sun.cycle === 'normal' && (
+              `Sunrise ${formatTimestamp(sun.sunrise)} · Sunset ${formatTimestamp(sun.sunset)}`
+            )}
+            {sun.cycle === 'midnightSun' && 'Midnight sun'}
+            {sun.cycle === 'polarNight' && 'Polar night'}
+            {` · ${MOON_PHASES[sun.moonPhase]} ${sun.moonIllumination}%`}
+          </p>
+        )}
+        {jump && (
+          <p className="jump">
+            Clock {jump.jumpMs > 0 ? 'set forward' : 'set back'} by
+            {` ${Math.round(Math.abs(jump.jumpMs) / 1000)} s`}
+          </p>
+        )}
+        {syncStatus && (
+          <p className={syncStatus.reachable ? 'sync' : 'sync unreachable'}>
+            {syncStatus.reachable
+              ? `NTP offset ${syncStatus.offsetMs} ms · drift ${syncStatus.driftPpm} ppm`
+              : 'NTP server unreachable'}
+          </p>
+        )}
+        {ringing?.id && (
+          <button
+            className="snooze"
+            onClick={async () => {
+              await invoke('snooze_alarm', { id: ringing.id });
+            }}
+          >
+            Snooze
+          </button>
+        )}
+        {ringing?.id && (
+          <button
+            className="dismiss"
+            onClick={async () => {
+              await invoke('dismiss_alarm', { id: ringing.id });
+              setRinging(null);
+            }}
+          >
+            Dismiss
+          </button>
+        )}
+        {ringing?.missedAt && (
+          <p className="missed">
+            Missed at {formatDigits(new Date(ringing.missedAt * 1000).getHours())}
+            :{formatDigits(new Date(ringing.missedAt * 1000).getMinutes())}
+          </p>
+        )}
+        {snoozedUntil && (
+          <p className="snoozed">
+            Snoozed until {formatDigits(snoozedUntil.getHours())}
+            :{formatDigits(snoozedUntil.getMinutes())}
+          </p>
+        )}
+        {Object.values(countdowns)
+          .filter((countdown) => !countdown.passed && countdown.id in countdownLabels)
+          .map((countdown) => (
+            <p className="countdown" key={countdown.id}>
+              {formatRemaining(countdown)} until {countdownLabels[countdown.id]}
+            </p>
+          ))}
+      </div>
+      <div className="worldClocks">
+        {Object.values(worldClocks).map((worldClock) => (
+          <div className="worldClock" key={worldClock.id} title={worldClock.zone}>
+            <div className="worldFace">
+              <div
+                className="worldHand hour"
+                style={{ transform: `rotate(${worldClock.clock.hoursAngle}rad)`}}>
+              </div>
+              <div
+                className="worldHand minute"
+                style={{ transform: `rotate(${worldClock.clock.minutesAngle}rad)`}}>
+              </div>
+            </div>
+            <p className="worldLabel">
+              {worldClock.label} {formatDigits(settings.hourFormat === 'h12'
+                ? worldClock.clock.hours12
+                : worldClock.clock.hours)}
+              :{formatDigits(worldClock.clock.minutes)}
+              {settings.hourFormat === 'h12' && (worldClock.clock.pm ? ' PM' : ' AM')}
+            </p>
+          </div>
+        ))}
       </div>
+      <Snackbar
+        open={reminder !== null}
+        autoHideDuration={60000}
+        onClose={() => setReminder(null)}
+        message={reminder?.text}
+      />
     </div>
   );
 }
@@ -2,11 +2,18 @@ import { Checkbox, FormControlLabel, FormGroup } from "@mui/material";
 import { useEffect, useState } from "react";
 import { DayChooserProps } from "./interfaces";
 
+// Shorthands the daemon may send instead of day names
+const dayGroups: Record<string, Array<string>> = {
+  Weekdays: ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday"],
+  Weekend: ["Saturday", "Sunday"],
+  Everyday: ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"],
+};
+
 export default function DayChooser(props: DayChooserProps) {
   const emptyArray: Array<string> = [];
   const [daysChecked, setDaysChecked] = useState(emptyArray);
   useEffect(() => {
-    setDaysChecked(props.days);
+    setDaysChecked(props.days.flatMap((day) => dayGroups[day] || [day]));
   });
   const dayLabels = [
     ["Mon", "Monday"],
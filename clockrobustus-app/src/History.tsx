@@ -0,0 +1,59 @@
+import { Done, History as HistoryIcon } from "@mui/icons-material";
+import { Dialog, DialogActions, DialogContent, DialogTitle, IconButton, List, ListItem, ListItemText } from "@mui/material";
+import { invoke } from "@tauri-apps/api/tauri";
+import { useState } from "react";
+import { Alarm, RingEvent } from "./interfaces";
+
+export default function History() {
+  const [open, setOpen] = useState(false);
+  const [events, setEvents] = useState<Array<RingEvent>>([]);
+  const [labels, setLabels] = useState<Record<number, string>>({});
+
+  const openDialog = async () => {
+    try {
+      const retrievedEvents: Array<RingEvent> = await invoke('get_alarm_history');
+      const alarms: Array<Alarm> = await invoke('get_alarms');
+
+      setEvents(retrievedEvents);
+      // Removed alarms keep their history, shown by id
+      setLabels(Object.fromEntries(
+        alarms.map((alarm) => [alarm.id, alarm.label || `Alarm #${alarm.id}`])
+      ));
+      setOpen(true);
+    } catch (error) {
+      alert(error);
+    }
+  };
+
+  return (
+    <div className="history-container">
+      <Dialog open={open} scroll="paper">
+        <DialogTitle>Alarm History</DialogTitle>
+        <DialogContent>
+          <List dense>
+            {
+              events.map((event) => {
+                return (
+                  <ListItem key={event.id}>
+                    <ListItemText
+                      primary={`${labels[event.alarmId] || `Alarm #${event.alarmId}`} ${event.outcome}`}
+                      secondary={new Date(event.timestamp * 1000).toLocaleString()}
+                    />
+                  </ListItem>
+                );
+              })
+            }
+          </List>
+        </DialogContent>
+        <DialogActions>
+          <IconButton aria-label="done" color="secondary" onClick={() => setOpen(false)}>
+            <Done />
+          </IconButton>
+        </DialogActions>
+      </Dialog>
+      <IconButton aria-label="alarm history" color="secondary" onClick={openDialog}>
+        <HistoryIcon />
+      </IconButton>
+    </div>
+  );
+}
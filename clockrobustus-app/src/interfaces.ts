@@ -3,11 +3,55 @@ import React, { ChangeEvent, ReactNode } from "react";
 
 export interface ClockMessage {
   hours: number,
+  // 12-hour representation (1 to 12)
+  hours12: number,
+  pm: boolean,
   minutes: number,
   seconds: number,
   hoursAngle: number,
   minutesAngle: number,
   secondsAngle: number,
+  theme: 'day' | 'dusk' | 'night',
+  year: number,
+  // 1 to 12
+  month: number,
+  day: number,
+  // Days since Monday (0 to 6)
+  weekday: number,
+  // Within the second, already swept by secondsAngle
+  millis: number,
+  // ISO 8601 week number (1 to 53)
+  isoWeek: number,
+  // 1 to 366
+  dayOfYear: number,
+  // Daylight saving time in effect
+  dst: boolean,
+  calendars?: CalendarDates,
+}
+
+export interface CalendarDate {
+  year: number,
+  month: number,
+  day: number,
+  leapMonth: boolean,
+}
+
+export interface CalendarDates {
+  hijri?: CalendarDate,
+  hebrew?: CalendarDate,
+  chinese?: CalendarDate,
+}
+
+export interface RotationWeek {
+  hour: number,
+  minute: number,
+  seconds: number,
+  activeDays?: Array<string>,
+}
+
+export interface Rotation {
+  start: string,
+  weeks: Array<RotationWeek | null>,
 }
 
 export interface Alarm {
@@ -15,16 +59,273 @@ export interface Alarm {
   hour: number,
   minute: number,
   seconds: number,
+  label: string,
+  metadata: Record<string, unknown>,
+  color?: string,
+  icon?: string,
+  jitterMinutes?: number,
+  rotation?: Rotation,
+  enabled?: boolean,
+  group?: string,
+  snoozeMinutes?: number,
+  // Time an occurrence keeps ringing unless dismissed or snoozed
+  ringMinutes?: number,
+  // Unlimited if unset
+  maxSnoozes?: number,
+  // Only set on the ALARM events announcing a snooze (Unix timestamp, seconds)
+  snoozedUntil?: number,
+  // Only set on the ALARM events of occurrences missed during a suspend (Unix timestamp, seconds)
+  missedAt?: number,
+  // YYYY-MM-DD, one-shot alarms only
+  date?: string,
+  // IANA zone (eg: Europe/Paris) or 'local'
+  timezone?: string,
+  // RRULE-like rule (eg: FREQ=MONTHLY;BYDAY=1MO), replacing the active days
+  recurrence?: string,
+  // YYYY-MM-DD days (included) the alarm may ring between
+  validFrom?: string,
+  validUntil?: string,
+  id?: number,
+}
+
+export interface Countdown {
+  label: string,
+  // Unix timestamp (seconds)
+  target: number,
+  id?: number,
+}
+
+export interface CountdownMessage {
+  id: number,
+  days: number,
+  hours: number,
+  minutes: number,
+  passed: boolean,
+}
+
+export interface IntervalTimer {
+  label: string,
+  everyMinutes: number,
+  // Unix timestamp (seconds) the reminders are counted from
+  since?: number,
+  enabled?: boolean,
+  id?: number,
+}
+
+export interface IntervalMessage {
+  id: number,
+  // Unix timestamp (seconds) the reminder was due at
+  dueAt: number,
+}
+
+export interface NextAlarmMessage {
+  // null if no alarm is upcoming
+  id: number | null,
+  // Unix timestamp (seconds) the alarm rings at, 0 if none
+  ringsAt: number,
+  minutesLeft: number,
+}
+
+export interface Bedtime {
+  activeDays: Array<string>,
+  hour: number,
+  minute: number,
+  // Minutes between the reminder and the bedtime
+  remindMinutes?: number,
+  id?: number,
+}
+
+export interface BedtimeMessage {
+  id: number,
+  // Unix timestamp (seconds) of the bedtime
+  bedtime: number,
+  minutesLeft: number,
+}
+
+export interface Reminder {
+  // Unix timestamp (seconds)
+  dueAt: number,
+  // At most 255 bytes
+  text: string,
+  done?: boolean,
+  id?: number,
+}
+
+export interface ReminderMessage {
+  id: number,
+  // Unix timestamp (seconds) the reminder was due at
+  dueAt: number,
+  text: string,
+}
+
+export interface WorldClock {
+  // At most 64 bytes
+  label: string,
+  // IANA name (eg: 'Europe/Paris') or 'local'
+  zone: string,
+  id?: number,
+}
+
+export interface WorldClockMessage {
+  id: number,
+  zone: string,
+  label: string,
+  clock: ClockMessage,
+}
+
+export type SunCycle = 'normal' | 'midnightSun' | 'polarNight';
+
+export type MoonPhase = 'newMoon' | 'waxingCrescent' | 'firstQuarter' | 'waxingGibbous'
+  | 'fullMoon' | 'waningGibbous' | 'lastQuarter' | 'waningCrescent';
+
+export interface SunMessage {
+  cycle: SunCycle,
+  // Unix timestamps (seconds), 0 when the sun does not rise or set
+  sunrise: number,
+  sunset: number,
+  daylightRemainingMinutes: number,
+  moonPhase: MoonPhase,
+  // Percents
+  moonIllumination: number,
+}
+
+export interface SyncStatusMessage {
+  // If not, the other values are the last known
+  reachable: boolean,
+  stratum: number,
+  // Milliseconds to add to the daemon clock to get the NTP server time
+  offsetMs: number,
+  delayMs: number,
+  driftPpm: number,
+  // Unix timestamp (milliseconds)
+  checkedAt: number,
+}
+
+export interface ClockJumpMessage {
+  // Milliseconds, negative when set back
+  jumpMs: number,
+  // Unix timestamp (milliseconds) after the jump
+  wallMs: number,
+}
+
+export type SpeakLocale = 'en' | 'fr';
+
+// Time announced by the speaking clock, the text is to be read out loud
+export interface SpeakMessage {
+  hours: number,
+  minutes: number,
+  locale: SpeakLocale,
+  text: string,
+}
+
+// Periodic status of the daemon, telling clients it is still running
+export interface HeartbeatMessage {
+  uptimeS: number,
+  alarmCount: number,
+  version: string,
+}
+
+// What the heartbeats tell of the daemon (see libclockrobustus::heartbeat::Liveness)
+export type Liveness = 'up' | 'late' | 'down';
+
+// Messages of a topic dropped on the way, what is displayed from it may be stale
+export interface MissedMessages {
+  topic: string,
+  count: number,
+}
+
+export type HourFormat = 'h24' | 'h12';
+
+export interface Settings {
+  hourFormat: HourFormat,
+}
+
+export type AlarmSort = 'id' | 'time' | 'nextOccurrence' | 'label' | 'recentlyEdited';
+
+export interface Health {
+  database: boolean,
+  scheduler: boolean,
+  panics: number,
+}
+
+export interface TimerState {
+  durationMs: number,
+  remainingMs: number,
+  running: boolean,
+  finished: boolean,
+}
+
+export interface TimerPreset {
+  name: string,
+  durationMs: number,
   id?: number,
 }
 
+export interface StopwatchState {
+  elapsedMs: number,
+  running: boolean,
+  laps: number[],
+}
+
+export interface PomodoroSettings {
+  workMinutes: number,
+  shortBreakMinutes: number,
+  longBreakMinutes: number,
+  // Number of work sessions between two long breaks
+  longBreakEvery: number,
+}
+
+export type PomodoroPhase = 'idle' | 'work' | 'shortBreak' | 'longBreak';
+
+export interface PomodoroMessage {
+  phase: PomodoroPhase,
+  completed: number,
+  // End of the phase (milliseconds since the Unix epoch), 0 while idle
+  endsAtMs: number,
+}
+
+export interface RoundTrip {
+  samples: number,
+  minMs: number,
+  averageMs: number,
+  maxMs: number,
+}
+
+export type RingOutcome = 'rang' | 'dismissed' | 'snoozed' | 'missed' | 'ignored' | 'silenced';
+
+export interface RingEvent {
+  id?: number,
+  alarmId: number,
+  // Unix timestamp (seconds)
+  timestamp: number,
+  outcome: RingOutcome,
+  offsetSeconds: number,
+}
+
+export interface RingStats {
+  averageSnoozes: Array<{ alarmId: number, average: number }>,
+  mostMissed?: { alarmId: number, count: number },
+  ringsPerWeekday: Array<number>,
+}
+
 export const dummyClockMessage: ClockMessage = {
   hours: 0,
+  hours12: 12,
+  pm: false,
   minutes: 0,
   seconds: 0,
   hoursAngle: 0,
   minutesAngle: 0,
   secondsAngle: 0,
+  theme: 'day',
+  year: 1970,
+  month: 1,
+  day: 1,
+  weekday: 3,
+  millis: 0,
+  isoWeek: 1,
+  dayOfYear: 1,
+  dst: false,
 };
 
 export const dummyAlarm: Alarm = {
@@ -32,6 +333,8 @@ export const dummyAlarm: Alarm = {
   hour: 12,
   minute: 0,
   seconds: 0,
+  label: '',
+  metadata: {},
 }
 
 export interface AlarmFormProps {
@@ -0,0 +1,20 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Message types are generated by libclockrobustus (see its protobuf feature)
+    #[cfg(feature = "grpc")]
+    tonic_build::configure()
+        .extern_path(
+            ".clockrobustus.Message",
+            "::libclockrobustus::proto::Message",
+        )
+        .extern_path(".clockrobustus.Alarm", "::libclockrobustus::proto::Alarm")
+        .extern_path(
+            ".clockrobustus.ClockTick",
+            "::libclockrobustus::proto::ClockTick",
+        )
+        .compile(
+            &["proto/clockrobustus.proto"],
+            &["proto", "../libclockrobustus/proto"],
+        )?;
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use libclockrobustus::{error::ClockError, import::ImportSource, token::Role};
+
+/// Number of occurrences listed by `--list-next` when no count is given.
+const DEFAULT_LIST_COUNT: usize = 10;
+/// Duration (in seconds) of `--profile-latency` when none is given.
+const DEFAULT_PROFILE_SECONDS: u64 = 10;
+
+/// What the daemon has been asked to do on the command line.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Default mode: run the clock server.
+    Serve,
+    /// Print the next N alarm occurrences and exit.
+    ListNext(usize),
+    /// Validate the configuration, database and endpoints, then exit.
+    Check,
+    /// Write the alarm history as CSV to the given file (standard output if none) and exit.
+    ExportHistory(Option<String>),
+    /// Issue a new API token with the given name and role, print its secret and exit.
+    MintToken(String, Role),
+    /// Revoke the API token with the given id and exit.
+    RevokeToken(i64),
+    /// List issued API tokens and exit.
+    ListTokens,
+    /// Import the alarms of another app from the given export file and exit.
+    Import(ImportSource, String),
+    /// Listen to the running daemon for N seconds, print the latency distribution and exit.
+    ProfileLatency(u64),
+    /// Print usage and exit.
+    Help,
+}
+
+pub const USAGE: &str = "Usage: clockrobustusd [OPTIONS]
+
+Options:
+  --list-next [N]  Print the next N alarm occurrences (defaults to 10) and exit
+  --check          Validate configuration, database and endpoints, then exit
+  --export-history [FILE]
+                   Write the alarm history as CSV to FILE (or standard output) and exit
+  --mint-token NAME [--read-only]
+                   Issue an API token for remote clients and print its secret.
+                   Read-only tokens may subscribe and list alarms, but not change them
+  --revoke-token ID
+                   Revoke an API token
+  --list-tokens    List issued API tokens
+  --import SOURCE FILE
+                   Import alarms exported by another app (gnome-clocks, windows-alarms)
+                   or the reminders of an iCalendar file (ical)
+  --profile-latency [SECONDS]
+                   Listen to the running daemon (10 seconds by default) and print the
+                   distribution of message latencies
+  -h, --help       Print this help and exit";
+
+/// Parses the daemon arguments (program name excluded).
+pub fn parse<I>(args: I) -> Result<Mode, ClockError>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut args = args.into_iter().peekable();
+    let mut mode = Mode::Serve;
+
+    while let Some(arg) = args.next() {
+        mode = match arg.as_str() {
+            "--list-next" => Mode::ListNext(match args.next_if(|value| !value.starts_with('-')) {
+                Some(value) => value.parse::<usize>()?,
+                None => DEFAULT_LIST_COUNT,
+            }),
+            "--check" => Mode::Check,
+            "--export-history" => {
+                Mode::ExportHistory(args.next_if(|value| !value.starts_with('-')))
+            }
+            "--mint-token" => Mode::MintToken(
+                args.next()
+                    .ok_or(ClockError("Missing token name after --mint-token"))?,
+                match args.next_if(|value| value == "--read-only") {
+                    Some(_) => Role::ReadOnly,
+                    None => Role::Admin,
+                },
+            ),
+            "--revoke-token" => Mode::RevokeToken(
+                args.next()
+                    .ok_or(ClockError("Missing token id after --revoke-token"))?
+                    .parse()?,
+            ),
+            "--list-tokens" => Mode::ListTokens,
+            "--import" => Mode::Import(
+                ImportSource::try_from(
+                    args.next()
+                        .ok_or(ClockError("Missing import source after --import"))?
+                        .as_str(),
+                )?,
+                args.next()
+                    .ok_or(ClockError("Missing export file after --import"))?,
+            ),
+            "--profile-latency" => {
+                Mode::ProfileLatency(match args.next_if(|value| !value.starts_with('-')) {
+                    Some(value) => value.parse()?,
+                    None => DEFAULT_PROFILE_SECONDS,
+                })
+            }
+            "-h" | "--help" => Mode::Help,
+            _ => return Err(ClockError("Unknown command line argument")),
+        };
+    }
+
+    Ok(mode)
+}
@@ -0,0 +1,922 @@
+use chrono::{Local, Utc};
+use libclockrobustus::{
+    alarm::Alarm,
+    control::{Envelope, Health, Request, Response},
+    error::ClockError,
+    heartbeat::HeartbeatMessage,
+    history::{RingEvent, RingOutcome},
+    ical,
+    latency::now_ms,
+    pomodoro::{Pomodoro, PomodoroMessage},
+    profile::Profile,
+    timer::{Stopwatch, Timer},
+    token::ApiToken,
+};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{ratelimit::RateLimiter, supervisor::Supervisor};
+
+/// Control requests larger than this are rejected.
+pub const MAX_REQUEST_BYTES: i64 = 64 * 1024;
+/// Longest delay (in seconds) accepted for an alarm preview.
+const MAX_PREVIEW_DELAY: u64 = 3600;
+/// Label of the alarm published when the timer ends.
+const TIMER_LABEL: &str = "Timer";
+/// Time between two publications of an alarm ringing until it is acknowledged.
+const RING_REPEAT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Runtime facts about the daemon, shared between the tick loop and the control channel.
+pub struct DaemonState {
+    /// Outcome of the last tick.
+    pub last_tick_ok: bool,
+    /// An admin client asked the daemon to stop.
+    pub shutdown_requested: bool,
+    /// Alarm previews waiting to be published, with their due time.
+    previews: Vec<(Instant, Alarm)>,
+    /// Snoozed alarms (and their snooze announcements) waiting to be published, with their due
+    /// time.
+    snoozes: Vec<(Instant, Alarm)>,
+    /// One-off alarms taken to be published, with their due time and whether they were snoozed,
+    /// until the publication is confirmed or fails.
+    taken: Vec<(Instant, Alarm, bool)>,
+    /// Alarms rung but neither dismissed nor snoozed yet.
+    ringing: Vec<Ringing>,
+    /// Times the current occurrence of each alarm has been snoozed.
+    snooze_counts: HashMap<i64, u8>,
+    timer: Timer,
+    stopwatch: Stopwatch,
+    pomodoro: Pomodoro,
+    /// Last pomodoro phase published.
+    pomodoro_published: Option<PomodoroMessage>,
+    supervisor: Supervisor,
+    /// When the daemon started.
+    started: Instant,
+}
+
+impl DaemonState {
+    /// Initial state, the timer, stopwatch and pomodoro being restored from the database.
+    pub fn new(supervisor: Supervisor, conn: &sqlite::Connection) -> Result<Self, ClockError> {
+        Ok(Self {
+            last_tick_ok: true,
+            shutdown_requested: false,
+            previews: Vec::new(),
+            snoozes: Vec::new(),
+            taken: Vec::new(),
+            ringing: Vec::new(),
+            snooze_counts: HashMap::new(),
+            timer: Timer::load(conn)?,
+            stopwatch: Stopwatch::load(conn)?,
+            pomodoro: Pomodoro::load(conn)?,
+            pomodoro_published: None,
+            supervisor,
+            started: Instant::now(),
+        })
+    }
+
+    /// Status of the daemon, as published periodically and told on request.
+    pub fn heartbeat(&self, conn: &sqlite::Connection) -> Result<HeartbeatMessage, ClockError> {
+        Ok(HeartbeatMessage::new(
+            self.started.elapsed().as_secs(),
+            Alarm::count(conn)? as u32,
+            env!("CARGO_PKG_VERSION"),
+        ))
+    }
+
+    /// Removes and returns the one-off alarms due at `now`: previews, snoozed alarms, the end of
+    /// the timer, and the alarms still ringing, published again every minute. While `quiet` (see
+    /// [libclockrobustus::quiet::QuietWindow]), snoozed alarms due are recorded as silenced
+    /// instead and ringing ones are not published again. On error, nothing is taken: previews and
+    /// snoozes are kept for the next attempt. Once published, the alarms taken are confirmed with
+    /// [Self::published], or put back with [Self::requeue] if publishing them failed.
+    pub fn take_due_alarms(
+        &mut self,
+        conn: &sqlite::Connection,
+        now: Instant,
+        quiet: bool,
+    ) -> Result<Vec<Alarm>, ClockError> {
+        // Snooze announcements are not rings, they go through
+        let silenced = |due_at: &Instant, alarm: &Alarm| {
+            quiet && *due_at <= now && alarm.snoozed_until.is_none()
+        };
+        for (_, alarm) in self
+            .snoozes
+            .iter()
+            .filter(|(due_at, alarm)| silenced(due_at, alarm))
+        {
+            if let Some(alarm_id) = alarm.id {
+                RingEvent::now(alarm_id, RingOutcome::Silenced).save(conn)?;
+            }
+        }
+
+        let mut timer = self.timer.clone();
+        let timer_finished = timer.take_finished(now_ms()?);
+        if timer_finished {
+            // Not ringing again after a restart
+            timer.save(conn)?;
+            self.timer = timer;
+        }
+
+        self.snoozes
+            .retain(|(due_at, alarm)| !silenced(due_at, alarm));
+
+        for (queue, snoozed) in [(&mut self.previews, false), (&mut self.snoozes, true)] {
+            let (due, pending) = queue
+                .drain(..)
+                .partition::<Vec<_>, _>(|(due_at, _)| *due_at <= now);
+            *queue = pending;
+            self.taken.extend(
+                due.into_iter()
+                    .map(|(due_at, alarm)| (due_at, alarm, snoozed)),
+            );
+        }
+        if timer_finished {
+            // Published again as a preview if publishing it fails
+            let alarm = Alarm {
+                label: TIMER_LABEL.to_string(),
+                ..Default::default()
+            };
+            self.taken.push((now, alarm, false));
+        }
+
+        let mut alarms = Vec::new();
+        for ringing in &mut self.ringing {
+            if !quiet
+                && now.duration_since(ringing.published_at) >= RING_REPEAT_INTERVAL
+                && !ringing.expired(now)
+            {
+                ringing.published_at = now;
+                alarms.push(ringing.alarm.clone());
+            }
+        }
+        alarms.extend(self.taken.iter().map(|(_, alarm, _)| alarm.clone()));
+
+        Ok(alarms)
+    }
+
+    /// Records that the one-off alarms taken by [Self::take_due_alarms] have been published:
+    /// snoozed alarms ringing again now wait for an acknowledgement, previews and snooze
+    /// announcements do not.
+    pub fn published(&mut self, now: Instant) {
+        for (_, alarm, snoozed) in std::mem::take(&mut self.taken) {
+            if snoozed && alarm.id.is_some() && alarm.snoozed_until.is_none() {
+                self.ringing.push(Ringing::new(alarm, now));
+            }
+        }
+    }
+
+    /// Puts the one-off alarms taken by [Self::take_due_alarms] back in their queue, publishing
+    /// them having failed, to be taken again on the next call.
+    pub fn requeue(&mut self) {
+        for (due_at, alarm, snoozed) in std::mem::take(&mut self.taken) {
+            if snoozed {
+                self.snoozes.push((due_at, alarm));
+            } else {
+                self.previews.push((due_at, alarm));
+            }
+        }
+    }
+
+    /// Moves the pomodoro on to its phase at `now`, returning the phase to publish if it changed
+    /// since the last call (on its own or on request). The first call returns the current phase.
+    pub fn take_pomodoro_change(
+        &mut self,
+        conn: &sqlite::Connection,
+        now: u64,
+    ) -> Result<Option<PomodoroMessage>, ClockError> {
+        if self.pomodoro.advance(now) {
+            self.pomodoro.save(conn)?;
+        }
+
+        let message = self.pomodoro.message();
+        if self.pomodoro_published == Some(message) {
+            return Ok(None);
+        }
+        self.pomodoro_published = Some(message);
+        Ok(Some(message))
+    }
+
+    /// Records that a new occurrence of the (saved) alarm has been published, ringing until it
+    /// is acknowledged.
+    pub fn ring(&mut self, alarm: Alarm, now: Instant) {
+        if let Some(alarm_id) = alarm.id {
+            self.acknowledge(alarm_id);
+            self.snooze_counts.remove(&alarm_id);
+            self.ringing.push(Ringing::new(alarm, now));
+        }
+    }
+
+    /// Stops the alarms having rung for longer than their ring duration without being
+    /// acknowledged, recording them as ignored in the history, and returns their ids.
+    pub fn expire_ringing(
+        &mut self,
+        conn: &sqlite::Connection,
+        now: Instant,
+    ) -> Result<Vec<i64>, ClockError> {
+        let (expired, ringing) = self
+            .ringing
+            .drain(..)
+            .partition::<Vec<_>, _>(|ringing| ringing.expired(now));
+        self.ringing = ringing;
+
+        let mut ids = Vec::new();
+        for alarm_id in expired.iter().filter_map(|ringing| ringing.alarm.id) {
+            RingEvent::now(alarm_id, RingOutcome::Ignored).save(conn)?;
+            self.snooze_counts.remove(&alarm_id);
+            ids.push(alarm_id);
+        }
+
+        Ok(ids)
+    }
+
+    // Whether the alarm rings, waiting for an acknowledgement
+    fn is_ringing(&self, alarm_id: i64) -> bool {
+        self.ringing
+            .iter()
+            .any(|ringing| ringing.alarm.id == Some(alarm_id))
+    }
+
+    // Stops waiting for an acknowledgement of the alarm, returns false if it was not ringing
+    fn acknowledge(&mut self, alarm_id: i64) -> bool {
+        let ringing = self.ringing.len();
+        self.ringing
+            .retain(|ringing| ringing.alarm.id != Some(alarm_id));
+
+        self.ringing.len() < ringing
+    }
+}
+
+/// An alarm occurrence waiting to be acknowledged.
+struct Ringing {
+    alarm: Alarm,
+    rang_at: Instant,
+    published_at: Instant,
+}
+
+impl Ringing {
+    fn new(alarm: Alarm, now: Instant) -> Self {
+        Self {
+            alarm,
+            rang_at: now,
+            published_at: now,
+        }
+    }
+
+    // Rang for longer than the alarm ring duration, never when it has none
+    fn expired(&self, now: Instant) -> bool {
+        self.alarm.ring_minutes != 0
+            && now.duration_since(self.rang_at)
+                >= Duration::from_secs(self.alarm.ring_minutes as u64 * 60)
+    }
+}
+
+/// Checks the credentials (and role) of a control request, then computes its reply.
+fn authorize_and_handle(
+    envelope: Envelope,
+    conn: &sqlite::Connection,
+    state: &mut DaemonState,
+) -> Response {
+    if let Some(required) = envelope.request.required_role() {
+        match ApiToken::role(conn, envelope.token.as_deref()) {
+            Ok(Some(role)) if role >= required => (),
+            Ok(Some(_)) => {
+                return Response::Error {
+                    message: "Forbidden".to_string(),
+                }
+            }
+            Ok(None) => {
+                return Response::Error {
+                    message: "Unauthorized".to_string(),
+                }
+            }
+            Err(error) => {
+                return Response::Error {
+                    message: error.0.to_string(),
+                }
+            }
+        }
+    }
+
+    handle(envelope.request, conn, state)
+}
+
+/// Computes the reply to a single control request.
+fn handle(request: Request, conn: &sqlite::Connection, state: &mut DaemonState) -> Response {
+    match request {
+        Request::Ping => Response::Health(Health {
+            // Probed read-only, a health check must not migrate the database
+            database: Alarm::probe_schema(conn).is_ok_and(|problems| problems.is_empty()),
+            scheduler: state.last_tick_ok,
+            panics: state.supervisor.panics(),
+        }),
+        Request::Echo { nonce } => Response::Echo { nonce },
+        Request::Status => match state.heartbeat(conn) {
+            Ok(status) => Response::Status(status),
+            Err(error) => Response::Error {
+                message: error.0.to_string(),
+            },
+        },
+        Request::ListAlarms {
+            sort,
+            limit,
+            offset,
+        } => {
+            let alarms = match limit {
+                Some(limit) => Alarm::page(conn, sort, limit, offset.unwrap_or(0)),
+                None => Alarm::all_sorted(conn, sort),
+            };
+            match alarms {
+                Ok(alarms) => Response::Alarms { alarms },
+                Err(error) => Response::Error {
+                    message: error.0.to_string(),
+                },
+            }
+        }
+        Request::UpsertAlarm { alarm, duplicates } => {
+            let saved = match duplicates {
+                Some(policy) => alarm.save_unique(conn, policy),
+                None => alarm.save(conn),
+            };
+            match saved {
+                Ok(()) => Response::Done,
+                Err(error) => Response::Error {
+                    message: error.0.to_string(),
+                },
+            }
+        }
+        Request::DeleteAlarm { alarm_id } => {
+            match find_alarm(conn, alarm_id).and_then(|alarm| alarm.remove(conn)) {
+                Ok(()) => Response::Done,
+                Err(error) => Response::Error {
+                    message: error.0.to_string(),
+                },
+            }
+        }
+        Request::RestoreAlarm { .. }
+        | Request::CountAlarms
+        | Request::SetAlarmsEnabled { .. }
+        | Request::SetGroupEnabled { .. }
+        | Request::NextRing { .. }
+        | Request::SkipNext { .. }
+        | Request::AlarmTags { .. }
+        | Request::SetAlarmTags { .. }
+        | Request::SearchAlarms { .. }
+        | Request::ImportAlarms { .. }
+        | Request::ExportAlarms
+        | Request::RestoreBackup { .. }
+        | Request::ExportIcal
+        | Request::History { .. }
+        | Request::RingStats
+        | Request::ExportHistory => match handle_alarms(request, conn) {
+            Ok(response) => response,
+            Err(error) => Response::Error {
+                message: error.0.to_string(),
+            },
+        },
+        Request::TimerStart { .. }
+        | Request::TimerPause
+        | Request::TimerReset
+        | Request::TimerStatus
+        | Request::StopwatchStart
+        | Request::StopwatchPause
+        | Request::StopwatchReset
+        | Request::StopwatchLap
+        | Request::StopwatchStatus => match now_ms() {
+            Ok(now) => handle_timekeeping(request, conn, state, now),
+            Err(error) => Response::Error {
+                message: error.0.to_string(),
+            },
+        },
+        Request::PomodoroStart { .. }
+        | Request::PomodoroStop
+        | Request::PomodoroSkip
+        | Request::PomodoroStatus => match handle_pomodoro(request, conn, state) {
+            Ok(message) => Response::Pomodoro(message),
+            Err(error) => Response::Error {
+                message: error.0.to_string(),
+            },
+        },
+        Request::Shutdown => {
+            state.shutdown_requested = true;
+            Response::Done
+        }
+        Request::Preview { delay, .. } if delay > MAX_PREVIEW_DELAY => Response::Error {
+            message: "Preview delay too long".to_string(),
+        },
+        Request::Preview { alarm_id, delay } => match find_alarm(conn, alarm_id) {
+            Ok(alarm) => {
+                state
+                    .previews
+                    .push((Instant::now() + Duration::from_secs(delay), alarm));
+                Response::Done
+            }
+            Err(error) => Response::Error {
+                message: error.0.to_string(),
+            },
+        },
+        Request::Dismiss { alarm_id } => {
+            if !state.acknowledge(alarm_id) {
+                return Response::Error {
+                    message: "Alarm is not ringing".to_string(),
+                };
+            }
+            state.snooze_counts.remove(&alarm_id);
+            match RingEvent::now(alarm_id, RingOutcome::Dismissed).save(conn) {
+                Ok(()) => Response::Done,
+                Err(error) => Response::Error {
+                    message: error.0.to_string(),
+                },
+            }
+        }
+        Request::SwitchProfile { name } => match Profile::activate(conn, &name) {
+            Ok(updated) => Response::ProfileSwitched { updated },
+            Err(error) => Response::Error {
+                message: error.0.to_string(),
+            },
+        },
+        Request::Snooze { alarm_id, minutes } => match snooze(conn, state, alarm_id, minutes) {
+            Ok(until) => Response::Snoozed { until },
+            Err(error) => Response::Error {
+                message: error.0.to_string(),
+            },
+        },
+    }
+}
+
+/// Computes the reply to the alarm management requests (besides listing, saving and removing).
+fn handle_alarms(request: Request, conn: &sqlite::Connection) -> Result<Response, ClockError> {
+    let response = match request {
+        Request::RestoreAlarm { alarm_id } => {
+            // Removed alarms cannot be looked up, the id is all restoring needs
+            Alarm {
+                id: Some(alarm_id),
+                ..Default::default()
+            }
+            .restore(conn)?;
+            Response::Done
+        }
+        Request::CountAlarms => Response::Count {
+            count: Alarm::count(conn)?,
+        },
+        Request::SetAlarmsEnabled { ids, enabled } => Response::Count {
+            count: Alarm::set_enabled_by_ids(conn, &ids, enabled)?,
+        },
+        Request::SetGroupEnabled { group, enabled } => Response::Count {
+            count: Alarm::set_enabled_by_group(conn, &group, enabled)?,
+        },
+        Request::NextRing { alarm } => Response::NextRing {
+            at: alarm
+                .next_ring(conn, Local::now())?
+                .map(|next| next.timestamp()),
+        },
+        Request::SkipNext { alarm_id } => Response::Skipped {
+            date: find_alarm(conn, alarm_id)?.skip_next(conn)?,
+        },
+        Request::AlarmTags { alarm_id } => Response::Tags {
+            tags: find_alarm(conn, alarm_id)?.tags(conn)?,
+        },
+        Request::SetAlarmTags { alarm_id, tags } => {
+            find_alarm(conn, alarm_id)?.set_tags(conn, &tags)?;
+            Response::Done
+        }
+        Request::SearchAlarms { query } => Response::Alarms {
+            alarms: Alarm::search(conn, &query)?,
+        },
+        Request::ImportAlarms { alarms } => {
+            // Imported alarms are always new ones, updates go through UpsertAlarm
+            for alarm in &alarms {
+                alarm.duplicate().save(conn)?;
+            }
+            Response::Count {
+                count: alarms.len(),
+            }
+        }
+        Request::ExportAlarms => Response::Exported {
+            content: Alarm::export_all(conn)?,
+        },
+        Request::RestoreBackup { content } => Response::Count {
+            count: Alarm::import(conn, &content)?,
+        },
+        Request::ExportIcal => Response::Exported {
+            content: ical::export(&Alarm::all(conn)?, Local::now().date_naive(), Utc::now())?,
+        },
+        Request::History { alarm_id, limit } => Response::History {
+            events: RingEvent::query(conn, alarm_id, None, limit)?,
+        },
+        Request::RingStats => Response::RingStats(RingEvent::stats(conn)?),
+        Request::ExportHistory => Response::Exported {
+            content: RingEvent::export_csv(conn)?,
+        },
+        _ => return Err(ClockError("Not an alarm management request")),
+    };
+
+    Ok(response)
+}
+
+/// Looks a stored alarm up by id.
+fn find_alarm(conn: &sqlite::Connection, alarm_id: i64) -> Result<Alarm, ClockError> {
+    Alarm::by_id(conn, alarm_id)?.ok_or(ClockError("No such alarm"))
+}
+
+/// Schedules the alarm to ring again after its snooze delay (or `minutes`), announces the
+/// snooze to clients on the next tick and records it in the history. Returns the Unix timestamp
+/// (seconds) the alarm rings again at.
+fn snooze(
+    conn: &sqlite::Connection,
+    state: &mut DaemonState,
+    alarm_id: i64,
+    minutes: Option<u8>,
+) -> Result<i64, ClockError> {
+    let alarm = find_alarm(conn, alarm_id)?;
+    let minutes = minutes.unwrap_or(alarm.snooze_minutes);
+
+    // Neither snoozing ahead of time nor ringing an alarm that was not due
+    if !state.is_ringing(alarm_id) {
+        return Err(ClockError("Alarm is not ringing"));
+    }
+
+    if minutes == 0 {
+        return Err(ClockError("Snooze delay must be positive"));
+    }
+    let snoozes = state.snooze_counts.get(&alarm_id).copied().unwrap_or(0);
+    if alarm.max_snoozes.is_some_and(|max| snoozes >= max) {
+        return Err(ClockError("Snooze limit reached"));
+    }
+
+    let delay = Duration::from_secs(minutes as u64 * 60);
+    let until = now_ms()? as i64 / 1000 + delay.as_secs() as i64;
+
+    RingEvent::now(alarm_id, RingOutcome::Snoozed).save(conn)?;
+    state.acknowledge(alarm_id);
+    state
+        .snooze_counts
+        .insert(alarm_id, snoozes.saturating_add(1));
+    state
+        .snoozes
+        .push((Instant::now(), alarm.clone().snoozed(until)));
+    state.snoozes.push((Instant::now() + delay, alarm));
+
+    Ok(until)
+}
+
+/// Computes the reply to timer and stopwatch requests.
+fn handle_timekeeping(
+    request: Request,
+    conn: &sqlite::Connection,
+    state: &mut DaemonState,
+    now: u64,
+) -> Response {
+    match request {
+        Request::TimerStart { duration_ms } => state.timer.start(duration_ms, now),
+        Request::TimerPause => state.timer.pause(now),
+        Request::TimerReset => state.timer.reset(),
+        Request::StopwatchStart => state.stopwatch.start(now),
+        Request::StopwatchPause => state.stopwatch.pause(now),
+        Request::StopwatchReset => state.stopwatch.reset(),
+        Request::StopwatchLap => state.stopwatch.lap(now),
+        _ => (),
+    }
+
+    // Persisted so a restarted daemon resumes them
+    let saved = match request {
+        Request::TimerStatus | Request::StopwatchStatus => Ok(()),
+        Request::TimerStart { .. } | Request::TimerPause | Request::TimerReset => {
+            state.timer.save(conn)
+        }
+        _ => state.stopwatch.save(conn),
+    };
+    if let Err(error) = saved {
+        return Response::Error {
+            message: error.0.to_string(),
+        };
+    }
+
+    match request {
+        Request::TimerStart { .. }
+        | Request::TimerPause
+        | Request::TimerReset
+        | Request::TimerStatus => Response::Timer(state.timer.state(now)),
+        _ => Response::Stopwatch(state.stopwatch.state(now)),
+    }
+}
+
+/// Computes the reply to pomodoro requests. The phase change is published on the next loop.
+fn handle_pomodoro(
+    request: Request,
+    conn: &sqlite::Connection,
+    state: &mut DaemonState,
+) -> Result<PomodoroMessage, ClockError> {
+    let now = now_ms()?;
+
+    match request {
+        Request::PomodoroStart { settings } => state.pomodoro.start(settings, now)?,
+        Request::PomodoroStop => state.pomodoro.stop(),
+        Request::PomodoroSkip => state.pomodoro.skip(now),
+        _ => (),
+    }
+    state.pomodoro.advance(now);
+    // Persisted so a restarted daemon resumes it
+    state.pomodoro.save(conn)?;
+
+    Ok(state.pomodoro.message())
+}
+
+/// Answers control requests until `deadline` is reached, so the tick rhythm is kept while the
+/// daemon stays responsive between two ticks. Clients (told apart by their address) exceeding
+/// their rate are rejected without touching the database.
+pub fn serve_until(
+    socket: &zmq::Socket,
+    conn: &sqlite::Connection,
+    state: &mut DaemonState,
+    limiter: &mut RateLimiter,
+    deadline: Instant,
+) -> Result<(), ClockError> {
+    let mut msg = zmq::Message::new();
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+
+        match socket.poll(zmq::POLLIN, remaining.as_millis() as i64) {
+            // Interrupted (most likely by Ctrl+C), let the main loop decide what to do.
+            Err(zmq::Error::EINTR) => break,
+            Err(error) => return Err(error.into()),
+            Ok(0) => continue,
+            Ok(_) => {
+                socket.recv(&mut msg, 0)?;
+                let client = msg.gets("Peer-Address").unwrap_or("unknown").to_string();
+                // A REP socket must always reply, even to garbage.
+                let response = if !limiter.allow(&client) {
+                    Response::Error {
+                        message: "Too many requests".to_string(),
+                    }
+                } else {
+                    match serde_json::from_slice::<Envelope>(&msg) {
+                        Ok(envelope) => authorize_and_handle(envelope, conn, state),
+                        Err(_) => Response::Error {
+                            message: "Malformed request".to_string(),
+                        },
+                    }
+                };
+
+                socket.send(serde_json::to_vec(&response)?, 0)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    use libclockrobustus::token::Role;
+
+    use super::*;
+
+    fn state(conn: &sqlite::Connection) -> DaemonState {
+        DaemonState::new(Supervisor::new(Arc::new(AtomicBool::new(true))), conn).unwrap()
+    }
+
+    fn saved_alarm(conn: &sqlite::Connection, alarm: Alarm) -> Alarm {
+        alarm.save(conn).unwrap();
+        Alarm::all(conn).unwrap().pop().unwrap()
+    }
+
+    #[test]
+    fn test_expire_ringing() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let mut state = state(&conn);
+        let now = Instant::now();
+        let short = saved_alarm(
+            &conn,
+            Alarm {
+                ring_minutes: 1,
+                ..Default::default()
+            },
+        );
+        let endless = saved_alarm(
+            &conn,
+            Alarm {
+                ring_minutes: 0,
+                ..Default::default()
+            },
+        );
+
+        state.ring(short.clone(), now);
+        state.ring(endless.clone(), now);
+
+        assert!(state
+            .expire_ringing(&conn, now + Duration::from_secs(30))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            state
+                .expire_ringing(&conn, now + Duration::from_secs(60))
+                .unwrap(),
+            vec![short.id.unwrap()]
+        );
+        let history = RingEvent::all(&conn).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].outcome, RingOutcome::Ignored);
+
+        // Without a ring duration, an alarm rings until it is acknowledged
+        assert!(state
+            .expire_ringing(&conn, now + Duration::from_secs(24 * 3600))
+            .unwrap()
+            .is_empty());
+        assert!(state.acknowledge(endless.id.unwrap()));
+        assert!(!state.acknowledge(short.id.unwrap()));
+    }
+
+    #[test]
+    fn test_ping() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let mut state = state(&conn);
+        Alarm::check_schema(&conn).unwrap();
+        let empty = sqlite::open(":memory:").unwrap();
+
+        let healthy = |response| match response {
+            Response::Health(health) => health.database,
+            _ => false,
+        };
+        assert!(healthy(handle(Request::Ping, &conn, &mut state)));
+        // Reported, not created
+        assert!(!healthy(handle(Request::Ping, &empty, &mut state)));
+        assert!(!Alarm::probe_schema(&empty).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_only() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let mut state = state(&conn);
+        let alarm = saved_alarm(&conn, Alarm::default());
+        let alarm_id = alarm.id.unwrap();
+        let (_, secret) = ApiToken::issue(&conn, "wall display", Role::ReadOnly).unwrap();
+        state.ring(alarm, Instant::now());
+
+        let mut read_only = |request| {
+            authorize_and_handle(
+                Envelope {
+                    token: Some(secret.clone()),
+                    request,
+                },
+                &conn,
+                &mut state,
+            )
+        };
+        let forbidden = Response::Error {
+            message: "Forbidden".to_string(),
+        };
+        assert_ne!(read_only(Request::CountAlarms), forbidden);
+        assert_eq!(
+            read_only(Request::Snooze {
+                alarm_id,
+                minutes: None
+            }),
+            forbidden
+        );
+        assert_eq!(
+            read_only(Request::Preview { alarm_id, delay: 0 }),
+            forbidden
+        );
+        // Still ringing, nothing scheduled
+        assert!(state.is_ringing(alarm_id));
+        assert!(state.snoozes.is_empty() && state.previews.is_empty());
+    }
+
+    #[test]
+    fn test_quiet() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let mut state = state(&conn);
+        let alarm = saved_alarm(&conn, Alarm::default());
+        let alarm_id = alarm.id.unwrap();
+        let now = Instant::now();
+
+        // Published again a minute later, unless quiet
+        state.ring(alarm.clone(), now);
+        let later = now + RING_REPEAT_INTERVAL;
+        assert!(state
+            .take_due_alarms(&conn, later, true)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            state.take_due_alarms(&conn, later, false).unwrap(),
+            vec![alarm.clone()]
+        );
+
+        // The snooze is announced, the snoozed alarm does not ring again
+        snooze(&conn, &mut state, alarm_id, Some(1)).unwrap();
+        let later = Instant::now() + Duration::from_secs(60);
+        let published = state.take_due_alarms(&conn, later, true).unwrap();
+        assert_eq!(published.len(), 1);
+        assert!(published[0].snoozed_until.is_some());
+        assert!(state
+            .take_due_alarms(&conn, later, false)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            RingEvent::all(&conn)
+                .unwrap()
+                .iter()
+                .map(|event| event.outcome)
+                .collect::<Vec<_>>(),
+            vec![RingOutcome::Snoozed, RingOutcome::Silenced]
+        );
+    }
+
+    #[test]
+    fn test_snooze_counts() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let mut state = state(&conn);
+        let alarm = saved_alarm(
+            &conn,
+            Alarm {
+                max_snoozes: Some(2),
+                ..Default::default()
+            },
+        );
+        let alarm_id = alarm.id.unwrap();
+        // Snoozed alarms ring again after their delay
+        let ring_again = |state: &mut DaemonState| {
+            let later = Instant::now() + Duration::from_secs(60);
+            state.take_due_alarms(&conn, later, false).unwrap();
+            state.published(later);
+        };
+
+        state.ring(alarm.clone(), Instant::now());
+        assert!(snooze(&conn, &mut state, alarm_id, Some(1)).is_ok());
+        ring_again(&mut state);
+        assert!(snooze(&conn, &mut state, alarm_id, Some(1)).is_ok());
+        ring_again(&mut state);
+        assert!(snooze(&conn, &mut state, alarm_id, Some(1)).is_err());
+        assert_eq!(state.snooze_counts.get(&alarm_id), Some(&2));
+
+        // A new occurrence starts over
+        state.ring(alarm.clone(), Instant::now());
+        assert!(state.snooze_counts.is_empty());
+        assert!(snooze(&conn, &mut state, alarm_id, Some(1)).is_ok());
+
+        // So does an ignored one
+        state.ring(alarm, Instant::now());
+        state
+            .expire_ringing(&conn, Instant::now() + Duration::from_secs(3600))
+            .unwrap();
+        assert!(state.snooze_counts.is_empty());
+    }
+
+    #[test]
+    fn test_requeue() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let mut state = state(&conn);
+        let alarm = saved_alarm(&conn, Alarm::default());
+        let alarm_id = alarm.id.unwrap();
+        state.ring(alarm.clone(), Instant::now());
+        snooze(&conn, &mut state, alarm_id, Some(1)).unwrap();
+        let now = Instant::now();
+        assert_eq!(state.take_due_alarms(&conn, now, false).unwrap().len(), 1);
+        state.published(now);
+
+        // Ringing again once published, taken again if publishing failed
+        let later = now + Duration::from_secs(60);
+        assert_eq!(
+            state.take_due_alarms(&conn, later, false).unwrap(),
+            vec![alarm.clone()]
+        );
+        assert!(!state.acknowledge(alarm_id));
+        state.requeue();
+        assert_eq!(
+            state.take_due_alarms(&conn, later, false).unwrap(),
+            vec![alarm]
+        );
+        state.published(later);
+        assert!(state.acknowledge(alarm_id));
+    }
+
+    #[test]
+    fn test_snooze_not_ringing() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let mut state = state(&conn);
+        let alarm = saved_alarm(&conn, Alarm::default());
+        let alarm_id = alarm.id.unwrap();
+
+        // Neither counted nor scheduled to ring
+        assert!(snooze(&conn, &mut state, alarm_id, Some(1)).is_err());
+        assert!(state.snooze_counts.is_empty());
+        assert!(state
+            .take_due_alarms(&conn, Instant::now() + Duration::from_secs(3600), false)
+            .unwrap()
+            .is_empty());
+        assert!(RingEvent::all(&conn).unwrap().is_empty());
+
+        // Nor once dismissed
+        state.ring(alarm, Instant::now());
+        state.acknowledge(alarm_id);
+        assert!(snooze(&conn, &mut state, alarm_id, Some(1)).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use libclockrobustus::{
+    alarm::Alarm,
+    error::ClockError,
+    message::{Message, ALARM_TOPIC, CLOCK_TOPIC},
+    queue::listen,
+    token::{ApiToken, Role},
+};
+use std::{
+    net::ToSocketAddrs,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tonic::{service::Interceptor, transport::Server, Request, Response, Status};
+
+use crate::supervisor::Supervisor;
+
+/// Code generated from `proto/clockrobustus.proto`, along with the message types it imports
+pub mod proto {
+    pub use libclockrobustus::proto::{Alarm, ClockTick};
+
+    tonic::include_proto!("clockrobustus");
+}
+
+use proto::{alarms_server::AlarmsServer, events_server::EventsServer};
+
+/// Number of queue messages buffered for streaming clients. A client lagging further behind
+/// skips the oldest ones.
+const EVENTS_CAPACITY: usize = 64;
+
+type EventStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+fn internal(error: ClockError) -> Status {
+    Status::internal(error.0)
+}
+
+/// Rejects calls not made with an admin token (see [TokenCheck]).
+fn require_admin<T>(request: &Request<T>) -> Result<(), Status> {
+    match request.extensions().get::<Role>() {
+        Some(Role::Admin) => Ok(()),
+        _ => Err(Status::permission_denied("Admin token required")),
+    }
+}
+
+/// Rejects calls lacking a valid `authorization: Bearer <token>` metadata once API tokens have
+/// been issued. The [Role] granted by the token is attached to the request extensions.
+#[derive(Clone)]
+struct TokenCheck {
+    conn: Arc<Mutex<sqlite::Connection>>,
+}
+
+impl Interceptor for TokenCheck {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let token = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| Status::internal("Database connection lock poisoned"))?;
+
+        match ApiToken::role(&conn, token) {
+            Ok(Some(role)) => {
+                request.extensions_mut().insert(role);
+                Ok(request)
+            }
+            Ok(None) => Err(Status::unauthenticated("Invalid API token")),
+            Err(error) => Err(internal(error)),
+        }
+    }
+}
+
+/// Alarm management, with its own database connection.
+struct AlarmsService {
+    conn: Mutex<sqlite::Connection>,
+}
+
+impl AlarmsService {
+    fn with_conn<T, F>(&self, callback: F) -> Result<T, Status>
+    where
+        F: FnOnce(&sqlite::Connection) -> Result<T, ClockError>,
+    {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| Status::internal("Database connection lock poisoned"))?;
+
+        callback(&conn).map_err(internal)
+    }
+}
+
+#[tonic::async_trait]
+impl proto::alarms_server::Alarms for AlarmsService {
+    async fn list(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::AlarmList>, Status> {
+        let alarms = self.with_conn(Alarm::all)?;
+
+        Ok(Response::new(proto::AlarmList {
+            alarms: alarms.into_iter().map(proto::Alarm::from).collect(),
+        }))
+    }
+
+    async fn upsert(
+        &self,
+        request: Request<proto::Alarm>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        require_admin(&request)?;
+        let alarm = Alarm::try_from(request.into_inner())
+            .map_err(|error| Status::invalid_argument(error.0))?;
+
+        self.with_conn(|conn| alarm.save(conn))?;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn delete(
+        &self,
+        request: Request<proto::AlarmId>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        require_admin(&request)?;
+        let alarm = Alarm {
+            id: Some(request.into_inner().id),
+            ..Default::default()
+        };
+
+        self.with_conn(|conn| alarm.remove(conn))?;
+        Ok(Response::new(proto::Empty {}))
+    }
+}
+
+/// Streams fed by the daemon queue.
+struct EventsService {
+    sender: broadcast::Sender<Message>,
+}
+
+impl EventsService {
+    fn stream<T, F>(&self, filter: F) -> EventStream<T>
+    where
+        T: Send + 'static,
+        F: Fn(Message) -> Option<T> + Send + 'static,
+    {
+        let stream = BroadcastStream::new(self.sender.subscribe())
+            .filter_map(move |message| message.ok().and_then(&filter).map(Ok));
+
+        Box::pin(stream)
+    }
+}
+
+#[tonic::async_trait]
+impl proto::events_server::Events for EventsService {
+    type ClockStream = EventStream<proto::ClockTick>;
+    type AlarmsStream = EventStream<proto::Alarm>;
+
+    async fn clock(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<Self::ClockStream>, Status> {
+        Ok(Response::new(self.stream(|message| match message {
+            Message::Clock(clock) => Some(proto::ClockTick::from(clock)),
+            _ => None,
+        })))
+    }
+
+    async fn alarms(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<Self::AlarmsStream>, Status> {
+        Ok(Response::new(self.stream(|message| match message {
+            Message::Alarm(alarm) => Some(proto::Alarm::from(alarm)),
+            _ => None,
+        })))
+    }
+}
+
+/// Starts the gRPC server in the background. Streams are fed by listening to the daemon's own
+/// queue, alarms are managed through a dedicated database connection. Once API tokens have been
+/// issued, calls must present one.
+pub fn spawn(
+    host: &str,
+    port: u16,
+    db_path: &str,
+    supervisor: &Supervisor,
+) -> Result<(), ClockError> {
+    let db_path = db_path.to_string();
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or(ClockError("Unable to resolve gRPC server address"))?;
+    let (sender, _) = broadcast::channel(EVENTS_CAPACITY);
+    let relay = sender.clone();
+    let running = supervisor.running();
+
+    supervisor.spawn("gRPC event relay", move || {
+        // Only clock and alarm messages are streamed
+        listen(running.clone(), &[CLOCK_TOPIC, ALARM_TOPIC], |message| {
+            // No subscriber is not an error
+            let _ = relay.send(message);
+        })
+    });
+
+    supervisor.spawn("gRPC server", move || {
+        let runtime = tokio::runtime::Runtime::new()?;
+        let token_check = TokenCheck {
+            conn: Arc::new(Mutex::new(sqlite::Connection::open(&db_path)?)),
+        };
+        let server = Server::builder()
+            .add_service(AlarmsServer::with_interceptor(
+                AlarmsService {
+                    conn: Mutex::new(sqlite::Connection::open(&db_path)?),
+                },
+                token_check.clone(),
+            ))
+            .add_service(EventsServer::with_interceptor(
+                EventsService {
+                    sender: sender.clone(),
+                },
+                token_check,
+            ))
+            .serve(address);
+
+        runtime
+            .block_on(server)
+            .map_err(|_| ClockError("gRPC transport error"))
+    });
+
+    println!("gRPC server listening on {}", address);
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use libclockrobustus::{error::ClockError, message::Message, queue::listen, token::ApiToken};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crate::{ratelimit::RateLimiter, supervisor::Supervisor};
+
+/// Time given to a client to send its request headers.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time given to a client to take an event, stalled clients being dropped so they do not hold up
+/// the others (events are written to every client in turn).
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Requests (request line and headers) larger than this are rejected.
+const MAX_REQUEST_BYTES: u64 = 8 * 1024;
+
+const SSE_HEADERS: &str = "HTTP/1.1 200 OK\r
+Content-Type: text/event-stream\r
+Cache-Control: no-cache\r
+Connection: keep-alive\r
+Access-Control-Allow-Origin: *\r
+\r
+";
+
+const UNAUTHORIZED: &str = "HTTP/1.1 401 Unauthorized\r
+WWW-Authenticate: Bearer\r
+Content-Length: 0\r
+Connection: close\r
+\r
+";
+
+const TOO_MANY_REQUESTS: &str = "HTTP/1.1 429 Too Many Requests\r
+Retry-After: 1\r
+Content-Length: 0\r
+Connection: close\r
+\r
+";
+
+const NOT_FOUND: &str = "HTTP/1.1 404 Not Found\r
+Content-Length: 0\r
+Connection: close\r
+\r
+";
+
+/// Connected server-sent events clients.
+type Clients = Arc<Mutex<Vec<TcpStream>>>;
+
+/// Formats a message as a server-sent event, named after its topic and carrying its JSON
+/// representation.
+fn sse_event(message: &Message) -> Result<String, ClockError> {
+    Ok(format!(
+        "event: {}\ndata: {}\n\n",
+        String::from_utf8_lossy(message.topic()),
+        message.to_json()?
+    ))
+}
+
+/// What is used from an HTTP request
+struct HttpRequest {
+    path: String,
+    /// From an `Authorization: Bearer` header, or from a `token` query parameter (browsers'
+    /// `EventSource` cannot set headers)
+    token: Option<String>,
+}
+
+/// Reads the request line and headers.
+fn read_request(stream: &TcpStream) -> Result<HttpRequest, ClockError> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_BYTES));
+    let mut request_line = String::new();
+    let mut token = None;
+    reader.read_line(&mut request_line)?;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            // Either the client hung up or the size limit has been hit
+            return Err(ClockError("Incomplete or oversized HTTP request"));
+        }
+        if header.trim().is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("authorization") {
+                token = value.trim().strip_prefix("Bearer ").map(str::to_string);
+            }
+        }
+    }
+
+    let target = match request_line.split_whitespace().collect::<Vec<&str>>()[..] {
+        ["GET", target, _] => target.to_string(),
+        _ => return Err(ClockError("Malformed HTTP request")),
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target.as_str(), ""));
+
+    Ok(HttpRequest {
+        path: path.to_string(),
+        token: token.or_else(|| {
+            query
+                .split('&')
+                .find_map(|param| param.strip_prefix("token="))
+                .map(str::to_string)
+        }),
+    })
+}
+
+/// Turns away the clients over their request rate, before a thread is spawned for them.
+fn admit(stream: &mut TcpStream, limiter: &mut RateLimiter) -> Result<bool, ClockError> {
+    let client = stream.peer_addr()?.ip().to_string();
+
+    if !limiter.allow(&client) {
+        stream.write_all(TOO_MANY_REQUESTS.as_bytes())?;
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+fn accept(
+    mut stream: TcpStream,
+    conn: &Mutex<sqlite::Connection>,
+    clients: &Clients,
+) -> Result<(), ClockError> {
+    let request = read_request(&stream)?;
+
+    let authorized = {
+        let conn = conn
+            .lock()
+            .map_err(|_| ClockError("HTTP database lock poisoned"))?;
+        ApiToken::authorize(&conn, request.token.as_deref())?
+    };
+
+    if !authorized {
+        stream.write_all(UNAUTHORIZED.as_bytes())?;
+        return Ok(());
+    }
+
+    match request.path.as_str() {
+        "/events" => {
+            stream.write_all(SSE_HEADERS.as_bytes())?;
+            stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+            clients
+                .lock()
+                .map_err(|_| ClockError("HTTP clients lock poisoned"))?
+                .push(stream);
+        }
+        _ => stream.write_all(NOT_FOUND.as_bytes())?,
+    }
+
+    Ok(())
+}
+
+/// Starts the HTTP server in the background. `GET /events` streams clock and alarm messages as
+/// server-sent events (so a browser `EventSource` is enough to consume the daemon). Events are
+/// fed by listening to the daemon's own queue. Once API tokens have been issued, requests must
+/// present one.
+pub fn spawn(
+    host: &str,
+    port: u16,
+    db_path: &str,
+    rate_limit: u32,
+    supervisor: &Supervisor,
+) -> Result<(), ClockError> {
+    let listener = TcpListener::bind((host, port))?;
+    let conn = Arc::new(Mutex::new(sqlite::Connection::open(db_path)?));
+    let mut limiter = RateLimiter::new(rate_limit);
+    let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+    let relay_clients = clients.clone();
+    let running = supervisor.running();
+
+    supervisor.spawn("HTTP event relay", move || {
+        listen(running.clone(), &[], |message| {
+            let event = match sse_event(&message) {
+                Ok(event) => event,
+                Err(_) => return,
+            };
+
+            if let Ok(mut clients) = relay_clients.lock() {
+                // Disconnected (or stalled) clients fail to write and are dropped
+                clients.retain_mut(|client| client.write_all(event.as_bytes()).is_ok());
+            }
+        })
+    });
+
+    supervisor.spawn("HTTP server", move || {
+        for mut stream in listener.incoming().flatten() {
+            match admit(&mut stream, &mut limiter) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(error) => {
+                    println!("Rejected HTTP request : {:?}", error);
+                    continue;
+                }
+            }
+
+            // One thread per connection: a client slow to send its request does not hold up
+            // the others
+            let (conn, clients) = (conn.clone(), clients.clone());
+            thread::spawn(move || {
+                if let Err(error) = accept(stream, &conn, &clients) {
+                    println!("Rejected HTTP request : {:?}", error);
+                }
+            });
+        }
+
+        Ok(())
+    });
+
+    println!("HTTP server listening on {}:{}", host, port);
+    Ok(())
+}
@@ -1,38 +1,860 @@
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use cli::Mode;
+use control::DaemonState;
 use libclockrobustus::{
-    alarm::Alarm, check_database_directory, clock::ClockMessage, env::ClockEnv, error::ClockError, message::Message,
+    alarm::Alarm,
+    astro::Location,
+    backup,
+    bedtime::Bedtime,
+    check_database_directory,
+    clock::{ClockMessage, Zone},
+    countdown::Countdown,
+    database_path,
+    env::{ClockEnv, Constants},
+    error::ClockError,
+    heartbeat::HEARTBEAT_INTERVAL_S,
+    history::{RingEvent, RingOutcome},
+    import::ImportSource,
+    interval::IntervalTimer,
+    jump::JumpDetector,
+    latency::{self, LatencyHistogram},
+    message::{Message, Topics, WireFormat},
+    next::NextAlarmMessage,
+    ntp::{NtpSample, SyncStatusMessage},
+    queue::listen_timed,
+    quiet::QuietWindow,
+    reminder::Reminder,
+    sequence::{self, Sequencer},
+    speak::SpeakSchedule,
+    token::ApiToken,
+    world_clock::WorldClock,
 };
+use ratelimit::RateLimiter;
 use std::{
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        mpsc::{self, Receiver},
+        Arc, Mutex,
     },
-    thread::sleep,
-    time::Duration,
+    thread,
+    time::{Duration, Instant},
 };
+use supervisor::Supervisor;
+use triggers::Triggers;
 
-/// Tick function. Checks alarms and generates the clock signal.
-/// (see libclockrobustus documentation for more explanations)
-fn tick(socket: &zmq::Socket, conn: &sqlite::Connection) -> Result<(), ClockError> {
+mod cli;
+mod control;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod http;
+mod ratelimit;
+mod supervisor;
+mod triggers;
+
+/// Time between two checks of the backup schedule.
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Time between two publications of the countdowns.
+const COUNTDOWN_INTERVAL: Duration = Duration::from_secs(60);
+/// Time between two queries of the NTP server.
+const NTP_CHECK_INTERVAL: Duration = Duration::from_secs(600);
+/// Shortest gap between two ticks (twice the tick duration at least) treated as a suspend of
+/// the daemon, the alarms due in between being caught up.
+const CATCH_UP_GAP: Duration = Duration::from_secs(5);
+/// Time between two purges of the removed alarms.
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+/// Time during which a removed alarm can be restored, before being purged.
+const REMOVED_ALARMS_RETENTION: Duration = Duration::from_secs(86400);
+
+/// Numbering of the published messages, one sequence per topic (the daemon has a single
+/// publishing socket).
+static SEQUENCER: Sequencer = Sequencer::new();
+
+/// Publishes a message as a multipart (topic, payload, send timestamp, sequence number) message,
+/// using `buffer` to encode binary payloads.
+fn publish(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    message: &Message,
+    buffer: &mut [u8],
+) -> Result<(), ClockError> {
+    let payload;
+    let frame = match topics.format() {
+        WireFormat::Binary => match message.encode_payload_into(buffer) {
+            Ok(len) => &buffer[..len],
+            // Alarms and batches too large for the buffer, the only messages of unbounded size
+            Err(_) => {
+                payload = message.payload()?;
+                payload.as_slice()
+            }
+        },
+        WireFormat::Json => {
+            payload = message.to_json()?.into_bytes();
+            payload.as_slice()
+        }
+        WireFormat::MessagePack => {
+            payload = message.to_msgpack()?;
+            payload.as_slice()
+        }
+    };
+
+    socket.send(topics.of(message), zmq::SNDMORE)?;
+    socket.send(frame, zmq::SNDMORE)?;
+    socket.send(
+        &latency::encode_timestamp(latency::stamp_ms()?)[..],
+        zmq::SNDMORE,
+    )?;
+    socket.send(&sequence::encode_sequence(SEQUENCER.next(message))[..], 0)?;
+
+    Ok(())
+}
+
+/// Tick function. Checks alarms and generates the clock signal, returning the time of the tick
+/// and the alarms rung. (see libclockrobustus documentation for more explanations)
+fn tick(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    conn: &sqlite::Connection,
+    constants: &Constants,
+    one_offs: Vec<Alarm>,
+    previous: DateTime<Local>,
+    triggers: &mut Triggers,
+) -> Result<(DateTime<Local>, Vec<Alarm>), ClockError> {
     // Fetching alarms
     let alarms = Alarm::all(conn)?;
+    triggers.retain(&alarms);
 
-    // Triggering relevant alarms
-    for alarm in alarms {
-        if alarm.must_ring()? {
-            let msg = zmq::Message::from(Message::from(alarm).as_bytes());
+    // Reused for every message, only alarms too large for it are allocated (see publish)
+    let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+
+    let now = Local::now();
+    let tick_duration = chrono::Duration::milliseconds(constants.tick_duration() as i64);
+    let gap = Duration::from_millis(constants.tick_duration() * 2).max(CATCH_UP_GAP);
+
+    // Do-not-disturb windows hold the alarms back, the occurrences being recorded as silenced
+    let quiet = QuietWindow::is_quiet(conn, now.naive_local())?;
+
+    // Alarms due since the previous tick ring now, whatever the tick duration. Woken up (eg:
+    // after a suspend), the alarms due long before are caught up as missed.
+    let mut rung = Vec::new();
+    let since = if (now - previous).to_std().unwrap_or_default() > gap {
+        rung = catch_up(
+            socket,
+            topics,
+            conn,
+            &alarms,
+            previous,
+            now - tick_duration,
+            triggers,
+            &mut buffer,
+        )?;
+        now - tick_duration
+    } else {
+        previous
+    };
+
+    // Triggering relevant alarms (at their jittered time)
+    let due = due_occurrences(conn, &alarms, since, now, triggers)?;
+    let mut ringing = Vec::new();
+    if !quiet {
+        ringing.extend(due.iter().map(|(alarm, ..)| (*alarm).clone()));
+    }
 
-            socket.send(msg, 0)?;
+    // One-off alarms (previews, snoozed alarms, timer) ring like the real thing, but are not part
+    // of the history. Snooze announcements are published the same way.
+    ringing.extend(one_offs);
+
+    // Alarms ringing in the same tick go out as a single batch
+    match ringing.len() {
+        0 => (),
+        1 => publish(
+            socket,
+            topics,
+            &Message::from(ringing.remove(0)),
+            &mut buffer,
+        )?,
+        _ => publish(socket, topics, &Message::AlarmBatch(ringing), &mut buffer)?,
+    }
+
+    // Accounted for once published, the next tick retrying the occurrences that could not be
+    for (alarm, date, ring_time) in due {
+        let Some(alarm_id) = alarm.id else {
+            continue;
+        };
+        if let Some(ring_time) = ring_time {
+            triggers.commit(alarm_id, ring_time);
+        }
+
+        let outcome = if quiet {
+            RingOutcome::Silenced
+        } else {
+            rung.push(alarm.clone());
+            RingOutcome::Rang
+        };
+        RingEvent::now(alarm_id, outcome)
+            .with_offset(alarm.jitter(date).num_seconds())
+            .save(conn)?;
+        // Rung once and for all
+        if alarm.date.is_some() {
+            Alarm::set_enabled_by_ids(conn, &[alarm_id], false)?;
         }
     }
 
     // Sending clockmessage.
-    let clock_message = zmq::Message::from(Message::from(ClockMessage::default()).as_bytes());
-    socket.send(clock_message, 0)?;
+    let clock_message =
+        ClockMessage::now_in(constants.timezone()).with_theme_hours(constants.theme_hours());
+    #[cfg(feature = "calendars")]
+    let clock_message = clock_message.with_calendars(constants.calendars())?;
+    publish(socket, topics, &Message::from(clock_message), &mut buffer)?;
+
+    // Then the time of every other zone the user follows
+    for world_clock in WorldClock::all(conn)? {
+        let message = world_clock.message(constants.theme_hours());
+        publish(socket, topics, &Message::from(message), &mut buffer)?;
+    }
+
+    Ok((now, rung))
+}
+
+/// Occurrences of the `alarms` due after `since` and until `now` (see [Alarm::due_between]) with
+/// the instant they ring at, the skipped ones and the ones already triggered excluded.
+fn due_occurrences<'a>(
+    conn: &sqlite::Connection,
+    alarms: &'a [Alarm],
+    since: DateTime<Local>,
+    now: DateTime<Local>,
+    triggers: &Triggers,
+) -> Result<Vec<(&'a Alarm, NaiveDate, Option<DateTime<Local>>)>, ClockError> {
+    let mut due = Vec::new();
+
+    for alarm in alarms {
+        for date in alarm.due_between(since, now)? {
+            // Silenced single occurrence (eg: public holiday)
+            if alarm.is_skipped(conn, date)? {
+                continue;
+            }
+            // Guards against the clock going back, the same occurrence being due again
+            let ring_time = ring_time(alarm, date);
+            if let (Some(alarm_id), Some(ring_time)) = (alarm.id, ring_time) {
+                if !triggers.is_due(alarm_id, ring_time) {
+                    continue;
+                }
+            }
+
+            due.push((alarm, date, ring_time));
+        }
+    }
+
+    Ok(due)
+}
+
+/// Instant the occurrence of `alarm` scheduled on `date` rings at (see [Alarm::ring_time]), the
+/// end of the gap for an occurrence skipped by a DST change. A ring time that cannot be told is
+/// reported rather than failing the tick: failed ticks are retried on the same window, and would
+/// fail again and again.
+fn ring_time(alarm: &Alarm, date: NaiveDate) -> Option<DateTime<Local>> {
+    match alarm.ring_time(date) {
+        Ok(ring_time) => ring_time,
+        Err(error) => {
+            println!(
+                "Could not tell when alarm {} rings on {} : {:?}",
+                alarm, date, error
+            );
+            None
+        }
+    }
+}
+
+/// Publishes the alarms that were due between the `previous` tick and `until`, when the daemon
+/// could not tick in between (eg: suspended machine). Every missed occurrence is recorded in the
+/// history, as silenced if it fell in a [QuietWindow]. Only the latest occurrence of each alarm
+/// outside of quiet windows is published, flagged as missed. Occurrences are accounted for in
+/// `triggers` once recorded, a tick failing afterwards catches up the same window again without
+/// recording or publishing them twice. Returns the alarms published.
+#[allow(clippy::too_many_arguments)]
+fn catch_up(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    conn: &sqlite::Connection,
+    alarms: &[Alarm],
+    previous: DateTime<Local>,
+    until: DateTime<Local>,
+    triggers: &mut Triggers,
+    buffer: &mut [u8],
+) -> Result<Vec<Alarm>, ClockError> {
+    let mut published = Vec::new();
+
+    for alarm in alarms {
+        let Some(alarm_id) = alarm.id else {
+            continue;
+        };
+        let mut missed = Vec::new();
+
+        for date in alarm.due_between(previous, until)? {
+            if alarm.is_skipped(conn, date)? {
+                continue;
+            }
+            match ring_time(alarm, date) {
+                Some(ring_time) if triggers.is_pending(alarm_id, ring_time) => {
+                    // Held back by the quiet window it fell in, not by the one woken up in
+                    let quiet = QuietWindow::is_quiet(conn, ring_time.naive_local())?;
+                    missed.push((date, ring_time, quiet))
+                }
+                _ => (),
+            }
+        }
+        missed.sort_by_key(|(_, ring_time, _)| *ring_time);
+
+        if missed.is_empty() {
+            continue;
+        }
+        let latest = missed.iter().rev().find(|(.., quiet)| !quiet);
+        println!(
+            "Alarm #{} ({}) missed {} time(s), {}",
+            alarm_id,
+            alarm,
+            missed.len(),
+            match latest {
+                Some((_, missed_at, _)) => format!(
+                    "published late for {}",
+                    missed_at.format("%Y-%m-%d %H:%M:%S")
+                ),
+                None => "silenced".to_string(),
+            }
+        );
+        if let Some((_, missed_at, _)) = latest {
+            let late = alarm.clone().missed(missed_at.timestamp());
+            publish(socket, topics, &Message::from(late.clone()), buffer)?;
+            published.push(late);
+        }
+
+        for (date, ring_time, quiet) in missed {
+            let outcome = if quiet {
+                RingOutcome::Silenced
+            } else {
+                RingOutcome::Missed
+            };
+            RingEvent {
+                timestamp: ring_time.timestamp(),
+                ..RingEvent::now(alarm_id, outcome)
+            }
+            .with_offset(alarm.jitter(date).num_seconds())
+            .save(conn)?;
+            triggers.commit(alarm_id, ring_time);
+        }
+        // Rung (late) once and for all
+        if alarm.date.is_some() {
+            Alarm::set_enabled_by_ids(conn, &[alarm_id], false)?;
+        }
+    }
+
+    Ok(published)
+}
+
+/// Publishes the time left for every countdown.
+fn publish_countdowns(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    conn: &sqlite::Connection,
+) -> Result<(), ClockError> {
+    let now = Local::now().timestamp();
+    let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+
+    for countdown in Countdown::all(conn)? {
+        publish(
+            socket,
+            topics,
+            &Message::from(countdown.remaining(now)),
+            &mut buffer,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Publishes the status of the daemon, so that clients can tell whether it still runs.
+fn publish_heartbeat(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    conn: &sqlite::Connection,
+    state: &DaemonState,
+) -> Result<(), ClockError> {
+    let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+    let message = Message::from(state.heartbeat(conn)?);
+
+    publish(socket, topics, &message, &mut buffer)
+}
+
+/// Publishes the interval timer reminders due after `after` and up to `until` (Unix timestamps,
+/// seconds), unless a do-not-disturb window holds them back.
+fn publish_intervals(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    conn: &sqlite::Connection,
+    after: i64,
+    until: i64,
+) -> Result<(), ClockError> {
+    if QuietWindow::is_quiet(conn, Local::now().naive_local())? {
+        return Ok(());
+    }
+
+    let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+
+    for timer in IntervalTimer::all(conn)? {
+        if let Some(due_at) = timer.due_between(after, until) {
+            publish(
+                socket,
+                topics,
+                &Message::from(timer.message(due_at)),
+                &mut buffer,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Publishes the bedtime reminders due after `after` and up to `until`, unless a do-not-disturb
+/// window holds them back.
+fn publish_bedtimes(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    conn: &sqlite::Connection,
+    after: DateTime<Local>,
+    until: DateTime<Local>,
+) -> Result<(), ClockError> {
+    if QuietWindow::is_quiet(conn, until.naive_local())? {
+        return Ok(());
+    }
+
+    let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+
+    for bedtime in Bedtime::all(conn)? {
+        if let Some(at) = bedtime.due_between(after, until) {
+            publish(
+                socket,
+                topics,
+                &Message::from(bedtime.message(at)),
+                &mut buffer,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Publishes the reminders due and not published yet. During a do-not-disturb window they are
+/// kept for later.
+fn publish_reminders(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    conn: &sqlite::Connection,
+) -> Result<(), ClockError> {
+    let now = Local::now();
+
+    if QuietWindow::is_quiet(conn, now.naive_local())? {
+        return Ok(());
+    }
+
+    let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+
+    for reminder in Reminder::take_due(conn, now.timestamp())? {
+        publish(
+            socket,
+            topics,
+            &Message::from(reminder.message()),
+            &mut buffer,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Publishes the pomodoro phase when it changed.
+fn publish_pomodoro(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    conn: &sqlite::Connection,
+    state: &mut DaemonState,
+) -> Result<(), ClockError> {
+    if let Some(message) = state.take_pomodoro_change(conn, latency::now_ms()?)? {
+        let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+
+        publish(socket, topics, &Message::from(message), &mut buffer)?;
+    }
+
+    Ok(())
+}
+
+/// Publishes the sunrise and sunset of the day, once a day (the `last` publication being on
+/// another day).
+fn publish_sun(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    location: &Location,
+    last: &mut Option<NaiveDate>,
+) -> Result<(), ClockError> {
+    let now = Local::now();
+
+    if *last != Some(now.date_naive()) {
+        let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+
+        let message = Message::from(location.sun(now));
+
+        publish(socket, topics, &message, &mut buffer)?;
+        *last = Some(now.date_naive());
+    }
+
+    Ok(())
+}
+
+/// Announces the time whenever a period of the speaking clock starts (not when the daemon
+/// starts), `last_slot` being the period of the previous call.
+fn publish_speak(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    schedule: SpeakSchedule,
+    zone: &Zone,
+    last_slot: &mut Option<u32>,
+) -> Result<(), ClockError> {
+    let time = zone.wall_time(Utc::now()).time();
+    let slot = schedule.slot(time);
+
+    if matches!(last_slot.replace(slot), Some(last) if last != slot) {
+        let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+        let message = Message::from(schedule.announce(time));
+
+        publish(socket, topics, &message, &mut buffer)?;
+    }
+
+    Ok(())
+}
+
+/// Queries the NTP server in the background every [NTP_CHECK_INTERVAL] (a query may take a
+/// while), the statuses being sent to the tick loop, which owns the publishing socket.
+fn spawn_ntp_client(server: &str, supervisor: &Supervisor) -> Receiver<SyncStatusMessage> {
+    let (sender, receiver) = mpsc::channel();
+    let server = server.to_string();
+    let mut status = SyncStatusMessage::default();
+
+    supervisor.spawn("NTP client", move || loop {
+        status = status.next(NtpSample::query(&server), latency::now_ms()? as i64);
+        if !status.reachable {
+            println!("NTP server {} unreachable", server);
+        }
+        // The tick loop is gone
+        if sender.send(status).is_err() {
+            return Ok(());
+        }
+
+        thread::sleep(NTP_CHECK_INTERVAL);
+    });
+
+    receiver
+}
+
+/// Publishes the synchronization statuses received from the NTP client since the last call.
+fn publish_sync_statuses(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    statuses: &Receiver<SyncStatusMessage>,
+) -> Result<(), ClockError> {
+    let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+
+    for status in statuses.try_iter() {
+        publish(socket, topics, &Message::from(status), &mut buffer)?;
+    }
+
+    Ok(())
+}
+
+/// Publishes a change of the system clock, if there has been one since the last check. Returns
+/// whether the clock jumped.
+fn publish_clock_jump(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    detector: &mut JumpDetector,
+) -> Result<bool, ClockError> {
+    match detector.check(Instant::now(), latency::now_ms()? as i64) {
+        Some(jump) => {
+            let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+
+            println!("System clock jumped by {} ms", jump.jump_ms);
+            publish(socket, topics, &Message::from(jump), &mut buffer)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Publishes the next alarm when it (or the time left before it) changed since the `last`
+/// publication.
+fn publish_next_alarm(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    conn: &sqlite::Connection,
+    last: &mut Option<NextAlarmMessage>,
+) -> Result<(), ClockError> {
+    let next = NextAlarmMessage::find(conn, Local::now())?;
+
+    if *last != Some(next) {
+        let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+
+        publish(socket, topics, &Message::from(next), &mut buffer)?;
+        *last = Some(next);
+    }
+
+    Ok(())
+}
+
+/// Dry-run mode: prints the upcoming alarm occurrences without starting the server.
+fn list_next(count: usize) -> Result<(), ClockError> {
+    let conn = sqlite::Connection::open(check_database_directory()?)?;
+    let upcoming = Alarm::next_n(&conn, count)?;
+
+    if upcoming.is_empty() {
+        println!("No upcoming alarm");
+    }
+
+    for (occurrence, alarm) in upcoming {
+        // Jittered alarms are listed at the time they will actually ring, the offset depending
+        // on the date in the alarm zone
+        let date = alarm
+            .timezone
+            .wall_time(occurrence.with_timezone(&Utc))
+            .date();
+        let offset = alarm.jitter(date);
+        println!(
+            "{}  alarm #{}{}{}{}",
+            (occurrence + offset).format("%a %Y-%m-%d %H:%M:%S"),
+            alarm.id.unwrap_or_default(),
+            match alarm.timezone {
+                Zone::Local => String::new(),
+                zone => format!(
+                    "  ({} {})",
+                    zone.wall_time((occurrence + offset).with_timezone(&Utc))
+                        .format("%H:%M:%S"),
+                    zone
+                ),
+            },
+            if alarm.jitter_minutes > 0 {
+                format!(
+                    "  ({:+}s, ±{} min jitter)",
+                    offset.num_seconds(),
+                    alarm.jitter_minutes
+                )
+            } else {
+                String::new()
+            },
+            if alarm.is_skipped(&conn, date)? {
+                "  (skipped)"
+            } else {
+                ""
+            },
+        );
+    }
+
+    Ok(())
+}
+
+/// Configuration check mode: reports every problem found in the environment, the database and
+/// the endpoints instead of stopping at the first one.
+fn check() -> Result<(), ClockError> {
+    let mut problems = Vec::new();
+
+    match ClockEnv::new() {
+        Ok(env) => {
+            let ctx = zmq::Context::new();
+            let endpoints = [
+                (zmq::PUB, env.queue().port()),
+                (zmq::REP, env.queue().control_port()),
+            ];
+
+            for (kind, port) in endpoints {
+                let endpoint = format!("tcp://{}:{}", env.queue().host(), port);
+                let bound = ctx.socket(kind).and_then(|socket| socket.bind(&endpoint));
+                if let Err(error) = bound {
+                    problems.push(format!("Cannot bind {}: {}", endpoint, error));
+                }
+            }
+        }
+        Err(error) => problems.push(format!("Invalid environment: {}", error)),
+    }
+
+    // Read-only: checking must neither create the database nor migrate its schema
+    let flags = sqlite::OpenFlags::new().set_read_only();
+    match database_path()
+        .and_then(|db_path| Ok(sqlite::Connection::open_with_flags(db_path, flags)?))
+    {
+        Ok(conn) => match Alarm::probe_schema(&conn) {
+            Ok(schema) if schema.is_empty() => match Alarm::all(&conn) {
+                Ok(alarms) => {
+                    let invalid = |hour: u8, minute: u8, seconds: u8| {
+                        hour > 23 || minute > 59 || seconds > 59
+                    };
+                    for alarm in alarms.iter().filter(|alarm| {
+                        invalid(alarm.hour, alarm.minute, alarm.seconds)
+                            || alarm
+                                .rotation
+                                .iter()
+                                .flat_map(|rotation| &rotation.weeks)
+                                .any(|week| {
+                                    week.is_some_and(|week| {
+                                        invalid(week.hour, week.minute, week.seconds)
+                                    })
+                                })
+                    }) {
+                        problems.push(format!(
+                            "Alarm #{} has an invalid time and will never ring",
+                            alarm.id.unwrap_or_default()
+                        ));
+                    }
+                }
+                Err(error) => problems.push(format!("Cannot read alarms: {}", error)),
+            },
+            Ok(schema) => problems.extend(
+                schema
+                    .into_iter()
+                    .map(|problem| format!("Invalid database schema: {}", problem)),
+            ),
+            Err(error) => problems.push(format!("Invalid database schema: {}", error)),
+        },
+        Err(error) => problems.push(format!("Database not accessible: {}", error)),
+    }
+
+    if problems.is_empty() {
+        println!("Configuration OK");
+        Ok(())
+    } else {
+        for problem in &problems {
+            println!("- {}", problem);
+        }
+        Err(ClockError("Configuration check failed"))
+    }
+}
+
+/// Exports the alarm history as CSV, to a file or to the standard output.
+fn export_history(path: Option<String>) -> Result<(), ClockError> {
+    let conn = sqlite::Connection::open(check_database_directory()?)?;
+    let csv = RingEvent::export_csv(&conn)?;
+
+    match path {
+        Some(path) => std::fs::write(path, csv)?,
+        None => print!("{}", csv),
+    }
+
+    Ok(())
+}
+
+/// Client mode: listens to the running daemon for `seconds` and prints the distribution of the
+/// end-to-end latencies.
+fn profile_latency(seconds: u64) -> Result<(), ClockError> {
+    let running = Arc::new(AtomicBool::new(true));
+    let rc = running.clone();
+    let histogram = Mutex::new(LatencyHistogram::default());
+
+    thread::spawn(move || {
+        thread::sleep(Duration::from_secs(seconds));
+        rc.store(false, Ordering::SeqCst);
+    });
+
+    println!("Listening for {} seconds...", seconds);
+    listen_timed(running, Arc::new(AtomicBool::new(true)), |_, sent_at| {
+        if let (Some(sent_at), Ok(mut histogram)) = (sent_at, histogram.lock()) {
+            // The clock is read right after reception, an error here is not worth stopping for
+            let _ = histogram.record_since(sent_at);
+        }
+    })?;
+
+    let histogram = histogram
+        .into_inner()
+        .map_err(|_| ClockError("Latency histogram lock poisoned"))?;
+
+    if histogram.count() == 0 {
+        println!("No timestamped message received, is the daemon running?");
+    } else {
+        println!("{}", histogram);
+    }
+
+    Ok(())
+}
+
+/// Imports the alarms of another app.
+fn import(source: ImportSource, path: &str) -> Result<(), ClockError> {
+    let conn = sqlite::Connection::open(check_database_directory()?)?;
+    let alarms = source.parse(&std::fs::read_to_string(path)?)?;
+
+    Alarm::save_all(&conn, &alarms)?;
+
+    println!(
+        "{} alarm(s) imported from {}",
+        alarms.len(),
+        source.as_str()
+    );
+
+    Ok(())
+}
+
+/// API tokens management (see [ApiToken]).
+fn manage_tokens(mode: Mode) -> Result<(), ClockError> {
+    let conn = sqlite::Connection::open(check_database_directory()?)?;
+
+    match mode {
+        Mode::MintToken(name, role) => {
+            let (token, secret) = ApiToken::issue(&conn, &name, role)?;
+            println!(
+                "Token #{} ({}, {:?}) issued, keep its secret now, it cannot be displayed again:",
+                token.id.unwrap_or_default(),
+                token.name,
+                token.role
+            );
+            println!("{}", secret);
+        }
+        Mode::RevokeToken(id) => {
+            ApiToken::revoke(&conn, id)?;
+            println!("Token #{} revoked", id);
+        }
+        _ => {
+            for token in ApiToken::all(&conn)? {
+                println!(
+                    "#{}  {}  {:?}  issued {}{}",
+                    token.id.unwrap_or_default(),
+                    token.name,
+                    token.role,
+                    Local
+                        .timestamp_opt(token.created_at, 0)
+                        .single()
+                        .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_default(),
+                    if token.revoked { "  (revoked)" } else { "" },
+                );
+            }
+        }
+    }
 
     Ok(())
 }
 
 fn main() -> Result<(), ClockError> {
+    match cli::parse(std::env::args().skip(1))? {
+        Mode::Serve => serve(),
+        Mode::ListNext(count) => list_next(count),
+        Mode::Check => check(),
+        Mode::ExportHistory(path) => export_history(path),
+        mode @ (Mode::MintToken(..) | Mode::RevokeToken(_) | Mode::ListTokens) => {
+            manage_tokens(mode)
+        }
+        Mode::Import(source, path) => import(source, &path),
+        Mode::ProfileLatency(seconds) => profile_latency(seconds),
+        Mode::Help => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+    }
+}
+
+/// Server mode, publishes clock and alarm messages until interrupted.
+fn serve() -> Result<(), ClockError> {
     // Initializations (using an arc to concurrently tell the main loop to break if Ctlr+C is
     // pressed)
     let running = Arc::new(AtomicBool::new(true));
@@ -41,13 +863,74 @@ fn main() -> Result<(), ClockError> {
     let env = ClockEnv::new()?;
     let zmq_context = zmq::Context::new();
     let socket = zmq_context.socket(zmq::PUB)?;
-    let conn = sqlite::Connection::open(db_path)?;
+    let control_socket = zmq_context.socket(zmq::REP)?;
+    let conn = sqlite::Connection::open(&db_path)?;
+    let tick_duration = Duration::from_millis(env.constants().tick_duration());
+    let topics = env.queue().topics();
+    let supervisor = Supervisor::new(running.clone());
+    let mut state = DaemonState::new(supervisor.clone(), &conn)?;
+    let mut limiter = RateLimiter::new(env.constants().rate_limit());
+    let backup_directory = match env.backup().directory() {
+        Some(directory) => PathBuf::from(directory),
+        None => Path::new(&db_path)
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join("backups"),
+    };
+    let mut next_backup_check = Instant::now();
+    let mut next_countdowns = Instant::now();
+    let mut next_heartbeat = Instant::now();
+    let mut next_purge = Instant::now();
+    let mut last_intervals = Local::now().timestamp();
+    let mut last_bedtimes = Local::now();
+    let mut last_next_alarm = None;
+    let mut last_sun = None;
+    let mut last_speak_slot = None;
+    let mut jumps = JumpDetector::default();
+    let mut failed_ticks = 0;
+    let mut last_tick = Local::now();
+    let mut triggers = Triggers::default();
 
     socket.bind(&format!(
         "tcp://{}:{}",
         env.queue().host(),
         env.queue().port(),
     ))?;
+    // Oversized requests are dropped by zeromq itself, before being buffered
+    control_socket.set_maxmsgsize(control::MAX_REQUEST_BYTES)?;
+    control_socket.bind(&format!(
+        "tcp://{}:{}",
+        env.queue().host(),
+        env.queue().control_port(),
+    ))?;
+
+    match env.queue().grpc_port() {
+        #[cfg(feature = "grpc")]
+        Some(port) => grpc::spawn(env.queue().host(), port, &db_path, &supervisor)?,
+        #[cfg(not(feature = "grpc"))]
+        Some(_) => println!("A gRPC port is set but this daemon was built without gRPC support"),
+        None => (),
+    }
+
+    #[cfg(not(feature = "calendars"))]
+    if !env.constants().calendars().is_empty() {
+        println!("Calendars are set but this daemon was built without calendars support");
+    }
+
+    let sync_statuses = env
+        .constants()
+        .ntp_server()
+        .map(|server| spawn_ntp_client(server, &supervisor));
+
+    if let Some(port) = env.queue().http_port() {
+        http::spawn(
+            env.queue().host(),
+            port,
+            &db_path,
+            env.constants().rate_limit(),
+            &supervisor,
+        )?;
+    }
 
     ctrlc::set_handler(move || {
         println!("Interrupt, gracefully shutting down the service");
@@ -60,15 +943,339 @@ fn main() -> Result<(), ClockError> {
             break;
         }
 
-        if let Err(error) = tick(&socket, &conn) {
-            println!("Encountered an error during tick : {:?}", error);
-            println!("Please check your configuration !");
-            println!("Still running");
+        let mut deadline = Instant::now() + tick_duration;
+
+        // Told before the clock messages following the jump
+        match publish_clock_jump(&socket, &topics, &mut jumps) {
+            // What is published once in a while is published again with the new time
+            Ok(true) => {
+                next_countdowns = Instant::now();
+                last_sun = None;
+            }
+            Ok(false) => (),
+            Err(error) => println!("Encountered an error publishing a clock jump : {:?}", error),
+        }
+
+        if let Some(schedule) = env.backup().schedule() {
+            if Instant::now() >= next_backup_check {
+                next_backup_check = Instant::now() + BACKUP_CHECK_INTERVAL;
+                match backup::run_if_due(&conn, &backup_directory, schedule, env.backup().keep()) {
+                    Ok(Some(path)) => println!("Database backed up to {}", path.display()),
+                    Ok(None) => (),
+                    Err(error) => println!("Encountered an error during backup : {:?}", error),
+                }
+            }
+        }
+
+        if Instant::now() >= next_purge {
+            next_purge = Instant::now() + PURGE_INTERVAL;
+            let before = Local::now().timestamp() - REMOVED_ALARMS_RETENTION.as_secs() as i64;
+            match Alarm::purge(&conn, before) {
+                Ok(0) => (),
+                Ok(purged) => println!("Purged {} removed alarm(s)", purged),
+                Err(error) => println!("Encountered an error purging alarms : {:?}", error),
+            }
+        }
+
+        if Instant::now() >= next_heartbeat {
+            next_heartbeat = Instant::now() + Duration::from_secs(HEARTBEAT_INTERVAL_S);
+            if let Err(error) = publish_heartbeat(&socket, &topics, &conn, &state) {
+                println!(
+                    "Encountered an error publishing the heartbeat : {:?}",
+                    error
+                );
+            }
+        }
+
+        if Instant::now() >= next_countdowns {
+            next_countdowns = Instant::now() + COUNTDOWN_INTERVAL;
+            if let Err(error) = publish_countdowns(&socket, &topics, &conn) {
+                println!("Encountered an error publishing countdowns : {:?}", error);
+            }
+        }
+
+        let now = Local::now().timestamp();
+        if now > last_intervals {
+            if let Err(error) = publish_intervals(&socket, &topics, &conn, last_intervals, now) {
+                println!(
+                    "Encountered an error publishing interval timers : {:?}",
+                    error
+                );
+            }
+            last_intervals = now;
+        }
+
+        let now = Local::now();
+        if now > last_bedtimes {
+            if let Err(error) = publish_bedtimes(&socket, &topics, &conn, last_bedtimes, now) {
+                println!("Encountered an error publishing bedtimes : {:?}", error);
+            }
+            last_bedtimes = now;
+        }
+
+        if let Err(error) = publish_reminders(&socket, &topics, &conn) {
+            println!("Encountered an error publishing reminders : {:?}", error);
+        }
+
+        if let Some(location) = env.constants().location() {
+            if let Err(error) = publish_sun(&socket, &topics, location, &mut last_sun) {
+                println!("Encountered an error publishing the sun : {:?}", error);
+            }
+        }
+
+        if let Some(schedule) = env.constants().speak() {
+            let zone = env.constants().timezone();
+            if let Err(error) =
+                publish_speak(&socket, &topics, schedule, zone, &mut last_speak_slot)
+            {
+                println!("Encountered an error announcing the time : {:?}", error);
+            }
+        }
+
+        if let Some(statuses) = &sync_statuses {
+            if let Err(error) = publish_sync_statuses(&socket, &topics, statuses) {
+                println!(
+                    "Encountered an error publishing the sync status : {:?}",
+                    error
+                );
+            }
+        }
+
+        if let Err(error) = publish_pomodoro(&socket, &topics, &conn, &mut state) {
+            println!("Encountered an error publishing the pomodoro : {:?}", error);
+        }
+
+        if let Err(error) = publish_next_alarm(&socket, &topics, &conn, &mut last_next_alarm) {
+            println!(
+                "Encountered an error publishing the next alarm : {:?}",
+                error
+            );
+        }
+
+        let quiet = match QuietWindow::is_quiet(&conn, Local::now().naive_local()) {
+            Ok(quiet) => quiet,
+            Err(error) => {
+                println!("Encountered an error checking quiet windows : {:?}", error);
+                false
+            }
+        };
+        let one_offs = match state.take_due_alarms(&conn, Instant::now(), quiet) {
+            Ok(one_offs) => one_offs,
+            Err(error) => {
+                println!("Encountered an error taking due alarms : {:?}", error);
+                Vec::new()
+            }
+        };
+
+        // A panicking tick (eg: on a corrupted alarm) is reported, the next one will try again.
+        // Repeated failures space the ticks out, fatal ones stop the daemon.
+        state.last_tick_ok = match supervisor.guard("tick", || {
+            tick(
+                &socket,
+                &topics,
+                &conn,
+                env.constants(),
+                one_offs,
+                last_tick,
+                &mut triggers,
+            )
+        }) {
+            Ok((now, rung)) => {
+                last_tick = now;
+                failed_ticks = 0;
+                state.published(Instant::now());
+                for alarm in rung {
+                    state.ring(alarm, Instant::now());
+                }
+                true
+            }
+            Err(error) => {
+                println!("Encountered an error during tick : {:?}", error);
+                // Previews, snoozed alarms and the timer ring on the next tick instead
+                state.requeue();
+                match error.category().retry_delay(tick_duration, failed_ticks) {
+                    Some(delay) => {
+                        println!("Please check your configuration !");
+                        println!("Still running, next tick in {:?}", delay);
+                        deadline = Instant::now() + delay;
+                        failed_ticks += 1;
+                    }
+                    None => {
+                        println!("Unrecoverable error, shutting down");
+                        return Err(error);
+                    }
+                }
+                false
+            }
+        };
+        match state.expire_ringing(&conn, Instant::now()) {
+            Ok(ignored) => {
+                for alarm_id in ignored {
+                    println!("Alarm #{} has not been acknowledged", alarm_id);
+                }
+            }
+            Err(error) => println!("Encountered an error logging ignored alarms : {:?}", error),
+        }
+        // Take a breath, answering control requests in the meantime
+        if let Err(error) =
+            control::serve_until(&control_socket, &conn, &mut state, &mut limiter, deadline)
+        {
+            println!("Encountered an error on the control channel : {:?}", error);
+        }
+        if state.shutdown_requested {
+            println!("Shutdown requested on the control channel");
+            break;
         }
-        // Take a breath
-        sleep(Duration::from_millis(env.constants().tick_duration()));
     }
 
     println!("zzzzZZZZZzzzzz");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use libclockrobustus::alarm::ActiveDays;
+
+    use super::*;
+
+    #[test]
+    fn test_quiet_catch_up() {
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PUB).unwrap();
+        let topics = Topics::default();
+        let conn = sqlite::open(":memory:").unwrap();
+        let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+        Alarm {
+            active_days: ActiveDays::EVERYDAY,
+            hour: 7,
+            ..Default::default()
+        }
+        .save(&conn)
+        .unwrap();
+        // Quiet on Wednesday mornings
+        QuietWindow {
+            days: ActiveDays(0x04),
+            start_hour: 6,
+            end_hour: 8,
+            ..Default::default()
+        }
+        .save(&conn)
+        .unwrap();
+        let alarms = Alarm::all(&conn).unwrap();
+        // From Monday 2023-07-03 to Thursday, woken up outside of the quiet window
+        let at = |day| {
+            Local
+                .from_local_datetime(
+                    &NaiveDate::from_ymd_opt(2023, 7, day)
+                        .unwrap()
+                        .and_hms_opt(0, 0, 0)
+                        .unwrap(),
+                )
+                .unwrap()
+        };
+
+        let published = catch_up(
+            &socket,
+            &topics,
+            &conn,
+            &alarms,
+            at(3),
+            at(6),
+            &mut Triggers::default(),
+            &mut buffer,
+        )
+        .unwrap();
+
+        // The latest occurrence outside of the quiet window is published
+        assert_eq!(published.len(), 1);
+        assert_eq!(
+            published[0].missed_at,
+            Some((at(4) + chrono::Duration::hours(7)).timestamp())
+        );
+        assert_eq!(
+            RingEvent::all(&conn)
+                .unwrap()
+                .iter()
+                .map(|event| event.outcome)
+                .collect::<Vec<_>>(),
+            vec![
+                RingOutcome::Missed,
+                RingOutcome::Missed,
+                RingOutcome::Silenced
+            ]
+        );
+    }
+
+    #[test]
+    fn test_catch_up_once() {
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PUB).unwrap();
+        let topics = Topics::default();
+        let conn = sqlite::open(":memory:").unwrap();
+        let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+        let mut triggers = Triggers::default();
+        Alarm {
+            active_days: ActiveDays::EVERYDAY,
+            hour: 7,
+            ..Default::default()
+        }
+        .save(&conn)
+        .unwrap();
+        let alarms = Alarm::all(&conn).unwrap();
+        let until = Local::now();
+        let previous = until - chrono::Duration::days(2);
+
+        let mut catch_up_again = || {
+            catch_up(
+                &socket,
+                &topics,
+                &conn,
+                &alarms,
+                previous,
+                until,
+                &mut triggers,
+                &mut buffer,
+            )
+            .unwrap()
+        };
+
+        // A failed tick catches up the same window again
+        assert_eq!(catch_up_again().len(), 1);
+        assert!(catch_up_again().is_empty());
+        assert_eq!(RingEvent::all(&conn).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_due_in_dst_gap() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let mut triggers = Triggers::default();
+        // Clocks went from 02:00 to 03:00 in Paris on Sunday 2023-03-26
+        let paris: Zone = "Europe/Paris".parse().unwrap();
+        Alarm {
+            active_days: ActiveDays(0x40),
+            hour: 2,
+            minute: 30,
+            timezone: paris,
+            ..Default::default()
+        }
+        .save(&conn)
+        .unwrap();
+        let alarms = Alarm::all(&conn).unwrap();
+        let sunday = NaiveDate::from_ymd_opt(2023, 3, 26).unwrap();
+        let gap_end = paris
+            .instant(sunday.and_hms_opt(3, 0, 0).unwrap())
+            .unwrap()
+            .with_timezone(&Local);
+        let tick = chrono::Duration::seconds(1);
+
+        let due = |triggers: &Triggers| {
+            due_occurrences(&conn, &alarms, gap_end - tick, gap_end, triggers).unwrap()
+        };
+
+        // Due in the tick the clocks go forward, until published
+        assert_eq!(due(&triggers), vec![(&alarms[0], sunday, Some(gap_end))]);
+        assert_eq!(due(&triggers).len(), 1);
+        triggers.commit(alarms[0].id.unwrap(), gap_end);
+        assert!(due(&triggers).is_empty());
+    }
+}
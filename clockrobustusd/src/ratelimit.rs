@@ -0,0 +1,59 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Clients idle for this long are forgotten (their bucket is full again anyway).
+const IDLE_EXPIRY: Duration = Duration::from_secs(60);
+
+struct Bucket {
+    tokens: f64,
+    last_seen: Instant,
+}
+
+/// Per-client token bucket: each client may send `per_second` requests per second on average,
+/// with bursts of up to twice that amount.
+pub struct RateLimiter {
+    per_second: f64,
+    burst: f64,
+    buckets: HashMap<String, Bucket>,
+    last_cleanup: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_second: u32) -> Self {
+        Self {
+            per_second: per_second as f64,
+            burst: per_second as f64 * 2.0,
+            buckets: HashMap::new(),
+            last_cleanup: Instant::now(),
+        }
+    }
+
+    /// Accounts for one request of `client`, returns false if it exceeds its rate.
+    pub fn allow(&mut self, client: &str) -> bool {
+        let now = Instant::now();
+
+        if now.duration_since(self.last_cleanup) > IDLE_EXPIRY {
+            self.buckets
+                .retain(|_, bucket| now.duration_since(bucket.last_seen) < IDLE_EXPIRY);
+            self.last_cleanup = now;
+        }
+
+        let bucket = self.buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: self.burst,
+            last_seen: now,
+        });
+        let elapsed = now.duration_since(bucket.last_seen).as_secs_f64();
+
+        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.burst);
+        bucket.last_seen = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use libclockrobustus::error::ClockError;
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Delay before restarting a failed task, doubled on each consecutive failure.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// Longest delay between two restarts.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Keeps the daemon tasks alive: panics are caught and logged instead of silently killing the
+/// tick loop or a background server, and crashed threads are restarted. The number of caught
+/// panics is reported in the daemon health (see [libclockrobustus::control::Health]).
+#[derive(Clone)]
+pub struct Supervisor {
+    running: Arc<AtomicBool>,
+    panics: Arc<AtomicU32>,
+}
+
+/// Best effort extraction of a panic message
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown cause"
+    }
+}
+
+impl Supervisor {
+    pub fn new(running: Arc<AtomicBool>) -> Self {
+        Self {
+            running,
+            panics: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    /// Flag cleared when the daemon stops.
+    pub fn running(&self) -> Arc<AtomicBool> {
+        self.running.clone()
+    }
+
+    /// Number of panics caught since the daemon started.
+    pub fn panics(&self) -> u32 {
+        self.panics.load(Ordering::SeqCst)
+    }
+
+    /// Runs `task` once, turning a panic into an error.
+    pub fn guard<T, F>(&self, name: &str, task: F) -> Result<T, ClockError>
+    where
+        F: FnOnce() -> Result<T, ClockError>,
+    {
+        panic::catch_unwind(AssertUnwindSafe(task)).unwrap_or_else(|payload| {
+            self.panics.fetch_add(1, Ordering::SeqCst);
+            println!(
+                "Recovered from a panic in {} : {}",
+                name,
+                panic_message(&*payload)
+            );
+            Err(ClockError("Task panicked"))
+        })
+    }
+
+    /// Runs `task` in a background thread, restarting it (with an increasing delay) whenever it
+    /// fails or panics, until it returns successfully or the daemon stops.
+    pub fn spawn<F>(&self, name: &'static str, mut task: F)
+    where
+        F: FnMut() -> Result<(), ClockError> + Send + 'static,
+    {
+        let supervisor = self.clone();
+
+        thread::spawn(move || {
+            let mut backoff = INITIAL_BACKOFF;
+
+            while supervisor.running.load(Ordering::SeqCst) {
+                match supervisor.guard(name, &mut task) {
+                    Ok(()) => break,
+                    Err(error) => println!(
+                        "{} stopped : {:?}, restarting in {:?}",
+                        name, error, backoff
+                    ),
+                }
+
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn test_guard() {
+        let supervisor = Supervisor::new(Arc::new(AtomicBool::new(true)));
+
+        assert_eq!(supervisor.guard("answer", || Ok(42)).unwrap(), 42);
+        assert!(supervisor
+            .guard("failing", || -> Result<(), ClockError> {
+                Err(ClockError("Failed"))
+            })
+            .is_err());
+        assert_eq!(supervisor.panics(), 0);
+
+        assert!(supervisor
+            .guard("panicking", || -> Result<(), ClockError> {
+                panic!("Invalid alarm")
+            })
+            .is_err());
+        assert_eq!(supervisor.panics(), 1);
+    }
+
+    #[test]
+    fn test_spawn_restarts() {
+        let supervisor = Supervisor::new(Arc::new(AtomicBool::new(true)));
+        let (sender, receiver) = mpsc::channel();
+        let mut attempts = 0;
+
+        supervisor.spawn("flaky", move || {
+            attempts += 1;
+            sender.send(attempts).unwrap();
+            match attempts {
+                1 => panic!("First attempt"),
+                2 => Err(ClockError("Second attempt")),
+                _ => Ok(()),
+            }
+        });
+
+        for attempt in 1..=3 {
+            assert_eq!(
+                receiver.recv_timeout(Duration::from_secs(5)).unwrap(),
+                attempt
+            );
+        }
+        // Not restarted once successful
+        assert!(receiver.recv_timeout(Duration::from_secs(1)).is_err());
+        assert_eq!(supervisor.panics(), 1);
+    }
+}
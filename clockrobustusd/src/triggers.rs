@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local};
+use libclockrobustus::alarm::Alarm;
+
+/// Occurrences already triggered, so that each one fires exactly once whatever the tick rate,
+/// even if the clock goes back (eg: time synchronisation) and an occurrence is due again.
+#[derive(Default)]
+pub struct Triggers {
+    // Last ring time triggered, per alarm id
+    fired: HashMap<i64, DateTime<Local>>,
+}
+
+impl Triggers {
+    /// Whether the occurrence of the alarm ringing at `ring_time` has not been triggered yet.
+    pub fn is_due(&self, alarm_id: i64, ring_time: DateTime<Local>) -> bool {
+        self.fired.get(&alarm_id) != Some(&ring_time)
+    }
+
+    /// Whether neither the occurrence of the alarm ringing at `ring_time` nor a later one has
+    /// been triggered yet, for occurrences accounted for in order (see [Triggers::commit]).
+    pub fn is_pending(&self, alarm_id: i64, ring_time: DateTime<Local>) -> bool {
+        self.fired
+            .get(&alarm_id)
+            .map_or(true, |fired| ring_time > *fired)
+    }
+
+    /// Accounts for the occurrence of the alarm ringing at `ring_time`, once published: an
+    /// occurrence that could not be published is still due.
+    pub fn commit(&mut self, alarm_id: i64, ring_time: DateTime<Local>) {
+        self.fired.insert(alarm_id, ring_time);
+    }
+
+    /// Forgets the alarms that are not part of `alarms` anymore.
+    pub fn retain(&mut self, alarms: &[Alarm]) {
+        self.fired
+            .retain(|id, _| alarms.iter().any(|alarm| alarm.id == Some(*id)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    #[test]
+    fn test_fire() {
+        let mut triggers = Triggers::default();
+        let ring_time = Local.with_ymd_and_hms(2023, 7, 3, 7, 0, 0).unwrap();
+
+        // Not published (yet)
+        assert!(triggers.is_due(1, ring_time));
+        assert!(triggers.is_due(1, ring_time));
+        triggers.commit(1, ring_time);
+        // Later ticks within the same second
+        assert!(!triggers.is_due(1, ring_time));
+        // Other alarm, next occurrence
+        assert!(triggers.is_due(2, ring_time));
+        assert!(triggers.is_due(1, ring_time + Duration::days(1)));
+    }
+
+    #[test]
+    fn test_pending() {
+        let mut triggers = Triggers::default();
+        let ring_time = Local.with_ymd_and_hms(2023, 7, 3, 7, 0, 0).unwrap();
+
+        assert!(triggers.is_pending(1, ring_time));
+        triggers.commit(1, ring_time);
+        // Caught up in order, the earlier occurrences are done with as well
+        assert!(!triggers.is_pending(1, ring_time));
+        assert!(!triggers.is_pending(1, ring_time - Duration::days(1)));
+        assert!(triggers.is_pending(1, ring_time + Duration::days(1)));
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut triggers = Triggers::default();
+        let ring_time = Local.with_ymd_and_hms(2023, 7, 3, 7, 0, 0).unwrap();
+        let alarm = |id| Alarm {
+            id: Some(id),
+            ..Default::default()
+        };
+
+        triggers.commit(1, ring_time);
+        triggers.commit(2, ring_time);
+        triggers.retain(&[alarm(2), Alarm::default()]);
+
+        // A removed then recreated id fires again
+        assert!(triggers.is_due(1, ring_time));
+        assert!(!triggers.is_due(2, ring_time));
+    }
+}
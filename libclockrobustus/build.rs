@@ -0,0 +1,6 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "protobuf")]
+    prost_build::compile_protos(&["proto/message.proto"], &["proto"])?;
+
+    Ok(())
+}
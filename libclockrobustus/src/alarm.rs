@@ -1,11 +1,22 @@
-use chrono::{Datelike, Duration, Local, NaiveTime, Weekday};
-use serde::{de::Visitor, ser::SerializeSeq, Deserialize, Serialize};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, Timelike, Utc, Weekday};
+use serde::{de::DeserializeOwned, de::Visitor, ser::SerializeSeq, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use sqlite::State;
+use std::{fmt::Display, str::FromStr};
 
-use crate::error::ClockError;
+use crate::{
+    add_missing_columns,
+    clock::Zone,
+    codec,
+    error::{ClockError, DecodeError},
+    in_transaction,
+    schedule::Recurrence,
+};
 /// Extremely small memory footprint way to represent days of the week where an alarm is active.  
 /// Serializes and Deserializes as an array of strings but uses a single byte to store data (not
 /// true in the database representation but true in program memory).
+/// The "Weekdays", "Weekend" and "Everyday" shorthands are understood, and emitted when the days
+/// match one of them exactly.
 ///
 /// # Examples
 ///
@@ -18,11 +29,21 @@ use crate::error::ClockError;
 /// assert_eq!(json, "[\"Monday\"]");
 /// let days: ActiveDays = serde_json::from_str("[\"Monday\", \"Tuesday\"]").unwrap();
 /// assert_eq!(days, ActiveDays(0x03));
+/// let days: ActiveDays = serde_json::from_str("[\"Weekend\", \"Monday\"]").unwrap();
+/// assert_eq!(days, ActiveDays(0x61));
+/// assert_eq!(serde_json::to_string(&ActiveDays(0x1F)).unwrap(), "[\"Weekdays\"]");
 /// ```
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
 pub struct ActiveDays(pub u8);
 
 impl ActiveDays {
+    /// Monday to Friday
+    pub const WEEKDAYS: Self = Self(0x1F);
+    /// Saturday and Sunday
+    pub const WEEKEND: Self = Self(0x60);
+    /// Every day of the week
+    pub const EVERYDAY: Self = Self(0x7F);
+
     /// Used for code factorisation
     fn as_vec<T: Clone>(&self, src_vec: Vec<T>) -> Vec<T> {
         let mut days_vec = Vec::new();
@@ -78,6 +99,27 @@ impl ActiveDays {
 
         self.as_vec(days_chrono)
     }
+
+    /// Same days, moved `days` later in the week (earlier if negative), wrapping around Sunday.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::ActiveDays;
+    ///
+    /// // Monday and Sunday
+    /// let days = ActiveDays(0x41);
+    ///
+    /// assert_eq!(days.shifted(1), ActiveDays(0x03));
+    /// assert_eq!(days.shifted(-1), ActiveDays(0x60));
+    /// assert_eq!(days.shifted(7), days);
+    /// ```
+    pub fn shifted(self, days: i64) -> Self {
+        let days = days.rem_euclid(7) as u32;
+        let week = self.0 & Self::EVERYDAY.0;
+
+        Self(((week << days) | (week >> (7 - days))) & Self::EVERYDAY.0)
+    }
 }
 
 impl Serialize for ActiveDays {
@@ -86,8 +128,13 @@ impl Serialize for ActiveDays {
         S: serde::Serializer,
     {
         // Serializing is easy, we have a method to transform this into a vector of strings, hence we're
-        // serializing it as a vector.
-        let vec = self.to_day_strings_vec();
+        // serializing it as a vector. Exact day groups are shortened.
+        let vec = match ActiveDays(self.0 & Self::EVERYDAY.0) {
+            Self::EVERYDAY => vec!["Everyday".to_string()],
+            Self::WEEKDAYS => vec!["Weekdays".to_string()],
+            Self::WEEKEND => vec!["Weekend".to_string()],
+            _ => self.to_day_strings_vec(),
+        };
         let mut seq = serializer.serialize_seq(Some(vec.len()))?;
         for e in vec {
             seq.serialize_element(&e)?;
@@ -118,10 +165,11 @@ impl<'de> Deserialize<'de> for ActiveDays {
                     if iteration > 7 {
                         break;
                     }
-                    let elt = seq.next_element::<&str>()?;
+                    // Owned, buffering deserializers (eg: tagged messages) cannot lend strings
+                    let elt = seq.next_element::<String>()?;
                     if let Some(e) = elt {
                         // Bitwise affectation. Each bit stands for a day (except the last one)
-                        value |= match e {
+                        value |= match e.as_str() {
                             "Monday" => 0x01,
                             "Tuesday" => 0x02,
                             "Wednesday" => 0x04,
@@ -129,6 +177,9 @@ impl<'de> Deserialize<'de> for ActiveDays {
                             "Friday" => 0x10,
                             "Saturday" => 0x20,
                             "Sunday" => 0x40,
+                            "Weekdays" => ActiveDays::WEEKDAYS.0,
+                            "Weekend" => ActiveDays::WEEKEND.0,
+                            "Everyday" => ActiveDays::EVERYDAY.0,
                             _ => 0x00,
                         };
                     } else {
@@ -152,7 +203,146 @@ impl<'de> Deserialize<'de> for ActiveDays {
     }
 }
 
+/// One week of a [Rotation].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationWeek {
+    pub hour: u8,
+    pub minute: u8,
+    pub seconds: u8,
+    /// Days the alarm rings on during this week, the alarm's own days if not set
+    #[serde(default)]
+    pub active_days: Option<ActiveDays>,
+}
+
+/// Multi-week pattern (eg: early, late and night shift weeks) replacing the time of an alarm.
+/// The pattern repeats after its last week.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use libclockrobustus::alarm::{Rotation, RotationWeek};
+///
+/// let early = RotationWeek { hour: 5, minute: 0, seconds: 0, active_days: None };
+/// let late = RotationWeek { hour: 13, minute: 0, seconds: 0, active_days: None };
+/// let rotation = Rotation {
+///     // A Wednesday, its week is the first one of the pattern
+///     start: NaiveDate::from_ymd_opt(2023, 7, 5).unwrap(),
+///     weeks: vec![Some(early), Some(late), None],
+/// };
+///
+/// assert_eq!(rotation.week(NaiveDate::from_ymd_opt(2023, 7, 3).unwrap()), Some(&early));
+/// assert_eq!(rotation.week(NaiveDate::from_ymd_opt(2023, 7, 10).unwrap()), Some(&late));
+/// assert_eq!(rotation.week(NaiveDate::from_ymd_opt(2023, 7, 17).unwrap()), None);
+/// assert_eq!(rotation.week(NaiveDate::from_ymd_opt(2023, 7, 24).unwrap()), Some(&early));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rotation {
+    /// Any day of the first week of the pattern
+    pub start: NaiveDate,
+    /// Weeks of the pattern, in order, [None] standing for a week off
+    pub weeks: Vec<Option<RotationWeek>>,
+}
+
+impl Rotation {
+    /// Week of the pattern `date` falls in (weeks start on Monday), [None] for a week off or an
+    /// empty pattern.
+    pub fn week(&self, date: NaiveDate) -> Option<&RotationWeek> {
+        if self.weeks.is_empty() {
+            return None;
+        }
+
+        let monday =
+            |date: NaiveDate| date - Duration::days(date.weekday().num_days_from_monday() as i64);
+        let index = ((monday(date) - monday(self.start)).num_days() / 7)
+            .rem_euclid(self.weeks.len() as i64);
+
+        self.weeks[index as usize].as_ref()
+    }
+}
+
 const TNAME: &str = "alarms";
+/// Dates single occurrences of alarms are skipped on
+const EXCEPTIONS_TNAME: &str = "alarm_exceptions";
+/// Free-form tags of alarms
+const TAGS_TNAME: &str = "alarm_tags";
+/// Columns added after the first release, appended to tables created by older versions.
+const LATE_COLUMNS: &[(&str, &str)] = &[
+    ("label", "TEXT NOT NULL DEFAULT ''"),
+    ("metadata", "TEXT NOT NULL DEFAULT '{}'"),
+    ("color", "TEXT"),
+    ("icon", "TEXT"),
+    ("updated_at", "INTEGER NOT NULL DEFAULT 0"),
+    ("jitter_minutes", "INTEGER NOT NULL DEFAULT 0"),
+    ("rotation", "TEXT"),
+    ("enabled", "INTEGER NOT NULL DEFAULT 1"),
+    ("group_name", "TEXT"),
+    ("snooze_minutes", "INTEGER NOT NULL DEFAULT 9"),
+    ("date", "TEXT"),
+    ("timezone", "TEXT"),
+    ("recurrence", "TEXT"),
+    ("valid_from", "TEXT"),
+    ("valid_until", "TEXT"),
+    ("ring_minutes", "INTEGER NOT NULL DEFAULT 10"),
+    ("max_snoozes", "INTEGER"),
+    ("deleted_at", "INTEGER"),
+];
+
+/// Message of the error returned by [Alarm::save_unique] when rejecting a duplicate
+pub const DUPLICATE_ALARM: &str = "An alarm already rings at this time on these days";
+
+/// What [Alarm::save_unique] does with an alarm ringing at the same time on the same days as a
+/// stored one.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicatePolicy {
+    /// Nothing is saved, a [DUPLICATE_ALARM] error is returned
+    #[default]
+    Reject,
+    /// The stored alarm takes the settings of the new one (metadata being merged)
+    Merge,
+}
+
+/// Orderings available when listing alarms.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::alarm::AlarmSort;
+///
+/// let sort: AlarmSort = serde_json::from_str("\"nextOccurrence\"").unwrap();
+///
+/// assert_eq!(sort, AlarmSort::NextOccurrence);
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AlarmSort {
+    /// Creation order
+    #[default]
+    Id,
+    /// Time of day, earliest first
+    Time,
+    /// Next ring time, soonest first (alarms that never ring come last)
+    NextOccurrence,
+    /// Label, alphabetically (case insensitive)
+    Label,
+    /// Last saved first
+    RecentlyEdited,
+}
+
+impl AlarmSort {
+    /// SQL ordering clause. Every ordering ends with the id so that it is stable.
+    fn order_by(&self) -> &'static str {
+        match self {
+            Self::Id | Self::NextOccurrence => "id",
+            Self::Time => "hour, minute, seconds, id",
+            Self::Label => "label COLLATE NOCASE, id",
+            Self::RecentlyEdited => "updated_at DESC, id DESC",
+        }
+    }
+}
 /// Serializable, deserializable, writable in database structure to hold all necesary information
 /// about alarms.
 ///
@@ -180,9 +370,10 @@ const TNAME: &str = "alarms";
 ///     hour: 12,
 ///     minute: 0,
 ///     seconds: 0,
+///     ..Default::default()
 /// });
 /// ```
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct Alarm {
     pub id: Option<i64>,
@@ -190,221 +381,2190 @@ pub struct Alarm {
     pub hour: u8,
     pub minute: u8,
     pub seconds: u8,
+    /// Free text displayed to the user
+    #[serde(default)]
+    pub label: String,
+    /// Arbitrary data attached by frontends or plugins (stored as a JSON object), see
+    /// [Alarm::metadata] and [Alarm::set_metadata] for typed access.
+    #[serde(default, with = "metadata_format")]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+    /// Display color, as a CSS hex string (eg: "#ff367c")
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Icon name, used by frontends to pick a matching picture
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Each occurrence rings up to this many minutes earlier or later, see [Alarm::jitter]
+    #[serde(default)]
+    pub jitter_minutes: u8,
+    /// Shift pattern overriding the time (and possibly the days) of the alarm week by week
+    #[serde(default)]
+    pub rotation: Option<Rotation>,
+    /// Disabled alarms are kept but never ring
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Name of the group the alarm belongs to, used to enable or disable alarms together
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Delay before a snoozed occurrence rings again
+    #[serde(default = "default_snooze_minutes")]
+    pub snooze_minutes: u8,
+    /// Time an occurrence keeps ringing (the daemon publishing it again every minute) until it
+    /// is dismissed or snoozed, after which it is recorded as ignored. 0 for no limit.
+    #[serde(default = "default_ring_minutes")]
+    pub ring_minutes: u8,
+    /// Number of times an occurrence may be snoozed, unlimited if [None]
+    #[serde(default)]
+    pub max_snoozes: Option<u8>,
+    /// Unix timestamp (seconds) a ringing occurrence has been snoozed until. Only set in the
+    /// alarm messages announcing a snooze (which must not ring), never stored.
+    #[serde(default)]
+    pub snoozed_until: Option<i64>,
+    /// Unix timestamp (seconds) an occurrence missed while the daemon was not running (eg:
+    /// suspended machine) was due at. Only set in the alarm messages published late, never
+    /// stored.
+    #[serde(default)]
+    pub missed_at: Option<i64>,
+    /// Date of a one-shot alarm, ringing on that day only (active days and rotation are
+    /// ignored). The daemon disables it once rung.
+    #[serde(default)]
+    pub date: Option<NaiveDate>,
+    /// Zone the alarm time is expressed in, so it rings at that wall clock time there whatever
+    /// the zone of the daemon host
+    #[serde(default)]
+    pub timezone: Zone,
+    /// Recurrence rule deciding the days the alarm rings on instead of the active days and
+    /// rotation (eg: first Monday of the month, every 2 days)
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    /// First day (in the alarm [Zone]) the alarm may ring on, eg: the start of a course
+    #[serde(default)]
+    pub valid_from: Option<NaiveDate>,
+    /// Last day (in the alarm [Zone], included) the alarm may ring on, eg: the end of a course
+    #[serde(default)]
+    pub valid_until: Option<NaiveDate>,
 }
 
-impl Alarm {
-    /// Returns true if alarm is set in timespan between it's own defined time and one second
-    /// later.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the current alarm cannot be converted to [chrono::NaiveTime].
-    pub fn must_ring(&self) -> Result<bool, ClockError> {
-        let local = Local::now();
-        let alarm_naive =
-            NaiveTime::from_hms_opt(self.hour as u32, self.minute as u32, self.seconds as u32)
-                .ok_or(ClockError("Could not create naive time for alarm"))?;
-        if self.active_days.to_weekdays().contains(&local.weekday()) {
-            let alarm_delta = local.time() - alarm_naive;
-            if local.time() >= alarm_naive && alarm_delta < Duration::seconds(1) {
-                Ok(true)
-            } else {
-                Ok(false)
+/// Metadata is free-form JSON: kept as is by human readable formats, carried as a JSON string by
+/// binary ones (see [crate::codec]), which cannot tell the types of free-form values.
+mod metadata_format {
+    use serde::{
+        de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    type Metadata = serde_json::Map<String, serde_json::Value>;
+
+    pub fn serialize<S: Serializer>(metadata: &Metadata, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            metadata.serialize(serializer)
+        } else {
+            serde_json::to_string(metadata)
+                .map_err(S::Error::custom)?
+                .serialize(serializer)
+        }
+    }
+
+    /// Either form, tagged messages (see [crate::message::Message]) being buffered as human
+    /// readable whatever their format
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Readable {
+        Map(Metadata),
+        Json(String),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Metadata, D::Error> {
+        if deserializer.is_human_readable() {
+            match Readable::deserialize(deserializer)? {
+                Readable::Map(metadata) => Ok(metadata),
+                Readable::Json(json) => serde_json::from_str(&json).map_err(D::Error::custom),
             }
         } else {
-            Ok(false)
+            serde_json::from_str(&String::deserialize(deserializer)?).map_err(D::Error::custom)
         }
     }
+}
 
-    // Essential db check
-    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
-        let query = "SELECT name FROM sqlite_master WHERE type='table' AND name = ?";
-        if conn.prepare(query)?.into_iter().bind((1, TNAME))?.count() == 0 {
-            let query = format!(
-                "CREATE TABLE {} (
-                id INTEGER PRIMARY KEY,
-                active_days INTEGER NOT NULL,
-                hour INTEGER NOT NULL,
-                minute INTEGER NOT NULL,
-                seconds INTEGER NOT NULL
-                )",
-                TNAME
-            );
-            conn.execute(query)?;
+fn enabled_by_default() -> bool {
+    true
+}
+
+fn default_snooze_minutes() -> u8 {
+    9
+}
+
+fn default_ring_minutes() -> u8 {
+    10
+}
+
+impl Default for Alarm {
+    fn default() -> Self {
+        Self {
+            id: None,
+            active_days: ActiveDays::default(),
+            hour: 0,
+            minute: 0,
+            seconds: 0,
+            label: String::new(),
+            metadata: serde_json::Map::new(),
+            color: None,
+            icon: None,
+            jitter_minutes: 0,
+            rotation: None,
+            enabled: enabled_by_default(),
+            group: None,
+            snooze_minutes: default_snooze_minutes(),
+            ring_minutes: default_ring_minutes(),
+            max_snoozes: None,
+            snoozed_until: None,
+            missed_at: None,
+            date: None,
+            timezone: Zone::Local,
+            recurrence: None,
+            valid_from: None,
+            valid_until: None,
         }
+    }
+}
 
-        Ok(())
+/// Checked way of building an [Alarm]: unlike a struct literal, [AlarmBuilder::build] refuses
+/// alarms which would be saved but never ring (invalid time, no day to ring on).
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::alarm::{ActiveDays, AlarmBuilder};
+///
+/// let alarm = AlarmBuilder::new()
+///     .time(7, 30, 0)
+///     .days(ActiveDays::WEEKDAYS)
+///     .label("Work")
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!((alarm.hour, alarm.minute), (7, 30));
+/// assert!(AlarmBuilder::new().time(99, 0, 0).days(ActiveDays::WEEKDAYS).build().is_err());
+/// // No day to ring on
+/// assert!(AlarmBuilder::new().time(7, 30, 0).build().is_err());
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct AlarmBuilder {
+    alarm: Alarm,
+}
+
+impl AlarmBuilder {
+    /// Enabled alarm ringing at midnight on no day
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    /// Saves the current clock using the given [sqlite::Connection]. Creates the table 'alarms' if
-    /// not present.
+    pub fn time(mut self, hour: u8, minute: u8, seconds: u8) -> Self {
+        self.alarm.hour = hour;
+        self.alarm.minute = minute;
+        self.alarm.seconds = seconds;
+        self
+    }
+
+    pub fn days(mut self, days: ActiveDays) -> Self {
+        self.alarm.active_days = days;
+        self
+    }
+
+    pub fn label(mut self, label: &str) -> Self {
+        self.alarm.label = label.to_string();
+        self
+    }
+
+    pub fn group(mut self, group: &str) -> Self {
+        self.alarm.group = Some(group.to_string());
+        self
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.alarm.enabled = enabled;
+        self
+    }
+
+    /// One-shot alarm ringing on `date` only, no active day needed
+    pub fn date(mut self, date: NaiveDate) -> Self {
+        self.alarm.date = Some(date);
+        self
+    }
+
+    /// Days decided by a rule instead of the active days
+    pub fn recurrence(mut self, recurrence: Recurrence) -> Self {
+        self.alarm.recurrence = Some(recurrence);
+        self
+    }
+
+    pub fn rotation(mut self, rotation: Rotation) -> Self {
+        self.alarm.rotation = Some(rotation);
+        self
+    }
+
+    pub fn timezone(mut self, timezone: Zone) -> Self {
+        self.alarm.timezone = timezone;
+        self
+    }
+
+    pub fn jitter_minutes(mut self, minutes: u8) -> Self {
+        self.alarm.jitter_minutes = minutes;
+        self
+    }
+
+    pub fn snooze_minutes(mut self, minutes: u8) -> Self {
+        self.alarm.snooze_minutes = minutes;
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the alarm is not valid (see [Alarm::validate])
+    pub fn build(self) -> Result<Alarm, ClockError> {
+        self.alarm.validate()?;
+
+        Ok(self.alarm)
+    }
+}
+
+impl Alarm {
+    /// Returns true if alarm is set in timespan between it's own defined time (shifted by its
+    /// [Alarm::jitter]) and one second later.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current alarm cannot be converted to [chrono::NaiveTime].
+    pub fn must_ring(&self) -> Result<bool, ClockError> {
+        Ok(self.due_occurrence(Local::now())?.is_some())
+    }
+
+    /// Scheduled date (in the alarm [Zone]) of the occurrence ringing at `now` (within one
+    /// second), if any. With a jitter, the occurrence may ring the day before or after its
+    /// scheduled date. Disabled alarms never ring.
     ///
     /// # Panics
     ///
-    /// Panics if an SQL error has been encountered
+    /// Panics if the current alarm cannot be converted to [chrono::NaiveTime].
     ///
     /// # Examples
     ///
     /// ```
+    /// use chrono::{Local, TimeZone};
     /// use libclockrobustus::alarm::{Alarm, ActiveDays};
     ///
-    /// let alarm = Alarm {
-    ///     id: None,
-    ///     active_days: ActiveDays(0x01),
-    ///     hour: 12,
-    ///     minute: 0,
-    ///     seconds: 0,
-    /// };
-    ///
-    /// let conn = sqlite::open(":memory:").unwrap();
+    /// let alarm = Alarm { active_days: ActiveDays(0x7F), hour: 7, ..Default::default() };
+    /// let now = Local.with_ymd_and_hms(2023, 7, 3, 7, 0, 0).unwrap();
     ///
-    /// assert!(alarm.save(&conn).is_ok());
+    /// assert_eq!(alarm.due_occurrence(now).unwrap(), Some(now.date_naive()));
     /// ```
-    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
-        Self::check_table(conn)?;
-        if let Some(eid) = self.id {
-            let query = format!(
-                "UPDATE {} SET
-                active_days = {},
-                hour = {},
-                minute = {},
-                seconds = {}
-                WHERE id = {}",
-                TNAME, self.active_days.0, self.hour, self.minute, self.seconds, eid,
-            );
+    pub fn due_occurrence(&self, now: DateTime<Local>) -> Result<Option<NaiveDate>, ClockError> {
+        if !self.enabled {
+            return Ok(None);
+        }
 
-            conn.execute(query)?;
+        let local = self.timezone.wall_time(now.with_timezone(&Utc));
+        let today = local.date();
+        let dates = if self.jitter_minutes == 0 {
+            vec![today]
         } else {
-            let query = format!(
-                "INSERT INTO {} (
-                    active_days,
-                    hour,
-                    minute,
-                    seconds
-                ) VALUES (
-                    {}, {}, {}, {}
-                )",
-                TNAME, self.active_days.0, self.hour, self.minute, self.seconds,
-            );
+            vec![today - Duration::days(1), today, today + Duration::days(1)]
+        };
 
-            conn.execute(query)?;
+        for date in dates {
+            if let Some(time) = self.time_on(date)? {
+                let ring_at = date.and_time(time) + self.jitter(date);
+                if local >= ring_at && local - ring_at < Duration::seconds(1) {
+                    return Ok(Some(date));
+                }
+            }
         }
-        Ok(())
+
+        Ok(None)
     }
 
-    /// Get all the alarms stored in database
+    /// Scheduled dates (in the alarm [Zone]) of the occurrences ringing after `from` and until
+    /// `to` (included), earliest first, eg: to catch up with the occurrences missed while the
+    /// machine was suspended. Disabled alarms never ring.
     ///
     /// # Panics
     ///
-    /// Panics if a SQL error is encountered
+    /// Panics if the current alarm cannot be converted to [chrono::NaiveTime].
     ///
     /// # Examples
     ///
     /// ```
+    /// use chrono::{Local, NaiveDate, TimeZone};
     /// use libclockrobustus::alarm::{Alarm, ActiveDays};
     ///
-    /// let alarm = Alarm {
-    ///     id: None,
-    ///     active_days: ActiveDays(0x01),
-    ///     hour: 12,
-    ///     minute: 0,
-    ///     seconds: 0,
-    /// };
-    ///
-    /// let conn = sqlite::open(":memory:").unwrap();
-    /// alarm.save(&conn).unwrap();
-    ///
-    /// let alarms = Alarm::all(&conn).unwrap();
+    /// let alarm = Alarm { active_days: ActiveDays(0x7F), hour: 7, ..Default::default() };
+    /// let from = Local.with_ymd_and_hms(2023, 7, 3, 6, 0, 0).unwrap();
+    /// let to = Local.with_ymd_and_hms(2023, 7, 4, 7, 0, 0).unwrap();
     ///
-    /// assert!(alarms.len() > 0);
+    /// assert_eq!(
+    ///     alarm.due_between(from, to).unwrap(),
+    ///     vec![NaiveDate::from_ymd_opt(2023, 7, 3).unwrap(), NaiveDate::from_ymd_opt(2023, 7, 4).unwrap()]
+    /// );
     /// ```
-    pub fn all(conn: &sqlite::Connection) -> Result<Vec<Self>, ClockError> {
-        Self::check_table(conn)?;
-        let query = format!("SELECT * FROM {}", TNAME);
+    pub fn due_between(
+        &self,
+        from: DateTime<Local>,
+        to: DateTime<Local>,
+    ) -> Result<Vec<NaiveDate>, ClockError> {
+        if !self.enabled || to <= from {
+            return Ok(Vec::new());
+        }
+
+        let from = self.timezone.wall_time(from.with_timezone(&Utc));
+        let to = self.timezone.wall_time(to.with_timezone(&Utc));
+        let mut date = from.date() - Duration::days(1);
         let mut res = Vec::new();
-        let mut statement = conn.prepare(query)?;
 
-        while let Ok(State::Row) = statement.next() {
-            res.push(Alarm {
-                id: Some(statement.read::<i64, _>("id")?),
-                active_days: ActiveDays(statement.read::<i64, _>("active_days")? as u8),
-                hour: statement.read::<i64, _>("hour")? as u8,
-                minute: statement.read::<i64, _>("minute")? as u8,
-                seconds: statement.read::<i64, _>("seconds")? as u8,
-            })
+        // A jitter may move an occurrence to the day before or after
+        while date <= to.date() + Duration::days(1) {
+            if let Some(time) = self.time_on(date)? {
+                let ring_at = date.and_time(time) + self.jitter(date);
+                if from < ring_at && ring_at <= to {
+                    res.push(date);
+                }
+            }
+            date += Duration::days(1);
         }
 
         Ok(res)
     }
 
-    /// Removes a saved alarm
+    /// Instant the occurrence scheduled on `date` (in the alarm [Zone]) rings at, its
+    /// [Alarm::jitter] applied, [None] if the alarm does not ring that day. Occurrences skipped by
+    /// a DST change ring at the end of the gap (see [Zone::instant_or_after]).
     ///
     /// # Panics
     ///
-    /// Panics if the represented alarm has no id (eg: not saved).
+    /// Panics if the current alarm cannot be converted to [chrono::NaiveTime].
+    pub fn ring_time(&self, date: NaiveDate) -> Result<Option<DateTime<Local>>, ClockError> {
+        match self.time_on(date)? {
+            Some(time) => Ok(Some(
+                self.timezone
+                    .instant_or_after(date.and_time(time) + self.jitter(date))?
+                    .with_timezone(&Local),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    // Time the alarm rings at on `date`, [None] if it does not ring that day (outside of the
+    // validity window, inactive day, rotation week off, other day than the one-shot date or not
+    // matching the recurrence rule)
+    fn time_on(&self, date: NaiveDate) -> Result<Option<NaiveTime>, ClockError> {
+        if self.valid_from.is_some_and(|from| date < from)
+            || self.valid_until.is_some_and(|until| date > until)
+        {
+            return Ok(None);
+        }
+
+        let rings_on = match (self.date, &self.recurrence) {
+            (Some(once), _) => Some(once == date),
+            (None, Some(recurrence)) => Some(recurrence.matches(date)),
+            (None, None) => None,
+        };
+
+        if let Some(rings_on) = rings_on {
+            if !rings_on {
+                return Ok(None);
+            }
+
+            return NaiveTime::from_hms_opt(
+                self.hour as u32,
+                self.minute as u32,
+                self.seconds as u32,
+            )
+            .map(Some)
+            .ok_or(ClockError("Could not create naive time for alarm"));
+        }
+
+        let (hour, minute, seconds, active_days) = match &self.rotation {
+            Some(rotation) => match rotation.week(date) {
+                Some(week) => (
+                    week.hour,
+                    week.minute,
+                    week.seconds,
+                    week.active_days.unwrap_or(self.active_days),
+                ),
+                None => return Ok(None),
+            },
+            None => (self.hour, self.minute, self.seconds, self.active_days),
+        };
+        let time = NaiveTime::from_hms_opt(hour as u32, minute as u32, seconds as u32)
+            .ok_or(ClockError("Could not create naive time for alarm"))?;
+
+        if active_days.to_weekdays().contains(&date.weekday()) {
+            Ok(Some(time))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Random offset (to the second, within ±[Alarm::jitter_minutes]) applied to the occurrence
+    /// scheduled on `date`. The offset is derived from the alarm id and the date, hence the
+    /// daemon and the upcoming list agree on it without storing anything.
     ///
     /// # Examples
     ///
     /// ```
-    /// use libclockrobustus::alarm::{Alarm, ActiveDays};
-    ///
-    /// let alarm = Alarm {
-    ///     id: None,
-    ///     active_days: ActiveDays(0x01),
-    ///     hour: 12,
-    ///     minute: 0,
-    ///     seconds: 0,
-    /// };
-    ///
-    /// let conn = sqlite::open(":memory:").unwrap();
-    /// alarm.save(&conn).unwrap();
-    ///
-    /// let alarms = Alarm::all(&conn).unwrap();
-    ///
-    /// assert!(alarms.len() > 0);
-    /// assert!(alarms[0].remove(&conn).is_ok());
+    /// use chrono::{Duration, NaiveDate};
+    /// use libclockrobustus::alarm::Alarm;
     ///
-    /// let alarm3 = Alarm {
-    ///     id: None,
-    ///     active_days: ActiveDays(0x02),
-    ///     hour: 12,
-    ///     minute: 13,
-    ///     seconds: 25,
-    /// };
+    /// let date = NaiveDate::from_ymd_opt(2023, 7, 3).unwrap();
+    /// let alarm = Alarm { id: Some(1), jitter_minutes: 10, ..Default::default() };
     ///
-    /// assert!(alarm3.remove(&conn).is_err());
+    /// assert!(alarm.jitter(date).num_seconds().abs() <= 600);
+    /// assert_eq!(alarm.jitter(date), alarm.clone().jitter(date));
+    /// assert_eq!(Alarm::default().jitter(date), Duration::zero());
     /// ```
-    pub fn remove(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
-        Self::check_table(conn)?;
+    pub fn jitter(&self, date: NaiveDate) -> Duration {
+        if self.jitter_minutes == 0 {
+            return Duration::zero();
+        }
 
-        let eid = self
-            .id
-            .ok_or(ClockError("Impossible to delete an unsaved alarm"))?;
-        let query = format!("DELETE FROM {} WHERE id = {}", TNAME, eid);
+        let span = self.jitter_minutes as i64 * 60;
+        let digest = Sha256::new()
+            .chain_update(self.id.unwrap_or_default().to_be_bytes())
+            .chain_update(date.num_days_from_ce().to_be_bytes())
+            .finalize();
+        let mut seed = [0u8; 8];
+        seed.copy_from_slice(&digest[..8]);
 
-        conn.execute(query)?;
-        Ok(())
+        Duration::seconds((u64::from_be_bytes(seed) % (2 * span as u64 + 1)) as i64 - span)
     }
 
-    /// Binary representation of the alarm (to be used in a queue).
+    /// Copy of the alarm, to be saved as a new one (the id is cleared).
     ///
     /// # Examples
     ///
     /// ```
-    /// use libclockrobustus::alarm::{Alarm, ActiveDays};
+    /// use libclockrobustus::alarm::Alarm;
     ///
-    /// let alarm = Alarm {
-    ///     id: None,
-    ///     active_days: ActiveDays(0x01),
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// Alarm { hour: 7, ..Default::default() }.save(&conn).unwrap();
+    ///
+    /// let alarm = &Alarm::all(&conn).unwrap()[0];
+    /// alarm.duplicate().shifted(30).save(&conn).unwrap();
+    ///
+    /// let alarms = Alarm::all(&conn).unwrap();
+    /// assert_eq!(alarms.len(), 2);
+    /// assert_eq!((alarms[1].hour, alarms[1].minute), (7, 30));
+    /// ```
+    pub fn duplicate(&self) -> Self {
+        Self {
+            id: None,
+            ..self.clone()
+        }
+    }
+
+    /// Same alarm, ringing `minutes` later (earlier if negative). Crossing midnight moves the
+    /// active days accordingly. A [Rotation] is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::{Alarm, ActiveDays};
+    ///
+    /// // Monday 23:30
+    /// let alarm = Alarm { active_days: ActiveDays(0x01), hour: 23, minute: 30, ..Default::default() };
+    /// let shifted = alarm.shifted(45);
+    ///
+    /// assert_eq!((shifted.hour, shifted.minute), (0, 15));
+    /// assert_eq!(shifted.active_days, ActiveDays(0x02));
+    /// ```
+    pub fn shifted(self, minutes: i64) -> Self {
+        let seconds =
+            self.hour as i64 * 3_600 + self.minute as i64 * 60 + self.seconds as i64 + minutes * 60;
+        let time = seconds.rem_euclid(86_400);
+
+        Self {
+            active_days: self.active_days.shifted(seconds.div_euclid(86_400)),
+            hour: (time / 3_600) as u8,
+            minute: (time % 3_600 / 60) as u8,
+            seconds: (time % 60) as u8,
+            ..self
+        }
+    }
+
+    /// Typed read access to a metadata entry. Returns [None] if the key is missing or if its value
+    /// does not match the requested type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::Alarm;
+    ///
+    /// let mut alarm = Alarm::default();
+    /// alarm.set_metadata("color", "#ff367c").unwrap();
+    /// alarm.set_metadata("volume", 80).unwrap();
+    ///
+    /// assert_eq!(alarm.metadata::<String>("color"), Some("#ff367c".to_string()));
+    /// assert_eq!(alarm.metadata::<u8>("volume"), Some(80));
+    /// assert_eq!(alarm.metadata::<u8>("color"), None);
+    /// assert_eq!(alarm.metadata::<String>("notes"), None);
+    /// ```
+    pub fn metadata<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.metadata
+            .get(key)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+
+    /// Sets (or replaces) a metadata entry.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value cannot be represented as JSON.
+    pub fn set_metadata<T: Serialize>(&mut self, key: &str, value: T) -> Result<(), ClockError> {
+        self.metadata
+            .insert(key.to_string(), serde_json::to_value(value)?);
+
+        Ok(())
+    }
+
+    /// Removes a metadata entry, returning its raw value if it was present.
+    pub fn remove_metadata(&mut self, key: &str) -> Option<serde_json::Value> {
+        self.metadata.remove(key)
+    }
+
+    /// Computes the first time strictly after `after` at which the alarm will ring, or [None] if
+    /// the alarm never rings (disabled, no active day or invalid time). The [Rotation], if any,
+    /// is applied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{Local, TimeZone, Timelike, Weekday, Datelike};
+    /// use libclockrobustus::alarm::{Alarm, ActiveDays};
+    ///
+    /// let alarm = Alarm {
+    ///     id: None,
+    ///     active_days: ActiveDays(0x01),
+    ///     hour: 7,
+    ///     minute: 30,
+    ///     seconds: 0,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// // 2023-07-03 was a Monday
+    /// let after = Local.with_ymd_and_hms(2023, 7, 3, 8, 0, 0).unwrap();
+    /// let next = alarm.next_occurrence(after).unwrap();
+    ///
+    /// assert_eq!(next.weekday(), Weekday::Mon);
+    /// assert_eq!(next.day(), 10);
+    /// assert_eq!((next.hour(), next.minute()), (7, 30));
+    /// ```
+    pub fn next_occurrence(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        if !self.enabled {
+            return None;
+        }
+
+        let occurrence = |date: NaiveDate| {
+            let time = self.time_on(date).ok()??;
+            let instant = self.timezone.instant_or_after(date.and_time(time)).ok()?;
+
+            Some(instant.with_timezone(&Local))
+        };
+
+        // A one-shot alarm may be scheduled long after the days scanned below
+        if let Some(date) = self.date {
+            return occurrence(date).filter(|occurrence| *occurrence > after);
+        }
+
+        // Nothing rings before the validity window, which may be far ahead
+        let start = self.timezone.wall_time(after.with_timezone(&Utc)).date();
+        let start = self.valid_from.map_or(start, |from| from.max(start));
+        if self.valid_until.is_some_and(|until| start > until) {
+            return None;
+        }
+
+        // Recurrence rules may skip months or years, their own scheduler finds the next day
+        if let Some(recurrence) = &self.recurrence {
+            let today = recurrence.next_date(start)?;
+
+            return match occurrence(today).filter(|occurrence| *occurrence > after) {
+                Some(occurrence) => Some(occurrence),
+                None => occurrence(recurrence.next_date(today + Duration::days(1))?),
+            };
+        }
+
+        // A weekly recurrence rings at most 7 days after the current day, one more day is
+        // checked to cover the case where today's occurrence is already past. A rotation only
+        // repeats after all of its weeks.
+        let days = 8 + self
+            .rotation
+            .as_ref()
+            .map_or(0, |rotation| 7 * rotation.weeks.len() as i64);
+
+        (0..days)
+            .map(|offset| start + Duration::days(offset))
+            .filter_map(occurrence)
+            .find(|occurrence| *occurrence > after)
+    }
+
+    /// Merges the next `count` occurrences of the given alarms, sorted chronologically.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::Local;
+    /// use libclockrobustus::alarm::{Alarm, ActiveDays};
+    ///
+    /// let alarms = vec![
+    ///     Alarm { id: Some(1), active_days: ActiveDays(0x7F), hour: 7, ..Default::default() },
+    ///     Alarm { id: Some(2), active_days: ActiveDays(0x01), hour: 9, ..Default::default() },
+    /// ];
+    ///
+    /// let upcoming = Alarm::upcoming(&alarms, Local::now(), 5);
+    ///
+    /// assert_eq!(upcoming.len(), 5);
+    /// assert!(upcoming.windows(2).all(|w| w[0].0 <= w[1].0));
+    /// ```
+    pub fn upcoming(
+        alarms: &[Self],
+        after: DateTime<Local>,
+        count: usize,
+    ) -> Vec<(DateTime<Local>, Self)> {
+        let mut res = Vec::new();
+
+        for alarm in alarms {
+            let mut cursor = after;
+            for _ in 0..count {
+                match alarm.next_occurrence(cursor) {
+                    Some(occurrence) => {
+                        res.push((occurrence, alarm.clone()));
+                        cursor = occurrence;
+                    }
+                    None => break,
+                }
+            }
+        }
+
+        res.sort_by_key(|(occurrence, _)| *occurrence);
+        res.truncate(count);
+        res
+    }
+
+    // Essential db check
+    pub(crate) fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = "SELECT name FROM sqlite_master WHERE type='table' AND name = ?";
+        if conn.prepare(query)?.into_iter().bind((1, TNAME))?.count() == 0 {
+            let query = format!(
+                "CREATE TABLE {} (
+                id INTEGER PRIMARY KEY,
+                active_days INTEGER NOT NULL,
+                hour INTEGER NOT NULL,
+                minute INTEGER NOT NULL,
+                seconds INTEGER NOT NULL
+                )",
+                TNAME
+            );
+            conn.execute(query)?;
+        }
+
+        add_missing_columns(conn, TNAME, LATE_COLUMNS)
+    }
+
+    // Binds every stored field (but the id) to its named parameter
+    fn bind_fields(&self, statement: &mut sqlite::Statement<'_>) -> Result<(), ClockError> {
+        statement.bind((":active_days", self.active_days.0 as i64))?;
+        statement.bind((":hour", self.hour as i64))?;
+        statement.bind((":minute", self.minute as i64))?;
+        statement.bind((":seconds", self.seconds as i64))?;
+        statement.bind((":label", self.label.as_str()))?;
+        statement.bind((":metadata", serde_json::to_string(&self.metadata)?.as_str()))?;
+        statement.bind((":color", self.color.as_deref()))?;
+        statement.bind((":icon", self.icon.as_deref()))?;
+        statement.bind((":jitter_minutes", self.jitter_minutes as i64))?;
+        let rotation = self
+            .rotation
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        statement.bind((":rotation", rotation.as_deref()))?;
+        statement.bind((":enabled", self.enabled as i64))?;
+        statement.bind((":group_name", self.group.as_deref()))?;
+        statement.bind((":snooze_minutes", self.snooze_minutes as i64))?;
+        statement.bind((":ring_minutes", self.ring_minutes as i64))?;
+        statement.bind((":max_snoozes", self.max_snoozes.map(|max| max as i64)))?;
+        let date = self.date.map(|date| date.to_string());
+        statement.bind((":date", date.as_deref()))?;
+        let timezone = match self.timezone {
+            Zone::Local => None,
+            zone => Some(zone.to_string()),
+        };
+        statement.bind((":timezone", timezone.as_deref()))?;
+        // Rules without a start are anchored at the day they are set
+        let today = self.timezone.wall_time(Utc::now()).date();
+        let recurrence = self
+            .recurrence
+            .as_ref()
+            .map(|recurrence| recurrence.anchored(today).to_string());
+        statement.bind((":recurrence", recurrence.as_deref()))?;
+        let valid_from = self.valid_from.map(|date| date.to_string());
+        statement.bind((":valid_from", valid_from.as_deref()))?;
+        let valid_until = self.valid_until.map(|date| date.to_string());
+        statement.bind((":valid_until", valid_until.as_deref()))?;
+        statement.bind((":updated_at", Local::now().timestamp()))?;
+
+        Ok(())
+    }
+
+    // Reads an alarm from the current row of a `SELECT *` statement
+    fn from_row(statement: &sqlite::Statement<'_>) -> Result<Self, ClockError> {
+        Ok(Alarm {
+            id: Some(statement.read::<i64, _>("id")?),
+            active_days: ActiveDays(statement.read::<i64, _>("active_days")? as u8),
+            hour: statement.read::<i64, _>("hour")? as u8,
+            minute: statement.read::<i64, _>("minute")? as u8,
+            seconds: statement.read::<i64, _>("seconds")? as u8,
+            label: statement.read::<String, _>("label")?,
+            metadata: serde_json::from_str(&statement.read::<String, _>("metadata")?)?,
+            color: statement.read::<Option<String>, _>("color")?,
+            icon: statement.read::<Option<String>, _>("icon")?,
+            jitter_minutes: statement.read::<i64, _>("jitter_minutes")? as u8,
+            rotation: statement
+                .read::<Option<String>, _>("rotation")?
+                .map(|rotation| serde_json::from_str(&rotation))
+                .transpose()?,
+            enabled: statement.read::<i64, _>("enabled")? != 0,
+            group: statement.read::<Option<String>, _>("group_name")?,
+            snooze_minutes: statement.read::<i64, _>("snooze_minutes")? as u8,
+            ring_minutes: statement.read::<i64, _>("ring_minutes")? as u8,
+            max_snoozes: statement
+                .read::<Option<i64>, _>("max_snoozes")?
+                .map(|max| max as u8),
+            snoozed_until: None,
+            missed_at: None,
+            date: Self::read_date(statement, "date")?,
+            timezone: match statement.read::<Option<String>, _>("timezone")? {
+                Some(timezone) => timezone.parse()?,
+                None => Zone::Local,
+            },
+            recurrence: statement
+                .read::<Option<String>, _>("recurrence")?
+                .map(|recurrence| recurrence.parse())
+                .transpose()?,
+            valid_from: Self::read_date(statement, "valid_from")?,
+            valid_until: Self::read_date(statement, "valid_until")?,
+        })
+    }
+
+    // Reads an optional YYYY-MM-DD column
+    fn read_date(
+        statement: &sqlite::Statement<'_>,
+        column: &str,
+    ) -> Result<Option<NaiveDate>, ClockError> {
+        statement
+            .read::<Option<String>, _>(column)?
+            .map(|date| date.parse::<NaiveDate>())
+            .transpose()
+            .map_err(|_| ClockError("Invalid alarm date"))
+    }
+
+    /// Verifies that the alarms table exists (creating it if needed) and exposes every column
+    /// the library expects.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::Alarm;
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// assert!(Alarm::check_schema(&conn).is_ok());
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// conn.execute("CREATE TABLE alarms (id INTEGER PRIMARY KEY)").unwrap();
+    /// assert!(Alarm::check_schema(&conn).is_err());
+    /// ```
+    pub fn check_schema(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "SELECT {} FROM {} LIMIT 1",
+            Self::columns().join(", "),
+            TNAME
+        );
+        conn.prepare(query)?;
+
+        Ok(())
+    }
+
+    /// Inspects the alarms table without ever writing to the database, returning the problems
+    /// found (missing table or columns). Unlike [Alarm::check_schema], the missing parts are
+    /// reported instead of being created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::Alarm;
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// assert_eq!(Alarm::probe_schema(&conn).unwrap(), vec!["Missing table alarms"]);
+    ///
+    /// Alarm::check_schema(&conn).unwrap();
+    /// assert!(Alarm::probe_schema(&conn).unwrap().is_empty());
+    /// ```
+    pub fn probe_schema(conn: &sqlite::Connection) -> Result<Vec<String>, ClockError> {
+        let query = "SELECT name FROM sqlite_master WHERE type='table' AND name = ?";
+        if conn.prepare(query)?.into_iter().bind((1, TNAME))?.count() == 0 {
+            return Ok(vec![format!("Missing table {}", TNAME)]);
+        }
+
+        let mut existing = Vec::new();
+        let mut statement = conn.prepare(format!("PRAGMA table_info({})", TNAME))?;
+        while let Ok(State::Row) = statement.next() {
+            existing.push(statement.read::<String, _>("name")?);
+        }
+
+        Ok(Self::columns()
+            .into_iter()
+            .filter(|column| !existing.iter().any(|name| name == column))
+            .map(|column| format!("Missing column {}.{}", TNAME, column))
+            .collect())
+    }
+
+    // Every column of the alarms table, late ones included
+    fn columns() -> Vec<&'static str> {
+        ["id", "active_days", "hour", "minute", "seconds"]
+            .into_iter()
+            .chain(LATE_COLUMNS.iter().map(|(name, _)| *name))
+            .collect()
+    }
+
+    /// Checks the alarm can ring: valid times (rotation weeks included) and some day to ring on
+    /// (active days, one-shot date, recurrence rule or rotation week days).
+    ///
+    /// # Panics
+    ///
+    /// Panics if an hour is not below 24 or if minutes or seconds are not below 60, or if the
+    /// alarm has no day to ring on.
+    pub fn validate(&self) -> Result<(), ClockError> {
+        self.check_times()?;
+
+        let rotation_days = self.rotation.as_ref().is_some_and(|rotation| {
+            rotation
+                .weeks
+                .iter()
+                .flatten()
+                .any(|week| week.active_days.is_some_and(|days| days.0 != 0))
+        });
+        if self.active_days.0 == 0
+            && self.date.is_none()
+            && self.recurrence.is_none()
+            && !rotation_days
+        {
+            return Err(ClockError("Alarm has no day to ring on"));
+        }
+
+        Ok(())
+    }
+
+    // Times which cannot be saved, the alarm would never ring
+    fn check_times(&self) -> Result<(), ClockError> {
+        match self.invalid_time() {
+            Some(_) => Err(ClockError("Invalid alarm time")),
+            None => Ok(()),
+        }
+    }
+
+    // First field holding a time which cannot be saved
+    fn invalid_time(&self) -> Option<&'static str> {
+        let invalid = |hour: u8, minute: u8, seconds: u8| match (hour, minute, seconds) {
+            (24.., _, _) => Some("hour"),
+            (_, 60.., _) => Some("minute"),
+            (_, _, 60..) => Some("seconds"),
+            _ => None,
+        };
+
+        invalid(self.hour, self.minute, self.seconds).or_else(|| {
+            self.rotation
+                .iter()
+                .flat_map(|rotation| rotation.weeks.iter().flatten())
+                .find_map(|week| invalid(week.hour, week.minute, week.seconds))
+                .map(|_| "rotation")
+        })
+    }
+
+    /// Saves the current clock using the given [sqlite::Connection]. Creates the table 'alarms' if
+    /// not present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the alarm time is not valid (see [Alarm::validate]) or if an SQL error has been
+    /// encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::{Alarm, ActiveDays};
+    ///
+    /// let alarm = Alarm {
+    ///     id: None,
+    ///     active_days: ActiveDays(0x01),
+    ///     hour: 12,
+    ///     minute: 0,
+    ///     seconds: 0,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    ///
+    /// assert!(alarm.save(&conn).is_ok());
+    /// assert!(Alarm { hour: 99, ..alarm }.save(&conn).is_err());
+    /// ```
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        self.check_times()?;
+        Self::check_table(conn)?;
+        if let Some(eid) = self.id {
+            let query = format!(
+                "UPDATE {} SET
+                active_days = :active_days,
+                hour = :hour,
+                minute = :minute,
+                seconds = :seconds,
+                label = :label,
+                metadata = :metadata,
+                color = :color,
+                icon = :icon,
+                jitter_minutes = :jitter_minutes,
+                rotation = :rotation,
+                enabled = :enabled,
+                group_name = :group_name,
+                snooze_minutes = :snooze_minutes,
+                date = :date,
+                timezone = :timezone,
+                recurrence = :recurrence,
+                valid_from = :valid_from,
+                valid_until = :valid_until,
+                ring_minutes = :ring_minutes,
+                max_snoozes = :max_snoozes,
+                updated_at = :updated_at
+                WHERE id = :id",
+                TNAME,
+            );
+            let mut statement = conn.prepare(query)?;
+
+            self.bind_fields(&mut statement)?;
+            statement.bind((":id", eid))?;
+            statement.next()?;
+        } else {
+            let query = format!(
+                "INSERT INTO {} (
+                    active_days,
+                    hour,
+                    minute,
+                    seconds,
+                    label,
+                    metadata,
+                    color,
+                    icon,
+                    jitter_minutes,
+                    rotation,
+                    enabled,
+                    group_name,
+                    snooze_minutes,
+                    date,
+                    timezone,
+                    recurrence,
+                    valid_from,
+                    valid_until,
+                    ring_minutes,
+                    max_snoozes,
+                    updated_at
+                ) VALUES (
+                    :active_days, :hour, :minute, :seconds, :label, :metadata, :color, :icon,
+                    :jitter_minutes, :rotation, :enabled, :group_name, :snooze_minutes, :date,
+                    :timezone, :recurrence, :valid_from, :valid_until, :ring_minutes, :max_snoozes,
+                    :updated_at
+                )",
+                TNAME,
+            );
+            let mut statement = conn.prepare(query)?;
+
+            self.bind_fields(&mut statement)?;
+            statement.next()?;
+        }
+        Ok(())
+    }
+
+    /// Another stored alarm ringing at the same time on the same days (same time, zone, active
+    /// days, one-shot date and recurrence rule), [None] if there is none
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn find_duplicate(&self, conn: &sqlite::Connection) -> Result<Option<Self>, ClockError> {
+        Ok(Self::all(conn)?.into_iter().find(|other| {
+            other.id != self.id
+                && (other.hour, other.minute, other.seconds)
+                    == (self.hour, self.minute, self.seconds)
+                && other.active_days == self.active_days
+                && other.timezone == self.timezone
+                && other.date == self.date
+                && other.recurrence == self.recurrence
+        }))
+    }
+
+    /// Saves the alarm unless another one rings at the same time on the same days (see
+    /// [Alarm::find_duplicate]), in which case `policy` applies.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::{ActiveDays, Alarm, DuplicatePolicy, DUPLICATE_ALARM};
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// let alarm = Alarm { hour: 7, active_days: ActiveDays::WEEKDAYS, ..Default::default() };
+    /// alarm.save_unique(&conn, DuplicatePolicy::Reject).unwrap();
+    ///
+    /// let error = alarm.save_unique(&conn, DuplicatePolicy::Reject).unwrap_err();
+    /// assert_eq!(error.0, DUPLICATE_ALARM);
+    ///
+    /// let work = Alarm { label: "Work".to_string(), ..alarm };
+    /// work.save_unique(&conn, DuplicatePolicy::Merge).unwrap();
+    /// let alarms = Alarm::all(&conn).unwrap();
+    /// assert_eq!(alarms.len(), 1);
+    /// assert_eq!(alarms[0].label, "Work");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the alarm is a duplicate and `policy` is [DuplicatePolicy::Reject], or if a SQL
+    /// error is encountered
+    pub fn save_unique(
+        &self,
+        conn: &sqlite::Connection,
+        policy: DuplicatePolicy,
+    ) -> Result<(), ClockError> {
+        match (self.find_duplicate(conn)?, policy) {
+            (None, _) => self.save(conn),
+            (Some(_), DuplicatePolicy::Reject) => Err(ClockError(DUPLICATE_ALARM)),
+            (Some(existing), DuplicatePolicy::Merge) => {
+                let mut metadata = existing.metadata;
+                metadata.extend(self.metadata.clone());
+
+                Self {
+                    id: existing.id,
+                    metadata,
+                    ..self.clone()
+                }
+                .save(conn)?;
+                // The merged alarm replaces this one
+                match self.id {
+                    Some(_) => self.remove(conn),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Get all the alarms stored in database
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::{Alarm, ActiveDays};
+    ///
+    /// let alarm = Alarm {
+    ///     id: None,
+    ///     active_days: ActiveDays(0x01),
+    ///     hour: 12,
+    ///     minute: 0,
+    ///     seconds: 0,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// alarm.save(&conn).unwrap();
+    ///
+    /// let alarms = Alarm::all(&conn).unwrap();
+    ///
+    /// assert!(alarms.len() > 0);
+    /// ```
+    pub fn all(conn: &sqlite::Connection) -> Result<Vec<Self>, ClockError> {
+        Self::all_sorted(conn, AlarmSort::default())
+    }
+
+    /// Get all the alarms stored in database, in the given order
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::{Alarm, AlarmSort};
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// Alarm { hour: 9, label: "b".to_string(), ..Default::default() }.save(&conn).unwrap();
+    /// Alarm { hour: 7, label: "a".to_string(), ..Default::default() }.save(&conn).unwrap();
+    ///
+    /// let alarms = Alarm::all_sorted(&conn, AlarmSort::Time).unwrap();
+    ///
+    /// assert_eq!(alarms[0].hour, 7);
+    /// ```
+    pub fn all_sorted(conn: &sqlite::Connection, sort: AlarmSort) -> Result<Vec<Self>, ClockError> {
+        Self::query(conn, sort, None)
+    }
+
+    /// Get at most `limit` alarms, skipping the first `offset` ones of the given order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::{Alarm, AlarmSort};
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// for hour in 0..5 {
+    ///     Alarm { hour, ..Default::default() }.save(&conn).unwrap();
+    /// }
+    ///
+    /// let page = Alarm::page(&conn, AlarmSort::Time, 2, 2).unwrap();
+    ///
+    /// assert_eq!(page.iter().map(|alarm| alarm.hour).collect::<Vec<u8>>(), vec![2, 3]);
+    /// assert_eq!(Alarm::count(&conn).unwrap(), 5);
+    /// ```
+    pub fn page(
+        conn: &sqlite::Connection,
+        sort: AlarmSort,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<Self>, ClockError> {
+        Self::query(conn, sort, Some((limit, offset)))
+    }
+
+    /// Number of alarms stored in database
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn count(conn: &sqlite::Connection) -> Result<usize, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "SELECT COUNT(*) AS count FROM {} WHERE deleted_at IS NULL",
+            TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+
+        statement.next()?;
+        Ok(statement.read::<i64, _>("count")? as usize)
+    }
+
+    /// Stored alarm `id`, [None] if there is none
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::Alarm;
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// Alarm { hour: 7, ..Default::default() }.save(&conn).unwrap();
+    ///
+    /// assert_eq!(Alarm::by_id(&conn, 1).unwrap().unwrap().hour, 7);
+    /// assert_eq!(Alarm::by_id(&conn, 2).unwrap(), None);
+    /// ```
+    pub fn by_id(conn: &sqlite::Connection, id: i64) -> Result<Option<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "SELECT * FROM {} WHERE id = :id AND deleted_at IS NULL",
+            TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":id", id))?;
+        match statement.next()? {
+            State::Row => Ok(Some(Self::from_row(&statement)?)),
+            State::Done => Ok(None),
+        }
+    }
+
+    /// Enabled alarms whose active days include `weekday`, by time. Alarms ringing on given dates
+    /// (one-shot or recurrence rule) are left out, whatever their weekday.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::Weekday;
+    /// use libclockrobustus::alarm::{ActiveDays, Alarm};
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// let alarm = |hour, active_days| Alarm { hour, active_days, ..Default::default() };
+    /// alarm(9, ActiveDays::WEEKEND).save(&conn).unwrap();
+    /// alarm(7, ActiveDays::WEEKDAYS).save(&conn).unwrap();
+    ///
+    /// let alarms = Alarm::for_day(&conn, Weekday::Sun).unwrap();
+    ///
+    /// assert_eq!(alarms.len(), 1);
+    /// assert_eq!(alarms[0].hour, 9);
+    /// ```
+    pub fn for_day(conn: &sqlite::Connection, weekday: Weekday) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "SELECT * FROM {} WHERE enabled = 1 AND active_days & :day != 0
+            AND date IS NULL AND recurrence IS NULL AND deleted_at IS NULL
+            ORDER BY {}",
+            TNAME,
+            AlarmSort::Time.order_by()
+        );
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":day", 1i64 << weekday.num_days_from_monday()))?;
+        while let Ok(State::Row) = statement.next() {
+            res.push(Self::from_row(&statement)?)
+        }
+
+        Ok(res)
+    }
+
+    /// Next `count` occurrences of the enabled alarms from now, see [Alarm::upcoming].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::{ActiveDays, Alarm};
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// let alarm = |hour, enabled| Alarm { hour, enabled, active_days: ActiveDays::EVERYDAY, ..Default::default() };
+    /// alarm(7, true).save(&conn).unwrap();
+    /// alarm(9, false).save(&conn).unwrap();
+    ///
+    /// let next = Alarm::next_n(&conn, 3).unwrap();
+    ///
+    /// assert_eq!(next.len(), 3);
+    /// assert!(next.iter().all(|(_, alarm)| alarm.hour == 7));
+    /// ```
+    pub fn next_n(
+        conn: &sqlite::Connection,
+        count: usize,
+    ) -> Result<Vec<(DateTime<Local>, Self)>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "SELECT * FROM {} WHERE enabled = 1 AND deleted_at IS NULL ORDER BY id",
+            TNAME
+        );
+        let mut alarms = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            alarms.push(Self::from_row(&statement)?)
+        }
+
+        Ok(Self::upcoming(&alarms, Local::now(), count))
+    }
+
+    /// Every stored alarm as a JSON array, to be restored with [Alarm::import].
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn export_all(conn: &sqlite::Connection) -> Result<String, ClockError> {
+        Ok(serde_json::to_string_pretty(&Self::all(conn)?)?)
+    }
+
+    /// Restores alarms exported by [Alarm::export_all]. An alarm whose id is still in use
+    /// replaces the stored one, the others (unknown or missing id) are added as new alarms.
+    /// Nothing is saved unless the whole export can be read and saved. Returns the number of
+    /// alarms imported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::Alarm;
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// Alarm { hour: 7, ..Default::default() }.save(&conn).unwrap();
+    /// let backup = Alarm::export_all(&conn).unwrap();
+    ///
+    /// let mut alarm = Alarm::all(&conn).unwrap().remove(0);
+    /// alarm.hour = 8;
+    /// alarm.save(&conn).unwrap();
+    ///
+    /// assert_eq!(Alarm::import(&conn, &backup).unwrap(), 1);
+    /// assert_eq!(Alarm::all(&conn).unwrap()[0].hour, 7);
+    /// assert!(Alarm::import(&conn, "[{\"hour\": 7}, 3]").is_err());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the export is not a JSON array of alarms or if a SQL error is encountered
+    pub fn import(conn: &sqlite::Connection, json: &str) -> Result<usize, ClockError> {
+        let alarms: Vec<Self> = serde_json::from_str(json)?;
+        let stored = Self::all(conn)?
+            .into_iter()
+            .filter_map(|alarm| alarm.id)
+            .collect::<Vec<i64>>();
+
+        in_transaction(conn, || {
+            for alarm in &alarms {
+                match alarm.id {
+                    Some(id) if stored.contains(&id) => alarm.save(conn)?,
+                    _ => alarm.duplicate().save(conn)?,
+                }
+            }
+
+            Ok(alarms.len())
+        })
+    }
+
+    /// Saves (creates or updates) every alarm, all of them or none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::Alarm;
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// let alarms = vec![Alarm { hour: 7, ..Default::default() }; 3];
+    ///
+    /// Alarm::save_all(&conn, &alarms).unwrap();
+    /// assert_eq!(Alarm::all(&conn).unwrap().len(), 3);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered, nothing being saved then
+    pub fn save_all(conn: &sqlite::Connection, alarms: &[Self]) -> Result<(), ClockError> {
+        in_transaction(conn, || {
+            alarms.iter().try_for_each(|alarm| alarm.save(conn))
+        })
+    }
+
+    // Listing with an optional (limit, offset) window
+    fn query(
+        conn: &sqlite::Connection,
+        sort: AlarmSort,
+        window: Option<(usize, usize)>,
+    ) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let mut query = format!(
+            "SELECT * FROM {} WHERE deleted_at IS NULL ORDER BY {}",
+            TNAME,
+            sort.order_by()
+        );
+        // Next occurrences are computed here, not by SQLite, so the window is applied after
+        // sorting.
+        if let (Some((limit, offset)), false) = (window, sort == AlarmSort::NextOccurrence) {
+            query.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
+        }
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            res.push(Self::from_row(&statement)?)
+        }
+
+        if sort == AlarmSort::NextOccurrence {
+            let now = Local::now();
+            // Stable sort: alarms ringing at the same time stay in creation order, and [None]
+            // (never rings) is mapped after any date.
+            res.sort_by_key(|alarm| {
+                let next = alarm.next_occurrence(now);
+                (next.is_none(), next)
+            });
+
+            if let Some((limit, offset)) = window {
+                res = res.into_iter().skip(offset).take(limit).collect();
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Enables or disables every alarm of the given ids at once, returning the number of alarms
+    /// changed. Unknown ids are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::Alarm;
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// for hour in 6..9 {
+    ///     Alarm { hour, ..Default::default() }.save(&conn).unwrap();
+    /// }
+    ///
+    /// assert_eq!(Alarm::set_enabled_by_ids(&conn, &[1, 3, 42], false).unwrap(), 2);
+    ///
+    /// let enabled = Alarm::all(&conn).unwrap().iter().map(|alarm| alarm.enabled).collect::<Vec<bool>>();
+    /// assert_eq!(enabled, vec![false, true, false]);
+    /// ```
+    pub fn set_enabled_by_ids(
+        conn: &sqlite::Connection,
+        ids: &[i64],
+        enabled: bool,
+    ) -> Result<usize, ClockError> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let ids = ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        Self::set_enabled_where(conn, &format!("id IN ({})", ids), None, enabled)
+    }
+
+    /// Enables or disables every alarm of a group at once, returning the number of alarms
+    /// changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::Alarm;
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// let work = Some("work".to_string());
+    /// Alarm { hour: 7, group: work.clone(), ..Default::default() }.save(&conn).unwrap();
+    /// Alarm { hour: 8, group: work, ..Default::default() }.save(&conn).unwrap();
+    /// Alarm { hour: 9, ..Default::default() }.save(&conn).unwrap();
+    ///
+    /// assert_eq!(Alarm::set_enabled_by_group(&conn, "work", false).unwrap(), 2);
+    /// assert_eq!(Alarm::all(&conn).unwrap().iter().filter(|alarm| alarm.enabled).count(), 1);
+    /// ```
+    pub fn set_enabled_by_group(
+        conn: &sqlite::Connection,
+        group: &str,
+        enabled: bool,
+    ) -> Result<usize, ClockError> {
+        Self::set_enabled_where(conn, "group_name = :group_name", Some(group), enabled)
+    }
+
+    // Single statement update, hence applied to every matching alarm or to none
+    fn set_enabled_where(
+        conn: &sqlite::Connection,
+        condition: &str,
+        group: Option<&str>,
+        enabled: bool,
+    ) -> Result<usize, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "UPDATE {} SET enabled = :enabled, updated_at = :updated_at
+            WHERE deleted_at IS NULL AND ({})",
+            TNAME, condition
+        );
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":enabled", enabled as i64))?;
+        statement.bind((":updated_at", Local::now().timestamp()))?;
+        if let Some(group) = group {
+            statement.bind((":group_name", group))?;
+        }
+        statement.next()?;
+
+        let mut statement = conn.prepare("SELECT changes() AS count")?;
+        statement.next()?;
+        Ok(statement.read::<i64, _>("count")? as usize)
+    }
+
+    // Essential db check of the exceptions table
+    fn check_exceptions_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            alarm_id INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            PRIMARY KEY (alarm_id, date)
+            )",
+            EXCEPTIONS_TNAME
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    /// Silences the occurrence scheduled on `date` (in the alarm [Zone]), eg: a public holiday,
+    /// the alarm staying enabled for the other days.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the alarm has no id (eg: not saved) or if a SQL error is encountered
+    pub fn skip(&self, conn: &sqlite::Connection, date: NaiveDate) -> Result<(), ClockError> {
+        Self::check_exceptions_table(conn)?;
+        let eid = self.id.ok_or(ClockError(
+            "Impossible to skip an occurrence of an unsaved alarm",
+        ))?;
+        let query = format!(
+            "INSERT OR IGNORE INTO {} (alarm_id, date) VALUES (:alarm_id, :date)",
+            EXCEPTIONS_TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":alarm_id", eid))?;
+        statement.bind((":date", date.to_string().as_str()))?;
+        statement.next()?;
+
+        Ok(())
+    }
+
+    /// Skips the next occurrence that is not skipped yet, returning its scheduled date (in the
+    /// alarm [Zone]), [None] if the alarm never rings.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the alarm has no id (eg: not saved) or if a SQL error is encountered
+    pub fn skip_next(&self, conn: &sqlite::Connection) -> Result<Option<NaiveDate>, ClockError> {
+        self.skip_next_after(conn, Local::now())
+    }
+
+    /// Same as [Alarm::skip_next], considering the occurrences after `after` only.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{Local, NaiveDate, TimeZone};
+    /// use libclockrobustus::alarm::{Alarm, ActiveDays};
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// Alarm { active_days: ActiveDays::EVERYDAY, hour: 7, ..Default::default() }.save(&conn).unwrap();
+    /// let alarm = Alarm::all(&conn).unwrap().remove(0);
+    /// let after = Local.with_ymd_and_hms(2023, 7, 3, 8, 0, 0).unwrap();
+    ///
+    /// assert_eq!(alarm.skip_next_after(&conn, after).unwrap(), NaiveDate::from_ymd_opt(2023, 7, 4));
+    /// assert_eq!(alarm.skip_next_after(&conn, after).unwrap(), NaiveDate::from_ymd_opt(2023, 7, 5));
+    /// assert_eq!(alarm.skipped_dates(&conn).unwrap().len(), 2);
+    /// ```
+    pub fn skip_next_after(
+        &self,
+        conn: &sqlite::Connection,
+        after: DateTime<Local>,
+    ) -> Result<Option<NaiveDate>, ClockError> {
+        match self.next_unskipped(conn, after, |_, _| true)? {
+            Some((_, date)) => {
+                self.skip(conn, date)?;
+                Ok(Some(date))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Instant the alarm actually rings at next, strictly after `after`: skipped occurrences
+    /// are passed over and the [Alarm::jitter] is applied. [None] if the alarm never rings.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{Local, TimeZone};
+    /// use libclockrobustus::alarm::{Alarm, ActiveDays};
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// Alarm { active_days: ActiveDays::EVERYDAY, hour: 7, ..Default::default() }.save(&conn).unwrap();
+    /// let alarm = Alarm::all(&conn).unwrap().remove(0);
+    /// let after = Local.with_ymd_and_hms(2023, 7, 3, 8, 0, 0).unwrap();
+    ///
+    /// alarm.skip_next_after(&conn, after).unwrap();
+    /// assert_eq!(
+    ///     alarm.next_ring(&conn, after).unwrap(),
+    ///     Local.with_ymd_and_hms(2023, 7, 5, 7, 0, 0).single()
+    /// );
+    /// ```
+    pub fn next_ring(
+        &self,
+        conn: &sqlite::Connection,
+        after: DateTime<Local>,
+    ) -> Result<Option<DateTime<Local>>, ClockError> {
+        // Occurrences scheduled up to the jitter before `after` may still ring after it, the
+        // ones shifted before it have rung already
+        let ring = |next: DateTime<Local>, date| next + self.jitter(date);
+        let from = after - Duration::minutes(self.jitter_minutes as i64);
+
+        Ok(self
+            .next_unskipped(conn, from, |next, date| ring(next, date) > after)?
+            .map(|(next, date)| ring(next, date)))
+    }
+
+    // Next occurrence after `after` (with its date in the alarm [Zone]) that is not skipped and
+    // that `keep` accepts
+    fn next_unskipped(
+        &self,
+        conn: &sqlite::Connection,
+        after: DateTime<Local>,
+        keep: impl Fn(DateTime<Local>, NaiveDate) -> bool,
+    ) -> Result<Option<(DateTime<Local>, NaiveDate)>, ClockError> {
+        let skipped = self.skipped_dates(conn)?;
+        let mut after = after;
+
+        // Every already skipped occurrence is passed over, at most three more are looked at
+        for _ in 0..skipped.len() + 3 {
+            let Some(next) = self.next_occurrence(after) else {
+                return Ok(None);
+            };
+            let date = self.timezone.wall_time(next.with_timezone(&Utc)).date();
+
+            if !skipped.contains(&date) && keep(next, date) {
+                return Ok(Some((next, date)));
+            }
+            after = next;
+        }
+
+        Ok(None)
+    }
+
+    /// Dates (in the alarm [Zone]) the alarm occurrences are skipped on, earliest first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn skipped_dates(&self, conn: &sqlite::Connection) -> Result<Vec<NaiveDate>, ClockError> {
+        Self::check_exceptions_table(conn)?;
+        let Some(eid) = self.id else {
+            return Ok(Vec::new());
+        };
+        let query = format!(
+            "SELECT date FROM {} WHERE alarm_id = :alarm_id ORDER BY date",
+            EXCEPTIONS_TNAME
+        );
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":alarm_id", eid))?;
+        while let Ok(State::Row) = statement.next() {
+            res.push(
+                statement
+                    .read::<String, _>("date")?
+                    .parse()
+                    .map_err(|_| ClockError("Invalid exception date"))?,
+            );
+        }
+
+        Ok(res)
+    }
+
+    /// True if the occurrence scheduled on `date` has been skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn is_skipped(
+        &self,
+        conn: &sqlite::Connection,
+        date: NaiveDate,
+    ) -> Result<bool, ClockError> {
+        Ok(self.skipped_dates(conn)?.contains(&date))
+    }
+
+    /// Restores a skipped occurrence, returning false if it was not skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn unskip(&self, conn: &sqlite::Connection, date: NaiveDate) -> Result<bool, ClockError> {
+        Self::check_exceptions_table(conn)?;
+        let Some(eid) = self.id else {
+            return Ok(false);
+        };
+        let query = format!(
+            "DELETE FROM {} WHERE alarm_id = :alarm_id AND date = :date",
+            EXCEPTIONS_TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":alarm_id", eid))?;
+        statement.bind((":date", date.to_string().as_str()))?;
+        statement.next()?;
+
+        let mut statement = conn.prepare("SELECT changes() AS count")?;
+        statement.next()?;
+        Ok(statement.read::<i64, _>("count")? > 0)
+    }
+
+    // Tags table check
+    fn check_tags_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            alarm_id INTEGER NOT NULL,
+            tag TEXT NOT NULL,
+            PRIMARY KEY (alarm_id, tag)
+            )",
+            TAGS_TNAME
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    /// Free-form tags of the alarm, alphabetically.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn tags(&self, conn: &sqlite::Connection) -> Result<Vec<String>, ClockError> {
+        Self::check_tags_table(conn)?;
+        let Some(eid) = self.id else {
+            return Ok(Vec::new());
+        };
+        let query = format!(
+            "SELECT tag FROM {} WHERE alarm_id = :alarm_id ORDER BY tag",
+            TAGS_TNAME
+        );
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":alarm_id", eid))?;
+        while let Ok(State::Row) = statement.next() {
+            res.push(statement.read::<String, _>("tag")?);
+        }
+
+        Ok(res)
+    }
+
+    /// Replaces the tags of the alarm. Tags are trimmed, empty ones are ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the alarm has no id (eg: not saved) or if a SQL error is encountered
+    pub fn set_tags(&self, conn: &sqlite::Connection, tags: &[String]) -> Result<(), ClockError> {
+        Self::check_tags_table(conn)?;
+        let eid = self
+            .id
+            .ok_or(ClockError("Impossible to tag an unsaved alarm"))?;
+
+        conn.execute(format!(
+            "DELETE FROM {} WHERE alarm_id = {}",
+            TAGS_TNAME, eid
+        ))?;
+        let query = format!(
+            "INSERT OR IGNORE INTO {} (alarm_id, tag) VALUES (:alarm_id, :tag)",
+            TAGS_TNAME
+        );
+        for tag in tags
+            .iter()
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+        {
+            let mut statement = conn.prepare(&query)?;
+
+            statement.bind((":alarm_id", eid))?;
+            statement.bind((":tag", tag))?;
+            statement.next()?;
+        }
+
+        Ok(())
+    }
+
+    /// Alarms matching every word of `query` (case insensitive) in their label, group or tags,
+    /// in creation order. An empty query matches every alarm.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::Alarm;
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// Alarm { label: "Gym".to_string(), ..Default::default() }.save(&conn).unwrap();
+    /// Alarm { label: "Work".to_string(), ..Default::default() }.save(&conn).unwrap();
+    ///
+    /// let work = Alarm::all(&conn).unwrap().remove(1);
+    /// work.set_tags(&conn, &["weekdays".to_string(), "office".to_string()]).unwrap();
+    ///
+    /// assert_eq!(Alarm::search(&conn, "OFFICE work").unwrap(), vec![work]);
+    /// assert_eq!(Alarm::search(&conn, "").unwrap().len(), 2);
+    /// assert!(Alarm::search(&conn, "gym office").unwrap().is_empty());
+    /// ```
+    pub fn search(conn: &sqlite::Connection, query: &str) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        Self::check_tags_table(conn)?;
+        let words = query.split_whitespace().collect::<Vec<&str>>();
+        let conditions = (0..words.len())
+            .map(|index| {
+                format!(
+                    "(label LIKE :word{0} ESCAPE '\\' OR group_name LIKE :word{0} ESCAPE '\\'
+                    OR id IN (SELECT alarm_id FROM {1} WHERE tag LIKE :word{0} ESCAPE '\\'))",
+                    index, TAGS_TNAME
+                )
+            })
+            .chain(["deleted_at IS NULL".to_string()])
+            .collect::<Vec<String>>();
+        let query = format!(
+            "SELECT * FROM {} WHERE {} ORDER BY id",
+            TNAME,
+            conditions.join(" AND ")
+        );
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        for (index, word) in words.iter().enumerate() {
+            // LIKE is case insensitive (for ASCII), its wildcards are escaped
+            let pattern = word
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            statement.bind((
+                format!(":word{}", index).as_str(),
+                format!("%{}%", pattern).as_str(),
+            ))?;
+        }
+        while let Ok(State::Row) = statement.next() {
+            res.push(Self::from_row(&statement)?)
+        }
+
+        Ok(res)
+    }
+
+    /// Removes a saved alarm. The removal is soft: the alarm is only marked as deleted, being left
+    /// out of every listing, until [Alarm::restore] brings it back or [Alarm::purge] erases it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the represented alarm has no id (eg: not saved).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::{Alarm, ActiveDays};
+    ///
+    /// let alarm = Alarm {
+    ///     id: None,
+    ///     active_days: ActiveDays(0x01),
+    ///     hour: 12,
+    ///     minute: 0,
+    ///     seconds: 0,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// alarm.save(&conn).unwrap();
+    ///
+    /// let alarms = Alarm::all(&conn).unwrap();
+    ///
+    /// assert!(alarms.len() > 0);
+    /// assert!(alarms[0].remove(&conn).is_ok());
+    ///
+    /// let alarm3 = Alarm {
+    ///     id: None,
+    ///     active_days: ActiveDays(0x02),
+    ///     hour: 12,
+    ///     minute: 13,
+    ///     seconds: 25,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert!(alarm3.remove(&conn).is_err());
+    /// ```
+    pub fn remove(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+
+        let eid = self
+            .id
+            .ok_or(ClockError("Impossible to delete an unsaved alarm"))?;
+        let query = format!(
+            "UPDATE {} SET deleted_at = :deleted_at WHERE id = :id AND deleted_at IS NULL",
+            TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":deleted_at", Local::now().timestamp()))?;
+        statement.bind((":id", eid))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// Brings back a removed alarm (eg: undoing a deletion), with its skipped dates and tags.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the alarm has no id, if it is not removed (or has been purged) or if a SQL error
+    /// is encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::Alarm;
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// Alarm { hour: 7, ..Default::default() }.save(&conn).unwrap();
+    /// let alarm = Alarm::all(&conn).unwrap().remove(0);
+    ///
+    /// alarm.remove(&conn).unwrap();
+    /// assert_eq!(Alarm::count(&conn).unwrap(), 0);
+    ///
+    /// alarm.restore(&conn).unwrap();
+    /// assert_eq!(Alarm::all(&conn).unwrap(), vec![alarm.clone()]);
+    /// assert!(alarm.restore(&conn).is_err());
+    /// ```
+    pub fn restore(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+
+        let eid = self
+            .id
+            .ok_or(ClockError("Impossible to restore an unsaved alarm"))?;
+        let query = format!(
+            "UPDATE {} SET deleted_at = NULL WHERE id = :id AND deleted_at IS NOT NULL",
+            TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":id", eid))?;
+        statement.next()?;
+
+        let mut statement = conn.prepare("SELECT changes() AS count")?;
+        statement.next()?;
+        if statement.read::<i64, _>("count")? == 0 {
+            return Err(ClockError("No removed alarm to restore"));
+        }
+        Ok(())
+    }
+
+    /// Erases for good the alarms removed before `before` (unix timestamp, seconds), along with
+    /// their skipped dates and tags. Returns the number of alarms erased.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn purge(conn: &sqlite::Connection, before: i64) -> Result<usize, ClockError> {
+        Self::check_table(conn)?;
+        Self::check_exceptions_table(conn)?;
+        Self::check_tags_table(conn)?;
+
+        let purged = format!(
+            "SELECT id FROM {} WHERE deleted_at IS NOT NULL AND deleted_at < :before",
+            TNAME
+        );
+        // Exceptions and tags are meaningless without their alarm
+        for table in [EXCEPTIONS_TNAME, TAGS_TNAME] {
+            let query = format!("DELETE FROM {} WHERE alarm_id IN ({})", table, purged);
+            let mut statement = conn.prepare(query)?;
+
+            statement.bind((":before", before))?;
+            statement.next()?;
+        }
+        let query = format!(
+            "DELETE FROM {} WHERE deleted_at IS NOT NULL AND deleted_at < :before",
+            TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":before", before))?;
+        statement.next()?;
+
+        let mut statement = conn.prepare("SELECT changes() AS count")?;
+        statement.next()?;
+        Ok(statement.read::<i64, _>("count")? as usize)
+    }
+
+    /// Announcement of a snooze of this alarm, see [Alarm::snoozed_until].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::Alarm;
+    ///
+    /// let alarm = Alarm::default().snoozed(1_700_000_000);
+    ///
+    /// assert_eq!(alarm.snoozed_until, Some(1_700_000_000));
+    /// ```
+    pub fn snoozed(self, until: i64) -> Self {
+        Self {
+            snoozed_until: Some(until),
+            ..self
+        }
+    }
+
+    /// Late publication of an occurrence due at `at`, see [Alarm::missed_at].
+    pub fn missed(self, at: i64) -> Self {
+        Self {
+            missed_at: Some(at),
+            ..self
+        }
+    }
+
+    /// Binary representation of the whole alarm (see [crate::codec]), to be used in a queue.
+    /// Hot loops should rather encode into a reused buffer (see [Alarm::encode_into]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::{Alarm, ActiveDays};
+    ///
+    /// let alarm = Alarm {
+    ///     id: Some(3),
+    ///     active_days: ActiveDays(0x01),
     ///     hour: 12,
     ///     minute: 9,
-    ///     seconds: 9,
+    ///     label: "Lunch".to_string(),
+    ///     ..Default::default()
     /// };
     ///
-    /// assert_eq!(alarm.as_bytes(), vec![0x01, 12, 9, 9]);
+    /// assert_eq!(Alarm::try_from(alarm.as_bytes().unwrap()).unwrap(), alarm);
+    /// ```
+    pub fn as_bytes(&self) -> Result<Vec<u8>, ClockError> {
+        codec::to_vec(self)
+    }
+
+    /// Writes the binary representation at the start of a caller-provided buffer, returning the
+    /// number of bytes written. Labels, metadata and rotations have no bound, no buffer fits
+    /// every alarm: callers fall back to [Alarm::as_bytes] when this fails (as the daemon does
+    /// with [crate::message::Message::payload]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is too short for the alarm.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::Alarm;
+    ///
+    /// let alarm = Alarm { id: Some(3), ..Default::default() };
+    /// let mut buffer = [0u8; 64];
+    ///
+    /// let len = alarm.encode_into(&mut buffer).unwrap();
+    ///
+    /// assert_eq!(Alarm::try_from(&buffer[..len]).unwrap(), alarm);
+    /// assert!(alarm.encode_into(&mut [0u8; 4]).is_err());
+    /// ```
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        codec::encode(self, buffer)
+    }
+
+    /// Strict counterpart of the [TryFrom] conversion from bytes: truncated alarms, trailing
+    /// bytes (see [codec::decode_strict]), unknown day bits and out of range times are rejected.
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let alarm: Self = codec::decode_strict(bytes)?;
+
+        alarm.check_ranges()?;
+        Ok(alarm)
+    }
+
+    /// Rejects the fields out of range, once decoded (see [Alarm::try_from_strict])
+    pub(crate) fn check_ranges(&self) -> Result<(), DecodeError> {
+        DecodeError::check_range(self.active_days.0, "active_days", 0..=0x7F)?;
+
+        match self.invalid_time() {
+            Some(field) => Err(DecodeError::new(0, field, "Value out of range")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Display for Alarm {
+    /// Compact one-line form for logs and command line tools: the days (or the date of a
+    /// one-shot alarm, `-` if none), the time and the label between brackets if any, eg:
+    /// `Mon,Tue 07:30:00 [Work]`. Other settings are left out.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let days = self
+            .active_days
+            .to_weekdays()
+            .iter()
+            .map(|weekday| weekday.to_string())
+            .collect::<Vec<String>>();
+
+        match (self.date, days.is_empty()) {
+            (Some(date), _) => write!(f, "{}", date.format("%Y-%m-%d"))?,
+            (None, true) => write!(f, "-")?,
+            (None, false) => write!(f, "{}", days.join(","))?,
+        }
+        write!(
+            f,
+            " {:02}:{:02}:{:02}",
+            self.hour, self.minute, self.seconds
+        )?;
+        if !self.label.is_empty() {
+            write!(f, " [{}]", self.label)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Alarm {
+    type Err = ClockError;
+
+    /// Parses the form written by [Display] (seconds being optional), other settings taking
+    /// their default value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::{ActiveDays, Alarm};
+    ///
+    /// let alarm: Alarm = "Mon,Tue 07:30 [Work]".parse().unwrap();
+    ///
+    /// assert_eq!(alarm.active_days, ActiveDays(0x03));
+    /// assert_eq!((alarm.hour, alarm.minute, alarm.seconds), (7, 30, 0));
+    /// assert_eq!(alarm.label, "Work");
+    /// assert_eq!(alarm.to_string(), "Mon,Tue 07:30:00 [Work]");
     /// ```
-    pub fn as_bytes(&self) -> Vec<u8> {
-        vec![self.active_days.0, self.hour, self.minute, self.seconds]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (days, rest) = s
+            .trim()
+            .split_once(' ')
+            .ok_or(ClockError("Missing alarm time"))?;
+        let (time, label) = match rest.trim().split_once(' ') {
+            Some((time, label)) => (time, Some(label.trim())),
+            None => (rest.trim(), None),
+        };
+        let time = NaiveTime::parse_from_str(time, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+            .map_err(|_| ClockError("Invalid alarm time"))?;
+        let mut alarm = Alarm {
+            hour: time.hour() as u8,
+            minute: time.minute() as u8,
+            seconds: time.second() as u8,
+            ..Default::default()
+        };
+
+        if let Some(label) = label {
+            alarm.label = label
+                .strip_prefix('[')
+                .and_then(|label| label.strip_suffix(']'))
+                .ok_or(ClockError("Alarm label must be between brackets"))?
+                .to_string();
+        }
+
+        if let Ok(date) = NaiveDate::parse_from_str(days, "%Y-%m-%d") {
+            alarm.date = Some(date);
+        } else if days != "-" {
+            for day in days.split(',') {
+                let weekday = day
+                    .trim()
+                    .parse::<Weekday>()
+                    .map_err(|_| ClockError("Invalid alarm day"))?;
+                alarm.active_days.0 |= 1 << weekday.num_days_from_monday();
+            }
+        }
+
+        Ok(alarm)
     }
 }
 
@@ -412,30 +2572,129 @@ impl TryFrom<Vec<u8>> for Alarm {
     type Error = ClockError;
 
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
-        if value.is_empty() {
-            Err(ClockError("Cannot convert alarm from empty bytes vector"))
-        } else if value.len() < 4 {
-            Err(ClockError(
-                "binary data is too short to create an alarm message",
-            ))
-        } else {
-            Ok(Self {
-                id: None,
-                active_days: ActiveDays(value[0]),
-                hour: value[1],
-                minute: value[2],
-                seconds: value[3],
-            })
+        Self::try_from(value.as_slice())
+    }
+}
+
+impl TryFrom<&[u8]> for Alarm {
+    type Error = ClockError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        codec::decode(value)
+    }
+}
+
+#[cfg(feature = "protobuf")]
+impl From<Alarm> for crate::proto::Alarm {
+    fn from(value: Alarm) -> Self {
+        Self {
+            id: value.id,
+            active_days: value.active_days.0 as u32,
+            hour: value.hour as u32,
+            minute: value.minute as u32,
+            seconds: value.seconds as u32,
+            label: value.label,
+            metadata: serde_json::Value::Object(value.metadata).to_string(),
+            color: value.color,
+            icon: value.icon,
+            jitter_minutes: value.jitter_minutes as u32,
+            rotation: value
+                .rotation
+                .and_then(|rotation| serde_json::to_string(&rotation).ok())
+                .unwrap_or_default(),
+            enabled: Some(value.enabled),
+            group: value.group,
+            snooze_minutes: Some(value.snooze_minutes as u32),
+            ring_minutes: Some(value.ring_minutes as u32),
+            max_snoozes: value.max_snoozes.map(|max| max as u32),
+            snoozed_until: value.snoozed_until,
+            missed_at: value.missed_at,
+            date: value.date.map(|date| date.to_string()),
+            timezone: match value.timezone {
+                Zone::Local => String::new(),
+                zone => zone.to_string(),
+            },
+            recurrence: value.recurrence.map(|recurrence| recurrence.to_string()),
+            valid_from: value.valid_from.map(|date| date.to_string()),
+            valid_until: value.valid_until.map(|date| date.to_string()),
         }
     }
 }
 
+#[cfg(feature = "protobuf")]
+impl TryFrom<crate::proto::Alarm> for Alarm {
+    type Error = ClockError;
+
+    fn try_from(value: crate::proto::Alarm) -> Result<Self, Self::Error> {
+        let byte = |value: u32| u8::try_from(value).map_err(|_| ClockError("Value out of range"));
+        let date = |value: Option<String>| {
+            value
+                .map(|date| date.parse())
+                .transpose()
+                .map_err(|_| ClockError("Invalid date"))
+        };
+
+        Ok(Self {
+            id: value.id,
+            active_days: ActiveDays(byte(value.active_days)?),
+            hour: byte(value.hour)?,
+            minute: byte(value.minute)?,
+            seconds: byte(value.seconds)?,
+            label: value.label,
+            metadata: if value.metadata.is_empty() {
+                Default::default()
+            } else {
+                serde_json::from_str(&value.metadata)
+                    .map_err(|_| ClockError("Metadata must be a JSON object"))?
+            },
+            color: value.color,
+            icon: value.icon,
+            jitter_minutes: byte(value.jitter_minutes)?,
+            rotation: if value.rotation.is_empty() {
+                None
+            } else {
+                Some(
+                    serde_json::from_str(&value.rotation)
+                        .map_err(|_| ClockError("Invalid rotation"))?,
+                )
+            },
+            enabled: value.enabled.unwrap_or(true),
+            group: value.group,
+            snooze_minutes: match value.snooze_minutes {
+                Some(minutes) => byte(minutes)?,
+                None => default_snooze_minutes(),
+            },
+            // 0 rings until acknowledged
+            ring_minutes: match value.ring_minutes {
+                Some(minutes) => byte(minutes)?,
+                None => default_ring_minutes(),
+            },
+            max_snoozes: value.max_snoozes.map(byte).transpose()?,
+            snoozed_until: value.snoozed_until,
+            missed_at: value.missed_at,
+            date: date(value.date)?,
+            timezone: value
+                .timezone
+                .parse()
+                .map_err(|_| ClockError("Unknown timezone"))?,
+            recurrence: value
+                .recurrence
+                .map(|recurrence| recurrence.parse())
+                .transpose()
+                .map_err(|_| ClockError("Invalid recurrence rule"))?,
+            valid_from: date(value.valid_from)?,
+            valid_until: date(value.valid_until)?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use chrono::{Local, Timelike};
+    use chrono::{Duration, Local, NaiveDate, TimeZone, Timelike, Utc};
     use sqlite::Connection;
 
-    use super::{ActiveDays, Alarm};
+    use super::{ActiveDays, Alarm, AlarmSort, Rotation, RotationWeek};
+    use crate::{clock::Zone, error::ClockError, in_transaction};
 
     #[test]
     fn test_must_ring() {
@@ -447,21 +2706,142 @@ mod tests {
             hour: time.hour() as u8,
             minute: time.minute() as u8,
             seconds: time.second() as u8,
+            ..Default::default()
         };
 
         assert!(alarm.must_ring().unwrap());
 
+        // Disabled alarms are kept silent
+        let disabled = Alarm {
+            enabled: false,
+            ..alarm.clone()
+        };
+
+        assert!(!disabled.must_ring().unwrap());
+        assert_eq!(disabled.next_occurrence(now), None);
+
         let alarm = Alarm {
             id: None,
             active_days: ActiveDays(0x01),
             hour: ((time.hour() + 4) % 24) as u8,
             minute: time.minute() as u8,
             seconds: time.second() as u8,
+            ..Default::default()
         };
 
         assert!(!alarm.must_ring().unwrap());
     }
 
+    #[test]
+    fn test_jitter() {
+        // 2023-07-03 was a Monday
+        let date = NaiveDate::from_ymd_opt(2023, 7, 3).unwrap();
+        let alarm = Alarm {
+            id: Some(1),
+            active_days: ActiveDays(0x7F),
+            hour: 0,
+            minute: 5,
+            jitter_minutes: 15,
+            ..Default::default()
+        };
+        let offsets = (0..30)
+            .map(|day| alarm.jitter(date + Duration::days(day)).num_seconds())
+            .collect::<Vec<i64>>();
+
+        assert!(offsets.iter().all(|offset| offset.abs() <= 15 * 60));
+        // Occurrences do not all get the same offset
+        assert!(offsets.iter().any(|offset| *offset != offsets[0]));
+
+        // Each occurrence rings at its shifted time, possibly the day before
+        for day in 0..30 {
+            let date = date + Duration::days(day);
+            let ring_at = Local
+                .from_local_datetime(&date.and_hms_opt(0, 5, 0).unwrap())
+                .unwrap()
+                + alarm.jitter(date);
+
+            assert_eq!(alarm.due_occurrence(ring_at).unwrap(), Some(date));
+        }
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        // 2023-07-05 was a Wednesday
+        let after = Local.with_ymd_and_hms(2023, 7, 5, 12, 0, 0).unwrap();
+        let alarm = Alarm {
+            id: None,
+            active_days: ActiveDays(0x05),
+            hour: 12,
+            minute: 0,
+            seconds: 0,
+            ..Default::default()
+        };
+
+        // Same time is not strictly after: next one is next Monday
+        let next = alarm.next_occurrence(after).unwrap();
+        assert_eq!(next, Local.with_ymd_and_hms(2023, 7, 10, 12, 0, 0).unwrap());
+
+        // Later the same day
+        let alarm = Alarm { hour: 18, ..alarm };
+        let next = alarm.next_occurrence(after).unwrap();
+        assert_eq!(next, Local.with_ymd_and_hms(2023, 7, 5, 18, 0, 0).unwrap());
+
+        // No active day
+        let alarm = Alarm {
+            active_days: ActiveDays(0x00),
+            ..alarm
+        };
+        assert!(alarm.next_occurrence(after).is_none());
+    }
+
+    #[test]
+    fn test_rotation() {
+        // 2023-07-03 was a Monday
+        let monday = NaiveDate::from_ymd_opt(2023, 7, 3).unwrap();
+        let week = |hour| {
+            Some(RotationWeek {
+                hour,
+                minute: 0,
+                seconds: 0,
+                active_days: None,
+            })
+        };
+        let alarm = Alarm {
+            active_days: ActiveDays(0x1F),
+            hour: 9,
+            rotation: Some(Rotation {
+                start: monday,
+                weeks: vec![week(5), None, week(13)],
+            }),
+            ..Default::default()
+        };
+        let at = |date: NaiveDate, hour| {
+            Local
+                .from_local_datetime(&date.and_hms_opt(hour, 0, 0).unwrap())
+                .unwrap()
+        };
+
+        // Early week: the rotation time replaces the alarm one, weekends stay off
+        let friday = monday + Duration::days(4);
+        assert_eq!(alarm.next_occurrence(at(monday, 0)), Some(at(monday, 5)));
+        assert_eq!(
+            alarm.next_occurrence(at(friday, 6)),
+            Some(at(monday + Duration::days(14), 13))
+        );
+        // The pattern repeats after the third week
+        assert_eq!(
+            alarm.next_occurrence(at(friday + Duration::days(14), 14)),
+            Some(at(monday + Duration::days(21), 5))
+        );
+        assert_eq!(alarm.due_occurrence(at(monday, 5)).unwrap(), Some(monday));
+        assert_eq!(
+            alarm
+                .due_occurrence(at(monday + Duration::days(7), 5))
+                .unwrap(),
+            None
+        );
+    }
+
     #[test]
     fn test_saving() {
         let conn = Connection::open(":memory:").unwrap();
@@ -471,6 +2851,7 @@ mod tests {
             hour: 12,
             minute: 0,
             seconds: 0,
+            ..Default::default()
         };
         // Create
         assert!(alarm.save(&conn).is_ok());
@@ -480,11 +2861,40 @@ mod tests {
         assert!(!alarms.is_empty());
 
         // Update
-        let mut current_alarm = alarms[0];
+        let mut current_alarm = alarms[0].clone();
 
         current_alarm.hour = 13;
         current_alarm.minute = 42;
         current_alarm.seconds = 22;
+        current_alarm.label = "Wake 'up', \"now\"".to_string();
+        current_alarm
+            .set_metadata("playlist", "spotify:playlist:42")
+            .unwrap();
+        current_alarm.color = Some("#ff367c".to_string());
+        current_alarm.icon = Some("work".to_string());
+        current_alarm.jitter_minutes = 5;
+        current_alarm.enabled = false;
+        current_alarm.group = Some("work".to_string());
+        current_alarm.snooze_minutes = 5;
+        current_alarm.date = NaiveDate::from_ymd_opt(2031, 1, 1);
+        current_alarm.timezone = Zone::Named(chrono_tz::Europe::Paris);
+        current_alarm.recurrence = Some("FREQ=MONTHLY;BYDAY=1MO;DTSTART=20230703".parse().unwrap());
+        current_alarm.valid_from = NaiveDate::from_ymd_opt(2030, 9, 1);
+        current_alarm.valid_until = NaiveDate::from_ymd_opt(2031, 6, 30);
+        current_alarm.ring_minutes = 3;
+        current_alarm.max_snoozes = Some(2);
+        current_alarm.rotation = Some(Rotation {
+            start: NaiveDate::from_ymd_opt(2023, 7, 3).unwrap(),
+            weeks: vec![
+                Some(RotationWeek {
+                    hour: 22,
+                    minute: 0,
+                    seconds: 0,
+                    active_days: Some(ActiveDays(0x4F)),
+                }),
+                None,
+            ],
+        });
 
         assert!(current_alarm.save(&conn).is_ok());
 
@@ -493,6 +2903,367 @@ mod tests {
         assert_eq!(alarms[0], current_alarm);
     }
 
+    #[test]
+    fn test_sorting() {
+        let conn = Connection::open(":memory:").unwrap();
+        let alarms = vec![
+            Alarm {
+                active_days: ActiveDays(0x7F),
+                hour: 12,
+                label: "lunch".to_string(),
+                ..Default::default()
+            },
+            Alarm {
+                active_days: ActiveDays(0x00),
+                hour: 6,
+                label: "Never".to_string(),
+                ..Default::default()
+            },
+            Alarm {
+                active_days: ActiveDays(0x7F),
+                hour: 8,
+                label: "Breakfast".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        for alarm in &alarms {
+            alarm.save(&conn).unwrap();
+        }
+
+        let hours = |sort| {
+            Alarm::all_sorted(&conn, sort)
+                .unwrap()
+                .iter()
+                .map(|alarm| alarm.hour)
+                .collect::<Vec<u8>>()
+        };
+
+        assert_eq!(hours(AlarmSort::Id), vec![12, 6, 8]);
+        assert_eq!(hours(AlarmSort::Time), vec![6, 8, 12]);
+        assert_eq!(hours(AlarmSort::Label), vec![8, 12, 6]);
+        // The alarm without active days never rings, hence comes last
+        assert_eq!(hours(AlarmSort::NextOccurrence).last(), Some(&6));
+    }
+
+    #[test]
+    fn test_timezone() {
+        // 07:00 in Tokyo is 22:00 UTC the day before (no DST there)
+        let alarm = Alarm {
+            active_days: ActiveDays(0x01),
+            hour: 7,
+            timezone: Zone::Named(chrono_tz::Asia::Tokyo),
+            ..Default::default()
+        };
+        // Monday 2023-07-03 in Tokyo
+        let ring_at = Utc
+            .with_ymd_and_hms(2023, 7, 2, 22, 0, 0)
+            .unwrap()
+            .with_timezone(&Local);
+
+        assert_eq!(
+            alarm.due_occurrence(ring_at).unwrap(),
+            NaiveDate::from_ymd_opt(2023, 7, 3)
+        );
+        assert_eq!(
+            alarm.next_occurrence(ring_at - Duration::hours(1)),
+            Some(ring_at)
+        );
+        assert_eq!(
+            alarm.due_occurrence(ring_at + Duration::hours(1)).unwrap(),
+            None
+        );
+        assert_eq!(
+            serde_json::to_value(alarm).unwrap()["timezone"],
+            "Asia/Tokyo"
+        );
+    }
+
+    #[test]
+    fn test_validity_window() {
+        let alarm = Alarm {
+            active_days: ActiveDays::EVERYDAY,
+            hour: 7,
+            valid_from: NaiveDate::from_ymd_opt(2023, 9, 1),
+            valid_until: NaiveDate::from_ymd_opt(2024, 6, 30),
+            ..Default::default()
+        };
+        let at = |year, month, day| {
+            Local
+                .from_local_datetime(
+                    &NaiveDate::from_ymd_opt(year, month, day)
+                        .unwrap()
+                        .and_hms_opt(7, 0, 0)
+                        .unwrap(),
+                )
+                .unwrap()
+        };
+
+        assert!(alarm.due_occurrence(at(2023, 8, 31)).unwrap().is_none());
+        assert!(alarm.due_occurrence(at(2023, 9, 1)).unwrap().is_some());
+        assert!(alarm.due_occurrence(at(2024, 6, 30)).unwrap().is_some());
+        assert!(alarm.due_occurrence(at(2024, 7, 1)).unwrap().is_none());
+
+        // Months ahead of the window
+        assert_eq!(alarm.next_occurrence(at(2023, 1, 1)), Some(at(2023, 9, 1)));
+        assert_eq!(alarm.next_occurrence(at(2024, 6, 30)), None);
+    }
+
+    #[test]
+    fn test_due_between() {
+        let alarm = Alarm {
+            active_days: ActiveDays(0x01),
+            hour: 7,
+            ..Default::default()
+        };
+        // 2023-07-03 was a Monday
+        let monday = NaiveDate::from_ymd_opt(2023, 7, 3).unwrap();
+        let at = |date: NaiveDate, hour| {
+            Local
+                .from_local_datetime(&date.and_hms_opt(hour, 0, 0).unwrap())
+                .unwrap()
+        };
+
+        // A fortnight of suspend
+        assert_eq!(
+            alarm
+                .due_between(at(monday, 6), at(monday + Duration::days(14), 6))
+                .unwrap(),
+            vec![monday, monday + Duration::days(7)]
+        );
+        // Bounds: the start is excluded, the end included
+        assert!(alarm
+            .due_between(at(monday, 7), at(monday, 8))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            alarm.due_between(at(monday, 6), at(monday, 7)).unwrap(),
+            vec![monday]
+        );
+        assert_eq!(alarm.ring_time(monday).unwrap(), Some(at(monday, 7)));
+        assert_eq!(alarm.ring_time(monday + Duration::days(1)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_dst_gap() {
+        // Clocks went from 02:00 to 03:00 in Paris on Sunday 2023-03-26
+        let paris = chrono_tz::Europe::Paris;
+        let alarm = Alarm {
+            active_days: ActiveDays(0x40),
+            hour: 2,
+            minute: 30,
+            timezone: Zone::Named(paris),
+            ..Default::default()
+        };
+        let sunday = NaiveDate::from_ymd_opt(2023, 3, 26).unwrap();
+        let gap_end = paris
+            .with_ymd_and_hms(2023, 3, 26, 3, 0, 0)
+            .unwrap()
+            .with_timezone(&Local);
+
+        // Due during the night, ringing once the clocks went forward
+        assert_eq!(
+            alarm
+                .due_between(gap_end - Duration::hours(2), gap_end + Duration::hours(1))
+                .unwrap(),
+            vec![sunday]
+        );
+        assert_eq!(alarm.ring_time(sunday).unwrap(), Some(gap_end));
+        assert_eq!(
+            alarm.next_occurrence(gap_end - Duration::hours(2)),
+            Some(gap_end)
+        );
+        // Not ringing that day is not an error
+        assert_eq!(alarm.ring_time(sunday - Duration::days(1)).unwrap(), None);
+        // Other days are not shifted
+        assert_eq!(
+            alarm.ring_time(sunday + Duration::days(7)).unwrap(),
+            Some(
+                paris
+                    .with_ymd_and_hms(2023, 4, 2, 2, 30, 0)
+                    .unwrap()
+                    .with_timezone(&Local)
+            )
+        );
+    }
+
+    #[test]
+    fn test_skipping() {
+        let conn = Connection::open(":memory:").unwrap();
+        Alarm {
+            active_days: ActiveDays(0x01),
+            hour: 7,
+            ..Default::default()
+        }
+        .save(&conn)
+        .unwrap();
+        let alarm = Alarm::all(&conn).unwrap().remove(0);
+        // 2023-07-03 was a Monday
+        let monday = NaiveDate::from_ymd_opt(2023, 7, 3).unwrap();
+        let after = Local
+            .from_local_datetime(&monday.and_hms_opt(8, 0, 0).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            alarm.skip_next_after(&conn, after).unwrap(),
+            Some(monday + Duration::days(7))
+        );
+        assert!(alarm.is_skipped(&conn, monday + Duration::days(7)).unwrap());
+        assert!(!alarm.is_skipped(&conn, monday).unwrap());
+
+        assert!(alarm.unskip(&conn, monday + Duration::days(7)).unwrap());
+        assert!(!alarm.unskip(&conn, monday + Duration::days(7)).unwrap());
+        assert!(alarm.skipped_dates(&conn).unwrap().is_empty());
+
+        // Unsaved or never ringing alarms
+        assert!(Alarm::default().skip_next(&conn).is_ok());
+        assert!(Alarm::default().skip(&conn, monday).is_err());
+
+        // Exceptions are kept while the alarm can be restored, then go away with it
+        alarm.skip(&conn, monday).unwrap();
+        alarm.remove(&conn).unwrap();
+        assert_eq!(alarm.skipped_dates(&conn).unwrap(), vec![monday]);
+        assert_eq!(
+            Alarm::purge(&conn, Local::now().timestamp() - 60).unwrap(),
+            0
+        );
+        assert_eq!(
+            Alarm::purge(&conn, Local::now().timestamp() + 1).unwrap(),
+            1
+        );
+        assert!(alarm.skipped_dates(&conn).unwrap().is_empty());
+        assert!(alarm.restore(&conn).is_err());
+    }
+
+    #[test]
+    fn test_transaction() {
+        let conn = Connection::open(":memory:").unwrap();
+        Alarm::default().save(&conn).unwrap();
+
+        assert!(in_transaction(&conn, || {
+            Alarm::default().save(&conn)?;
+            Alarm::default().save(&conn)?;
+            Err::<(), _>(ClockError("Failed"))
+        })
+        .is_err());
+        assert_eq!(Alarm::all(&conn).unwrap().len(), 1);
+
+        in_transaction(&conn, || Alarm::default().save(&conn)).unwrap();
+        assert_eq!(Alarm::all(&conn).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_next_ring() {
+        let conn = Connection::open(":memory:").unwrap();
+        let alarm = Alarm {
+            id: Some(1),
+            active_days: ActiveDays::EVERYDAY,
+            hour: 7,
+            jitter_minutes: 30,
+            ..Default::default()
+        };
+        // 2023-07-03 was a Monday
+        let monday = NaiveDate::from_ymd_opt(2023, 7, 3).unwrap();
+
+        for day in 0..30 {
+            let date = monday + Duration::days(day);
+            let scheduled = Local
+                .from_local_datetime(&date.and_hms_opt(7, 0, 0).unwrap())
+                .unwrap();
+            let ring_at = scheduled + alarm.jitter(date);
+
+            // Never behind, even for an occurrence rung ahead of its scheduled time
+            for after in [ring_at, scheduled - Duration::seconds(1)] {
+                let next = alarm.next_ring(&conn, after).unwrap().unwrap();
+
+                assert!(next > after);
+                assert!(next <= ring_at.max(after) + Duration::days(2));
+            }
+            assert_eq!(
+                alarm
+                    .next_ring(&conn, ring_at - Duration::seconds(1))
+                    .unwrap(),
+                Some(ring_at)
+            );
+        }
+    }
+
+    #[test]
+    fn test_one_shot() {
+        let date = Local::now().date_naive() + Duration::days(30);
+        let alarm = Alarm {
+            active_days: ActiveDays::EVERYDAY,
+            hour: 7,
+            date: Some(date),
+            ..Default::default()
+        };
+        let ringing_at = |date: NaiveDate| {
+            Local
+                .from_local_datetime(&date.and_hms_opt(7, 0, 0).unwrap())
+                .unwrap()
+        };
+
+        // Far beyond a week, other days ignored
+        assert_eq!(alarm.next_occurrence(Local::now()), Some(ringing_at(date)));
+        assert_eq!(alarm.due_occurrence(ringing_at(date)).unwrap(), Some(date));
+        assert_eq!(
+            alarm
+                .due_occurrence(ringing_at(date - Duration::days(1)))
+                .unwrap(),
+            None
+        );
+        assert_eq!(alarm.next_occurrence(ringing_at(date)), None);
+    }
+
+    #[test]
+    fn test_recurrence() {
+        let alarm = Alarm {
+            active_days: ActiveDays(0x02),
+            hour: 7,
+            recurrence: Some("FREQ=MONTHLY;BYDAY=1MO".parse().unwrap()),
+            ..Default::default()
+        };
+        let at = |day, hour| {
+            Local
+                .from_local_datetime(
+                    &NaiveDate::from_ymd_opt(2023, 7, day)
+                        .unwrap()
+                        .and_hms_opt(hour, 0, 0)
+                        .unwrap(),
+                )
+                .unwrap()
+        };
+
+        // First Monday of July, the active days (Tuesday) being ignored
+        assert!(alarm.due_occurrence(at(3, 7)).unwrap().is_some());
+        assert!(alarm.due_occurrence(at(4, 7)).unwrap().is_none());
+        assert!(alarm.due_occurrence(at(10, 7)).unwrap().is_none());
+        assert_eq!(
+            alarm.next_occurrence(at(3, 6)).unwrap().date_naive(),
+            at(3, 7).date_naive()
+        );
+        assert_eq!(
+            alarm.next_occurrence(at(3, 8)).unwrap().date_naive(),
+            NaiveDate::from_ymd_opt(2023, 8, 7).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&alarm).unwrap()["recurrence"],
+            "FREQ=MONTHLY;BYDAY=1MO"
+        );
+
+        // Anchored at the day it is saved
+        let conn = Connection::open(":memory:").unwrap();
+        alarm.save(&conn).unwrap();
+        assert_eq!(
+            Alarm::all(&conn).unwrap()[0]
+                .recurrence
+                .as_ref()
+                .unwrap()
+                .start,
+            Some(Local::now().date_naive())
+        );
+    }
+
     #[test]
     fn test_binary_conversion() {
         let alarm = Alarm {
@@ -501,10 +3272,184 @@ mod tests {
             hour: 13,
             minute: 12,
             seconds: 9,
+            ..Default::default()
         };
 
-        let alarm2 = Alarm::try_from(alarm.as_bytes()).unwrap();
+        let alarm2 = Alarm::try_from(alarm.as_bytes().unwrap()).unwrap();
 
         assert_eq!(alarm, alarm2);
+
+        let missed = Alarm {
+            id: Some(7),
+            label: "Wake up".to_string(),
+            ..alarm
+        }
+        .snoozed(1_700_000_000)
+        .missed(1_600_000_000);
+        let bytes = missed.as_bytes().unwrap();
+
+        assert_eq!(Alarm::try_from(bytes.clone()).unwrap(), missed);
+        assert_eq!(Alarm::try_from(&bytes[..]).unwrap(), missed);
+        assert_eq!(Alarm::try_from_strict(&bytes).unwrap(), missed);
+        assert!(Alarm::try_from(bytes[..bytes.len() - 1].to_vec()).is_err());
+        assert_eq!(
+            Alarm::try_from_strict(&[bytes.as_slice(), &[0]].concat())
+                .unwrap_err()
+                .reason,
+            "Trailing bytes"
+        );
+
+        for (field, invalid) in [
+            (
+                "hour",
+                Alarm {
+                    hour: 99,
+                    ..missed.clone()
+                },
+            ),
+            (
+                "rotation",
+                Alarm {
+                    rotation: Some(Rotation {
+                        start: NaiveDate::from_ymd_opt(2023, 7, 3).unwrap(),
+                        weeks: vec![
+                            None,
+                            Some(RotationWeek {
+                                hour: 7,
+                                minute: 75,
+                                seconds: 0,
+                                active_days: None,
+                            }),
+                        ],
+                    }),
+                    ..missed.clone()
+                },
+            ),
+        ] {
+            let bytes = invalid.as_bytes().unwrap();
+            assert_eq!(Alarm::try_from(bytes.clone()).unwrap(), invalid);
+            let error = Alarm::try_from_strict(&bytes).unwrap_err();
+            assert_eq!((error.offset, error.field), (0, field));
+        }
+        // Days travel as names, unknown day bits are dropped on the way
+        assert_eq!(
+            Alarm {
+                active_days: ActiveDays(0xFF),
+                ..missed
+            }
+            .check_ranges()
+            .unwrap_err()
+            .field,
+            "active_days"
+        );
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[test]
+    fn test_protobuf() {
+        let endless = Alarm {
+            ring_minutes: 0,
+            snooze_minutes: 0,
+            ..Default::default()
+        };
+        let proto = crate::proto::Alarm::from(endless.clone());
+
+        assert_eq!(proto.ring_minutes, Some(0));
+        assert_eq!(Alarm::try_from(proto).unwrap(), endless);
+
+        // Unset durations fall back to the defaults
+        let proto = crate::proto::Alarm {
+            ring_minutes: None,
+            snooze_minutes: None,
+            ..endless.into()
+        };
+        let alarm = Alarm::try_from(proto).unwrap();
+        assert_eq!(alarm.ring_minutes, Alarm::default().ring_minutes);
+        assert_eq!(alarm.snooze_minutes, Alarm::default().snooze_minutes);
+    }
+
+    #[test]
+    fn test_text_format() {
+        for text in [
+            "Mon,Tue 07:30:00 [Work]",
+            "Mon,Tue,Wed,Thu,Fri,Sat,Sun 23:59:59",
+            "2023-07-12 09:00:05 [Dentist, bring the papers]",
+            "- 12:00:00 [Someday]",
+        ] {
+            assert_eq!(text.parse::<Alarm>().unwrap().to_string(), text);
+        }
+
+        let alarm = Alarm {
+            id: Some(3),
+            active_days: ActiveDays::WEEKEND,
+            hour: 10,
+            jitter_minutes: 5,
+            ..Default::default()
+        };
+        let parsed = alarm.to_string().parse::<Alarm>().unwrap();
+        assert_eq!((parsed.active_days, parsed.hour), (alarm.active_days, 10));
+
+        assert!("Mon".parse::<Alarm>().is_err());
+        assert!("Mon 25:00".parse::<Alarm>().is_err());
+        assert!("Moon 07:00".parse::<Alarm>().is_err());
+        assert!("Mon 07:00 Work".parse::<Alarm>().is_err());
+    }
+
+    #[test]
+    fn test_tags() {
+        let conn = Connection::open(":memory:").unwrap();
+
+        for label in ["Gym", "Work 100%", "Work_late"] {
+            Alarm {
+                label: label.to_string(),
+                group: Some("daily".to_string()),
+                ..Default::default()
+            }
+            .save(&conn)
+            .unwrap();
+        }
+        let alarms = Alarm::all(&conn).unwrap();
+        let tags = ["sport".to_string(), " ".to_string(), "morning ".to_string()];
+
+        assert!(Alarm::default().set_tags(&conn, &tags).is_err());
+        alarms[0].set_tags(&conn, &tags).unwrap();
+        assert_eq!(alarms[0].tags(&conn).unwrap(), vec!["morning", "sport"]);
+        alarms[0].set_tags(&conn, &tags[..1]).unwrap();
+        assert_eq!(alarms[0].tags(&conn).unwrap(), vec!["sport"]);
+
+        assert_eq!(Alarm::search(&conn, "SPORT daily").unwrap(), alarms[..1]);
+        assert_eq!(Alarm::search(&conn, "work").unwrap(), alarms[1..]);
+        assert_eq!(Alarm::search(&conn, "%").unwrap(), alarms[1..2]);
+        assert_eq!(Alarm::search(&conn, "_").unwrap(), alarms[2..]);
+        assert_eq!(Alarm::search(&conn, "  ").unwrap(), alarms);
+        assert!(Alarm::search(&conn, "sport morning").unwrap().is_empty());
+
+        alarms[0].remove(&conn).unwrap();
+        assert!(Alarm::search(&conn, "sport").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_probe_schema() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute(
+            "CREATE TABLE alarms (
+            id INTEGER PRIMARY KEY,
+            active_days INTEGER NOT NULL,
+            hour INTEGER NOT NULL
+            )",
+        )
+        .unwrap();
+
+        let problems = Alarm::probe_schema(&conn).unwrap();
+        assert!(problems.contains(&"Missing column alarms.seconds".to_string()));
+        assert!(!problems.contains(&"Missing column alarms.hour".to_string()));
+
+        // Reporting is all it does: the table is left untouched
+        let columns = conn
+            .prepare("PRAGMA table_info(alarms)")
+            .unwrap()
+            .into_iter()
+            .count();
+        assert_eq!(columns, 3);
     }
 }
@@ -0,0 +1,425 @@
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::{
+    codec,
+    error::{ClockError, DecodeError},
+};
+
+/// Julian date of the Unix epoch
+const UNIX_EPOCH_JD: f64 = 2440587.5;
+/// Julian date of the J2000 epoch (2000-01-01 12:00)
+const J2000_JD: f64 = 2451545.0;
+/// Days between 0001-01-01 and 2000-01-01
+const J2000_CE_DAYS: i32 = 730_120;
+/// Solar altitude of sunrise and sunset (refraction and solar disc included), degrees
+const HORIZON: f64 = -0.833;
+/// Obliquity of the Earth, degrees
+const OBLIQUITY: f64 = 23.4397;
+/// Julian date of a reference new moon (2000-01-06 18:14)
+const NEW_MOON_JD: f64 = 2451550.1;
+/// Mean length of a lunar cycle, days
+const SYNODIC_MONTH: f64 = 29.530588853;
+
+/// Place the sun is watched from, in degrees (north and east being positive).
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::astro::Location;
+///
+/// let paris: Location = "48.8566, 2.3522".parse().unwrap();
+///
+/// assert_eq!(paris.latitude, 48.8566);
+/// assert!("91,0".parse::<Location>().is_err());
+/// assert!("48.8566".parse::<Location>().is_err());
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl FromStr for Location {
+    type Err = ClockError;
+
+    /// Parses `latitude,longitude` degrees (eg: `48.8566,2.3522`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let degrees = s
+            .split(',')
+            .map(|degrees| degrees.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| ClockError("Invalid location"))?;
+
+        match degrees[..] {
+            [latitude, longitude]
+                if (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude) =>
+            {
+                Ok(Self {
+                    latitude,
+                    longitude,
+                })
+            }
+            _ => Err(ClockError("Location must be latitude,longitude degrees")),
+        }
+    }
+}
+
+impl Location {
+    /// Sunrise and sunset on `date` (Unix timestamps, seconds), [None] if the sun does not rise
+    /// or set that day, along with the [SunCycle]. Computed with the sunrise equation, within a
+    /// couple of minutes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use libclockrobustus::astro::{Location, SunCycle};
+    ///
+    /// let tromso: Location = "69.6492,18.9553".parse().unwrap();
+    /// let solstice = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+    ///
+    /// assert_eq!(tromso.sun_times(solstice), (SunCycle::MidnightSun, None));
+    /// ```
+    pub fn sun_times(&self, date: NaiveDate) -> (SunCycle, Option<(i64, i64)>) {
+        let days = date.num_days_from_ce() - J2000_CE_DAYS;
+        // Mean solar noon, then solar anomaly, ecliptic longitude and declination of the sun
+        let noon = days as f64 - self.longitude / 360.0;
+        let anomaly = (357.5291 + 0.98560028 * noon)
+            .rem_euclid(360.0)
+            .to_radians();
+        let center =
+            1.9148 * anomaly.sin() + 0.02 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+        let ecliptic = (anomaly.to_degrees() + center + 180.0 + 102.9372)
+            .rem_euclid(360.0)
+            .to_radians();
+        let transit = J2000_JD + noon + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * ecliptic).sin();
+        let declination = (ecliptic.sin() * OBLIQUITY.to_radians().sin()).asin();
+        let latitude = self.latitude.to_radians();
+        let hour_angle = (HORIZON.to_radians().sin() - latitude.sin() * declination.sin())
+            / (latitude.cos() * declination.cos());
+
+        if hour_angle < -1.0 {
+            return (SunCycle::MidnightSun, None);
+        }
+        if hour_angle > 1.0 {
+            return (SunCycle::PolarNight, None);
+        }
+
+        let half_day = hour_angle.acos().to_degrees() / 360.0;
+        let timestamp = |julian: f64| ((julian - UNIX_EPOCH_JD) * 86400.0).round() as i64;
+
+        (
+            SunCycle::Normal,
+            Some((timestamp(transit - half_day), timestamp(transit + half_day))),
+        )
+    }
+
+    /// Sunrise and sunset of the day of `now`, with the daylight remaining at `now`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use libclockrobustus::astro::{Location, SunCycle};
+    ///
+    /// let equator: Location = "0,0".parse().unwrap();
+    /// let noon = Utc.with_ymd_and_hms(2024, 3, 20, 12, 0, 0).unwrap();
+    /// let sun = equator.sun(noon);
+    ///
+    /// assert_eq!(sun.cycle, SunCycle::Normal);
+    /// // About six hours left
+    /// assert!((360..=375).contains(&sun.daylight_remaining_minutes));
+    /// ```
+    pub fn sun<Tz: TimeZone>(&self, now: DateTime<Tz>) -> SunMessage {
+        let today = now.date_naive();
+        let zone = now.timezone();
+        let now = now.timestamp();
+        let (cycle, times) = self.sun_times(today);
+        let (sunrise, sunset) = times.unwrap_or_default();
+        let daylight_until = match cycle {
+            SunCycle::Normal => sunset,
+            // Until the end of the day
+            SunCycle::MidnightSun => today
+                .succ_opt()
+                .and_then(|tomorrow| tomorrow.and_hms_opt(0, 0, 0))
+                .and_then(|midnight| zone.from_local_datetime(&midnight).earliest())
+                .map_or(now, |midnight| midnight.timestamp()),
+            SunCycle::PolarNight => now,
+        };
+
+        let (moon_phase, moon_illumination) = moon(now);
+
+        SunMessage {
+            cycle,
+            sunrise,
+            sunset,
+            daylight_remaining_minutes: ((daylight_until - now.max(sunrise)).max(0) as u32)
+                .div_ceil(60),
+            moon_phase,
+            moon_illumination,
+        }
+    }
+}
+
+/// Phase of the moon and its illuminated fraction (percents) at `timestamp` (Unix, seconds),
+/// from the mean lunar cycle, within about a day. The same everywhere on Earth.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use libclockrobustus::astro::{moon, MoonPhase};
+///
+/// let full_moon = Utc.with_ymd_and_hms(2024, 1, 25, 18, 0, 0).unwrap();
+///
+/// assert_eq!(moon(full_moon.timestamp()), (MoonPhase::FullMoon, 100));
+/// ```
+pub fn moon(timestamp: i64) -> (MoonPhase, u8) {
+    let julian = timestamp as f64 / 86400.0 + UNIX_EPOCH_JD;
+    // Fraction of the cycle elapsed since the last new moon
+    let age = ((julian - NEW_MOON_JD) / SYNODIC_MONTH).rem_euclid(1.0);
+    let illumination = (1.0 - (age * std::f64::consts::TAU).cos()) / 2.0;
+
+    (
+        MoonPhase::from_byte((age * 8.0).round() as u8 % 8).unwrap_or_default(),
+        (illumination * 100.0).round() as u8,
+    )
+}
+
+/// Eighth of the lunar cycle, named after its middle.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MoonPhase {
+    #[default]
+    NewMoon,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    FullMoon,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent,
+}
+
+impl MoonPhase {
+    fn from_byte(byte: u8) -> Result<Self, ClockError> {
+        match byte {
+            0 => Ok(Self::NewMoon),
+            1 => Ok(Self::WaxingCrescent),
+            2 => Ok(Self::FirstQuarter),
+            3 => Ok(Self::WaxingGibbous),
+            4 => Ok(Self::FullMoon),
+            5 => Ok(Self::WaningGibbous),
+            6 => Ok(Self::LastQuarter),
+            7 => Ok(Self::WaningCrescent),
+            _ => Err(ClockError("Unknown moon phase")),
+        }
+    }
+}
+
+/// Whether the sun rises and sets during the day, above the polar circles it may not.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SunCycle {
+    #[default]
+    Normal,
+    /// The sun stays above the horizon all day
+    MidnightSun,
+    /// The sun stays below the horizon all day
+    PolarNight,
+}
+
+/// Sunrise and sunset of the day, as published by the daemon once a day when a location is
+/// configured (see [Location::sun]), for dawn-aware clock faces. The phase of the moon is carried
+/// as well, so that lunar clock faces need no computation of their own.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SunMessage {
+    pub cycle: SunCycle,
+    /// Unix timestamp (seconds), 0 if the sun does not rise
+    pub sunrise: i64,
+    /// Unix timestamp (seconds), 0 if the sun does not set
+    pub sunset: i64,
+    /// Minutes of daylight left in the day when published, rounded up
+    pub daylight_remaining_minutes: u32,
+    #[serde(default)]
+    pub moon_phase: MoonPhase,
+    /// Illuminated fraction of the moon when published, percents
+    #[serde(default)]
+    pub moon_illumination: u8,
+}
+
+impl SunMessage {
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits)
+    pub const MAX_ENCODED_LEN: usize = 28;
+
+    /// Writes the binary representation at the start of a caller-provided buffer, returning the
+    /// number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is shorter than [SunMessage::MAX_ENCODED_LEN].
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        codec::encode(self, buffer)
+    }
+
+    /// Strict counterpart of the [TryFrom] conversion from bytes: truncated messages, trailing
+    /// bytes (see [codec::decode_strict]) and an illumination above 100% are rejected.
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let message: Self = codec::decode_strict(bytes)?;
+
+        DecodeError::check_range(message.moon_illumination, "moon_illumination", 0..=100)?;
+        Ok(message)
+    }
+}
+
+impl TryFrom<Vec<u8>> for SunMessage {
+    type Error = ClockError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        codec::decode(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    // Tolerance of the sunrise equation, seconds
+    const TOLERANCE: i64 = 180;
+
+    fn assert_near(actual: i64, expected: DateTime<Utc>) {
+        assert!(
+            (actual - expected.timestamp()).abs() <= TOLERANCE,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_sun_times() {
+        let paris: Location = "48.8566,2.3522".parse().unwrap();
+        let solstice = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let (cycle, times) = paris.sun_times(solstice);
+        let (sunrise, sunset) = times.unwrap();
+
+        // 05:47 and 21:58 CEST
+        assert_eq!(cycle, SunCycle::Normal);
+        assert_near(
+            sunrise,
+            Utc.with_ymd_and_hms(2024, 6, 21, 3, 47, 0).unwrap(),
+        );
+        assert_near(
+            sunset,
+            Utc.with_ymd_and_hms(2024, 6, 21, 19, 58, 0).unwrap(),
+        );
+
+        // West of Greenwich and south of the equator, 06:46 and 20:56 CLST
+        let santiago: Location = "-33.4489,-70.6693".parse().unwrap();
+        let (_, times) = santiago.sun_times(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        let (sunrise, sunset) = times.unwrap();
+        assert_near(
+            sunrise,
+            Utc.with_ymd_and_hms(2024, 1, 15, 9, 46, 0).unwrap(),
+        );
+        assert_near(
+            sunset,
+            Utc.with_ymd_and_hms(2024, 1, 15, 23, 56, 0).unwrap(),
+        );
+
+        let tromso: Location = "69.6492,18.9553".parse().unwrap();
+        let winter = NaiveDate::from_ymd_opt(2024, 12, 21).unwrap();
+        assert_eq!(tromso.sun_times(winter), (SunCycle::PolarNight, None));
+    }
+
+    #[test]
+    fn test_daylight_remaining() {
+        let paris: Location = "48.8566,2.3522".parse().unwrap();
+        let at = |hour| Utc.with_ymd_and_hms(2024, 6, 21, hour, 0, 0).unwrap();
+
+        // A whole day of daylight before sunrise, none after sunset
+        assert!((970..=975).contains(&paris.sun(at(2)).daylight_remaining_minutes));
+        assert!((175..=180).contains(&paris.sun(at(17)).daylight_remaining_minutes));
+        assert_eq!(paris.sun(at(21)).daylight_remaining_minutes, 0);
+
+        let tromso: Location = "69.6492,18.9553".parse().unwrap();
+        let night = tromso.sun(Utc.with_ymd_and_hms(2024, 12, 21, 12, 0, 0).unwrap());
+        assert_eq!(night.daylight_remaining_minutes, 0);
+    }
+
+    #[test]
+    fn test_binary_conversion() {
+        let paris: Location = "48.8566,2.3522".parse().unwrap();
+        let message = paris.sun(Utc.with_ymd_and_hms(2024, 6, 21, 12, 0, 0).unwrap());
+        let longest = SunMessage {
+            cycle: SunCycle::PolarNight,
+            sunrise: i64::MIN,
+            sunset: i64::MIN,
+            daylight_remaining_minutes: u32::MAX,
+            moon_phase: MoonPhase::WaningCrescent,
+            moon_illumination: u8::MAX,
+        };
+        let mut buffer = [0u8; SunMessage::MAX_ENCODED_LEN + 1];
+
+        let len = longest.encode_into(&mut buffer).unwrap();
+        assert_eq!(len, SunMessage::MAX_ENCODED_LEN);
+        assert_eq!(
+            SunMessage::try_from(buffer[..len].to_vec()).unwrap(),
+            longest
+        );
+        assert_eq!(
+            SunMessage::try_from_strict(&buffer[..len])
+                .unwrap_err()
+                .field,
+            "moon_illumination"
+        );
+
+        let len = message.encode_into(&mut buffer).unwrap();
+
+        assert_eq!(SunMessage::try_from(buffer.to_vec()).unwrap(), message);
+        assert!(SunMessage::try_from(buffer[..len - 1].to_vec()).is_err());
+        assert_eq!(
+            SunMessage::try_from_strict(&buffer[..len]).unwrap(),
+            message
+        );
+        assert_eq!(
+            SunMessage::try_from_strict(&buffer[..len + 1])
+                .unwrap_err()
+                .reason,
+            "Trailing bytes"
+        );
+
+        buffer[0] = 3;
+        assert_eq!(
+            SunMessage::try_from_strict(&buffer[..len])
+                .unwrap_err()
+                .reason,
+            "Invalid message body"
+        );
+    }
+
+    #[test]
+    fn test_moon() {
+        let at = |day, hour| {
+            Utc.with_ymd_and_hms(2024, 1, day, hour, 0, 0)
+                .unwrap()
+                .timestamp()
+        };
+
+        // New moon on the 11th at 11:57, full moon on the 25th at 17:54
+        assert_eq!(moon(at(11, 12)), (MoonPhase::NewMoon, 0));
+        assert_eq!(moon(at(18, 3)).0, MoonPhase::FirstQuarter);
+        assert_eq!(moon(at(25, 18)), (MoonPhase::FullMoon, 100));
+        assert_eq!(moon(at(29, 12)).0, MoonPhase::WaningGibbous);
+
+        let paris: Location = "48.8566,2.3522".parse().unwrap();
+        let sun = paris.sun(Utc.with_ymd_and_hms(2024, 1, 25, 18, 0, 0).unwrap());
+        assert_eq!(sun.moon_phase, MoonPhase::FullMoon);
+    }
+}
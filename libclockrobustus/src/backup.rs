@@ -0,0 +1,218 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone};
+
+use crate::error::ClockError;
+
+const FILE_PREFIX: &str = "dbase-";
+const FILE_SUFFIX: &str = ".sqlite";
+/// Timestamp embedded in backup file names. Sorting names sorts backups by age.
+const FILE_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// How often automatic backups are taken.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::backup::BackupSchedule;
+///
+/// assert_eq!(BackupSchedule::try_from("weekly").unwrap(), BackupSchedule::Weekly);
+/// assert!(BackupSchedule::try_from("hourly").is_err());
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BackupSchedule {
+    Daily,
+    Weekly,
+}
+
+impl BackupSchedule {
+    /// Time between two backups
+    pub fn period(&self) -> Duration {
+        match self {
+            Self::Daily => Duration::days(1),
+            Self::Weekly => Duration::weeks(1),
+        }
+    }
+
+    /// Tells whether a backup must be taken at `now`, given the time the last one was taken
+    /// (if any).
+    pub fn is_due(&self, last: Option<DateTime<Local>>, now: DateTime<Local>) -> bool {
+        match last {
+            Some(last) => now - last >= self.period(),
+            None => true,
+        }
+    }
+}
+
+impl TryFrom<&str> for BackupSchedule {
+    type Error = ClockError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "daily" => Ok(Self::Daily),
+            "weekly" => Ok(Self::Weekly),
+            _ => Err(ClockError("Unknown backup schedule")),
+        }
+    }
+}
+
+/// Writes a consistent snapshot of the database behind `conn` in `directory` (created if
+/// needed) and returns its path. The snapshot is a regular sqlite database: restoring it is a
+/// matter of copying it over the live database while the daemon is stopped.
+///
+/// # Panics
+///
+/// Panics if the directory cannot be created or if an SQL error is encountered.
+pub fn backup(conn: &sqlite::Connection, directory: &Path) -> Result<PathBuf, ClockError> {
+    fs::create_dir_all(directory)?;
+    let path = directory.join(format!(
+        "{}{}{}",
+        FILE_PREFIX,
+        Local::now().format(FILE_TIME_FORMAT),
+        FILE_SUFFIX
+    ));
+    let target = path
+        .to_str()
+        .ok_or(ClockError("Backup path is not valid UTF-8"))?;
+
+    conn.execute(format!("VACUUM INTO '{}'", target.replace('\'', "''")))?;
+
+    Ok(path)
+}
+
+/// Lists the backups found in `directory`, oldest first, along with the time they were taken.
+///
+/// # Panics
+///
+/// Panics if the directory exists but cannot be read.
+pub fn list(directory: &Path) -> Result<Vec<(DateTime<Local>, PathBuf)>, ClockError> {
+    if !directory.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut res = Vec::new();
+
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let taken_at = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(FILE_PREFIX))
+            .and_then(|name| name.strip_suffix(FILE_SUFFIX))
+            .and_then(|stamp| NaiveDateTime::parse_from_str(stamp, FILE_TIME_FORMAT).ok())
+            .and_then(|stamp| Local.from_local_datetime(&stamp).earliest());
+
+        // Foreign files are left alone
+        if let Some(taken_at) = taken_at {
+            res.push((taken_at, path));
+        }
+    }
+
+    res.sort();
+
+    Ok(res)
+}
+
+/// Removes the oldest backups of `directory`, keeping the `keep` most recent ones (at least
+/// one, so that pruning never removes the backup just taken). Returns the removed paths.
+///
+/// # Panics
+///
+/// Panics if the directory cannot be read or a backup cannot be removed.
+pub fn prune(directory: &Path, keep: usize) -> Result<Vec<PathBuf>, ClockError> {
+    let backups = list(directory)?;
+    let excess = backups.len().saturating_sub(keep.max(1));
+    let mut removed = Vec::new();
+
+    for (_, path) in backups.into_iter().take(excess) {
+        fs::remove_file(&path)?;
+        removed.push(path);
+    }
+
+    Ok(removed)
+}
+
+/// Takes a backup if the `schedule` says one is due, then prunes old ones. Returns the path of
+/// the new backup, [None] if none was due.
+///
+/// # Panics
+///
+/// Panics on any filesystem or SQL error.
+pub fn run_if_due(
+    conn: &sqlite::Connection,
+    directory: &Path,
+    schedule: BackupSchedule,
+    keep: usize,
+) -> Result<Option<PathBuf>, ClockError> {
+    let last = list(directory)?.last().map(|(taken_at, _)| *taken_at);
+
+    if !schedule.is_due(last, Local::now()) {
+        return Ok(None);
+    }
+
+    let path = backup(conn, directory)?;
+    prune(directory, keep)?;
+
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn test_schedule() {
+        let now = Local::now();
+
+        assert!(BackupSchedule::Daily.is_due(None, now));
+        assert!(BackupSchedule::Daily.is_due(Some(now - Duration::days(2)), now));
+        assert!(!BackupSchedule::Daily.is_due(Some(now - Duration::hours(2)), now));
+        assert!(!BackupSchedule::Weekly.is_due(Some(now - Duration::days(2)), now));
+    }
+
+    #[test]
+    fn test_backup_and_prune() {
+        let directory =
+            std::env::temp_dir().join(format!("clockrobustus-backup-test-{}", std::process::id()));
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE foo (bar INTEGER); INSERT INTO foo VALUES (42);")
+            .unwrap();
+
+        let path = backup(&conn, &directory).unwrap();
+        let restored = Connection::open(&path).unwrap();
+        let mut statement = restored.prepare("SELECT bar FROM foo").unwrap();
+        statement.next().unwrap();
+
+        assert_eq!(statement.read::<i64, _>("bar").unwrap(), 42);
+        // Taken right now, nothing is due
+        assert_eq!(
+            run_if_due(&conn, &directory, BackupSchedule::Daily, 7).unwrap(),
+            None
+        );
+
+        // Older backups, and a file which is not a backup
+        fs::write(directory.join("dbase-20200101-000000.sqlite"), b"").unwrap();
+        fs::write(directory.join("dbase-20210101-000000.sqlite"), b"").unwrap();
+        fs::write(directory.join("notes.txt"), b"").unwrap();
+
+        assert_eq!(list(&directory).unwrap().len(), 3);
+        assert_eq!(
+            prune(&directory, 1).unwrap(),
+            vec![
+                directory.join("dbase-20200101-000000.sqlite"),
+                directory.join("dbase-20210101-000000.sqlite"),
+            ]
+        );
+        assert_eq!(list(&directory).unwrap()[0].1, path);
+        // The latest backup is always kept
+        assert!(prune(&directory, 0).unwrap().is_empty());
+        assert!(directory.join("notes.txt").exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,325 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use sqlite::State;
+
+use crate::{
+    alarm::ActiveDays,
+    codec,
+    error::{ClockError, DecodeError},
+};
+
+const TNAME: &str = "bedtimes";
+
+/// Target sleep time on some days of the week (eg: 22:30 on weekdays, 23:30 on the weekend), the
+/// daemon publishing a [BedtimeMessage] `remind_minutes` before it.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{Local, TimeZone};
+/// use libclockrobustus::{alarm::ActiveDays, bedtime::Bedtime};
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+/// let weekdays = Bedtime {
+///     id: None,
+///     active_days: ActiveDays::WEEKDAYS,
+///     hour: 22,
+///     minute: 30,
+///     remind_minutes: 30,
+/// };
+///
+/// weekdays.save(&conn).unwrap();
+/// let bedtime = Bedtime::all(&conn).unwrap().remove(0);
+///
+/// // 2023-07-03 was a Monday, reminded at 22:00
+/// let after = Local.with_ymd_and_hms(2023, 7, 3, 21, 59, 0).unwrap();
+/// let until = Local.with_ymd_and_hms(2023, 7, 3, 22, 0, 0).unwrap();
+///
+/// assert_eq!(
+///     bedtime.due_between(after, until),
+///     Local.with_ymd_and_hms(2023, 7, 3, 22, 30, 0).single()
+/// );
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bedtime {
+    pub id: Option<i64>,
+    pub active_days: ActiveDays,
+    pub hour: u8,
+    pub minute: u8,
+    /// Minutes between the reminder and the bedtime
+    #[serde(default)]
+    pub remind_minutes: u8,
+}
+
+impl Bedtime {
+    // Essential db check
+    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER PRIMARY KEY,
+            active_days INTEGER NOT NULL,
+            hour INTEGER NOT NULL,
+            minute INTEGER NOT NULL,
+            remind_minutes INTEGER NOT NULL DEFAULT 0
+            )",
+            TNAME
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    /// Saves (creates or updates) the bedtime. Creates the table 'bedtimes' if not present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the time is invalid or if an SQL error has been encountered
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        if self.hour > 23 || self.minute > 59 {
+            return Err(ClockError("Invalid bedtime"));
+        }
+
+        Self::check_table(conn)?;
+        let query = match self.id {
+            Some(_) => format!(
+                "UPDATE {} SET active_days = :active_days, hour = :hour, minute = :minute,
+                remind_minutes = :remind_minutes WHERE id = :id",
+                TNAME
+            ),
+            None => format!(
+                "INSERT INTO {} (active_days, hour, minute, remind_minutes)
+                VALUES (:active_days, :hour, :minute, :remind_minutes)",
+                TNAME
+            ),
+        };
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":active_days", self.active_days.0 as i64))?;
+        statement.bind((":hour", self.hour as i64))?;
+        statement.bind((":minute", self.minute as i64))?;
+        statement.bind((":remind_minutes", self.remind_minutes as i64))?;
+        if let Some(id) = self.id {
+            statement.bind((":id", id))?;
+        }
+        statement.next()?;
+
+        Ok(())
+    }
+
+    /// Get every bedtime
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn all(conn: &sqlite::Connection) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("SELECT * FROM {} ORDER BY id", TNAME);
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            res.push(Bedtime {
+                id: Some(statement.read::<i64, _>("id")?),
+                active_days: ActiveDays(statement.read::<i64, _>("active_days")? as u8),
+                hour: statement.read::<i64, _>("hour")? as u8,
+                minute: statement.read::<i64, _>("minute")? as u8,
+                remind_minutes: statement.read::<i64, _>("remind_minutes")? as u8,
+            })
+        }
+
+        Ok(res)
+    }
+
+    /// Removes a saved bedtime
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bedtime has no id (eg: not saved).
+    pub fn remove(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let id = self
+            .id
+            .ok_or(ClockError("Impossible to delete an unsaved bedtime"))?;
+        let query = format!("DELETE FROM {} WHERE id = {}", TNAME, id);
+
+        conn.execute(query)?;
+        Ok(())
+    }
+
+    /// Bedtime on the given date, [None] if the date is not an active day.
+    fn on(&self, date: NaiveDate) -> Option<DateTime<Local>> {
+        if !self.active_days.to_weekdays().contains(&date.weekday()) {
+            return None;
+        }
+
+        Local
+            .from_local_datetime(&date.and_hms_opt(self.hour as u32, self.minute as u32, 0)?)
+            .earliest()
+    }
+
+    /// Bedtime whose reminder is due after `after` and up to `until`, [None] if there is none.
+    /// The reminder may fall on the day before (eg: a bedtime at 00:15 reminded at 23:45).
+    pub fn due_between(
+        &self,
+        after: DateTime<Local>,
+        until: DateTime<Local>,
+    ) -> Option<DateTime<Local>> {
+        let remind = Duration::minutes(self.remind_minutes as i64);
+        let today = until.date_naive();
+
+        [today, today + Duration::days(1)]
+            .into_iter()
+            .filter_map(|date| self.on(date))
+            .find(|bedtime| *bedtime - remind > after && *bedtime - remind <= until)
+    }
+
+    /// Message announcing the given bedtime.
+    pub fn message(&self, bedtime: DateTime<Local>) -> BedtimeMessage {
+        BedtimeMessage {
+            id: self.id.unwrap_or_default(),
+            bedtime: bedtime.timestamp(),
+            minutes_left: self.remind_minutes,
+        }
+    }
+}
+
+/// Reminder of an upcoming [Bedtime], as published by the daemon.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BedtimeMessage {
+    pub id: i64,
+    /// Unix timestamp (seconds) of the bedtime
+    pub bedtime: i64,
+    pub minutes_left: u8,
+}
+
+impl BedtimeMessage {
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits)
+    pub const MAX_ENCODED_LEN: usize = 21;
+
+    /// Writes the binary representation at the start of a caller-provided buffer, returning the
+    /// number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is shorter than [BedtimeMessage::MAX_ENCODED_LEN].
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        codec::encode(self, buffer)
+    }
+
+    /// Strict counterpart of the [TryFrom] conversion from bytes: truncated messages and
+    /// trailing bytes are rejected (see [codec::decode_strict]).
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        codec::decode_strict(bytes)
+    }
+}
+
+impl TryFrom<Vec<u8>> for BedtimeMessage {
+    type Error = ClockError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        codec::decode(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn test_saving() {
+        let conn = Connection::open(":memory:").unwrap();
+        let weekend = Bedtime {
+            id: None,
+            active_days: ActiveDays::WEEKEND,
+            hour: 23,
+            minute: 30,
+            remind_minutes: 15,
+        };
+
+        assert!(Bedtime {
+            hour: 24,
+            ..weekend.clone()
+        }
+        .save(&conn)
+        .is_err());
+        weekend.save(&conn).unwrap();
+
+        let mut saved = Bedtime::all(&conn).unwrap().remove(0);
+        assert_eq!(
+            saved,
+            Bedtime {
+                id: saved.id,
+                ..weekend
+            }
+        );
+
+        saved.remind_minutes = 45;
+        saved.save(&conn).unwrap();
+        assert_eq!(Bedtime::all(&conn).unwrap(), vec![saved.clone()]);
+
+        saved.remove(&conn).unwrap();
+        assert!(Bedtime::all(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_due_between() {
+        // Sunday night, reminded on Saturday
+        let bedtime = Bedtime {
+            id: Some(1),
+            active_days: ActiveDays(0x40),
+            hour: 0,
+            minute: 15,
+            remind_minutes: 30,
+        };
+        let at = |day: u32, hour: u32, minute: u32| {
+            Local
+                .with_ymd_and_hms(2023, 7, day, hour, minute, 0)
+                .unwrap()
+        };
+
+        // 2023-07-09 was a Sunday
+        assert_eq!(
+            bedtime.due_between(at(8, 23, 44), at(8, 23, 45)),
+            Some(at(9, 0, 15))
+        );
+        assert_eq!(bedtime.due_between(at(8, 23, 45), at(8, 23, 46)), None);
+        assert_eq!(bedtime.due_between(at(9, 23, 44), at(9, 23, 45)), None);
+        assert_eq!(
+            bedtime.message(at(9, 0, 15)),
+            BedtimeMessage {
+                id: 1,
+                bedtime: at(9, 0, 15).timestamp(),
+                minutes_left: 30,
+            }
+        );
+    }
+
+    #[test]
+    fn test_binary_conversion() {
+        let message = BedtimeMessage {
+            id: i64::MIN,
+            bedtime: i64::MIN,
+            minutes_left: u8::MAX,
+        };
+        let mut buffer = [0u8; BedtimeMessage::MAX_ENCODED_LEN + 1];
+
+        let len = message.encode_into(&mut buffer).unwrap();
+
+        assert_eq!(len, BedtimeMessage::MAX_ENCODED_LEN);
+        assert_eq!(BedtimeMessage::try_from(buffer.to_vec()).unwrap(), message);
+        assert!(BedtimeMessage::try_from(buffer[..len - 1].to_vec()).is_err());
+        assert_eq!(
+            BedtimeMessage::try_from_strict(&buffer[..len]).unwrap(),
+            message
+        );
+        assert_eq!(
+            BedtimeMessage::try_from_strict(&buffer).unwrap_err().reason,
+            "Trailing bytes"
+        );
+    }
+}
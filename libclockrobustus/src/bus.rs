@@ -0,0 +1,525 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{
+    alarm::Alarm, astro::SunMessage, bedtime::BedtimeMessage, clock::ClockMessage,
+    countdown::CountdownMessage, error::ClockError, heartbeat::HeartbeatMessage,
+    interval::IntervalMessage, jump::ClockJumpMessage, message::Message, next::NextAlarmMessage,
+    ntp::SyncStatusMessage, pomodoro::PomodoroMessage, queue::listen_tracked,
+    reminder::ReminderMessage, speak::SpeakMessage, world_clock::WorldClockMessage,
+};
+
+/// Delay before the listener is restarted after failing.
+const RESTART_DELAY: Duration = Duration::from_millis(500);
+
+/// State of the listener behind an [EventBus].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BusStatus {
+    /// The listener is connected and dispatching messages.
+    Listening,
+    /// The listener has been stopped (see [EventBus::stop]).
+    Stopped,
+    /// The listener died on an error, it is restarted shortly.
+    Failed(&'static str),
+}
+
+/// Messages of a topic dropped on the way to the listener (see [crate::sequence]), the data
+/// displayed from this topic may be stale.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MissedMessages {
+    pub topic: &'static str,
+    pub count: u64,
+}
+
+/// Handle returned when subscribing, used to unsubscribe.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Subscription(usize);
+
+type Handler<T> = Arc<dyn Fn(&T) + Send + Sync>;
+type Handlers<T> = RwLock<Vec<(usize, Handler<T>)>>;
+
+/// In-process fan-out of the daemon messages: a single SUB socket (see [listen_tracked]) feeds
+/// any number of per-type handlers, so every part of an application (window, tray,
+/// notifications) does not need its own socket.
+///
+/// Clock messages are only subscribed to while at least one clock handler is registered and the
+/// clock is not paused.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+/// use libclockrobustus::{bus::EventBus, clock::ClockMessage, message::Message};
+///
+/// let bus = EventBus::new();
+/// let ticks = Arc::new(AtomicUsize::new(0));
+/// let counter = ticks.clone();
+///
+/// let subscription = bus.on_clock(move |_| {
+///     counter.fetch_add(1, Ordering::SeqCst);
+/// });
+///
+/// // Normally done by the listener thread (see EventBus::start)
+/// bus.dispatch(Message::from(ClockMessage::default()));
+/// bus.unsubscribe(subscription);
+/// bus.dispatch(Message::from(ClockMessage::default()));
+///
+/// assert_eq!(ticks.load(Ordering::SeqCst), 1);
+/// ```
+pub struct EventBus {
+    clock: Handlers<ClockMessage>,
+    alarm: Handlers<Alarm>,
+    countdown: Handlers<CountdownMessage>,
+    pomodoro: Handlers<PomodoroMessage>,
+    interval: Handlers<IntervalMessage>,
+    next_alarm: Handlers<NextAlarmMessage>,
+    bedtime: Handlers<BedtimeMessage>,
+    reminder: Handlers<ReminderMessage>,
+    world_clock: Handlers<WorldClockMessage>,
+    sun: Handlers<SunMessage>,
+    sync_status: Handlers<SyncStatusMessage>,
+    clock_jump: Handlers<ClockJumpMessage>,
+    speak: Handlers<SpeakMessage>,
+    heartbeat: Handlers<HeartbeatMessage>,
+    missed: Handlers<MissedMessages>,
+    status: Handlers<BusStatus>,
+    next_id: AtomicUsize,
+    running: Arc<AtomicBool>,
+    clock_flag: Arc<AtomicBool>,
+    clock_paused: AtomicBool,
+}
+
+impl EventBus {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            clock: RwLock::new(Vec::new()),
+            alarm: RwLock::new(Vec::new()),
+            countdown: RwLock::new(Vec::new()),
+            pomodoro: RwLock::new(Vec::new()),
+            interval: RwLock::new(Vec::new()),
+            next_alarm: RwLock::new(Vec::new()),
+            bedtime: RwLock::new(Vec::new()),
+            reminder: RwLock::new(Vec::new()),
+            world_clock: RwLock::new(Vec::new()),
+            sun: RwLock::new(Vec::new()),
+            sync_status: RwLock::new(Vec::new()),
+            clock_jump: RwLock::new(Vec::new()),
+            speak: RwLock::new(Vec::new()),
+            heartbeat: RwLock::new(Vec::new()),
+            missed: RwLock::new(Vec::new()),
+            status: RwLock::new(Vec::new()),
+            next_id: AtomicUsize::new(0),
+            running: Arc::new(AtomicBool::new(true)),
+            clock_flag: Arc::new(AtomicBool::new(false)),
+            clock_paused: AtomicBool::new(false),
+        })
+    }
+
+    // Registers a handler in one of the lists
+    fn subscribe<T>(
+        &self,
+        handlers: &Handlers<T>,
+        handler: Box<dyn Fn(&T) + Send + Sync>,
+    ) -> Subscription {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+
+        handlers
+            .write()
+            .expect("Event bus lock poisoned")
+            .push((id, Arc::from(handler)));
+        self.update_clock_flag();
+
+        Subscription(id)
+    }
+
+    // Clock messages are wanted if someone listens and nobody paused them
+    fn update_clock_flag(&self) {
+        let wanted = !self.clock_paused.load(Ordering::SeqCst)
+            && !self
+                .clock
+                .read()
+                .expect("Event bus lock poisoned")
+                .is_empty();
+
+        self.clock_flag.store(wanted, Ordering::SeqCst);
+    }
+
+    /// Calls `handler` on every clock message.
+    pub fn on_clock<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&ClockMessage) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.clock, Box::new(handler))
+    }
+
+    /// Calls `handler` on every ringing alarm.
+    pub fn on_alarm<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&Alarm) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.alarm, Box::new(handler))
+    }
+
+    /// Calls `handler` on every countdown update.
+    pub fn on_countdown<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&CountdownMessage) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.countdown, Box::new(handler))
+    }
+
+    /// Calls `handler` on every pomodoro phase change.
+    pub fn on_pomodoro<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&PomodoroMessage) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.pomodoro, Box::new(handler))
+    }
+
+    /// Calls `handler` on every interval timer reminder.
+    pub fn on_interval<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&IntervalMessage) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.interval, Box::new(handler))
+    }
+
+    /// Calls `handler` on every change of the next alarm (or of the time left before it).
+    pub fn on_next_alarm<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&NextAlarmMessage) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.next_alarm, Box::new(handler))
+    }
+
+    /// Calls `handler` on every bedtime reminder.
+    pub fn on_bedtime<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&BedtimeMessage) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.bedtime, Box::new(handler))
+    }
+
+    /// Calls `handler` on every due reminder.
+    pub fn on_reminder<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&ReminderMessage) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.reminder, Box::new(handler))
+    }
+
+    /// Calls `handler` on every world clock time.
+    pub fn on_world_clock<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&WorldClockMessage) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.world_clock, Box::new(handler))
+    }
+
+    /// Calls `handler` whenever the sunrise and sunset of the day are published.
+    pub fn on_sun<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&SunMessage) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.sun, Box::new(handler))
+    }
+
+    /// Calls `handler` on every NTP synchronization status.
+    pub fn on_sync_status<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&SyncStatusMessage) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.sync_status, Box::new(handler))
+    }
+
+    /// Calls `handler` on every change of the system clock.
+    pub fn on_clock_jump<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&ClockJumpMessage) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.clock_jump, Box::new(handler))
+    }
+
+    /// Calls `handler` on every speaking clock announcement.
+    pub fn on_speak<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&SpeakMessage) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.speak, Box::new(handler))
+    }
+
+    /// Calls `handler` on every heartbeat of the daemon.
+    pub fn on_heartbeat<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&HeartbeatMessage) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.heartbeat, Box::new(handler))
+    }
+
+    /// Calls `handler` whenever messages were dropped on the way, before dispatching the message
+    /// following them.
+    pub fn on_missed<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&MissedMessages) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.missed, Box::new(handler))
+    }
+
+    /// Calls `handler` whenever the listener status changes.
+    pub fn on_status<F>(&self, handler: F) -> Subscription
+    where
+        F: Fn(&BusStatus) + Send + Sync + 'static,
+    {
+        self.subscribe(&self.status, Box::new(handler))
+    }
+
+    /// Removes a handler, whatever its type.
+    pub fn unsubscribe(&self, subscription: Subscription) {
+        let lists: [&dyn Unsubscribe; 16] = [
+            &self.clock,
+            &self.alarm,
+            &self.countdown,
+            &self.pomodoro,
+            &self.interval,
+            &self.next_alarm,
+            &self.bedtime,
+            &self.reminder,
+            &self.world_clock,
+            &self.sun,
+            &self.sync_status,
+            &self.clock_jump,
+            &self.speak,
+            &self.heartbeat,
+            &self.missed,
+            &self.status,
+        ];
+
+        for handlers in lists {
+            handlers.remove(subscription.0);
+        }
+        self.update_clock_flag();
+    }
+
+    /// Stops (or restarts) clock messages delivery, eg: while nothing is displayed. Every other
+    /// message (alarms, countdowns, reminders...) still comes through.
+    pub fn set_clock_paused(&self, paused: bool) {
+        self.clock_paused.store(paused, Ordering::SeqCst);
+        self.update_clock_flag();
+    }
+
+    /// Hands a message to the matching handlers.
+    pub fn dispatch(&self, message: Message) {
+        match message {
+            Message::Clock(clock) => Self::call(&self.clock, &clock),
+            Message::Alarm(alarm) => Self::call(&self.alarm, &alarm),
+            Message::Countdown(countdown) => Self::call(&self.countdown, &countdown),
+            Message::Pomodoro(pomodoro) => Self::call(&self.pomodoro, &pomodoro),
+            Message::Interval(interval) => Self::call(&self.interval, &interval),
+            Message::NextAlarm(next_alarm) => Self::call(&self.next_alarm, &next_alarm),
+            Message::Bedtime(bedtime) => Self::call(&self.bedtime, &bedtime),
+            Message::Reminder(reminder) => Self::call(&self.reminder, &reminder),
+            Message::WorldClock(world_clock) => Self::call(&self.world_clock, &world_clock),
+            Message::Sun(sun) => Self::call(&self.sun, &sun),
+            Message::SyncStatus(sync_status) => Self::call(&self.sync_status, &sync_status),
+            Message::ClockJump(clock_jump) => Self::call(&self.clock_jump, &clock_jump),
+            Message::Speak(speak) => Self::call(&self.speak, &speak),
+            Message::Heartbeat(heartbeat) => Self::call(&self.heartbeat, &heartbeat),
+            Message::AlarmBatch(alarms) => {
+                for alarm in &alarms {
+                    Self::call(&self.alarm, alarm);
+                }
+            }
+            // Newer message types, no handler to call
+            Message::Unknown(..) => (),
+        }
+    }
+
+    fn call<T>(handlers: &Handlers<T>, value: &T) {
+        // Called out of the lock, so that handlers can (un)subscribe
+        let handlers = handlers
+            .read()
+            .expect("Event bus lock poisoned")
+            .iter()
+            .map(|(_, handler)| handler.clone())
+            .collect::<Vec<_>>();
+
+        for handler in handlers {
+            handler(value);
+        }
+    }
+
+    /// Spawns the listener thread feeding the bus, until [EventBus::stop] is called. Status
+    /// handlers are told when it starts, fails (it is then restarted) and stops.
+    pub fn start(self: &Arc<Self>) -> JoinHandle<()> {
+        let bus = self.clone();
+
+        thread::spawn(move || {
+            bus.supervise(|| {
+                listen_tracked(
+                    bus.running.clone(),
+                    bus.clock_flag.clone(),
+                    |message, received| {
+                        if received.missed > 0 {
+                            let missed = MissedMessages {
+                                topic: std::str::from_utf8(message.topic()).unwrap_or_default(),
+                                count: received.missed,
+                            };
+
+                            Self::call(&bus.missed, &missed);
+                        }
+                        bus.dispatch(message)
+                    },
+                )
+            })
+        })
+    }
+
+    // Runs the listener, restarting it after each failure until the bus is stopped
+    fn supervise<F>(&self, mut listener: F)
+    where
+        F: FnMut() -> Result<(), ClockError>,
+    {
+        loop {
+            Self::call(&self.status, &BusStatus::Listening);
+
+            match listener() {
+                Ok(()) => break,
+                Err(error) => Self::call(&self.status, &BusStatus::Failed(error.0)),
+            }
+
+            thread::sleep(RESTART_DELAY);
+            if !self.running.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+
+        Self::call(&self.status, &BusStatus::Stopped);
+    }
+
+    /// Asks the listener thread to stop.
+    pub fn stop(&self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Type-erased removal, so [EventBus::unsubscribe] does not need to know the handler type.
+trait Unsubscribe {
+    fn remove(&self, id: usize);
+}
+
+impl<T> Unsubscribe for Handlers<T> {
+    fn remove(&self, id: usize) {
+        self.write()
+            .expect("Event bus lock poisoned")
+            .retain(|(handler_id, _)| *handler_id != id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn test_dispatch_by_type() {
+        let bus = EventBus::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let first = received.clone();
+        bus.on_alarm(move |alarm| first.lock().unwrap().push(("first", alarm.hour)));
+        let second = received.clone();
+        bus.on_alarm(move |alarm| second.lock().unwrap().push(("second", alarm.hour)));
+
+        // Nobody wants clock messages yet
+        assert!(!bus.clock_flag.load(Ordering::SeqCst));
+
+        bus.dispatch(Message::from(ClockMessage::default()));
+        bus.dispatch(Message::from(Alarm {
+            hour: 7,
+            ..Default::default()
+        }));
+
+        assert_eq!(*received.lock().unwrap(), vec![("first", 7), ("second", 7)]);
+    }
+
+    #[test]
+    fn test_clock_flag() {
+        let bus = EventBus::new();
+        let subscription = bus.on_clock(|_| ());
+
+        assert!(bus.clock_flag.load(Ordering::SeqCst));
+
+        bus.set_clock_paused(true);
+        assert!(!bus.clock_flag.load(Ordering::SeqCst));
+
+        bus.set_clock_paused(false);
+        assert!(bus.clock_flag.load(Ordering::SeqCst));
+
+        bus.unsubscribe(subscription);
+        assert!(!bus.clock_flag.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_subscribe_from_handler() {
+        let bus = EventBus::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let inner = bus.clone();
+        let sink = received.clone();
+        bus.on_alarm(move |_| {
+            let sink = sink.clone();
+            // Takes the handlers lock while they are being called
+            inner.on_alarm(move |alarm| sink.lock().unwrap().push(alarm.hour));
+        });
+
+        for hour in [7, 8] {
+            bus.dispatch(Message::from(Alarm {
+                hour,
+                ..Default::default()
+            }));
+        }
+
+        // Handlers subscribed during a dispatch are called from the next one
+        assert_eq!(*received.lock().unwrap(), vec![8]);
+    }
+
+    #[test]
+    fn test_restart_after_failure() {
+        let bus = EventBus::new();
+        let statuses = Arc::new(Mutex::new(Vec::new()));
+        let sink = statuses.clone();
+        bus.on_status(move |status| sink.lock().unwrap().push(*status));
+
+        let mut attempts = 0;
+        bus.supervise(|| {
+            attempts += 1;
+            if attempts < 3 {
+                return Err(ClockError("Listener failed"));
+            }
+            bus.stop();
+            Ok(())
+        });
+
+        assert_eq!(
+            *statuses.lock().unwrap(),
+            vec![
+                BusStatus::Listening,
+                BusStatus::Failed("Listener failed"),
+                BusStatus::Listening,
+                BusStatus::Failed("Listener failed"),
+                BusStatus::Listening,
+                BusStatus::Stopped,
+            ]
+        );
+    }
+}
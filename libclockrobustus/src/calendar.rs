@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ClockError, DecodeError};
+
+/// Non-Gregorian calendars that can be attached to clock messages (see
+/// [crate::clock::ClockMessage::with_calendars]).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CalendarKind {
+    /// Tabular (civil) Islamic calendar
+    Hijri,
+    Hebrew,
+    Chinese,
+}
+
+impl TryFrom<&str> for CalendarKind {
+    type Error = ClockError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.trim() {
+            "hijri" => Ok(Self::Hijri),
+            "hebrew" => Ok(Self::Hebrew),
+            "chinese" => Ok(Self::Chinese),
+            _ => Err(ClockError("Unknown calendar")),
+        }
+    }
+}
+
+/// A date in one of the [CalendarKind] calendars.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarDate {
+    /// Year number in the calendar era (the related Gregorian year for the Chinese calendar)
+    pub year: i32,
+    /// Month of the year, starting at 1 (leap months included)
+    pub month: u8,
+    pub day: u8,
+    /// True for a leap month (eg: Adar I, or a repeated Chinese month)
+    pub leap_month: bool,
+}
+
+/// Alternative calendar dates of the current day, carried by extended clock messages. Only the
+/// requested calendars are set.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::calendar::{CalendarDate, CalendarDates};
+///
+/// let dates = CalendarDates {
+///     hebrew: Some(CalendarDate { year: 5783, month: 10, day: 14, leap_month: false }),
+///     ..Default::default()
+/// };
+/// let json = serde_json::to_value(dates).unwrap();
+///
+/// assert_eq!(json["hebrew"]["year"], 5783);
+/// assert!(json["hijri"].is_null());
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarDates {
+    #[serde(default)]
+    pub hijri: Option<CalendarDate>,
+    #[serde(default)]
+    pub hebrew: Option<CalendarDate>,
+    #[serde(default)]
+    pub chinese: Option<CalendarDate>,
+}
+
+impl CalendarDates {
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits): every date set
+    pub const MAX_ENCODED_LEN: usize = 3 * 9;
+
+    /// Computes the dates of the requested calendars for a Gregorian date.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the date is out of the range supported by the calendars.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use libclockrobustus::calendar::{CalendarDates, CalendarKind};
+    ///
+    /// let date = NaiveDate::from_ymd_opt(2023, 7, 3).unwrap();
+    /// let dates = CalendarDates::compute(date, &[CalendarKind::Hebrew]).unwrap();
+    ///
+    /// assert!(dates.hijri.is_none());
+    /// assert_eq!(dates.hebrew.unwrap().year, 5783);
+    /// assert_eq!(dates.hebrew.unwrap().day, 14);
+    /// ```
+    #[cfg(feature = "calendars")]
+    pub fn compute(date: chrono::NaiveDate, kinds: &[CalendarKind]) -> Result<Self, ClockError> {
+        use chrono::Datelike;
+        use icu_calendar::{
+            chinese::Chinese, hebrew::Hebrew, islamic::IslamicCivil, AsCalendar, Date,
+        };
+
+        fn convert<A: AsCalendar>(date: Date<A>, related_iso: bool) -> CalendarDate {
+            let year = date.year();
+            let month = date.month();
+
+            CalendarDate {
+                year: match (related_iso, year.related_iso) {
+                    (true, Some(related)) => related,
+                    _ => year.number,
+                },
+                month: month.ordinal as u8,
+                day: date.day_of_month().0 as u8,
+                leap_month: month.code.0.as_str().ends_with('L'),
+            }
+        }
+
+        let iso = Date::try_new_iso_date(date.year(), date.month() as u8, date.day() as u8)
+            .map_err(|_| ClockError("Date out of the supported calendar range"))?;
+        let mut res = Self::default();
+
+        for kind in kinds {
+            match kind {
+                CalendarKind::Hijri => {
+                    res.hijri = Some(convert(iso.to_calendar(IslamicCivil::new()), false))
+                }
+                CalendarKind::Hebrew => {
+                    res.hebrew = Some(convert(iso.to_calendar(Hebrew::new()), false))
+                }
+                CalendarKind::Chinese => {
+                    res.chinese = Some(convert(iso.to_calendar(Chinese::new()), true))
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Rejects the dates out of range, once decoded (see [crate::codec::decode_strict]).
+    pub(crate) fn check_ranges(&self) -> Result<(), DecodeError> {
+        for date in [self.hijri, self.hebrew, self.chinese].iter().flatten() {
+            DecodeError::check_range(date.month, "calendar_month", 1..=13)?;
+            DecodeError::check_range(date.day, "calendar_day", 1..=31)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec;
+
+    #[test]
+    fn test_encoded_len() {
+        let date = |year| {
+            Some(CalendarDate {
+                year,
+                month: 13,
+                day: 30,
+                leap_month: true,
+            })
+        };
+        let dates = CalendarDates {
+            hijri: date(i32::MIN),
+            hebrew: date(i32::MAX),
+            chinese: date(i32::MIN),
+        };
+        let mut buffer = [0u8; CalendarDates::MAX_ENCODED_LEN];
+
+        let len = codec::encode(&dates, &mut buffer).unwrap();
+
+        assert_eq!(len, CalendarDates::MAX_ENCODED_LEN);
+        assert_eq!(
+            codec::decode_strict::<CalendarDates>(&buffer[..len]).unwrap(),
+            dates
+        );
+        assert!(dates.check_ranges().is_ok());
+        assert_eq!(
+            CalendarDates {
+                hebrew: date(0).map(|date| CalendarDate { month: 14, ..date }),
+                ..dates
+            }
+            .check_ranges()
+            .unwrap_err()
+            .field,
+            "calendar_month"
+        );
+    }
+
+    #[test]
+    fn test_calendar_kind() {
+        assert_eq!(
+            CalendarKind::try_from(" hijri").unwrap(),
+            CalendarKind::Hijri
+        );
+        assert!(CalendarKind::try_from("julian").is_err());
+    }
+
+    #[cfg(feature = "calendars")]
+    #[test]
+    fn test_compute() {
+        // 2023-03-01 was in Sha'ban 1444, in Adar 5783 and the 10th day of the 2nd month of the
+        // Chinese year starting 2023-01-22 (the leap 2nd month only starts on 2023-03-22)
+        let date = chrono::NaiveDate::from_ymd_opt(2023, 3, 1).unwrap();
+        let dates = CalendarDates::compute(
+            date,
+            &[
+                CalendarKind::Hijri,
+                CalendarKind::Hebrew,
+                CalendarKind::Chinese,
+            ],
+        )
+        .unwrap();
+        let hijri = dates.hijri.unwrap();
+        let chinese = dates.chinese.unwrap();
+
+        assert_eq!((hijri.year, hijri.month), (1444, 8));
+        assert_eq!(dates.hebrew.unwrap().year, 5783);
+        assert_eq!((chinese.year, chinese.month, chinese.day), (2023, 2, 10));
+        assert!(!chinese.leap_month);
+    }
+}
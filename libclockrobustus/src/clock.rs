@@ -1,11 +1,353 @@
-use crate::error::ClockError;
-use chrono::prelude::*;
-use serde::{Deserialize, Serialize};
-use std::f32::consts::PI;
+#[cfg(feature = "calendars")]
+use crate::calendar::CalendarKind;
+use crate::{
+    calendar::CalendarDates,
+    codec,
+    error::{ClockError, DecodeError},
+};
+use chrono::{prelude::*, LocalResult};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{f32::consts::PI, fmt::Display, str::FromStr};
+
+/// Theme hint carried by clock messages, so every client switches at the same moment.
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Theme {
+    #[default]
+    Day,
+    Dusk,
+    Night,
+}
+
+/// Hours at which the [Theme] switches: day from `day`, dusk from `dusk`, night from `night`
+/// until the next day.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::clock::{Theme, ThemeHours};
+///
+/// let hours: ThemeHours = "6,19,22".parse().unwrap();
+///
+/// assert_eq!(hours.theme_at(5), Theme::Night);
+/// assert_eq!(hours.theme_at(12), Theme::Day);
+/// assert_eq!(hours.theme_at(19), Theme::Dusk);
+/// assert_eq!(hours.theme_at(23), Theme::Night);
+/// assert!("6,19".parse::<ThemeHours>().is_err());
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ThemeHours {
+    pub day: u8,
+    pub dusk: u8,
+    pub night: u8,
+}
+
+impl Default for ThemeHours {
+    fn default() -> Self {
+        Self {
+            day: 7,
+            dusk: 18,
+            night: 21,
+        }
+    }
+}
+
+impl ThemeHours {
+    /// Theme of the given hour (0 to 23)
+    pub fn theme_at(&self, hour: u8) -> Theme {
+        if hour >= self.night || hour < self.day {
+            Theme::Night
+        } else if hour >= self.dusk {
+            Theme::Dusk
+        } else {
+            Theme::Day
+        }
+    }
+}
+
+impl FromStr for ThemeHours {
+    type Err = ClockError;
+
+    /// Parses `day,dusk,night` hours (eg: `7,18,21`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hours = s
+            .split(',')
+            .map(|hour| hour.trim().parse::<u8>())
+            .collect::<Result<Vec<u8>, _>>()?;
+
+        match hours[..] {
+            [day, dusk, night] if day <= dusk && dusk <= night && night < 24 => {
+                Ok(Self { day, dusk, night })
+            }
+            _ => Err(ClockError(
+                "Theme hours must be ordered day,dusk,night hours",
+            )),
+        }
+    }
+}
+
+/// How hours are displayed, a user preference (see [crate::settings::Settings]). Clock messages
+/// carry both representations, see [ClockMessage::display_hours].
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum HourFormat {
+    /// 0 to 23
+    #[default]
+    H24,
+    /// 1 to 12, with AM/PM
+    H12,
+}
+
+/// Unit of the angles given by a [Dial]
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AngleUnit {
+    #[default]
+    Radians,
+    Degrees,
+}
+
+/// Orientation of a clock dial, for frontends drawing hands with another convention than the
+/// angles carried by [ClockMessage] (radians, 12 o'clock being at `PI / 2` clockwise, as for a
+/// hand pointing to 9 o'clock when not rotated). Angles are given in `unit`, clockwise, 12
+/// o'clock being at `offset` (in `unit` as well).
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::clock::{ClockMessage, Dial};
+///
+/// // Eg: a servo motor at 0 degrees on 12 o'clock
+/// let dial: Dial = "degrees,0".parse().unwrap();
+/// let (hours, minutes, _) = ClockMessage::new(3, 30, 0).unwrap().angles_on(&dial);
+///
+/// assert_eq!((hours.round(), minutes.round()), (105.0, 180.0));
+/// assert_eq!("radians".parse::<Dial>().unwrap(), Dial::default());
+/// assert!("gradians,0".parse::<Dial>().is_err());
+/// ```
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Dial {
+    pub unit: AngleUnit,
+    pub offset: f32,
+}
+
+impl Default for Dial {
+    /// The convention of [ClockMessage] angles
+    fn default() -> Self {
+        Self {
+            unit: AngleUnit::Radians,
+            offset: PI / 2f32,
+        }
+    }
+}
+
+impl Dial {
+    /// Converts an angle carried by a [ClockMessage] to this dial.
+    pub fn angle(&self, radians: f32) -> f32 {
+        let clockwise = radians - PI / 2f32;
+
+        match self.unit {
+            AngleUnit::Radians => clockwise + self.offset,
+            AngleUnit::Degrees => clockwise.to_degrees() + self.offset,
+        }
+    }
+}
+
+impl FromStr for Dial {
+    type Err = ClockError;
+
+    /// Parses `unit[,offset]` (eg: `degrees,90`), the unit being `radians` or `degrees`. The
+    /// offset defaults to the one of [ClockMessage] angles.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (unit, offset) = match s.split_once(',') {
+            Some((unit, offset)) => (unit, Some(offset)),
+            None => (s, None),
+        };
+        let unit = match unit.trim() {
+            "radians" | "rad" => AngleUnit::Radians,
+            "degrees" | "deg" => AngleUnit::Degrees,
+            _ => return Err(ClockError("Unknown angle unit")),
+        };
+        let offset = match (offset, unit) {
+            (Some(offset), _) => offset
+                .trim()
+                .parse()
+                .map_err(|_| ClockError("Invalid dial offset"))?,
+            (None, AngleUnit::Radians) => PI / 2f32,
+            (None, AngleUnit::Degrees) => 90f32,
+        };
+
+        Ok(Self { unit, offset })
+    }
+}
+
+// Longest DST gap to skip, the longest one on record skipped a whole day (Samoa, 2011)
+const MAX_GAP_MINUTES: i64 = 24 * 60;
+
+/// Timezone used in time computations: the local zone of the machine, or any IANA zone.
+/// Serialized as its name (see [FromStr]).
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::clock::Zone;
+///
+/// assert_eq!("local".parse::<Zone>().unwrap(), Zone::Local);
+/// assert_eq!("Asia/Tokyo".parse::<Zone>().unwrap(), Zone::Named(chrono_tz::Asia::Tokyo));
+/// assert!("Mars/Olympus_Mons".parse::<Zone>().is_err());
+/// assert_eq!(Zone::Named(chrono_tz::Asia::Tokyo).to_string(), "Asia/Tokyo");
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Zone {
+    #[default]
+    Local,
+    Named(Tz),
+}
+
+impl Zone {
+    /// Absolute instant of a wall clock time in this zone. Times skipped by a DST change do not
+    /// exist, repeated ones resolve to their first occurrence.
+    pub fn instant(&self, time: NaiveDateTime) -> Result<DateTime<Utc>, ClockError> {
+        self.resolve(time)
+            .earliest()
+            .ok_or(ClockError("Time does not exist in this timezone"))
+    }
+
+    /// Absolute instant of a wall clock time in this zone, times skipped by a DST change
+    /// resolving to the end of the gap (eg: 03:00 for 02:30 on a spring-forward night), repeated
+    /// ones to their first occurrence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{NaiveDate, TimeZone};
+    /// use libclockrobustus::clock::Zone;
+    ///
+    /// let paris = Zone::Named(chrono_tz::Europe::Paris);
+    /// // Clocks went from 02:00 to 03:00 in Paris on 2023-03-26
+    /// let skipped = NaiveDate::from_ymd_opt(2023, 3, 26).unwrap().and_hms_opt(2, 30, 0).unwrap();
+    ///
+    /// assert!(paris.instant(skipped).is_err());
+    /// assert_eq!(
+    ///     paris.instant_or_after(skipped).unwrap(),
+    ///     chrono_tz::Europe::Paris.with_ymd_and_hms(2023, 3, 26, 3, 0, 0).unwrap()
+    /// );
+    /// ```
+    pub fn instant_or_after(&self, time: NaiveDateTime) -> Result<DateTime<Utc>, ClockError> {
+        // Gaps end on a minute boundary: the first minute that exists after the skipped time
+        // is the end of the gap
+        let shifted = |minutes| {
+            let minute = time.with_second(0)?.with_nanosecond(0)?;
+            self.resolve(minute + chrono::Duration::minutes(minutes))
+                .earliest()
+        };
+
+        match self.resolve(time) {
+            LocalResult::None => (1..=MAX_GAP_MINUTES)
+                .find_map(shifted)
+                .ok_or(ClockError("Time does not exist in this timezone")),
+            result => result
+                .earliest()
+                .ok_or(ClockError("Time does not exist in this timezone")),
+        }
+    }
+
+    // Instants a wall clock time stands for in this zone: none in a DST gap, two in an overlap
+    fn resolve(&self, time: NaiveDateTime) -> LocalResult<DateTime<Utc>> {
+        match self {
+            Self::Local => Local
+                .from_local_datetime(&time)
+                .map(|instant| instant.with_timezone(&Utc)),
+            Self::Named(tz) => tz
+                .from_local_datetime(&time)
+                .map(|instant| instant.with_timezone(&Utc)),
+        }
+    }
+
+    /// Wall clock time of an instant in this zone.
+    pub fn wall_time(&self, instant: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Self::Local => instant.with_timezone(&Local).naive_local(),
+            Self::Named(tz) => instant.with_timezone(tz).naive_local(),
+        }
+    }
+}
+
+impl FromStr for Zone {
+    type Err = ClockError;
+
+    /// Parses `local` (or an empty string) or an IANA zone name (eg: `Europe/Paris`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" | "local" => Ok(Self::Local),
+            name => name
+                .parse()
+                .map(Self::Named)
+                .map_err(|_| ClockError("Unknown timezone")),
+        }
+    }
+}
+
+impl Display for Zone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Local => write!(f, "local"),
+            Self::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+impl Serialize for Zone {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Zone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(|error: ClockError| serde::de::Error::custom(error.0))
+    }
+}
+
+/// Converts a wall clock time from a zone to another (see [Zone] for the accepted names), eg:
+/// what time is 9am in Tokyo here.
+///
+/// # Panics
+///
+/// Panics if a zone is unknown or if the time does not exist in the source zone.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use libclockrobustus::clock::convert;
+///
+/// let tokyo = NaiveDate::from_ymd_opt(2023, 7, 3).unwrap().and_hms_opt(9, 0, 0).unwrap();
+/// let paris = convert(tokyo, "Asia/Tokyo", "Europe/Paris").unwrap();
+///
+/// assert_eq!(paris, NaiveDate::from_ymd_opt(2023, 7, 3).unwrap().and_hms_opt(2, 0, 0).unwrap());
+/// ```
+pub fn convert(
+    time: NaiveDateTime,
+    from_tz: &str,
+    to_tz: &str,
+) -> Result<NaiveDateTime, ClockError> {
+    let from = from_tz.parse::<Zone>()?;
+    let to = to_tz.parse::<Zone>()?;
+
+    Ok(to.wall_time(from.instant(time)?))
+}
+
 /// A fully, minimal sized clock definition, serializable and deserializable (with [serde]),
 /// and fully integrated in the ZeroMQ workflow. It synchronizes with local time on initialization.
 /// it also carries angles in radians to place clock hands on a circular clock dial (thus limiting
-/// frontend computations).
+/// frontend computations), along with a [Theme] hint and the date of the day (so clients never
+/// read their own, possibly skewed, clock). Fields are read-only, through accessors of the same
+/// name, so that received messages stay consistent.
 ///
 /// # Examples
 ///
@@ -14,43 +356,157 @@ use std::f32::consts::PI;
 ///
 /// let message = ClockMessage::default();
 ///
-/// assert_eq!(message.as_bytes().len(), 15usize)
+/// assert!(message.as_bytes().unwrap().len() <= ClockMessage::MAX_ENCODED_LEN)
+/// ```
+///
+/// Reading a received message:
+///
+/// ```
+/// use libclockrobustus::{clock::ClockMessage, message::Message};
+///
+/// let sent = ClockMessage::new(15, 30, 45).unwrap();
+/// let received = Message::try_from(Message::from(sent).as_bytes().unwrap()).unwrap();
+///
+/// let Message::Clock(clock) = received else {
+///     panic!("Not a clock message");
+/// };
+/// assert_eq!((clock.hours(), clock.minutes(), clock.seconds()), (15, 30, 45));
+/// assert_eq!(clock.theme(), sent.theme());
 /// ```
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ClockMessage {
-    hours: u8,
-    minutes: u8,
-    seconds: u8,
-    hours_angle: f32,
-    minutes_angle: f32,
-    seconds_angle: f32,
+    pub(crate) hours: u8,
+    /// Hours of the 12-hour representation (1 to 12), derived from `hours`
+    #[serde(default)]
+    pub(crate) hours12: u8,
+    /// Afternoon flag of the 12-hour representation, derived from `hours`
+    #[serde(default)]
+    pub(crate) pm: bool,
+    pub(crate) minutes: u8,
+    pub(crate) seconds: u8,
+    pub(crate) hours_angle: f32,
+    pub(crate) minutes_angle: f32,
+    pub(crate) seconds_angle: f32,
+    #[serde(default)]
+    pub(crate) theme: Theme,
+    pub(crate) year: i32,
+    pub(crate) month: u8,
+    pub(crate) day: u8,
+    /// Days since Monday (0 to 6)
+    pub(crate) weekday: u8,
+    /// Milliseconds within the second, swept by the second hand (smooth with short ticks)
+    #[serde(default)]
+    pub(crate) millis: u16,
+    /// ISO 8601 week number (1 to 53)
+    #[serde(default)]
+    pub(crate) iso_week: u8,
+    /// Day of the year (1 to 366)
+    #[serde(default)]
+    pub(crate) day_of_year: u16,
+    /// Daylight saving time in effect
+    #[serde(default)]
+    pub(crate) dst: bool,
+    /// Set in extended messages only
+    #[serde(default)]
+    pub(crate) calendars: Option<CalendarDates>,
 }
 
 impl ClockMessage {
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits), calendar dates included (see [ClockMessage::calendars])
+    pub const MAX_ENCODED_LEN: usize = 35 + CalendarDates::MAX_ENCODED_LEN;
+
     /// Convert a [ClockMessage] to a vector of bytes
-    /// Useful for message queuing (and for binary saving)
+    /// Useful for message queuing (and for binary saving), hot loops should rather encode into a
+    /// reused stack buffer (see [ClockMessage::encode_into])
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::clock::ClockMessage;
+    ///
+    /// let bytes = ClockMessage::default().as_bytes().unwrap();
+    ///
+    /// assert!(bytes.len() <= ClockMessage::MAX_ENCODED_LEN);
+    /// ```
+    pub fn as_bytes(&self) -> Result<Vec<u8>, ClockError> {
+        codec::to_vec(self)
+    }
+
+    /// Writes the binary representation (see [crate::codec]) at the start of a caller-provided
+    /// buffer, returning the number of bytes written. Every clock message fits a reused
+    /// `[u8; ClockMessage::MAX_ENCODED_LEN]` buffer, so hot loops never allocate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is too short for the message ([ClockMessage::MAX_ENCODED_LEN] bytes
+    /// always fit).
     ///
     /// # Examples
     ///
     /// ```
     /// use libclockrobustus::clock::ClockMessage;
     ///
-    /// let bytes = ClockMessage::default().as_bytes();
+    /// let message = ClockMessage::default();
+    /// let mut buffer = [0u8; ClockMessage::MAX_ENCODED_LEN];
+    ///
+    /// let len = message.encode_into(&mut buffer).unwrap();
     ///
-    /// assert_eq!(bytes.len(), 15usize);
+    /// assert_eq!(&buffer[..len], message.as_bytes().unwrap().as_slice());
+    /// assert!(message.encode_into(&mut [0u8; 4]).is_err());
     /// ```
-    pub fn as_bytes(&self) -> Vec<u8> {
-        let mut v = Vec::new();
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        codec::encode(self, buffer)
+    }
 
-        v.push(self.hours);
-        v.push(self.minutes);
-        v.push(self.seconds);
-        v.append(&mut self.hours_angle.to_be_bytes().to_vec());
-        v.append(&mut self.minutes_angle.to_be_bytes().to_vec());
-        v.append(&mut self.seconds_angle.to_be_bytes().to_vec());
+    /// Strict counterpart of the [TryFrom] conversion from bytes: truncated messages, trailing
+    /// bytes (see [codec::decode_strict]), out of range times and dates, and angles off the dial
+    /// are rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::clock::ClockMessage;
+    ///
+    /// let mut bytes = ClockMessage::default().as_bytes().unwrap();
+    /// assert!(ClockMessage::try_from_strict(&bytes).is_ok());
+    ///
+    /// bytes.push(0);
+    /// assert_eq!(ClockMessage::try_from_strict(&bytes).unwrap_err().reason, "Trailing bytes");
+    /// ```
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let message: Self = codec::decode_strict(bytes)?;
 
-        v
+        message.check_ranges()?;
+        Ok(message)
+    }
+
+    /// Rejects the fields out of range, once decoded (see [ClockMessage::try_from_strict])
+    pub(crate) fn check_ranges(&self) -> Result<(), DecodeError> {
+        DecodeError::check_range(self.hours, "hours", 0..=23)?;
+        DecodeError::check_range(self.minutes, "minutes", 0..=59)?;
+        DecodeError::check_range(self.seconds, "seconds", 0..=59)?;
+        for (angle, field) in [
+            (self.hours_angle, "hours_angle"),
+            (self.minutes_angle, "minutes_angle"),
+            (self.seconds_angle, "seconds_angle"),
+        ] {
+            if !Self::is_on_dial(angle) {
+                return Err(DecodeError::new(0, field, "Value out of range"));
+            }
+        }
+        DecodeError::check_range(self.month, "month", 1..=12)?;
+        DecodeError::check_range(self.day, "day", 1..=31)?;
+        DecodeError::check_range(self.weekday, "weekday", 0..=6)?;
+        DecodeError::check_range(self.millis, "millis", 0..=999)?;
+        DecodeError::check_range(self.iso_week, "iso_week", 1..=53)?;
+        DecodeError::check_range(self.day_of_year, "day_of_year", 1..=366)?;
+
+        match &self.calendars {
+            Some(calendars) => calendars.check_ranges(),
+            None => Ok(()),
+        }
     }
 }
 
@@ -63,43 +519,485 @@ impl TryFrom<Vec<u8>> for ClockMessage {
     /// use libclockrobustus::clock::ClockMessage;
     ///
     /// let message1 = ClockMessage::default();
-    /// let bytes = message1.as_bytes();
+    /// let bytes = message1.as_bytes().unwrap();
     /// let message2 = ClockMessage::try_from(bytes).unwrap();
     ///
     /// assert_eq!(message1, message2);
     /// ```
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_slice())
+    }
+}
+
+impl TryFrom<&[u8]> for ClockMessage {
+    type Error = ClockError;
+    /// Initialize a [ClockMessage] from a borrowed binary buffer
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        codec::decode(value)
+    }
+}
+
+#[cfg(feature = "protobuf")]
+impl From<ClockMessage> for crate::proto::ClockTick {
+    fn from(value: ClockMessage) -> Self {
+        Self {
+            hours: value.hours as u32,
+            minutes: value.minutes as u32,
+            seconds: value.seconds as u32,
+            hours_angle: value.hours_angle,
+            minutes_angle: value.minutes_angle,
+            seconds_angle: value.seconds_angle,
+            theme: match value.theme {
+                Theme::Day => "day",
+                Theme::Dusk => "dusk",
+                Theme::Night => "night",
+            }
+            .to_string(),
+            year: value.year,
+            month: value.month as u32,
+            day: value.day as u32,
+            weekday: value.weekday as u32,
+            millis: value.millis as u32,
+            hours12: value.hours12 as u32,
+            pm: value.pm,
+            iso_week: value.iso_week as u32,
+            day_of_year: value.day_of_year as u32,
+            dst: value.dst,
+        }
+    }
+}
+
+#[cfg(feature = "protobuf")]
+impl TryFrom<crate::proto::ClockTick> for ClockMessage {
+    type Error = ClockError;
+
+    /// Calendar dates have no protobuf representation, they are never set.
+    fn try_from(value: crate::proto::ClockTick) -> Result<Self, Self::Error> {
+        let byte = |value: u32| u8::try_from(value).map_err(|_| ClockError("Value out of range"));
+
         Ok(Self {
-            hours: value[0],
-            minutes: value[1],
-            seconds: value[2],
-            hours_angle: f32::from_be_bytes(value[3..7].try_into()?),
-            minutes_angle: f32::from_be_bytes(value[7..11].try_into()?),
-            seconds_angle: f32::from_be_bytes(value[11..15].try_into()?),
+            hours: byte(value.hours)?,
+            hours12: byte(value.hours12)?,
+            pm: value.pm,
+            minutes: byte(value.minutes)?,
+            seconds: byte(value.seconds)?,
+            hours_angle: value.hours_angle,
+            minutes_angle: value.minutes_angle,
+            seconds_angle: value.seconds_angle,
+            theme: match value.theme.as_str() {
+                "day" => Theme::Day,
+                "dusk" => Theme::Dusk,
+                "night" => Theme::Night,
+                _ => return Err(ClockError("Unknown theme")),
+            },
+            year: value.year,
+            month: byte(value.month)?,
+            day: byte(value.day)?,
+            weekday: byte(value.weekday)?,
+            millis: u16::try_from(value.millis).map_err(|_| ClockError("Value out of range"))?,
+            iso_week: byte(value.iso_week)?,
+            day_of_year: u16::try_from(value.day_of_year)
+                .map_err(|_| ClockError("Value out of range"))?,
+            dst: value.dst,
+            calendars: None,
         })
     }
 }
 
 impl Default for ClockMessage {
-    /// Default initializer for [ClockMessage], synchronizes to current local time.
+    /// Default initializer for [ClockMessage], synchronizes to current local time (with the
+    /// default [ThemeHours]).
     fn default() -> Self {
-        let now = Local::now();
-        let hours = now.hour() as u8;
-        let minutes = now.minute() as u8;
-        let seconds = now.second() as u8;
+        Self::from_datetime(&Local::now())
+    }
+}
+
+impl ClockMessage {
+    /// Message for the given time of today (local date), eg: to publish a fixed time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the time is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::clock::ClockMessage;
+    ///
+    /// let message = ClockMessage::new(21, 0, 0).unwrap();
+    ///
+    /// assert_eq!(message.minutes_angle(), std::f32::consts::FRAC_PI_2);
+    /// assert!(ClockMessage::new(24, 0, 0).is_err());
+    /// ```
+    pub fn new(hours: u8, minutes: u8, seconds: u8) -> Result<Self, ClockError> {
+        let time = NaiveTime::from_hms_opt(hours as u32, minutes as u32, seconds as u32)
+            .ok_or(ClockError("Invalid time"))?;
+
+        let time = Local::now().date_naive().and_time(time);
+
+        // Times skipped by a DST change are kept as is
+        Ok(match Local.from_local_datetime(&time).earliest() {
+            Some(time) => Self::from_datetime(&time),
+            None => Self::from_naive(time, false),
+        })
+    }
+
+    /// Message for the given date and time, in its own time zone (with the default
+    /// [ThemeHours]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use libclockrobustus::clock::ClockMessage;
+    ///
+    /// let time = Utc.with_ymd_and_hms(2024, 2, 29, 23, 15, 30).unwrap();
+    /// let message = ClockMessage::from_datetime(&time);
+    ///
+    /// assert_eq!((message.hours(), message.minutes(), message.seconds()), (23, 15, 30));
+    /// assert_eq!((message.year(), message.month(), message.day()), (2024, 2, 29));
+    /// // Thursday
+    /// assert_eq!(message.weekday(), 3);
+    /// ```
+    pub fn from_datetime<Tz: TimeZone>(time: &DateTime<Tz>) -> Self {
+        Self::from_naive(time.naive_local(), Self::is_dst(time))
+    }
+
+    /// Message for the current time in the given zone, eg: a daemon running in UTC on a server
+    /// publishing the wall time of its users.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::clock::{ClockMessage, Zone};
+    ///
+    /// let message = ClockMessage::now_in(&"Asia/Tokyo".parse::<Zone>().unwrap());
+    /// let tokyo = chrono::Utc::now().with_timezone(&chrono_tz::Asia::Tokyo);
+    ///
+    /// assert_eq!(message.year(), chrono::Datelike::year(&tokyo));
+    /// ```
+    pub fn now_in(zone: &Zone) -> Self {
+        Self::at(Utc::now(), zone)
+    }
+
+    /// Message for a Unix timestamp (milliseconds), in the given zone (UTC if none), eg: to
+    /// replay a historical clock stream.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the timestamp is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::clock::{ClockMessage, Zone};
+    ///
+    /// let message = ClockMessage::from_timestamp(1_700_000_000_000, None).unwrap();
+    /// assert_eq!((message.hours(), message.minutes(), message.day()), (22, 13, 14));
+    ///
+    /// let paris = "Europe/Paris".parse::<Zone>().unwrap();
+    /// let message = ClockMessage::from_timestamp(1_700_000_000_000, Some(&paris)).unwrap();
+    /// assert_eq!(message.hours(), 23);
+    /// ```
+    pub fn from_timestamp(timestamp_ms: i64, zone: Option<&Zone>) -> Result<Self, ClockError> {
+        let instant = Utc
+            .timestamp_millis_opt(timestamp_ms)
+            .single()
+            .ok_or(ClockError("Timestamp out of range"))?;
+
+        Ok(match zone {
+            Some(zone) => Self::at(instant, zone),
+            None => Self::from_datetime(&instant),
+        })
+    }
+
+    /// Internal initialization from an instant, seen in the given zone
+    fn at(instant: DateTime<Utc>, zone: &Zone) -> Self {
+        match zone {
+            Zone::Local => Self::from_datetime(&instant.with_timezone(&Local)),
+            Zone::Named(tz) => Self::from_datetime(&instant.with_timezone(tz)),
+        }
+    }
+
+    /// Internal initialization from a wall clock date and time
+    fn from_naive(time: NaiveDateTime, dst: bool) -> Self {
+        let hours = time.hour() as u8;
+        let minutes = time.minute() as u8;
+        let seconds = time.second() as u8;
+        // Leap seconds are reported as over 1000 ms
+        let millis = (time.nanosecond() / 1_000_000).min(999) as u16;
+
+        let (hours12, pm) = Self::h24_to_h12(hours);
 
         Self {
             hours,
+            hours12,
+            pm,
             minutes,
             seconds,
             hours_angle: Self::h24_to_radians(hours, minutes),
             minutes_angle: Self::ms60_to_radians(minutes, Some(seconds)),
-            seconds_angle: Self::ms60_to_radians(seconds, None),
+            seconds_angle: Self::ms60_to_radians(seconds, None) + Self::millis_to_radians(millis),
+            theme: ThemeHours::default().theme_at(hours),
+            year: time.year(),
+            month: time.month() as u8,
+            day: time.day() as u8,
+            weekday: time.weekday().num_days_from_monday() as u8,
+            millis,
+            iso_week: time.iso_week().week() as u8,
+            day_of_year: time.ordinal() as u16,
+            dst,
+            calendars: None,
         }
     }
 }
 
 impl ClockMessage {
+    /// Read-only accessor.
+    pub fn hours(&self) -> u8 {
+        self.hours
+    }
+
+    /// Read-only accessor. Hours of the 12-hour representation (12 for midnight and noon).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::clock::{ClockMessage, HourFormat};
+    ///
+    /// let message = ClockMessage::new(0, 30, 0).unwrap();
+    ///
+    /// assert_eq!((message.hours12(), message.pm()), (12, false));
+    /// assert_eq!(message.display_hours(HourFormat::H12), 12);
+    /// assert_eq!(message.display_hours(HourFormat::H24), 0);
+    /// ```
+    pub fn hours12(&self) -> u8 {
+        self.hours12
+    }
+
+    /// Read-only accessor. True from noon, in the 12-hour representation.
+    pub fn pm(&self) -> bool {
+        self.pm
+    }
+
+    /// Hours, minutes and seconds hand angles converted to the given [Dial].
+    pub fn angles_on(&self, dial: &Dial) -> (f32, f32, f32) {
+        (
+            dial.angle(self.hours_angle),
+            dial.angle(self.minutes_angle),
+            dial.angle(self.seconds_angle),
+        )
+    }
+
+    /// Hours to display with the user's [HourFormat].
+    pub fn display_hours(&self, format: HourFormat) -> u8 {
+        match format {
+            HourFormat::H24 => self.hours,
+            HourFormat::H12 => self.hours12,
+        }
+    }
+
+    /// Read-only accessor.
+    pub fn minutes(&self) -> u8 {
+        self.minutes
+    }
+
+    /// Read-only accessor.
+    pub fn seconds(&self) -> u8 {
+        self.seconds
+    }
+
+    /// Read-only accessor (radians).
+    pub fn hours_angle(&self) -> f32 {
+        self.hours_angle
+    }
+
+    /// Read-only accessor (radians).
+    pub fn minutes_angle(&self) -> f32 {
+        self.minutes_angle
+    }
+
+    /// Read-only accessor (radians).
+    pub fn seconds_angle(&self) -> f32 {
+        self.seconds_angle
+    }
+
+    /// Read-only accessor.
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    /// Read-only accessor.
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+
+    /// Read-only accessor (1 to 12).
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// Read-only accessor (1 to 31).
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// Read-only accessor (days since Monday, 0 to 6).
+    pub fn weekday(&self) -> u8 {
+        self.weekday
+    }
+
+    /// Read-only accessor (0 to 999).
+    pub fn millis(&self) -> u16 {
+        self.millis
+    }
+
+    /// Same message, with the theme derived from the given hours.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::clock::{ClockMessage, Theme, ThemeHours};
+    ///
+    /// let always_night = ThemeHours { day: 0, dusk: 0, night: 0 };
+    ///
+    /// assert_eq!(ClockMessage::default().with_theme_hours(&always_night).theme(), Theme::Night);
+    /// ```
+    pub fn with_theme_hours(mut self, hours: &ThemeHours) -> Self {
+        self.theme = hours.theme_at(self.hours);
+        self
+    }
+
+    /// Read-only accessor. ISO 8601 week number (1 to 53), the first week of the year being the
+    /// one with its first Thursday.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::{TimeZone, Utc};
+    /// use libclockrobustus::clock::ClockMessage;
+    ///
+    /// // Still in the last week of 2020
+    /// let time = Utc.with_ymd_and_hms(2021, 1, 3, 12, 0, 0).unwrap();
+    /// let message = ClockMessage::from_datetime(&time);
+    ///
+    /// assert_eq!((message.iso_week(), message.day_of_year()), (53, 3));
+    /// assert!(!message.dst());
+    /// ```
+    pub fn iso_week(&self) -> u8 {
+        self.iso_week
+    }
+
+    /// Read-only accessor. Day of the year (1 to 366).
+    pub fn day_of_year(&self) -> u16 {
+        self.day_of_year
+    }
+
+    /// Read-only accessor. True while daylight saving time is in effect.
+    pub fn dst(&self) -> bool {
+        self.dst
+    }
+
+    /// Read-only accessor. Alternative calendar dates of the day, only set in extended messages.
+    pub fn calendars(&self) -> Option<&CalendarDates> {
+        self.calendars.as_ref()
+    }
+
+    /// Extended message carrying its date in the given calendars (none if `kinds` is
+    /// empty).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the date is out of the range supported by the calendars.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::{calendar::CalendarKind, clock::ClockMessage};
+    ///
+    /// let message = ClockMessage::default().with_calendars(&[CalendarKind::Hijri]).unwrap();
+    /// let decoded = ClockMessage::try_from(message.as_bytes().unwrap()).unwrap();
+    ///
+    /// assert!(decoded.calendars().unwrap().hijri.is_some());
+    /// assert!(decoded.calendars().unwrap().chinese.is_none());
+    /// ```
+    #[cfg(feature = "calendars")]
+    pub fn with_calendars(mut self, kinds: &[CalendarKind]) -> Result<Self, ClockError> {
+        self.calendars = if kinds.is_empty() {
+            None
+        } else {
+            let date = NaiveDate::from_ymd_opt(self.year, self.month as u32, self.day as u32)
+                .ok_or(ClockError("Invalid date"))?;
+
+            Some(CalendarDates::compute(date, kinds)?)
+        };
+
+        Ok(self)
+    }
+
+    /// Internal initialization handy method for today's (local) year, month, day and weekday
+    pub(crate) fn local_date() -> (i32, u8, u8, u8) {
+        let today = Local::now().date_naive();
+
+        (
+            today.year(),
+            today.month() as u8,
+            today.day() as u8,
+            today.weekday().num_days_from_monday() as u8,
+        )
+    }
+
+    /// Internal initialization handy method for the ISO week and day of year of a date (zeros for
+    /// an invalid one)
+    pub(crate) fn week_of(year: i32, month: u8, day: u8) -> (u8, u16) {
+        NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+            .map(|date| (date.iso_week().week() as u8, date.ordinal() as u16))
+            .unwrap_or_default()
+    }
+
+    /// Internal initialization handy method telling whether daylight saving time is in effect: the
+    /// offset is then above the lowest one of the year (in January or in July, depending on the
+    /// hemisphere)
+    fn is_dst<Tz: TimeZone>(time: &DateTime<Tz>) -> bool {
+        let offset_on = |month: u32| {
+            NaiveDate::from_ymd_opt(time.year(), month, 1)
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|date| {
+                    time.timezone()
+                        .offset_from_utc_datetime(&date)
+                        .fix()
+                        .local_minus_utc()
+                })
+        };
+
+        match (offset_on(1), offset_on(7)) {
+            (Some(january), Some(july)) => {
+                time.offset().fix().local_minus_utc() > january.min(july)
+            }
+            _ => false,
+        }
+    }
+
+    /// Internal initialization handy method for the 12-hour representation (hours and PM flag)
+    pub(crate) fn h24_to_h12(hours: u8) -> (u8, bool) {
+        match hours % 12 {
+            0 => (12, hours >= 12),
+            hours12 => (hours12, hours >= 12),
+        }
+    }
+
+    /// Internal handy method telling whether a hand angle is on the dial: hands start from the
+    /// top of the dial (PI / 2) and may go round once
+    pub(crate) fn is_on_dial(angle: f32) -> bool {
+        angle.is_finite() && (0.0..=2.5 * PI).contains(&angle)
+    }
+
+    /// Internal initialization handy method for the second hand sweep within a second (in
+    /// radians)
+    fn millis_to_radians(millis: u16) -> f32 {
+        (millis as f32) * PI / 30000f32
+    }
+
     /// Internal initialization handy method for hour hand angle computation (in radians)
     fn h24_to_radians(hours: u8, minutes: u8) -> f32 {
         let minute_arc = (minutes as f32) * PI / 360f32;
@@ -182,9 +1080,224 @@ mod tests {
     fn test_clockmessage_binary_convertion() {
         // Doing the conversion back and forth and testing equality.
         let message1 = ClockMessage::default();
-        let bytes = message1.as_bytes();
-        let message2 = ClockMessage::try_from(bytes).unwrap();
+        let bytes = message1.as_bytes().unwrap();
+        let message2 = ClockMessage::try_from(bytes.as_slice()).unwrap();
 
         assert_eq!(message1, message2);
+        assert_eq!(ClockMessage::try_from_strict(&bytes).unwrap(), message2);
+        assert_eq!(ClockMessage::try_from(bytes).unwrap(), message2);
+        assert!(ClockMessage::try_from(vec![12, 0]).is_err());
+    }
+
+    #[test]
+    fn test_clockmessage_millis() {
+        // Half a second after 45 s, the hand is half way to 46 s
+        let message = ClockMessage {
+            millis: 500,
+            ..Default::default()
+        };
+        tolerance_delta(
+            ClockMessage::ms60_to_radians(45, None) + ClockMessage::millis_to_radians(500),
+            PI / 60f32,
+        );
+
+        let bytes = message.as_bytes().unwrap();
+        assert_eq!(ClockMessage::try_from(bytes).unwrap().millis(), 500);
+    }
+
+    #[test]
+    fn test_clockmessage_from_datetime() {
+        let time = Utc
+            .with_ymd_and_hms(2023, 12, 31, 15, 30, 45)
+            .unwrap()
+            .with_timezone(&chrono_tz::Asia::Tokyo)
+            + chrono::Duration::milliseconds(250);
+        let message = ClockMessage::from_datetime(&time);
+
+        // Wall clock time of the given zone
+        assert_eq!(
+            (message.hours(), message.minutes(), message.seconds()),
+            (0, 30, 45)
+        );
+        assert_eq!(
+            (message.year(), message.month(), message.day()),
+            (2024, 1, 1)
+        );
+        assert_eq!(message.weekday(), 0);
+        assert_eq!(message.millis(), 250);
+        tolerance_delta(message.hours_angle(), PI / 2f32 + PI / 12f32);
+        tolerance_delta(
+            message.seconds_angle(),
+            ClockMessage::ms60_to_radians(45, None) + PI / 120f32,
+        );
+
+        let message = ClockMessage::new(12, 59, 59).unwrap();
+        assert_eq!((message.hours(), message.millis()), (12, 0));
+        assert!(ClockMessage::new(12, 60, 0).is_err());
+        assert!(ClockMessage::new(12, 0, 60).is_err());
+    }
+
+    #[test]
+    fn test_clockmessage_from_timestamp() {
+        let time = Utc.with_ymd_and_hms(2024, 7, 14, 9, 5, 0).unwrap()
+            + chrono::Duration::milliseconds(999);
+        let new_york = Zone::Named(chrono_tz::America::New_York);
+
+        let message = ClockMessage::from_timestamp(time.timestamp_millis(), None).unwrap();
+        assert_eq!(message, ClockMessage::from_datetime(&time));
+        assert_eq!(message.millis(), 999);
+        assert!(!message.dst());
+
+        let message =
+            ClockMessage::from_timestamp(time.timestamp_millis(), Some(&new_york)).unwrap();
+        assert_eq!((message.hours(), message.minutes()), (5, 5));
+        assert!(message.dst());
+
+        // Before the Unix epoch
+        let message = ClockMessage::from_timestamp(-1000, None).unwrap();
+        assert_eq!(
+            (message.year(), message.hours(), message.seconds()),
+            (1969, 23, 59)
+        );
+        assert!(ClockMessage::from_timestamp(i64::MAX, None).is_err());
+    }
+
+    #[test]
+    fn test_clockmessage_12h() {
+        let expected = [
+            (0, 12, false),
+            (1, 1, false),
+            (11, 11, false),
+            (12, 12, true),
+            (13, 1, true),
+            (23, 11, true),
+        ];
+
+        for (hours, hours12, pm) in expected {
+            let message = ClockMessage::new(hours, 0, 0).unwrap();
+            assert_eq!((message.hours12(), message.pm()), (hours12, pm));
+
+            // Derived on decoding, not carried
+            let decoded = ClockMessage::try_from(message.as_bytes().unwrap()).unwrap();
+            assert_eq!((decoded.hours12(), decoded.pm()), (hours12, pm));
+            let decoded = ClockMessage::try_from_strict(&message.as_bytes().unwrap()).unwrap();
+            assert_eq!(decoded.display_hours(HourFormat::H12), hours12);
+        }
+    }
+
+    #[test]
+    fn test_dial() {
+        let message = ClockMessage::new(9, 45, 0).unwrap();
+        let (hours, minutes, seconds) = message.angles_on(&Dial::default());
+
+        // Same angles as carried
+        tolerance_delta(hours, message.hours_angle());
+        tolerance_delta(minutes, message.minutes_angle());
+        tolerance_delta(seconds, message.seconds_angle());
+
+        // Clockwise from 3 o'clock, in degrees
+        let dial: Dial = "degrees, -90".parse().unwrap();
+        let (_, minutes, seconds) = message.angles_on(&dial);
+        tolerance_delta(minutes.round(), 180f32);
+        tolerance_delta(seconds.round(), -90f32);
+
+        assert!("degrees,north".parse::<Dial>().is_err());
+    }
+
+    #[test]
+    fn test_clockmessage_week() {
+        let paris = chrono_tz::Europe::Paris;
+        let sydney = chrono_tz::Australia::Sydney;
+        let winter =
+            ClockMessage::from_datetime(&paris.with_ymd_and_hms(2024, 12, 31, 9, 0, 0).unwrap());
+        let summer =
+            ClockMessage::from_datetime(&paris.with_ymd_and_hms(2024, 7, 1, 9, 0, 0).unwrap());
+
+        // Leap year, 31st of December is in the first week of 2025
+        assert_eq!(
+            (winter.iso_week(), winter.day_of_year(), winter.dst()),
+            (1, 366, false)
+        );
+        assert_eq!(
+            (summer.iso_week(), summer.day_of_year(), summer.dst()),
+            (27, 183, true)
+        );
+        // Southern summer
+        assert!(ClockMessage::from_datetime(
+            &sydney.with_ymd_and_hms(2024, 1, 15, 9, 0, 0).unwrap()
+        )
+        .dst());
+        assert!(!ClockMessage::from_datetime(&Utc::now()).dst());
+
+        let bytes = summer.as_bytes().unwrap();
+        assert_eq!(ClockMessage::try_from(bytes.clone()).unwrap(), summer);
+        assert_eq!(ClockMessage::try_from_strict(&bytes).unwrap(), summer);
+    }
+
+    #[test]
+    fn test_clockmessage_date() {
+        let message = ClockMessage {
+            year: 2024,
+            month: 2,
+            day: 29,
+            weekday: 3,
+            ..Default::default()
+        };
+        let bytes = message.as_bytes().unwrap();
+        let decoded = ClockMessage::try_from(bytes.clone()).unwrap();
+
+        assert_eq!((decoded.year(), decoded.day()), (2024, 29));
+        assert_eq!(
+            ClockMessage::try_from_strict(&bytes[..bytes.len() - 1])
+                .unwrap_err()
+                .reason,
+            "Missing field"
+        );
+
+        for (field, invalid) in [
+            ("day", ClockMessage { day: 32, ..message }),
+            (
+                "millis",
+                ClockMessage {
+                    millis: 1000,
+                    ..message
+                },
+            ),
+            (
+                "seconds_angle",
+                ClockMessage {
+                    seconds_angle: f32::NAN,
+                    ..message
+                },
+            ),
+        ] {
+            let bytes = invalid.as_bytes().unwrap();
+            assert!(ClockMessage::try_from(bytes.clone()).is_ok());
+            let error = ClockMessage::try_from_strict(&bytes).unwrap_err();
+            assert_eq!((error.field, error.reason), (field, "Value out of range"));
+        }
+    }
+
+    #[test]
+    fn test_convert() {
+        let date = |month, day, hour| {
+            NaiveDate::from_ymd_opt(2023, month, day)
+                .unwrap()
+                .and_hms_opt(hour, 30, 0)
+                .unwrap()
+        };
+
+        // Summer and winter time in New York, across the date line
+        assert_eq!(
+            convert(date(7, 3, 9), "Asia/Tokyo", "America/New_York").unwrap(),
+            date(7, 2, 20)
+        );
+        assert_eq!(
+            convert(date(1, 3, 9), "Asia/Tokyo", "America/New_York").unwrap(),
+            date(1, 2, 19)
+        );
+        // 2:30 is skipped when Paris switches to summer time
+        assert!(convert(date(3, 26, 2), "Europe/Paris", "UTC").is_err());
+        assert!(convert(date(3, 26, 2), "Europe/Nowhere", "UTC").is_err());
     }
 }
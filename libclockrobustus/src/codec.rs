@@ -0,0 +1,132 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::error::{ClockError, DecodeError};
+
+/// Writes the binary representation of `value` at the start of a caller-provided buffer,
+/// returning the number of bytes written. Message bodies are encoded this way from protocol
+/// version 2 on (see [crate::message::PROTOCOL_VERSION]): the postcard encoding of their serde
+/// representation, so a field is added to a message without maintaining byte offsets by hand.
+///
+/// # Panics
+///
+/// Panics if the buffer is too short for the value.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::{codec, jump::ClockJumpMessage};
+///
+/// let message = ClockJumpMessage { jump_ms: -3_600_000, wall_ms: 1_700_000_000_000 };
+/// let mut buffer = [0u8; 32];
+///
+/// let len = codec::encode(&message, &mut buffer).unwrap();
+///
+/// assert_eq!(codec::decode::<ClockJumpMessage>(&buffer[..len]).unwrap(), message);
+/// assert!(codec::encode(&message, &mut buffer[..4]).is_err());
+/// ```
+pub fn encode<T: Serialize>(value: &T, buffer: &mut [u8]) -> Result<usize, ClockError> {
+    Ok(postcard::to_slice(value, buffer)?.len())
+}
+
+/// Allocating counterpart of [encode], for values of unbounded size.
+pub fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, ClockError> {
+    Ok(postcard::to_allocvec(value)?)
+}
+
+/// Decodes a value encoded by [encode], trailing bytes being ignored.
+///
+/// # Panics
+///
+/// Panics if the bytes are truncated or do not represent a `T`.
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ClockError> {
+    Ok(postcard::take_from_bytes(bytes)?.0)
+}
+
+/// Strict counterpart of [decode]: trailing bytes are rejected as well. Fields are not delimited
+/// in the encoding, errors point where decoding stopped (the field that could not be read, or
+/// the end of the value). Ranges narrower than the field types are checked by the message types
+/// themselves (see [crate::message::Message::try_from_strict]).
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::{codec, jump::ClockJumpMessage};
+///
+/// let mut buffer = [0u8; 32];
+/// let len = codec::encode(&ClockJumpMessage::default(), &mut buffer).unwrap();
+///
+/// assert!(codec::decode_strict::<ClockJumpMessage>(&buffer[..len]).is_ok());
+///
+/// let error = codec::decode_strict::<ClockJumpMessage>(&buffer[..len + 1]).unwrap_err();
+/// assert_eq!((error.offset, error.reason), (len, "Trailing bytes"));
+/// ```
+pub fn decode_strict<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, DecodeError> {
+    let mut deserializer = postcard::Deserializer::from_bytes(bytes);
+    let value = <T as Deserialize>::deserialize(&mut deserializer);
+    let offset = bytes.len() - deserializer.finalize().map_or(0, |rest| rest.len());
+
+    let value = value.map_err(|error| match error {
+        postcard::Error::DeserializeUnexpectedEnd => {
+            DecodeError::new(offset, "body", "Missing field")
+        }
+        _ => DecodeError::new(offset, "body", "Invalid message body"),
+    })?;
+
+    DecodeError::check_end(bytes, offset)?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alarm::{ActiveDays, Alarm},
+        clock::ClockMessage,
+        speak::{SpeakLocale, SpeakMessage},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_alarm() {
+        let mut alarm = Alarm {
+            id: Some(42),
+            active_days: ActiveDays::WEEKDAYS,
+            hour: 7,
+            minute: 30,
+            label: "Work".to_string(),
+            snoozed_until: Some(1_700_000_000),
+            ..Default::default()
+        };
+        alarm.set_metadata("volume", 80).unwrap();
+
+        let bytes = to_vec(&alarm).unwrap();
+
+        assert_eq!(decode::<Alarm>(&bytes).unwrap(), alarm);
+        assert_eq!(decode_strict::<Alarm>(&bytes).unwrap(), alarm);
+        // Free text does not fit every buffer
+        assert!(encode(&alarm, &mut [0u8; 8]).is_err());
+    }
+
+    #[test]
+    fn test_decode() {
+        let message = SpeakMessage {
+            hours: 19,
+            minutes: 30,
+            locale: SpeakLocale::Fr,
+            text: "Il est dix-neuf heures trente".to_string(),
+        };
+        let mut buffer = [0u8; 64];
+        let len = encode(&message, &mut buffer).unwrap();
+
+        assert_eq!(decode::<SpeakMessage>(&buffer).unwrap(), message);
+        assert!(decode::<SpeakMessage>(&buffer[..len - 1]).is_err());
+        // Hours, minutes, locale and text length read, not the text
+        let error = decode_strict::<SpeakMessage>(&buffer[..len - 1]).unwrap_err();
+        assert_eq!((error.offset, error.reason), (4, "Missing field"));
+        assert_eq!(decode_strict::<ClockMessage>(&[]).unwrap_err().offset, 0);
+
+        buffer[2] = 2;
+        let error = decode_strict::<SpeakMessage>(&buffer[..len]).unwrap_err();
+        assert_eq!((error.offset, error.reason), (3, "Invalid message body"));
+    }
+}
@@ -0,0 +1,839 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alarm::{Alarm, AlarmSort, DuplicatePolicy},
+    env::ClockEnv,
+    error::ClockError,
+    heartbeat::HeartbeatMessage,
+    history::{RingEvent, RingStats},
+    pomodoro::{PomodoroMessage, PomodoroSettings},
+    timer::{StopwatchState, TimerState},
+    token::Role,
+};
+
+/// Time (in milliseconds) a client waits for the daemon before considering it dead.
+const REQUEST_TIMEOUT_MS: i32 = 2000;
+
+/// Requests accepted by the daemon on its control (REQ/REP) socket. Requests travel as JSON
+/// objects tagged by their `command` field.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::control::Request;
+///
+/// assert_eq!(serde_json::to_string(&Request::Ping).unwrap(), "{\"command\":\"ping\"}");
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "camelCase")]
+pub enum Request {
+    /// Lightweight liveness probe, answered with [Response::Health].
+    Ping,
+    /// Round-trip probe, answered right away with [Response::Echo] carrying the same nonce (see
+    /// [crate::queue::measure_latency]).
+    Echo {
+        nonce: u64,
+    },
+    /// Status of the daemon (uptime, number of alarms, version), answered with
+    /// [Response::Status].
+    Status,
+    /// Stops the daemon after the current tick, answered with [Response::Done].
+    Shutdown,
+    /// Stored alarms, a page of `limit` of them after `offset` if given, answered with
+    /// [Response::Alarms].
+    ListAlarms {
+        #[serde(default)]
+        sort: AlarmSort,
+        #[serde(default)]
+        limit: Option<usize>,
+        #[serde(default)]
+        offset: Option<usize>,
+    },
+    /// Saves an alarm (see [Alarm::save], or [Alarm::save_unique] given a policy), answered with
+    /// [Response::Done].
+    UpsertAlarm {
+        alarm: Box<Alarm>,
+        #[serde(default)]
+        duplicates: Option<DuplicatePolicy>,
+    },
+    /// Removes the alarm `alarm_id` (see [Alarm::remove]), answered with [Response::Done].
+    DeleteAlarm {
+        #[serde(rename = "alarmId")]
+        alarm_id: i64,
+    },
+    /// Brings back the removed alarm `alarm_id` (see [Alarm::restore]), answered with
+    /// [Response::Done].
+    RestoreAlarm {
+        #[serde(rename = "alarmId")]
+        alarm_id: i64,
+    },
+    /// Number of stored alarms, answered with [Response::Count].
+    CountAlarms,
+    /// Enables or disables the alarms of the given ids (see [Alarm::set_enabled_by_ids]),
+    /// answered with [Response::Count] (alarms changed).
+    SetAlarmsEnabled {
+        ids: Vec<i64>,
+        enabled: bool,
+    },
+    /// Enables or disables every alarm of a group (see [Alarm::set_enabled_by_group]), answered
+    /// with [Response::Count] (alarms changed).
+    SetGroupEnabled {
+        group: String,
+        enabled: bool,
+    },
+    /// Next ring of an alarm, saved or not (see [Alarm::next_ring]), answered with
+    /// [Response::NextRing].
+    NextRing {
+        alarm: Box<Alarm>,
+    },
+    /// Skips the next occurrence of the alarm `alarm_id` (see [Alarm::skip_next]), answered with
+    /// [Response::Skipped].
+    SkipNext {
+        #[serde(rename = "alarmId")]
+        alarm_id: i64,
+    },
+    /// Tags of the alarm `alarm_id`, answered with [Response::Tags].
+    AlarmTags {
+        #[serde(rename = "alarmId")]
+        alarm_id: i64,
+    },
+    /// Replaces the tags of the alarm `alarm_id` (see [Alarm::set_tags]), answered with
+    /// [Response::Done].
+    SetAlarmTags {
+        #[serde(rename = "alarmId")]
+        alarm_id: i64,
+        tags: Vec<String>,
+    },
+    /// Alarms matching every word of the query (see [Alarm::search]), answered with
+    /// [Response::Alarms].
+    SearchAlarms {
+        query: String,
+    },
+    /// Saves new alarms (eg: parsed by [crate::import::ImportSource::parse]), answered with
+    /// [Response::Count] (alarms saved).
+    ImportAlarms {
+        alarms: Vec<Alarm>,
+    },
+    /// Backup of every alarm (see [Alarm::export_all]), answered with [Response::Exported].
+    ExportAlarms,
+    /// Restores a backup made by [Request::ExportAlarms] (see [Alarm::import]), answered with
+    /// [Response::Count] (alarms restored). Requests are size-limited by the daemon, so large
+    /// backups are better restored offline.
+    RestoreBackup {
+        content: String,
+    },
+    /// Alarms as an iCalendar document (see [crate::ical::export]), answered with
+    /// [Response::Exported].
+    ExportIcal,
+    /// Latest alarm history events, newest first, optionally for a single alarm (see
+    /// [RingEvent::query]). Answered with [Response::History].
+    History {
+        #[serde(rename = "alarmId", default)]
+        alarm_id: Option<i64>,
+        limit: usize,
+    },
+    /// Statistics over the whole alarm history, answered with [Response::RingStats].
+    RingStats,
+    /// Alarm history as CSV (see [RingEvent::export_csv]), answered with [Response::Exported].
+    ExportHistory,
+    /// Publishes a one-off copy of a stored alarm `delay` seconds from now, so users can check
+    /// what it looks and sounds like. Answered with [Response::Done]. Nothing is recorded in the
+    /// alarm history.
+    Preview {
+        #[serde(rename = "alarmId")]
+        alarm_id: i64,
+        delay: u64,
+    },
+    /// Rings the alarm `alarm_id`, which must be ringing, again `minutes` from now (its own
+    /// snooze delay if not given), announcing it right away with an alarm message bearing
+    /// [Alarm::snoozed_until]. Answered with [Response::Snoozed].
+    Snooze {
+        #[serde(rename = "alarmId")]
+        alarm_id: i64,
+        #[serde(default)]
+        minutes: Option<u8>,
+    },
+    /// Acknowledges the ringing alarm `alarm_id`, which is recorded as dismissed in the history.
+    /// Answered with [Response::Done], or an error if the alarm is not ringing.
+    Dismiss {
+        #[serde(rename = "alarmId")]
+        alarm_id: i64,
+    },
+    /// Switches to the alarm profile `name` (see [crate::profile::Profile::activate]), enabling
+    /// and disabling the alarms of its groups. Answered with [Response::ProfileSwitched].
+    SwitchProfile {
+        name: String,
+    },
+    /// Starts a countdown of `duration_ms`, or resumes the paused one if no duration is given.
+    /// Timer requests are answered with [Response::Timer].
+    TimerStart {
+        #[serde(rename = "durationMs", default)]
+        duration_ms: Option<u64>,
+    },
+    TimerPause,
+    TimerReset,
+    TimerStatus,
+    /// Stopwatch requests are answered with [Response::Stopwatch].
+    StopwatchStart,
+    StopwatchPause,
+    StopwatchReset,
+    StopwatchLap,
+    StopwatchStatus,
+    /// Starts a new pomodoro cycle, with new settings if given (see
+    /// [crate::pomodoro::Pomodoro::start]). Pomodoro requests are answered with
+    /// [Response::Pomodoro].
+    PomodoroStart {
+        #[serde(default)]
+        settings: Option<PomodoroSettings>,
+    },
+    PomodoroStop,
+    /// Ends the current pomodoro phase early.
+    PomodoroSkip,
+    PomodoroStatus,
+}
+
+impl Request {
+    /// Minimal [Role] an API token (see [crate::token::ApiToken]) must grant for the daemon to
+    /// answer, [None] if no token is required. Liveness probes are always answered so supervisors
+    /// need no credentials.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::{control::Request, token::Role};
+    ///
+    /// assert_eq!(Request::Ping.required_role(), None);
+    /// assert_eq!(Request::Shutdown.required_role(), Some(Role::Admin));
+    /// ```
+    pub fn required_role(&self) -> Option<Role> {
+        match self {
+            Self::Ping | Self::Echo { .. } => None,
+            Self::Status
+            | Self::ListAlarms { .. }
+            | Self::CountAlarms
+            | Self::NextRing { .. }
+            | Self::AlarmTags { .. }
+            | Self::SearchAlarms { .. }
+            | Self::ExportAlarms
+            | Self::ExportIcal
+            | Self::History { .. }
+            | Self::RingStats
+            | Self::ExportHistory
+            | Self::TimerStatus
+            | Self::StopwatchStatus
+            | Self::PomodoroStatus => Some(Role::ReadOnly),
+            // Ringing, silencing alarms or driving the timekeepers changes the daemon state
+            Self::Preview { .. }
+            | Self::Snooze { .. }
+            | Self::Dismiss { .. }
+            | Self::TimerStart { .. }
+            | Self::TimerPause
+            | Self::TimerReset
+            | Self::StopwatchStart
+            | Self::StopwatchPause
+            | Self::StopwatchReset
+            | Self::StopwatchLap
+            | Self::PomodoroStart { .. }
+            | Self::PomodoroStop
+            | Self::PomodoroSkip
+            | Self::Shutdown
+            | Self::SwitchProfile { .. }
+            | Self::UpsertAlarm { .. }
+            | Self::DeleteAlarm { .. }
+            | Self::RestoreAlarm { .. }
+            | Self::SetAlarmsEnabled { .. }
+            | Self::SetGroupEnabled { .. }
+            | Self::SkipNext { .. }
+            | Self::SetAlarmTags { .. }
+            | Self::ImportAlarms { .. }
+            | Self::RestoreBackup { .. } => Some(Role::Admin),
+        }
+    }
+}
+
+/// What actually travels on the control socket: a [Request] along with the caller's API token.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::control::{Envelope, Request};
+///
+/// let envelope: Envelope = serde_json::from_str("{\"command\":\"ping\",\"token\":\"abc\"}").unwrap();
+///
+/// assert_eq!(envelope.request, Request::Ping);
+/// assert_eq!(envelope.token.as_deref(), Some("abc"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(flatten)]
+    pub request: Request,
+}
+
+/// Replies sent back by the daemon, tagged by their `reply` field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "reply", rename_all = "camelCase")]
+pub enum Response {
+    Health(Health),
+    /// The request has been carried out.
+    Done,
+    /// Reply to [Request::Echo].
+    Echo {
+        nonce: u64,
+    },
+    /// Reply to [Request::Status].
+    Status(HeartbeatMessage),
+    /// Reply to [Request::ListAlarms] and [Request::SearchAlarms].
+    Alarms {
+        alarms: Vec<Alarm>,
+    },
+    /// Reply to requests counting alarms: stored, changed, imported or restored.
+    Count {
+        count: usize,
+    },
+    /// Reply to [Request::NextRing], with the Unix timestamp (seconds) the alarm rings at next,
+    /// none if it never rings.
+    NextRing {
+        at: Option<i64>,
+    },
+    /// Reply to [Request::SkipNext], with the date of the skipped occurrence (in the alarm
+    /// timezone), none if the alarm never rings.
+    Skipped {
+        date: Option<NaiveDate>,
+    },
+    /// Reply to [Request::AlarmTags], alphabetically.
+    Tags {
+        tags: Vec<String>,
+    },
+    /// Reply to [Request::History].
+    History {
+        events: Vec<RingEvent>,
+    },
+    /// Reply to [Request::RingStats].
+    RingStats(RingStats),
+    /// Reply to export requests, with the exported document.
+    Exported {
+        content: String,
+    },
+    /// Reply to timer requests, with the resulting timer state.
+    Timer(TimerState),
+    /// Reply to stopwatch requests, with the resulting stopwatch state.
+    Stopwatch(StopwatchState),
+    /// Reply to pomodoro requests, with the resulting phase.
+    Pomodoro(PomodoroMessage),
+    /// Reply to [Request::Snooze], with the Unix timestamp (seconds) the alarm rings again at.
+    Snoozed {
+        until: i64,
+    },
+    /// Reply to [Request::SwitchProfile], with the number of alarms updated.
+    ProfileSwitched {
+        updated: usize,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Component-level health flags. A reply proves the daemon is alive, the flags tell which part
+/// of it is broken.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::control::Health;
+///
+/// let health = Health { database: false, scheduler: true, panics: 0 };
+///
+/// assert!(!health.is_ok());
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Health {
+    /// The alarms database is reachable and its schema is valid.
+    pub database: bool,
+    /// The last tick (alarm checks and clock publication) succeeded.
+    pub scheduler: bool,
+    /// Panics the daemon recovered from since it started (informative, a recovered panic does
+    /// not make the daemon unhealthy).
+    #[serde(default)]
+    pub panics: u32,
+}
+
+impl Health {
+    /// True if every component is healthy.
+    pub fn is_ok(&self) -> bool {
+        self.database && self.scheduler
+    }
+}
+
+/// Sends a [Request] to the daemon control socket and waits for its [Response]. The API token
+/// found in the environment (if any) is attached.
+///
+/// # Panics
+///
+/// Panics if the daemon does not answer within two seconds (most likely because it is not
+/// running), or if the reply cannot be decoded.
+pub fn send(request: &Request) -> Result<Response, ClockError> {
+    let env = ClockEnv::new()?;
+    let ctx = zmq::Context::new();
+    let socket = connect(&ctx, &env)?;
+
+    exchange(&socket, &env, request)
+}
+
+/// Opens a REQ socket to the daemon control channel.
+pub(crate) fn connect(ctx: &zmq::Context, env: &ClockEnv) -> Result<zmq::Socket, ClockError> {
+    let socket = ctx.socket(zmq::REQ)?;
+
+    socket.set_linger(0)?;
+    socket.set_sndtimeo(REQUEST_TIMEOUT_MS)?;
+    socket.set_rcvtimeo(REQUEST_TIMEOUT_MS)?;
+    socket.connect(&format!(
+        "tcp://{}:{}",
+        env.queue().host(),
+        env.queue().control_port(),
+    ))?;
+
+    Ok(socket)
+}
+
+/// Sends a request on a socket opened by [connect] and waits for its reply.
+pub(crate) fn exchange(
+    socket: &zmq::Socket,
+    env: &ClockEnv,
+    request: &Request,
+) -> Result<Response, ClockError> {
+    let envelope = Envelope {
+        token: env.queue().api_token().map(str::to_string),
+        request: request.clone(),
+    };
+
+    socket.send(serde_json::to_vec(&envelope)?, 0)?;
+    let bytes = socket.recv_bytes(0)?;
+
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+/// Pings the daemon. Returns [None] if the daemon is dead (or unreachable), its health flags
+/// otherwise.
+pub fn health() -> Option<Health> {
+    match send(&Request::Ping) {
+        Ok(Response::Health(health)) => Some(health),
+        _ => None,
+    }
+}
+
+/// Asks the daemon for its status (see [Request::Status]).
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (missing credentials).
+pub fn status() -> Result<HeartbeatMessage, ClockError> {
+    match send(&Request::Status)? {
+        Response::Status(status) => Ok(status),
+        _ => Err(ClockError("The daemon refused to tell its status")),
+    }
+}
+
+/// Asks the daemon for the stored alarms (see [Request::ListAlarms]).
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (missing credentials).
+pub fn list_alarms(
+    sort: AlarmSort,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Vec<Alarm>, ClockError> {
+    match send(&Request::ListAlarms {
+        sort,
+        limit,
+        offset,
+    })? {
+        Response::Alarms { alarms } => Ok(alarms),
+        _ => Err(ClockError("The daemon refused to list the alarms")),
+    }
+}
+
+/// Asks the daemon to remove the alarm `alarm_id` (see [Request::DeleteAlarm]).
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (unknown alarm, missing
+/// credentials).
+pub fn delete_alarm(alarm_id: i64) -> Result<(), ClockError> {
+    match send(&Request::DeleteAlarm { alarm_id })? {
+        Response::Done => Ok(()),
+        _ => Err(ClockError("The daemon refused to delete the alarm")),
+    }
+}
+
+/// Asks the daemon to bring back the removed alarm `alarm_id` (see [Request::RestoreAlarm]).
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (no such removed alarm, missing
+/// credentials).
+pub fn restore_alarm(alarm_id: i64) -> Result<(), ClockError> {
+    match send(&Request::RestoreAlarm { alarm_id })? {
+        Response::Done => Ok(()),
+        _ => Err(ClockError("The daemon refused to restore the alarm")),
+    }
+}
+
+/// Sends an alarm request answered with a number of alarms (stored, changed, imported or
+/// restored), returning it.
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (missing credentials, invalid
+/// backup).
+pub fn count(request: &Request) -> Result<usize, ClockError> {
+    match send(request)? {
+        Response::Count { count } => Ok(count),
+        _ => Err(ClockError("The daemon refused the alarm request")),
+    }
+}
+
+/// Asks the daemon when the alarm rings next (see [Request::NextRing]), returning the Unix
+/// timestamp (seconds), [None] if it never rings.
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (missing credentials).
+pub fn next_ring(alarm: Alarm) -> Result<Option<i64>, ClockError> {
+    match send(&Request::NextRing {
+        alarm: Box::new(alarm),
+    })? {
+        Response::NextRing { at } => Ok(at),
+        _ => Err(ClockError("The daemon refused to tell the next ring")),
+    }
+}
+
+/// Asks the daemon to skip the next occurrence of the alarm `alarm_id` (see
+/// [Request::SkipNext]), returning its date, [None] if the alarm never rings.
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (unknown alarm, missing
+/// credentials).
+pub fn skip_next(alarm_id: i64) -> Result<Option<NaiveDate>, ClockError> {
+    match send(&Request::SkipNext { alarm_id })? {
+        Response::Skipped { date } => Ok(date),
+        _ => Err(ClockError("The daemon refused to skip the alarm")),
+    }
+}
+
+/// Asks the daemon for the tags of the alarm `alarm_id` (see [Request::AlarmTags]).
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (unknown alarm, missing
+/// credentials).
+pub fn alarm_tags(alarm_id: i64) -> Result<Vec<String>, ClockError> {
+    match send(&Request::AlarmTags { alarm_id })? {
+        Response::Tags { tags } => Ok(tags),
+        _ => Err(ClockError("The daemon refused to tell the alarm tags")),
+    }
+}
+
+/// Asks the daemon to replace the tags of the alarm `alarm_id` (see [Request::SetAlarmTags]).
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (unknown alarm, missing
+/// credentials).
+pub fn set_alarm_tags(alarm_id: i64, tags: Vec<String>) -> Result<(), ClockError> {
+    match send(&Request::SetAlarmTags { alarm_id, tags })? {
+        Response::Done => Ok(()),
+        _ => Err(ClockError("The daemon refused to tag the alarm")),
+    }
+}
+
+/// Asks the daemon for the alarms matching the query (see [Request::SearchAlarms]).
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (missing credentials).
+pub fn search_alarms(query: &str) -> Result<Vec<Alarm>, ClockError> {
+    match send(&Request::SearchAlarms {
+        query: query.to_string(),
+    })? {
+        Response::Alarms { alarms } => Ok(alarms),
+        _ => Err(ClockError("The daemon refused to search the alarms")),
+    }
+}
+
+/// Asks the daemon for the latest alarm history events (see [Request::History]).
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (missing credentials).
+pub fn history(alarm_id: Option<i64>, limit: usize) -> Result<Vec<RingEvent>, ClockError> {
+    match send(&Request::History { alarm_id, limit })? {
+        Response::History { events } => Ok(events),
+        _ => Err(ClockError("The daemon refused to tell the alarm history")),
+    }
+}
+
+/// Asks the daemon for the alarm history statistics (see [Request::RingStats]).
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (missing credentials).
+pub fn ring_stats() -> Result<RingStats, ClockError> {
+    match send(&Request::RingStats)? {
+        Response::RingStats(stats) => Ok(stats),
+        _ => Err(ClockError("The daemon refused to tell the ring statistics")),
+    }
+}
+
+/// Sends an export request, returning the exported document.
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (missing credentials).
+pub fn export(request: &Request) -> Result<String, ClockError> {
+    match send(request)? {
+        Response::Exported { content } => Ok(content),
+        _ => Err(ClockError("The daemon refused the export")),
+    }
+}
+
+/// Asks the daemon to ring the alarm `alarm_id` in `delay` seconds (see [Request::Preview]).
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (unknown alarm, missing
+/// credentials, delay too long).
+pub fn preview(alarm_id: i64, delay: u64) -> Result<(), ClockError> {
+    match send(&Request::Preview { alarm_id, delay })? {
+        Response::Done => Ok(()),
+        _ => Err(ClockError("The daemon refused the alarm preview")),
+    }
+}
+
+/// Asks the daemon to snooze the alarm `alarm_id` (see [Request::Snooze]), returning the Unix
+/// timestamp (seconds) it rings again at.
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (unknown or not ringing alarm,
+/// missing credentials, no delay).
+pub fn snooze(alarm_id: i64, minutes: Option<u8>) -> Result<i64, ClockError> {
+    match send(&Request::Snooze { alarm_id, minutes })? {
+        Response::Snoozed { until } => Ok(until),
+        _ => Err(ClockError("The daemon refused to snooze the alarm")),
+    }
+}
+
+/// Tells the daemon the ringing alarm `alarm_id` has been dismissed (see [Request::Dismiss]).
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (alarm not ringing, missing
+/// credentials).
+pub fn dismiss(alarm_id: i64) -> Result<(), ClockError> {
+    match send(&Request::Dismiss { alarm_id })? {
+        Response::Done => Ok(()),
+        _ => Err(ClockError("The daemon refused to dismiss the alarm")),
+    }
+}
+
+/// Asks the daemon to switch to the alarm profile `name` (see [Request::SwitchProfile]),
+/// returning the number of alarms updated.
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (unknown profile, missing
+/// credentials).
+pub fn switch_profile(name: &str) -> Result<usize, ClockError> {
+    match send(&Request::SwitchProfile {
+        name: name.to_string(),
+    })? {
+        Response::ProfileSwitched { updated } => Ok(updated),
+        _ => Err(ClockError("The daemon refused to switch profiles")),
+    }
+}
+
+/// Sends a timer request, returning the resulting timer state.
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request.
+pub fn timer(request: &Request) -> Result<TimerState, ClockError> {
+    match send(request)? {
+        Response::Timer(state) => Ok(state),
+        _ => Err(ClockError("The daemon refused the timer request")),
+    }
+}
+
+/// Sends a stopwatch request, returning the resulting stopwatch state.
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request.
+pub fn stopwatch(request: &Request) -> Result<StopwatchState, ClockError> {
+    match send(request)? {
+        Response::Stopwatch(state) => Ok(state),
+        _ => Err(ClockError("The daemon refused the stopwatch request")),
+    }
+}
+
+/// Sends a pomodoro request, returning the resulting phase.
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable or refuses the request (eg: invalid settings).
+pub fn pomodoro(request: &Request) -> Result<PomodoroMessage, ClockError> {
+    match send(request)? {
+        Response::Pomodoro(message) => Ok(message),
+        _ => Err(ClockError("The daemon refused the pomodoro request")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_json() {
+        let response = Response::Health(Health {
+            database: true,
+            scheduler: false,
+            panics: 1,
+        });
+        let json = serde_json::to_string(&response).unwrap();
+
+        assert_eq!(
+            json,
+            "{\"reply\":\"health\",\"database\":true,\"scheduler\":false,\"panics\":1}"
+        );
+        assert_eq!(serde_json::from_str::<Response>(&json).unwrap(), response);
+    }
+
+    #[test]
+    fn test_list_alarms_json() {
+        let request: Request = serde_json::from_str("{\"command\":\"listAlarms\"}").unwrap();
+
+        assert_eq!(
+            request,
+            Request::ListAlarms {
+                sort: AlarmSort::default(),
+                limit: None,
+                offset: None
+            }
+        );
+        assert_eq!(request.required_role(), Some(Role::ReadOnly));
+        assert_eq!(
+            Request::DeleteAlarm { alarm_id: 3 }.required_role(),
+            Some(Role::Admin)
+        );
+    }
+
+    #[test]
+    fn test_alarm_management_json() {
+        let request: Request =
+            serde_json::from_str("{\"command\":\"history\",\"limit\":50}").unwrap();
+
+        assert_eq!(
+            request,
+            Request::History {
+                alarm_id: None,
+                limit: 50
+            }
+        );
+        assert_eq!(request.required_role(), Some(Role::ReadOnly));
+        assert_eq!(
+            Request::RestoreBackup {
+                content: "[]".to_string()
+            }
+            .required_role(),
+            Some(Role::Admin)
+        );
+
+        let response = Response::Skipped {
+            date: NaiveDate::from_ymd_opt(2023, 12, 25),
+        };
+        let json = serde_json::to_string(&response).unwrap();
+
+        assert_eq!(json, "{\"reply\":\"skipped\",\"date\":\"2023-12-25\"}");
+        assert_eq!(serde_json::from_str::<Response>(&json).unwrap(), response);
+    }
+
+    #[test]
+    fn test_preview_json() {
+        let request = Request::Preview {
+            alarm_id: 3,
+            delay: 5,
+        };
+        let json = serde_json::to_string(&request).unwrap();
+
+        assert_eq!(json, "{\"command\":\"preview\",\"alarmId\":3,\"delay\":5}");
+        assert_eq!(serde_json::from_str::<Request>(&json).unwrap(), request);
+    }
+
+    #[test]
+    fn test_snooze_json() {
+        let request: Request =
+            serde_json::from_str("{\"command\":\"snooze\",\"alarmId\":3}").unwrap();
+
+        assert_eq!(
+            request,
+            Request::Snooze {
+                alarm_id: 3,
+                minutes: None
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&Response::Snoozed { until: 60 }).unwrap(),
+            "{\"reply\":\"snoozed\",\"until\":60}"
+        );
+        assert_eq!(
+            serde_json::from_str::<Request>("{\"command\":\"dismiss\",\"alarmId\":3}").unwrap(),
+            Request::Dismiss { alarm_id: 3 }
+        );
+    }
+
+    #[test]
+    fn test_switch_profile_json() {
+        let request = Request::SwitchProfile {
+            name: "vacation".to_string(),
+        };
+        let json = serde_json::to_string(&request).unwrap();
+
+        assert_eq!(
+            json,
+            "{\"command\":\"switchProfile\",\"name\":\"vacation\"}"
+        );
+        assert_eq!(serde_json::from_str::<Request>(&json).unwrap(), request);
+        assert_eq!(request.required_role(), Some(Role::Admin));
+    }
+
+    #[test]
+    fn test_pomodoro_json() {
+        let request: Request = serde_json::from_str("{\"command\":\"pomodoroStart\"}").unwrap();
+
+        assert_eq!(request, Request::PomodoroStart { settings: None });
+
+        let request: Request = serde_json::from_str(
+            "{\"command\":\"pomodoroStart\",\"settings\":{\"workMinutes\":50,\
+            \"shortBreakMinutes\":10,\"longBreakMinutes\":30,\"longBreakEvery\":2}}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            request,
+            Request::PomodoroStart {
+                settings: Some(PomodoroSettings {
+                    work_minutes: 50,
+                    short_break_minutes: 10,
+                    long_break_minutes: 30,
+                    long_break_every: 2,
+                })
+            }
+        );
+    }
+}
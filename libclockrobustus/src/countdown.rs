@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+use sqlite::State;
+
+use crate::{
+    codec,
+    error::{ClockError, DecodeError},
+};
+
+const TNAME: &str = "countdowns";
+
+/// A date counted down to (eg: a birthday or an exam). Unlike alarms and timers, nothing rings:
+/// the daemon periodically publishes the time left (see [CountdownMessage]).
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::countdown::Countdown;
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+/// let exam = Countdown { id: None, label: "Exam".to_string(), target: 1_700_000_000 };
+///
+/// exam.save(&conn).unwrap();
+/// let countdowns = Countdown::all(&conn).unwrap();
+///
+/// assert_eq!(countdowns[0].label, "Exam");
+/// assert!(countdowns[0].remove(&conn).is_ok());
+/// assert!(Countdown::all(&conn).unwrap().is_empty());
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Countdown {
+    pub id: Option<i64>,
+    /// Free text displayed to the user
+    #[serde(default)]
+    pub label: String,
+    /// Unix timestamp (seconds)
+    pub target: i64,
+}
+
+impl Countdown {
+    // Essential db check
+    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER PRIMARY KEY,
+            label TEXT NOT NULL DEFAULT '',
+            target INTEGER NOT NULL
+            )",
+            TNAME
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    /// Saves (creates or updates) the countdown. Creates the table 'countdowns' if not present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an SQL error has been encountered
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let query = match self.id {
+            Some(_) => format!(
+                "UPDATE {} SET label = :label, target = :target WHERE id = :id",
+                TNAME
+            ),
+            None => format!(
+                "INSERT INTO {} (label, target) VALUES (:label, :target)",
+                TNAME
+            ),
+        };
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":label", self.label.as_str()))?;
+        statement.bind((":target", self.target))?;
+        if let Some(id) = self.id {
+            statement.bind((":id", id))?;
+        }
+        statement.next()?;
+
+        Ok(())
+    }
+
+    /// Get every countdown, soonest target first
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn all(conn: &sqlite::Connection) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("SELECT * FROM {} ORDER BY target, id", TNAME);
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            res.push(Countdown {
+                id: Some(statement.read::<i64, _>("id")?),
+                label: statement.read::<String, _>("label")?,
+                target: statement.read::<i64, _>("target")?,
+            })
+        }
+
+        Ok(res)
+    }
+
+    /// Removes a saved countdown
+    ///
+    /// # Panics
+    ///
+    /// Panics if the countdown has no id (eg: not saved).
+    pub fn remove(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let id = self
+            .id
+            .ok_or(ClockError("Impossible to delete an unsaved countdown"))?;
+        let query = format!("DELETE FROM {} WHERE id = {}", TNAME, id);
+
+        conn.execute(query)?;
+        Ok(())
+    }
+
+    /// Time left at `now` (Unix timestamp, seconds).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::countdown::Countdown;
+    ///
+    /// let birthday = Countdown { id: Some(3), label: "Birthday".to_string(), target: 200_000 };
+    ///
+    /// let remaining = birthday.remaining(200_000 - 2 * 86_400 - 5 * 3_600 - 90);
+    /// assert_eq!((remaining.days, remaining.hours, remaining.minutes), (2, 5, 1));
+    /// assert!(!remaining.passed);
+    ///
+    /// assert!(birthday.remaining(200_001).passed);
+    /// ```
+    pub fn remaining(&self, now: i64) -> CountdownMessage {
+        let left = (self.target - now).max(0);
+
+        CountdownMessage {
+            id: self.id.unwrap_or_default(),
+            days: (left / 86_400) as u32,
+            hours: (left % 86_400 / 3_600) as u8,
+            minutes: (left % 3_600 / 60) as u8,
+            passed: now >= self.target,
+        }
+    }
+}
+
+/// Time left before a [Countdown] target, as published by the daemon. Labels are not carried,
+/// clients match countdowns by id.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CountdownMessage {
+    pub id: i64,
+    pub days: u32,
+    pub hours: u8,
+    pub minutes: u8,
+    /// True once the target date has been reached
+    pub passed: bool,
+}
+
+impl CountdownMessage {
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits)
+    pub const MAX_ENCODED_LEN: usize = 18;
+
+    /// Writes the binary representation at the start of a caller-provided buffer, returning the
+    /// number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is shorter than [CountdownMessage::MAX_ENCODED_LEN].
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        codec::encode(self, buffer)
+    }
+
+    /// Strict counterpart of the [TryFrom] conversion from bytes: truncated messages, trailing
+    /// bytes (see [codec::decode_strict]) and out of range hours and minutes are rejected.
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let message: Self = codec::decode_strict(bytes)?;
+
+        DecodeError::check_range(message.hours, "hours", 0..=23)?;
+        DecodeError::check_range(message.minutes, "minutes", 0..=59)?;
+        Ok(message)
+    }
+}
+
+impl TryFrom<Vec<u8>> for CountdownMessage {
+    type Error = ClockError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        codec::decode(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn test_saving() {
+        let conn = Connection::open(":memory:").unwrap();
+        let countdowns = vec![
+            Countdown {
+                id: None,
+                label: "Exam".to_string(),
+                target: 2_000,
+            },
+            Countdown {
+                id: None,
+                label: "Birthday".to_string(),
+                target: 1_000,
+            },
+        ];
+
+        for countdown in &countdowns {
+            countdown.save(&conn).unwrap();
+        }
+
+        // Soonest first
+        let mut saved = Countdown::all(&conn).unwrap();
+        assert_eq!(saved[0].label, "Birthday");
+
+        saved[0].target = 3_000;
+        saved[0].save(&conn).unwrap();
+
+        let saved = Countdown::all(&conn).unwrap();
+        assert_eq!(saved[1].label, "Birthday");
+        assert_eq!(saved[1].target, 3_000);
+    }
+
+    #[test]
+    fn test_binary_conversion() {
+        let message = Countdown {
+            id: Some(42),
+            label: String::new(),
+            target: 10_000_000,
+        }
+        .remaining(0);
+        let longest = CountdownMessage {
+            id: i64::MIN,
+            days: u32::MAX,
+            hours: 23,
+            minutes: 59,
+            passed: true,
+        };
+        let mut buffer = [0u8; CountdownMessage::MAX_ENCODED_LEN + 1];
+
+        assert_eq!(
+            longest.encode_into(&mut buffer).unwrap(),
+            CountdownMessage::MAX_ENCODED_LEN
+        );
+
+        let len = message.encode_into(&mut buffer).unwrap();
+
+        assert_eq!(message.days, 115);
+        assert_eq!(
+            CountdownMessage::try_from(buffer.to_vec()).unwrap(),
+            message
+        );
+        assert!(CountdownMessage::try_from(buffer[..len - 1].to_vec()).is_err());
+        assert_eq!(
+            CountdownMessage::try_from_strict(&buffer[..len]).unwrap(),
+            message
+        );
+    }
+
+    #[test]
+    fn test_strict_decoding() {
+        let mut buffer = [0u8; CountdownMessage::MAX_ENCODED_LEN + 1];
+        let message = CountdownMessage {
+            id: 1,
+            days: 0,
+            hours: 0,
+            minutes: 0,
+            passed: false,
+        };
+        let len = message.encode_into(&mut buffer).unwrap();
+
+        let error = CountdownMessage::try_from_strict(&buffer[..len + 1]).unwrap_err();
+        assert_eq!((error.offset, error.reason), (len, "Trailing bytes"));
+
+        let error = CountdownMessage::try_from_strict(&buffer[..len - 1]).unwrap_err();
+        assert_eq!((error.offset, error.reason), (len - 1, "Missing field"));
+    }
+}
@@ -1,10 +1,25 @@
 use std::env;
 
-use crate::error::ClockError;
+use crate::{
+    astro::Location,
+    backup::BackupSchedule,
+    calendar::CalendarKind,
+    clock::{ThemeHours, Zone},
+    error::ClockError,
+    message::{Topics, WireFormat},
+    speak::SpeakSchedule,
+};
 /// Substructure related to queue data. Here to keep things tidy.
 pub struct QueueEnv {
     port: u16,
+    control_port: u16,
+    grpc_port: Option<u16>,
+    http_port: Option<u16>,
+    api_token: Option<String>,
     host: String,
+    topic_prefix: String,
+    strict_decode: bool,
+    wire_format: WireFormat,
 }
 
 impl QueueEnv {
@@ -13,15 +28,63 @@ impl QueueEnv {
         self.port
     }
 
+    /// Read-only accessor.
+    pub fn control_port(&self) -> u16 {
+        self.control_port
+    }
+
+    /// Read-only accessor. [None] when the gRPC server is disabled.
+    pub fn grpc_port(&self) -> Option<u16> {
+        self.grpc_port
+    }
+
+    /// Read-only accessor. [None] when the HTTP server is disabled.
+    pub fn http_port(&self) -> Option<u16> {
+        self.http_port
+    }
+
+    /// Read-only accessor. Token presented by clients to the daemon, if any.
+    pub fn api_token(&self) -> Option<&str> {
+        self.api_token.as_deref()
+    }
+
     /// Read-only accessor.
     pub fn host(&self) -> &str {
         &self.host
     }
+
+    /// Read-only accessor. Prefix of every published topic (empty by default).
+    pub fn topic_prefix(&self) -> &str {
+        &self.topic_prefix
+    }
+
+    /// Read-only accessor. True if listeners reject malformed messages with detailed
+    /// diagnostics (see [crate::message::Message::try_from_strict]).
+    pub fn strict_decode(&self) -> bool {
+        self.strict_decode
+    }
+
+    /// Read-only accessor. Encoding of the published payloads (binary by default).
+    pub fn wire_format(&self) -> WireFormat {
+        self.wire_format
+    }
+
+    /// Wire topics matching [QueueEnv::topic_prefix] and [QueueEnv::wire_format].
+    pub fn topics(&self) -> Topics {
+        Topics::new(&self.topic_prefix).with_format(self.wire_format)
+    }
 }
 
 /// Substructure related to constants data. Here to keep things tidy.
 pub struct Constants {
     tick_duration: u64,
+    rate_limit: u32,
+    theme_hours: ThemeHours,
+    calendars: Vec<CalendarKind>,
+    timezone: Zone,
+    location: Option<Location>,
+    ntp_server: Option<String>,
+    speak: Option<SpeakSchedule>,
 }
 
 impl Constants {
@@ -29,6 +92,66 @@ impl Constants {
     pub fn tick_duration(&self) -> u64 {
         self.tick_duration
     }
+
+    /// Read-only accessor.
+    pub fn rate_limit(&self) -> u32 {
+        self.rate_limit
+    }
+
+    /// Read-only accessor.
+    pub fn theme_hours(&self) -> &ThemeHours {
+        &self.theme_hours
+    }
+
+    /// Read-only accessor. Calendars added to clock messages (none by default).
+    pub fn calendars(&self) -> &[CalendarKind] {
+        &self.calendars
+    }
+
+    /// Read-only accessor. Timezone of the published clock (the local one by default).
+    pub fn timezone(&self) -> &Zone {
+        &self.timezone
+    }
+
+    /// Read-only accessor. Where sunrise and sunset are computed for (none by default).
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    /// Read-only accessor. `host:port` of the NTP server the clock is compared to (none by
+    /// default).
+    pub fn ntp_server(&self) -> Option<&str> {
+        self.ntp_server.as_deref()
+    }
+
+    /// Read-only accessor. When the speaking clock announces the time (never by default).
+    pub fn speak(&self) -> Option<SpeakSchedule> {
+        self.speak
+    }
+}
+
+/// Substructure related to automatic database backups. Here to keep things tidy.
+pub struct BackupEnv {
+    schedule: Option<BackupSchedule>,
+    keep: usize,
+    directory: Option<String>,
+}
+
+impl BackupEnv {
+    /// Read-only accessor. [None] when automatic backups are disabled.
+    pub fn schedule(&self) -> Option<BackupSchedule> {
+        self.schedule
+    }
+
+    /// Read-only accessor. Number of backups kept.
+    pub fn keep(&self) -> usize {
+        self.keep
+    }
+
+    /// Read-only accessor. [None] to store backups next to the database.
+    pub fn directory(&self) -> Option<&str> {
+        self.directory.as_deref()
+    }
 }
 
 /// Environment, useful to retrieve default values or environment set ones  
@@ -36,8 +159,41 @@ impl Constants {
 /// # Available env vars
 ///
 /// - CLOCKROBUSTUS_INTERNAL_QUEUE_PORT: port for zeromq outgoing channel (defaults to 5555)
+/// - CLOCKROBUSTUS_CONTROL_PORT: port for the zeromq control (request/reply) channel (defaults to 5556)
+/// - CLOCKROBUSTUS_GRPC_PORT: port for the optional gRPC server (disabled if not set, needs the
+///   daemon to be built with the `grpc` feature)
+/// - CLOCKROBUSTUS_HTTP_PORT: port for the optional HTTP server, streaming events as
+///   server-sent events (disabled if not set)
+/// - CLOCKROBUSTUS_API_TOKEN: API token presented by clients to the daemon (none by default)
 /// - CLOCKROBUSTUS_INTERNAL_QUEUE_HOST: host for zeromq outgoing channel (default to localhost)
-/// - CLOCKROBUSTUS_TICK_DURATION_MS: tick duration for the clock server (defaults to 1000)
+/// - CLOCKROBUSTUS_TOPIC_PREFIX: prefix of the published topics, so several instances can share
+///   a port (empty by default, daemon and clients must agree on it)
+/// - CLOCKROBUSTUS_STRICT_DECODE: `1` or `true` to have listeners reject out of range values and
+///   trailing bytes, printing which byte is wrong (lenient by default)
+/// - CLOCKROBUSTUS_WIRE_FORMAT: `binary`, `json` or `msgpack` encoding of the published payloads
+///   (defaults to `binary`, daemon and clients must agree on it)
+/// - CLOCKROBUSTUS_TICK_DURATION_MS: tick duration for the clock server (defaults to 1000), alarms
+///   due since the previous tick ringing once per occurrence whatever the duration
+/// - CLOCKROBUSTUS_RATE_LIMIT: requests per second allowed to each client of the control channel
+///   and HTTP server (defaults to 10, cannot be 0)
+/// - CLOCKROBUSTUS_THEME_HOURS: `day,dusk,night` hours at which the theme hint of clock messages
+///   switches (defaults to `7,18,21`)
+/// - CLOCKROBUSTUS_CALENDARS: comma separated `hijri`, `hebrew` or `chinese` calendars whose
+///   date is added to clock messages (none by default, needs the daemon to be built with the
+///   `calendars` feature)
+/// - CLOCKROBUSTUS_TIMEZONE: IANA timezone (eg: `Europe/Paris`) of the time published in clock
+///   messages (defaults to the local timezone of the machine). Alarms still ring on local time
+/// - CLOCKROBUSTUS_LOCATION: `latitude,longitude` degrees (eg: `48.8566,2.3522`) of the place
+///   sunrise and sunset are published for (not published if not set)
+/// - CLOCKROBUSTUS_NTP_SERVER: NTP server (eg: `pool.ntp.org`, port 123 if not given) regularly
+///   queried to publish how far the clock is from it (not queried if not set)
+/// - CLOCKROBUSTUS_SPEAK: `minutes[,locale]` (eg: `30` or `15,fr`) period of the speaking clock
+///   announcements, `en` or `fr` (disabled if not set)
+/// - CLOCKROBUSTUS_BACKUP_SCHEDULE: `daily` or `weekly` automatic database backups (disabled if
+///   not set)
+/// - CLOCKROBUSTUS_BACKUP_KEEP: number of automatic backups kept (defaults to 7, at least 1)
+/// - CLOCKROBUSTUS_BACKUP_DIR: directory holding the backups (defaults to a `backups` directory
+///   next to the database)
 /// # Panics
 ///
 /// The [ClockEnv] creation will panic if one of the numeric env values specified above is not
@@ -55,6 +211,7 @@ impl Constants {
 pub struct ClockEnv {
     queue: QueueEnv,
     constants: Constants,
+    backup: BackupEnv,
 }
 
 impl ClockEnv {
@@ -64,13 +221,83 @@ impl ClockEnv {
                 port: env::var("CLOCKROBUSTUS_INTERNAL_QUEUE_PORT")
                     .unwrap_or("5555".to_string())
                     .parse()?,
+                control_port: env::var("CLOCKROBUSTUS_CONTROL_PORT")
+                    .unwrap_or("5556".to_string())
+                    .parse()?,
+                grpc_port: match env::var("CLOCKROBUSTUS_GRPC_PORT") {
+                    Ok(port) => Some(port.parse()?),
+                    Err(_) => None,
+                },
+                http_port: match env::var("CLOCKROBUSTUS_HTTP_PORT") {
+                    Ok(port) => Some(port.parse()?),
+                    Err(_) => None,
+                },
+                api_token: env::var("CLOCKROBUSTUS_API_TOKEN").ok(),
                 host: env::var("CLOCKROBUSTUS_INTERNAL_QUEUE_HOST")
                     .unwrap_or("127.0.0.1".to_string()),
+                topic_prefix: env::var("CLOCKROBUSTUS_TOPIC_PREFIX").unwrap_or_default(),
+                strict_decode: matches!(
+                    env::var("CLOCKROBUSTUS_STRICT_DECODE").as_deref(),
+                    Ok("1") | Ok("true")
+                ),
+                wire_format: match env::var("CLOCKROBUSTUS_WIRE_FORMAT") {
+                    Ok(format) => format.parse()?,
+                    Err(_) => WireFormat::default(),
+                },
             },
             constants: Constants {
                 tick_duration: env::var("CLOCKROBUSTUS_TICK_DURATION_MS")
                     .unwrap_or("1000".to_string())
                     .parse()?,
+                rate_limit: match env::var("CLOCKROBUSTUS_RATE_LIMIT")
+                    .unwrap_or("10".to_string())
+                    .parse()?
+                {
+                    // Would reject every request
+                    0 => return Err(ClockError("Null rate limit")),
+                    rate_limit => rate_limit,
+                },
+                theme_hours: match env::var("CLOCKROBUSTUS_THEME_HOURS") {
+                    Ok(hours) => hours.parse()?,
+                    Err(_) => ThemeHours::default(),
+                },
+                calendars: match env::var("CLOCKROBUSTUS_CALENDARS") {
+                    Ok(calendars) => calendars
+                        .split(',')
+                        .filter(|calendar| !calendar.trim().is_empty())
+                        .map(CalendarKind::try_from)
+                        .collect::<Result<Vec<CalendarKind>, ClockError>>()?,
+                    Err(_) => Vec::new(),
+                },
+                timezone: match env::var("CLOCKROBUSTUS_TIMEZONE") {
+                    Ok(timezone) => timezone.parse()?,
+                    Err(_) => Zone::Local,
+                },
+                location: match env::var("CLOCKROBUSTUS_LOCATION") {
+                    Ok(location) => Some(location.parse()?),
+                    Err(_) => None,
+                },
+                ntp_server: env::var("CLOCKROBUSTUS_NTP_SERVER").ok().map(|server| {
+                    if server.contains(':') {
+                        server
+                    } else {
+                        format!("{}:123", server)
+                    }
+                }),
+                speak: match env::var("CLOCKROBUSTUS_SPEAK") {
+                    Ok(speak) => Some(speak.parse()?),
+                    Err(_) => None,
+                },
+            },
+            backup: BackupEnv {
+                schedule: match env::var("CLOCKROBUSTUS_BACKUP_SCHEDULE") {
+                    Ok(schedule) => Some(BackupSchedule::try_from(schedule.as_str())?),
+                    Err(_) => None,
+                },
+                keep: env::var("CLOCKROBUSTUS_BACKUP_KEEP")
+                    .unwrap_or("7".to_string())
+                    .parse()?,
+                directory: env::var("CLOCKROBUSTUS_BACKUP_DIR").ok(),
             },
         })
     }
@@ -82,18 +309,40 @@ impl ClockEnv {
     pub fn constants(&self) -> &Constants {
         &self.constants
     }
+
+    pub fn backup(&self) -> &BackupEnv {
+        &self.backup
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::env::{remove_var, set_var};
 
+    use crate::speak::SpeakLocale;
+
     use super::*;
 
     fn clean_env() {
         remove_var("CLOCKROBUSTUS_INTERNAL_QUEUE_PORT");
         remove_var("CLOCKROBUSTUS_INTERNAL_QUEUE_HOST");
+        remove_var("CLOCKROBUSTUS_CONTROL_PORT");
+        remove_var("CLOCKROBUSTUS_GRPC_PORT");
+        remove_var("CLOCKROBUSTUS_HTTP_PORT");
+        remove_var("CLOCKROBUSTUS_TOPIC_PREFIX");
+        remove_var("CLOCKROBUSTUS_STRICT_DECODE");
+        remove_var("CLOCKROBUSTUS_WIRE_FORMAT");
         remove_var("CLOCKROBUSTUS_TICK_DURATION_MS");
+        remove_var("CLOCKROBUSTUS_RATE_LIMIT");
+        remove_var("CLOCKROBUSTUS_THEME_HOURS");
+        remove_var("CLOCKROBUSTUS_CALENDARS");
+        remove_var("CLOCKROBUSTUS_TIMEZONE");
+        remove_var("CLOCKROBUSTUS_LOCATION");
+        remove_var("CLOCKROBUSTUS_NTP_SERVER");
+        remove_var("CLOCKROBUSTUS_SPEAK");
+        remove_var("CLOCKROBUSTUS_BACKUP_SCHEDULE");
+        remove_var("CLOCKROBUSTUS_BACKUP_KEEP");
+        remove_var("CLOCKROBUSTUS_BACKUP_DIR");
     }
 
     #[test]
@@ -102,8 +351,24 @@ mod tests {
         let env = ClockEnv::new().unwrap();
 
         assert_eq!(env.queue().port(), 5555u16);
+        assert_eq!(env.queue().control_port(), 5556u16);
+        assert_eq!(env.queue().grpc_port(), None);
+        assert_eq!(env.queue().http_port(), None);
         assert_eq!(env.queue().host(), "127.0.0.1");
+        assert_eq!(env.queue().topic_prefix(), "");
+        assert!(!env.queue().strict_decode());
+        assert_eq!(env.queue().wire_format(), WireFormat::Binary);
         assert_eq!(env.constants().tick_duration(), 1000u64);
+        assert_eq!(env.constants().rate_limit(), 10u32);
+        assert_eq!(env.constants().theme_hours(), &ThemeHours::default());
+        assert!(env.constants().calendars().is_empty());
+        assert_eq!(env.constants().timezone(), &Zone::Local);
+        assert_eq!(env.constants().location(), None);
+        assert_eq!(env.constants().ntp_server(), None);
+        assert_eq!(env.constants().speak(), None);
+        assert_eq!(env.backup().schedule(), None);
+        assert_eq!(env.backup().keep(), 7);
+        assert_eq!(env.backup().directory(), None);
     }
 
     #[test]
@@ -111,12 +376,54 @@ mod tests {
         set_var("CLOCKROBUSTUS_INTERNAL_QUEUE_PORT", "1234");
         set_var("CLOCKROBUSTUS_INTERNAL_QUEUE_HOST", "128.122.122.1");
         set_var("CLOCKROBUSTUS_TICK_DURATION_MS", "200");
+        set_var("CLOCKROBUSTUS_CONTROL_PORT", "1235");
+        set_var("CLOCKROBUSTUS_GRPC_PORT", "50051");
+        set_var("CLOCKROBUSTUS_HTTP_PORT", "8080");
+        set_var("CLOCKROBUSTUS_TOPIC_PREFIX", "kitchen/");
+        set_var("CLOCKROBUSTUS_BACKUP_SCHEDULE", "weekly");
+        set_var("CLOCKROBUSTUS_CALENDARS", "hebrew, chinese");
+        set_var("CLOCKROBUSTUS_STRICT_DECODE", "true");
+        set_var("CLOCKROBUSTUS_WIRE_FORMAT", "json");
+        set_var("CLOCKROBUSTUS_TIMEZONE", "America/New_York");
+        set_var("CLOCKROBUSTUS_LOCATION", "40.7128,-74.006");
+        set_var("CLOCKROBUSTUS_NTP_SERVER", "pool.ntp.org");
+        set_var("CLOCKROBUSTUS_SPEAK", "15,fr");
 
         let env = ClockEnv::new().unwrap();
 
         assert_eq!(env.queue().host(), "128.122.122.1");
         assert_eq!(env.constants().tick_duration(), 200u64);
         assert_eq!(env.queue().port(), 1234u16);
+        assert_eq!(env.queue().control_port(), 1235u16);
+        assert_eq!(env.queue().grpc_port(), Some(50051u16));
+        assert_eq!(env.queue().http_port(), Some(8080u16));
+        assert_eq!(env.queue().topics().alarm(), b"kitchen/alarm");
+        assert!(env.queue().strict_decode());
+        assert_eq!(env.queue().topics().format(), WireFormat::Json);
+        assert_eq!(env.backup().schedule(), Some(BackupSchedule::Weekly));
+        assert_eq!(
+            env.constants().calendars(),
+            &[CalendarKind::Hebrew, CalendarKind::Chinese]
+        );
+        assert_eq!(
+            env.constants().timezone(),
+            &Zone::Named(chrono_tz::America::New_York)
+        );
+        assert_eq!(
+            env.constants().location(),
+            Some(&Location {
+                latitude: 40.7128,
+                longitude: -74.006
+            })
+        );
+        assert_eq!(env.constants().ntp_server(), Some("pool.ntp.org:123"));
+        assert_eq!(
+            env.constants().speak(),
+            Some(SpeakSchedule {
+                every_minutes: 15,
+                locale: SpeakLocale::Fr
+            })
+        );
 
         clean_env();
     }
@@ -136,6 +443,46 @@ mod tests {
                 ("CLOCKROBUSTUS_INTERNAL_QUEUE_PORT", "1234"),
                 ("CLOCKROBUSTUS_TICK_DURATION_MS", "foobazbar"),
             ],
+            // Env with unordered theme hours
+            vec![
+                ("CLOCKROBUSTUS_TICK_DURATION_MS", "100"),
+                ("CLOCKROBUSTUS_THEME_HOURS", "21,18,7"),
+            ],
+            // Env with unknown calendar
+            vec![
+                ("CLOCKROBUSTUS_TICK_DURATION_MS", "100"),
+                ("CLOCKROBUSTUS_CALENDARS", "hebrew,julian"),
+            ],
+            // Env with unknown backup schedule
+            vec![
+                ("CLOCKROBUSTUS_TICK_DURATION_MS", "100"),
+                ("CLOCKROBUSTUS_BACKUP_SCHEDULE", "hourly"),
+            ],
+            // Env with unknown timezone
+            vec![
+                ("CLOCKROBUSTUS_TICK_DURATION_MS", "100"),
+                ("CLOCKROBUSTUS_TIMEZONE", "Mars/Olympus_Mons"),
+            ],
+            // Env with out of range latitude
+            vec![
+                ("CLOCKROBUSTUS_TICK_DURATION_MS", "100"),
+                ("CLOCKROBUSTUS_LOCATION", "100,0"),
+            ],
+            // Env with unknown wire format
+            vec![
+                ("CLOCKROBUSTUS_TICK_DURATION_MS", "100"),
+                ("CLOCKROBUSTUS_WIRE_FORMAT", "xml"),
+            ],
+            // Env with a null speaking clock period
+            vec![
+                ("CLOCKROBUSTUS_TICK_DURATION_MS", "100"),
+                ("CLOCKROBUSTUS_SPEAK", "0"),
+            ],
+            // Env with a null rate limit
+            vec![
+                ("CLOCKROBUSTUS_TICK_DURATION_MS", "100"),
+                ("CLOCKROBUSTUS_RATE_LIMIT", "0"),
+            ],
         ];
 
         for env in wrong_envs {
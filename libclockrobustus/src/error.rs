@@ -3,34 +3,272 @@ use std::env::VarError;
 use std::fmt::Display;
 use std::io;
 use std::num::{IntErrorKind, ParseIntError};
-use std::time::SystemTimeError;
+use std::ops::RangeInclusive;
+use std::time::{Duration, SystemTimeError};
 /// Thread-safe error object that bridges before many of error types encountered during a typical
 /// run  
 /// Every [From] trait implementation also prints to stdout the details of each error it binds to.
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct ClockError(pub &'static str);
 
 impl std::error::Error for ClockError {}
 
+/// Longest delay suggested by [ErrorCategory::retry_delay]
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// How a failed operation should be handled, see [ClockError::category].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// Expected to clear by itself right away (eg: database locked by another writer), retry
+    Transient,
+    /// May clear later (eg: daemon not started yet), retry after backing off
+    Retryable,
+    /// Retrying cannot help (eg: corrupted database, address already in use), give up
+    Fatal,
+}
+
+impl ErrorCategory {
+    /// Delay to wait before the `attempt`-th retry (starting at 0), [None] if the operation
+    /// should not be retried. Transient errors are retried after `base`, retryable ones after
+    /// a delay doubling at each attempt, up to [MAX_RETRY_DELAY].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use libclockrobustus::error::{ErrorCategory, MAX_RETRY_DELAY};
+    ///
+    /// let base = Duration::from_millis(100);
+    ///
+    /// assert_eq!(ErrorCategory::Transient.retry_delay(base, 5), Some(base));
+    /// assert_eq!(ErrorCategory::Retryable.retry_delay(base, 3), Some(base * 8));
+    /// assert_eq!(ErrorCategory::Retryable.retry_delay(base, 40), Some(MAX_RETRY_DELAY));
+    /// assert_eq!(ErrorCategory::Fatal.retry_delay(base, 0), None);
+    /// ```
+    pub fn retry_delay(&self, base: Duration, attempt: u32) -> Option<Duration> {
+        match self {
+            Self::Transient => Some(base),
+            Self::Retryable => Some(
+                base.saturating_mul(1 << attempt.min(16))
+                    .min(MAX_RETRY_DELAY),
+            ),
+            Self::Fatal => None,
+        }
+    }
+}
+
+/// Errors told apart by [ClockError::category]
+impl ClockError {
+    pub const DATABASE_LOCKED: Self = Self("Database Error: Locked");
+    pub const DATABASE_READ_ONLY: Self = Self("Database Error: Read only");
+    pub const DATABASE_CORRUPTED: Self = Self("Database Error: Corrupted");
+    pub const ZMQ_NO_ACCESS: Self = Self("ZMQ Error: No Access");
+    pub const ZMQ_ADDRESS_IN_USE: Self = Self("ZMQ Error: Address in use");
+    pub const ZMQ_WOULD_BLOCK: Self = Self("ZMQ Error: Would block");
+    pub const ZMQ_BUSY: Self = Self("ZMQ Error: Resource busy");
+    pub const ZMQ_ADDRESS_NOT_AVAILABLE: Self = Self("ZMQ Error: Address not available");
+    pub const ZMQ_INTERRUPTED: Self = Self("ZMQ Error: Interrput");
+    pub const ENV_VAR: Self = Self("Env Var Error");
+    pub const CTRL_C_HANDLER: Self = Self("Unable to setup Ctrl+C handler");
+
+    /// Classifies the error, errors not known to be transient or fatal being retryable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::error::{ClockError, ErrorCategory};
+    ///
+    /// assert_eq!(ClockError::DATABASE_LOCKED.category(), ErrorCategory::Transient);
+    /// assert_eq!(ClockError::DATABASE_CORRUPTED.category(), ErrorCategory::Fatal);
+    /// assert_eq!(ClockError("Alarm message too short").category(), ErrorCategory::Retryable);
+    /// ```
+    pub fn category(&self) -> ErrorCategory {
+        match *self {
+            Self::DATABASE_LOCKED
+            | Self::ZMQ_WOULD_BLOCK
+            | Self::ZMQ_BUSY
+            | Self::ZMQ_INTERRUPTED => ErrorCategory::Transient,
+            Self::DATABASE_CORRUPTED
+            | Self::DATABASE_READ_ONLY
+            | Self::ZMQ_NO_ACCESS
+            | Self::ZMQ_ADDRESS_IN_USE
+            | Self::ZMQ_ADDRESS_NOT_AVAILABLE
+            | Self::ENV_VAR
+            | Self::CTRL_C_HANDLER => ErrorCategory::Fatal,
+            _ => ErrorCategory::Retryable,
+        }
+    }
+
+    /// Shorthand for a [ErrorCategory::Fatal] category
+    pub fn is_fatal(&self) -> bool {
+        self.category() == ErrorCategory::Fatal
+    }
+}
+
 impl Display for ClockError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
     }
 }
 
+/// Detailed failure of a strict decoding (see [crate::message::Message::try_from_strict]),
+/// pointing at the offending byte of the decoded data.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::{error::DecodeError, message::Message};
+///
+/// // Legacy alarm frame: header, days, hour, minute and seconds
+/// let error = Message::try_from_strict(&[0xFF, 0x01, 24, 0, 0]).unwrap_err();
+///
+/// assert_eq!(error, DecodeError { offset: 2, field: "hour", reason: "Value out of range" });
+/// assert_eq!(error.to_string(), "Value out of range at offset 2 (hour)");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError {
+    pub offset: usize,
+    /// Name of the field starting at that offset
+    pub field: &'static str,
+    pub reason: &'static str,
+}
+
+impl DecodeError {
+    pub(crate) fn new(offset: usize, field: &'static str, reason: &'static str) -> Self {
+        Self {
+            offset,
+            field,
+            reason,
+        }
+    }
+
+    /// Same error, for data found `offset` bytes into a larger one
+    pub(crate) fn shifted(self, offset: usize) -> Self {
+        Self {
+            offset: self.offset + offset,
+            ..self
+        }
+    }
+
+    /// Reads the byte at `offset`, rejecting values out of `range`
+    pub(crate) fn byte(
+        bytes: &[u8],
+        offset: usize,
+        field: &'static str,
+        range: RangeInclusive<u8>,
+    ) -> Result<u8, Self> {
+        let byte = *bytes
+            .get(offset)
+            .ok_or(Self::new(offset, field, "Missing field"))?;
+
+        if range.contains(&byte) {
+            Ok(byte)
+        } else {
+            Err(Self::new(offset, field, "Value out of range"))
+        }
+    }
+
+    /// Reads the `N` bytes starting at `offset`
+    pub(crate) fn array<const N: usize>(
+        bytes: &[u8],
+        offset: usize,
+        field: &'static str,
+    ) -> Result<[u8; N], Self> {
+        bytes
+            .get(offset..offset + N)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or(Self::new(offset, field, "Missing field"))
+    }
+
+    /// Rejects a decoded `value` out of `range`. Fields of [crate::codec] bodies cannot be
+    /// located, the error points at the start of the data.
+    pub(crate) fn check_range<T: PartialOrd>(
+        value: T,
+        field: &'static str,
+        range: RangeInclusive<T>,
+    ) -> Result<(), Self> {
+        if range.contains(&value) {
+            Ok(())
+        } else {
+            Err(Self::new(0, field, "Value out of range"))
+        }
+    }
+
+    /// Rejects anything after the first `len` bytes
+    pub(crate) fn check_end(bytes: &[u8], len: usize) -> Result<(), Self> {
+        if bytes.len() > len {
+            Err(Self::new(len, "end", "Trailing bytes"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at offset {} ({})",
+            self.reason, self.offset, self.field
+        )
+    }
+}
+
+impl From<DecodeError> for ClockError {
+    fn from(value: DecodeError) -> Self {
+        println!("{}", value);
+        Self(value.reason)
+    }
+}
+
+impl From<postcard::Error> for ClockError {
+    fn from(value: postcard::Error) -> Self {
+        println!("{:?}", value);
+        match value {
+            postcard::Error::SerializeBufferFull => Self("Buffer too small to encode message"),
+            postcard::Error::DeserializeUnexpectedEnd => Self("Message too short"),
+            _ => Self("Postcard Error"),
+        }
+    }
+}
+
+#[cfg(feature = "protobuf")]
+impl From<prost::DecodeError> for ClockError {
+    fn from(value: prost::DecodeError) -> Self {
+        println!("{:?}", value);
+        Self("Protobuf Error")
+    }
+}
+
+impl From<rmp_serde::encode::Error> for ClockError {
+    fn from(value: rmp_serde::encode::Error) -> Self {
+        println!("{:?}", value);
+        Self("MessagePack Error")
+    }
+}
+
+impl From<rmp_serde::decode::Error> for ClockError {
+    fn from(value: rmp_serde::decode::Error) -> Self {
+        println!("{:?}", value);
+        Self("MessagePack Error")
+    }
+}
+
 impl From<zmq::Error> for ClockError {
     fn from(value: zmq::Error) -> Self {
         println!("{:?}", value);
         match value {
-            zmq::Error::EACCES => Self("ZMQ Error: No Access"),
-            zmq::Error::EADDRINUSE => Self("ZMQ Error: Address in use"),
-            zmq::Error::EAGAIN => Self("ZMQ Error: Would block"),
-            zmq::Error::EBUSY => Self("ZMQ Error: Resource busy"),
+            zmq::Error::EACCES => Self::ZMQ_NO_ACCESS,
+            zmq::Error::EADDRINUSE => Self::ZMQ_ADDRESS_IN_USE,
+            zmq::Error::EAGAIN => Self::ZMQ_WOULD_BLOCK,
+            zmq::Error::EBUSY => Self::ZMQ_BUSY,
             zmq::Error::ECONNREFUSED => Self("ZMQ Error: Connection refused"),
             zmq::Error::ENOTCONN => Self("ZMQ Error: Not connected"),
-            zmq::Error::EADDRNOTAVAIL => Self("ZMQ Error: Address not available"),
+            zmq::Error::EADDRNOTAVAIL => Self::ZMQ_ADDRESS_NOT_AVAILABLE,
             zmq::Error::EINVAL => Self("ZMQ Error: Invalid input"),
-            zmq::Error::EINTR => Self("ZMQ Error: Interrput"),
+            zmq::Error::EINTR => Self::ZMQ_INTERRUPTED,
             _ => Self("ZMQ Error"),
         }
     }
@@ -65,7 +303,13 @@ impl From<SystemTimeError> for ClockError {
 impl From<sqlite::Error> for ClockError {
     fn from(value: sqlite::Error) -> Self {
         println!("{:?}", value);
-        Self("Database Error")
+        // Primary result codes, see https://www.sqlite.org/rescode.html
+        match value.code.map(|code| code & 0xFF) {
+            Some(5) | Some(6) => Self::DATABASE_LOCKED,
+            Some(8) => Self::DATABASE_READ_ONLY,
+            Some(11) | Some(26) => Self::DATABASE_CORRUPTED,
+            _ => Self("Database Error"),
+        }
     }
 }
 
@@ -79,13 +323,20 @@ impl From<io::Error> for ClockError {
 impl From<VarError> for ClockError {
     fn from(value: VarError) -> Self {
         println!("{:?}", value);
-        Self("Env Var Error")
+        Self::ENV_VAR
     }
 }
 
 impl From<ctrlc::Error> for ClockError {
     fn from(value: ctrlc::Error) -> Self {
         println!("{:?}", value);
-        Self("Unable to setup Ctrl+C handler")
+        Self::CTRL_C_HANDLER
+    }
+}
+
+impl From<serde_json::Error> for ClockError {
+    fn from(value: serde_json::Error) -> Self {
+        println!("{:?}", value);
+        Self("JSON Error")
     }
 }
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+/// Seconds between two heartbeats of the daemon
+pub const HEARTBEAT_INTERVAL_S: u64 = 5;
+/// Heartbeats in a row a client may miss before it considers the daemon down, fewer being a
+/// network hiccup
+pub const MISSED_HEARTBEATS_DOWN: u64 = 3;
+
+/// Status of the daemon, as periodically published so that clients can tell whether it is still
+/// running (see [Liveness]).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeartbeatMessage {
+    /// Seconds since the daemon started, going back to 0 when it restarts
+    pub uptime_s: u64,
+    /// Alarms saved in the database
+    pub alarm_count: u32,
+    /// Version of the daemon, at most [HeartbeatMessage::MAX_VERSION_LEN] bytes
+    pub version: String,
+}
+
+impl HeartbeatMessage {
+    /// Longest version (in bytes) a message can carry
+    pub const MAX_VERSION_LEN: usize = 32;
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits)
+    pub const MAX_ENCODED_LEN: usize = 16 + Self::MAX_VERSION_LEN;
+
+    /// Heartbeat of a daemon started `uptime_s` seconds ago, the version being truncated to
+    /// [HeartbeatMessage::MAX_VERSION_LEN] bytes.
+    pub fn new(uptime_s: u64, alarm_count: u32, version: &str) -> Self {
+        let mut len = version.len().min(Self::MAX_VERSION_LEN);
+        while !version.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        Self {
+            uptime_s,
+            alarm_count,
+            version: version[..len].to_string(),
+        }
+    }
+}
+
+/// What a client makes of the heartbeats of the daemon.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Liveness {
+    /// Heartbeats arrive on time
+    Up,
+    /// A few heartbeats were missed, the connection is unreliable
+    Late,
+    /// No heartbeat for [MISSED_HEARTBEATS_DOWN] intervals, or none ever received
+    Down,
+}
+
+impl Liveness {
+    /// Liveness of the daemon `elapsed_s` seconds after its last heartbeat ([None] if none was
+    /// received).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::heartbeat::Liveness;
+    ///
+    /// assert_eq!(Liveness::since(Some(4)), Liveness::Up);
+    /// assert_eq!(Liveness::since(Some(12)), Liveness::Late);
+    /// assert_eq!(Liveness::since(Some(15)), Liveness::Down);
+    /// assert_eq!(Liveness::since(None), Liveness::Down);
+    /// ```
+    pub fn since(elapsed_s: Option<u64>) -> Self {
+        match elapsed_s {
+            // Heartbeats may arrive a bit late without anything being wrong
+            Some(elapsed) if elapsed <= HEARTBEAT_INTERVAL_S * 2 => Self::Up,
+            Some(elapsed) if elapsed < HEARTBEAT_INTERVAL_S * MISSED_HEARTBEATS_DOWN => Self::Late,
+            _ => Self::Down,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec;
+
+    #[test]
+    fn test_new() {
+        let heartbeat = HeartbeatMessage::new(60, 3, "0.1.0");
+
+        assert_eq!(heartbeat.version, "0.1.0");
+        assert_eq!(
+            HeartbeatMessage::new(60, 3, &"é".repeat(20)).version,
+            "é".repeat(16)
+        );
+        assert_eq!(
+            HeartbeatMessage::new(60, 3, &"a".repeat(33)).version.len(),
+            HeartbeatMessage::MAX_VERSION_LEN
+        );
+    }
+
+    #[test]
+    fn test_encoded_len() {
+        let heartbeat = HeartbeatMessage::new(u64::MAX, u32::MAX, &"a".repeat(40));
+        let mut buffer = [0u8; HeartbeatMessage::MAX_ENCODED_LEN];
+
+        let len = codec::encode(&heartbeat, &mut buffer).unwrap();
+
+        assert_eq!(
+            codec::decode_strict::<HeartbeatMessage>(&buffer[..len]).unwrap(),
+            heartbeat
+        );
+    }
+}
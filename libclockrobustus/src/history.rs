@@ -0,0 +1,550 @@
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use sqlite::State;
+
+use crate::{add_missing_columns, alarm::Alarm, error::ClockError};
+
+const TNAME: &str = "alarm_history";
+/// Columns added after the first release, appended to tables created by older versions.
+const LATE_COLUMNS: &[(&str, &str)] = &[("offset_seconds", "INTEGER NOT NULL DEFAULT 0")];
+
+/// What happened to an alarm occurrence.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RingOutcome {
+    /// The daemon published the alarm.
+    Rang,
+    /// A user stopped the alarm.
+    Dismissed,
+    /// A user postponed the alarm.
+    Snoozed,
+    /// The occurrence went by without being published.
+    Missed,
+    /// The alarm rang but nobody dismissed nor snoozed it.
+    Ignored,
+    /// The occurrence fell within a do-not-disturb window (see [crate::quiet::QuietWindow]).
+    Silenced,
+}
+
+impl RingOutcome {
+    /// Database representation
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rang => "rang",
+            Self::Dismissed => "dismissed",
+            Self::Snoozed => "snoozed",
+            Self::Missed => "missed",
+            Self::Ignored => "ignored",
+            Self::Silenced => "silenced",
+        }
+    }
+}
+
+impl TryFrom<&str> for RingOutcome {
+    type Error = ClockError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "rang" => Ok(Self::Rang),
+            "dismissed" => Ok(Self::Dismissed),
+            "snoozed" => Ok(Self::Snoozed),
+            "missed" => Ok(Self::Missed),
+            "ignored" => Ok(Self::Ignored),
+            "silenced" => Ok(Self::Silenced),
+            _ => Err(ClockError("Unknown ring outcome")),
+        }
+    }
+}
+
+/// A single entry of the alarm history.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::history::{RingEvent, RingOutcome};
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+///
+/// RingEvent::now(1, RingOutcome::Rang).save(&conn).unwrap();
+/// RingEvent::now(2, RingOutcome::Rang).with_offset(-90).save(&conn).unwrap();
+///
+/// let events = RingEvent::all(&conn).unwrap();
+///
+/// assert_eq!(events.len(), 2);
+/// assert_eq!(events[1].offset_seconds, -90);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RingEvent {
+    pub id: Option<i64>,
+    pub alarm_id: i64,
+    /// Unix timestamp (seconds)
+    pub timestamp: i64,
+    pub outcome: RingOutcome,
+    /// Random offset applied to the occurrence (see [Alarm::jitter]), in seconds
+    #[serde(default)]
+    pub offset_seconds: i64,
+}
+
+/// Average snooze count per occurrence of one alarm.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlarmSnoozes {
+    pub alarm_id: i64,
+    pub average: f64,
+}
+
+/// The alarm with the largest number of missed occurrences.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MissedAlarm {
+    pub alarm_id: i64,
+    pub count: i64,
+}
+
+/// Simple statistics computed over the whole alarm history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RingStats {
+    pub average_snoozes: Vec<AlarmSnoozes>,
+    pub most_missed: Option<MissedAlarm>,
+    /// Number of rings per weekday, Monday first.
+    pub rings_per_weekday: [i64; 7],
+}
+
+impl RingEvent {
+    /// Creates a new (unsaved) event happening right now.
+    pub fn now(alarm_id: i64, outcome: RingOutcome) -> Self {
+        Self {
+            id: None,
+            alarm_id,
+            timestamp: Local::now().timestamp(),
+            outcome,
+            offset_seconds: 0,
+        }
+    }
+
+    /// Records the random offset the occurrence rang with.
+    pub fn with_offset(self, offset_seconds: i64) -> Self {
+        Self {
+            offset_seconds,
+            ..self
+        }
+    }
+
+    // Essential db check
+    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER PRIMARY KEY,
+            alarm_id INTEGER NOT NULL,
+            timestamp INTEGER NOT NULL,
+            outcome TEXT NOT NULL
+            )",
+            TNAME
+        );
+        conn.execute(query)?;
+
+        add_missing_columns(conn, TNAME, LATE_COLUMNS)
+    }
+
+    /// Appends the event to the history. Creates the table 'alarm_history' if not present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an SQL error has been encountered
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "INSERT INTO {} (alarm_id, timestamp, outcome, offset_seconds)
+            VALUES ({}, {}, '{}', {})",
+            TNAME,
+            self.alarm_id,
+            self.timestamp,
+            self.outcome.as_str(),
+            self.offset_seconds,
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    /// Get the whole history, oldest event first
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn all(conn: &sqlite::Connection) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("SELECT * FROM {} ORDER BY timestamp, id", TNAME);
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            res.push(Self::from_row(&statement)?)
+        }
+
+        Ok(res)
+    }
+
+    /// Latest events, newest first, optionally restricted to one alarm and to the events
+    /// happening at or after `since` (Unix timestamp, seconds).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::history::{RingEvent, RingOutcome};
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// for (alarm_id, timestamp) in [(1, 100), (2, 200), (1, 300)] {
+    ///     RingEvent { timestamp, ..RingEvent::now(alarm_id, RingOutcome::Rang) }.save(&conn).unwrap();
+    /// }
+    ///
+    /// let events = RingEvent::query(&conn, Some(1), None, 10).unwrap();
+    /// assert_eq!(events.iter().map(|event| event.timestamp).collect::<Vec<i64>>(), vec![300, 100]);
+    /// assert_eq!(RingEvent::query(&conn, None, Some(150), 1).unwrap()[0].timestamp, 300);
+    /// ```
+    pub fn query(
+        conn: &sqlite::Connection,
+        alarm_id: Option<i64>,
+        since: Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "SELECT * FROM {}
+            WHERE (:alarm_id IS NULL OR alarm_id = :alarm_id)
+            AND (:since IS NULL OR timestamp >= :since)
+            ORDER BY timestamp DESC, id DESC
+            LIMIT :limit",
+            TNAME
+        );
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":alarm_id", alarm_id))?;
+        statement.bind((":since", since))?;
+        statement.bind((":limit", limit as i64))?;
+        while let Ok(State::Row) = statement.next() {
+            res.push(Self::from_row(&statement)?)
+        }
+
+        Ok(res)
+    }
+
+    /// Last time the daemon published the alarm, if ever, to check that it actually fired.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn last_rang(conn: &sqlite::Connection, alarm_id: i64) -> Result<Option<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "SELECT * FROM {} WHERE alarm_id = :alarm_id AND outcome = 'rang'
+            ORDER BY timestamp DESC, id DESC LIMIT 1",
+            TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":alarm_id", alarm_id))?;
+        match statement.next()? {
+            State::Row => Ok(Some(Self::from_row(&statement)?)),
+            State::Done => Ok(None),
+        }
+    }
+
+    // Reads an event from the current row of a `SELECT *` statement
+    fn from_row(statement: &sqlite::Statement<'_>) -> Result<Self, ClockError> {
+        Ok(RingEvent {
+            id: Some(statement.read::<i64, _>("id")?),
+            alarm_id: statement.read::<i64, _>("alarm_id")?,
+            timestamp: statement.read::<i64, _>("timestamp")?,
+            outcome: RingOutcome::try_from(statement.read::<String, _>("outcome")?.as_str())?,
+            offset_seconds: statement.read::<i64, _>("offset_seconds")?,
+        })
+    }
+
+    /// Average number of snoozes per ring, for each alarm having rung at least once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn average_snoozes(conn: &sqlite::Connection) -> Result<Vec<AlarmSnoozes>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "SELECT alarm_id,
+            SUM(outcome = 'snoozed') * 1.0 / SUM(outcome = 'rang') AS average
+            FROM {}
+            GROUP BY alarm_id
+            HAVING SUM(outcome = 'rang') > 0
+            ORDER BY alarm_id",
+            TNAME
+        );
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            res.push(AlarmSnoozes {
+                alarm_id: statement.read::<i64, _>("alarm_id")?,
+                average: statement.read::<f64, _>("average")?,
+            })
+        }
+
+        Ok(res)
+    }
+
+    /// The alarm that has been missed most often, if any alarm has ever been missed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn most_missed(conn: &sqlite::Connection) -> Result<Option<MissedAlarm>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "SELECT alarm_id, COUNT(*) AS count
+            FROM {}
+            WHERE outcome = 'missed'
+            GROUP BY alarm_id
+            ORDER BY count DESC, alarm_id
+            LIMIT 1",
+            TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+
+        if let Ok(State::Row) = statement.next() {
+            Ok(Some(MissedAlarm {
+                alarm_id: statement.read::<i64, _>("alarm_id")?,
+                count: statement.read::<i64, _>("count")?,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Number of rings for each weekday (local time), Monday first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn rings_per_weekday(conn: &sqlite::Connection) -> Result<[i64; 7], ClockError> {
+        Self::check_table(conn)?;
+        // strftime('%w') counts from Sunday = 0
+        let query = format!(
+            "SELECT CAST(strftime('%w', timestamp, 'unixepoch', 'localtime') AS INTEGER) AS weekday,
+            COUNT(*) AS count
+            FROM {}
+            WHERE outcome = 'rang'
+            GROUP BY weekday",
+            TNAME
+        );
+        let mut res = [0; 7];
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            let weekday = statement.read::<i64, _>("weekday")?;
+            res[((weekday + 6) % 7) as usize] = statement.read::<i64, _>("count")?;
+        }
+
+        Ok(res)
+    }
+
+    /// Dumps the whole history as CSV (one line per event, oldest first) with a header line.
+    /// Timestamps are written in local time, alarms by their id and label.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::alarm::Alarm;
+    /// use libclockrobustus::history::{RingEvent, RingOutcome};
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// Alarm { label: "Wake up".to_string(), ..Default::default() }.save(&conn).unwrap();
+    /// RingEvent::now(1, RingOutcome::Rang).save(&conn).unwrap();
+    ///
+    /// let csv = RingEvent::export_csv(&conn).unwrap();
+    /// let lines = csv.lines().collect::<Vec<&str>>();
+    ///
+    /// assert_eq!(lines[0], "timestamp,alarm_id,label,outcome");
+    /// assert!(lines[1].ends_with(",1,Wake up,rang"));
+    /// ```
+    pub fn export_csv(conn: &sqlite::Connection) -> Result<String, ClockError> {
+        Self::check_table(conn)?;
+        Alarm::check_table(conn)?;
+        let query = format!(
+            "SELECT h.timestamp, h.alarm_id, h.outcome, COALESCE(a.label, '') AS label
+            FROM {} h LEFT JOIN alarms a ON a.id = h.alarm_id
+            ORDER BY h.timestamp, h.id",
+            TNAME
+        );
+        let mut csv = String::from("timestamp,alarm_id,label,outcome\n");
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            let timestamp = Local
+                .timestamp_opt(statement.read::<i64, _>("timestamp")?, 0)
+                .single()
+                .ok_or(ClockError("Invalid timestamp in alarm history"))?;
+
+            csv.push_str(&format!(
+                "{},{},{},{}\n",
+                timestamp.format("%Y-%m-%d %H:%M:%S"),
+                statement.read::<i64, _>("alarm_id")?,
+                csv_field(&statement.read::<String, _>("label")?),
+                statement.read::<String, _>("outcome")?,
+            ));
+        }
+
+        Ok(csv)
+    }
+
+    /// Gathers every statistic above in one structure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::history::{RingEvent, RingOutcome};
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// RingEvent::now(1, RingOutcome::Rang).save(&conn).unwrap();
+    /// RingEvent::now(1, RingOutcome::Snoozed).save(&conn).unwrap();
+    ///
+    /// let stats = RingEvent::stats(&conn).unwrap();
+    ///
+    /// assert_eq!(stats.average_snoozes[0].average, 1.0);
+    /// assert!(stats.most_missed.is_none());
+    /// assert_eq!(stats.rings_per_weekday.iter().sum::<i64>(), 1);
+    /// ```
+    pub fn stats(conn: &sqlite::Connection) -> Result<RingStats, ClockError> {
+        Ok(RingStats {
+            average_snoozes: Self::average_snoozes(conn)?,
+            most_missed: Self::most_missed(conn)?,
+            rings_per_weekday: Self::rings_per_weekday(conn)?,
+        })
+    }
+}
+
+/// Quotes a CSV field when needed (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+    use sqlite::Connection;
+
+    use super::*;
+
+    fn event(alarm_id: i64, timestamp: i64, outcome: RingOutcome) -> RingEvent {
+        RingEvent {
+            id: None,
+            alarm_id,
+            timestamp,
+            outcome,
+            offset_seconds: 0,
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let conn = Connection::open(":memory:").unwrap();
+        // 2023-07-03 was a Monday, 2023-07-05 a Wednesday
+        let monday = Local
+            .with_ymd_and_hms(2023, 7, 3, 7, 0, 0)
+            .unwrap()
+            .timestamp();
+        let wednesday = Local
+            .with_ymd_and_hms(2023, 7, 5, 7, 0, 0)
+            .unwrap()
+            .timestamp();
+        let events = vec![
+            event(1, monday, RingOutcome::Rang),
+            event(1, monday + 300, RingOutcome::Snoozed),
+            event(1, monday + 600, RingOutcome::Snoozed),
+            event(1, wednesday, RingOutcome::Rang),
+            event(2, wednesday, RingOutcome::Rang),
+            event(2, wednesday + 60, RingOutcome::Dismissed),
+            event(3, monday, RingOutcome::Missed),
+            event(2, wednesday + 86400 * 7, RingOutcome::Missed),
+            event(2, wednesday + 86400 * 14, RingOutcome::Missed),
+        ];
+
+        for event in events {
+            event.save(&conn).unwrap();
+        }
+
+        let stats = RingEvent::stats(&conn).unwrap();
+
+        assert_eq!(
+            stats.average_snoozes,
+            vec![
+                AlarmSnoozes {
+                    alarm_id: 1,
+                    average: 1.0
+                },
+                AlarmSnoozes {
+                    alarm_id: 2,
+                    average: 0.0
+                },
+            ]
+        );
+        assert_eq!(
+            stats.most_missed,
+            Some(MissedAlarm {
+                alarm_id: 2,
+                count: 2
+            })
+        );
+        assert_eq!(stats.rings_per_weekday, [1, 0, 2, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_last_rang() {
+        let conn = Connection::open(":memory:").unwrap();
+
+        assert_eq!(RingEvent::last_rang(&conn, 1).unwrap(), None);
+
+        for event in [
+            event(1, 100, RingOutcome::Rang),
+            event(1, 200, RingOutcome::Rang),
+            event(1, 300, RingOutcome::Dismissed),
+            event(2, 400, RingOutcome::Rang),
+        ] {
+            event.save(&conn).unwrap();
+        }
+
+        assert_eq!(
+            RingEvent::last_rang(&conn, 1).unwrap().unwrap().timestamp,
+            200
+        );
+        assert_eq!(RingEvent::query(&conn, None, None, 10).unwrap().len(), 4);
+        assert!(RingEvent::query(&conn, Some(3), None, 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("Wake up"), "Wake up");
+        assert_eq!(csv_field("Wake, up"), "\"Wake, up\"");
+        assert_eq!(csv_field("\"Up\""), "\"\"\"Up\"\"\"");
+    }
+
+    #[test]
+    fn test_empty_history() {
+        let conn = Connection::open(":memory:").unwrap();
+        let stats = RingEvent::stats(&conn).unwrap();
+
+        assert!(stats.average_snoozes.is_empty());
+        assert!(stats.most_missed.is_none());
+        assert_eq!(stats.rings_per_weekday, [0; 7]);
+    }
+}
@@ -0,0 +1,613 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+
+use crate::{
+    alarm::{ActiveDays, Alarm},
+    clock::Zone,
+    error::ClockError,
+    schedule::{Frequency, Recurrence},
+};
+
+/// Longest content line (in bytes) before folding, see RFC 5545 section 3.1
+const LINE_LENGTH: usize = 75;
+
+/// iCalendar (RFC 5545) document with one event per enabled alarm, ringing (VALARM) when the
+/// event starts, so alarms can be imported into calendar apps. Events start on the first day
+/// from `from` the alarm rings on and repeat after its recurrence rule or its active days. The
+/// base time of rotating alarms is used, jitter is left out. Alarms which never ring are left
+/// out. `stamp` is the creation time of the document.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{NaiveDate, TimeZone, Utc};
+/// use libclockrobustus::{alarm::{ActiveDays, Alarm}, ical};
+///
+/// let alarm = Alarm {
+///     id: Some(1),
+///     active_days: ActiveDays::WEEKDAYS,
+///     hour: 7,
+///     minute: 30,
+///     label: "Work".to_string(),
+///     ..Default::default()
+/// };
+/// // Saturday
+/// let from = NaiveDate::from_ymd_opt(2023, 7, 1).unwrap();
+/// let ics = ical::export(&[alarm], from, Utc.timestamp_opt(0, 0).unwrap()).unwrap();
+///
+/// assert!(ics.contains("DTSTART:20230703T073000\r\n"));
+/// assert!(ics.contains("RRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR\r\n"));
+/// assert!(ics.contains("BEGIN:VALARM\r\n"));
+/// ```
+///
+/// # Panics
+///
+/// Panics if the time of an alarm is not valid
+pub fn export(
+    alarms: &[Alarm],
+    from: NaiveDate,
+    stamp: DateTime<Utc>,
+) -> Result<String, ClockError> {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//clockrobustus//alarms//EN".to_string(),
+    ];
+
+    for (index, alarm) in alarms.iter().enumerate() {
+        if alarm.enabled {
+            lines.extend(event(alarm, index, from, stamp)?);
+        }
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    Ok(lines.iter().map(|line| fold(line)).collect())
+}
+
+// Content lines of the VEVENT of an alarm, none if it never rings
+fn event(
+    alarm: &Alarm,
+    index: usize,
+    from: NaiveDate,
+    stamp: DateTime<Utc>,
+) -> Result<Vec<String>, ClockError> {
+    let time =
+        NaiveTime::from_hms_opt(alarm.hour as u32, alarm.minute as u32, alarm.seconds as u32)
+            .ok_or(ClockError("Unable to convert alarm time"))?;
+    let from = alarm
+        .valid_from
+        .map_or(from, |valid_from| valid_from.max(from));
+
+    // Repeating alarms are described by a rule, the active days making a weekly one
+    let rule = match (alarm.date, &alarm.recurrence) {
+        (Some(_), _) => None,
+        (None, Some(recurrence)) => Some(recurrence.clone()),
+        (None, None) if alarm.active_days.0 == 0 => return Ok(vec![]),
+        (None, None) => Some(Recurrence {
+            by_day: alarm
+                .active_days
+                .to_weekdays()
+                .into_iter()
+                .map(|weekday| (None, weekday))
+                .collect(),
+            ..Recurrence::new(Frequency::Weekly)
+        }),
+    };
+    let first = match (&rule, alarm.date) {
+        (Some(rule), _) => rule.next_date(from),
+        (None, date) => date.filter(|date| *date >= from),
+    };
+    let still_valid = |first: &NaiveDate| alarm.valid_until.is_none_or(|until| *first <= until);
+    let Some(first) = first.filter(still_valid) else {
+        return Ok(vec![]);
+    };
+
+    let summary = match alarm.label.is_empty() {
+        true => "Alarm".to_string(),
+        false => escape(&alarm.label),
+    };
+    let uid = match alarm.id {
+        Some(id) => format!("alarm-{}@clockrobustus", id),
+        None => format!("unsaved-{}@clockrobustus", index),
+    };
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", uid),
+        format!("DTSTAMP:{}", stamp.format("%Y%m%dT%H%M%SZ")),
+        format!(
+            "DTSTART{}",
+            date_time(&alarm.timezone, first.and_time(time))
+        ),
+        format!("DURATION:PT{}M", alarm.ring_minutes),
+        format!("SUMMARY:{}", summary),
+    ];
+
+    if let Some(rule) = rule {
+        // The start is carried by DTSTART, not by the rule
+        let mut rule = Recurrence {
+            start: None,
+            ..rule
+        }
+        .to_string();
+
+        if let Some(until) = alarm.valid_until {
+            let last = until.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap_or_default());
+            // Floating times end at a floating time, zoned times at an UTC one
+            let until = match alarm.timezone {
+                Zone::Local => last.format("%Y%m%dT%H%M%S").to_string(),
+                zone => zone.instant(last)?.format("%Y%m%dT%H%M%SZ").to_string(),
+            };
+            rule.push_str(&format!(";UNTIL={}", until));
+        }
+        lines.push(format!("RRULE:{}", rule));
+    }
+
+    lines.extend([
+        "BEGIN:VALARM".to_string(),
+        "ACTION:DISPLAY".to_string(),
+        format!("DESCRIPTION:{}", summary),
+        "TRIGGER:PT0S".to_string(),
+        "END:VALARM".to_string(),
+        "END:VEVENT".to_string(),
+    ]);
+
+    Ok(lines)
+}
+
+// DTSTART value (with its parameters) of a wall clock time: floating for the local zone, bound
+// to its IANA identifier otherwise
+fn date_time(zone: &Zone, time: NaiveDateTime) -> String {
+    match zone {
+        Zone::Local => format!(":{}", time.format("%Y%m%dT%H%M%S")),
+        Zone::Named(tz) => format!(";TZID={}:{}", tz.name(), time.format("%Y%m%dT%H%M%S")),
+    }
+}
+
+/// Alarms of the events of an iCalendar document (eg: exported by a calendar app) which have a
+/// reminder (VALARM), ringing when the reminder triggers. Events repeating weekly on given days
+/// get active days, other rules (eg: monthly) become a [Recurrence], events without rule become
+/// one-shot alarms. The end of a rule (`UNTIL`) sets [Alarm::valid_until], its number of
+/// occurrences (`COUNT`) is not kept. Events which cannot be mapped (all-day events, rules out of
+/// the supported subset) are left out.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::{alarm::ActiveDays, ical};
+///
+/// let ics = "BEGIN:VCALENDAR\r
+/// BEGIN:VEVENT\r
+/// SUMMARY:Standup\r
+/// DTSTART;TZID=Europe/Paris:20230703T093000\r
+/// RRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR\r
+/// BEGIN:VALARM\r
+/// TRIGGER:-PT5M\r
+/// END:VALARM\r
+/// END:VEVENT\r
+/// END:VCALENDAR\r
+/// ";
+/// let alarms = ical::parse(ics).unwrap();
+///
+/// assert_eq!(alarms[0].label, "Standup");
+/// assert_eq!((alarms[0].hour, alarms[0].minute), (9, 25));
+/// assert_eq!(alarms[0].active_days, ActiveDays::WEEKDAYS);
+/// assert_eq!(alarms[0].timezone.to_string(), "Europe/Paris");
+/// ```
+///
+/// # Panics
+///
+/// Panics if the document is not an iCalendar document
+pub fn parse(ics: &str) -> Result<Vec<Alarm>, ClockError> {
+    let unfolded = ics
+        .replace("\r\n ", "")
+        .replace("\r\n\t", "")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+    let mut alarms = Vec::new();
+    let mut event: Option<Event> = None;
+    let mut in_calendar = false;
+
+    for line in unfolded.lines().filter(|line| !line.trim().is_empty()) {
+        let (name, params, value) = content_line(line)?;
+
+        match (name.as_str(), value) {
+            ("BEGIN", "VCALENDAR") => in_calendar = true,
+            ("BEGIN", "VEVENT") => event = Some(Event::default()),
+            ("END", "VEVENT") => {
+                if let Some(alarm) = event.take().and_then(|event| event.alarm()) {
+                    alarms.push(alarm);
+                }
+            }
+            _ => {
+                if let Some(event) = event.as_mut() {
+                    event.set(&name, &params, value);
+                }
+            }
+        }
+    }
+
+    if !in_calendar {
+        return Err(ClockError("Not an iCalendar document"));
+    }
+
+    Ok(alarms)
+}
+
+// Parameters (upper case names and values) of a content line
+type Params = Vec<(String, String)>;
+
+// Name (upper case), parameters and value of a content line, see RFC 5545 section 3.1
+fn content_line(line: &str) -> Result<(String, Params, &str), ClockError> {
+    // The value starts at the first colon which is not quoted in a parameter
+    let mut quoted = false;
+    let colon = line
+        .char_indices()
+        .find(|(_, c)| {
+            if *c == '"' {
+                quoted = !quoted;
+            }
+            *c == ':' && !quoted
+        })
+        .map(|(index, _)| index)
+        .ok_or(ClockError("Invalid iCalendar content line"))?;
+    let mut parts = line[..colon].split(';');
+    let name = parts.next().unwrap_or_default().trim().to_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| {
+            (
+                key.trim().to_uppercase(),
+                value.trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+
+    Ok((name, params, line[colon + 1..].trim_end()))
+}
+
+// Properties of a VEVENT relevant to alarms
+#[derive(Default)]
+struct Event {
+    summary: String,
+    start: Option<(String, Option<String>)>,
+    rule: Option<String>,
+    // Offset (seconds) of the first reminder from the start, if any
+    reminder: Option<Option<i64>>,
+    in_alarm: bool,
+}
+
+impl Event {
+    fn set(&mut self, name: &str, params: &[(String, String)], value: &str) {
+        let param = |key: &str| {
+            params
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.clone())
+        };
+
+        match (self.in_alarm, name) {
+            (false, "BEGIN") if value == "VALARM" => self.in_alarm = true,
+            (true, "END") if value == "VALARM" => {
+                self.in_alarm = false;
+                // Reminders without a (relative) trigger ring when the event starts
+                self.reminder.get_or_insert(Some(0));
+            }
+            (false, "SUMMARY") => self.summary = unescape(value),
+            (false, "DTSTART") => self.start = Some((value.to_string(), param("TZID"))),
+            (false, "RRULE") => self.rule = Some(value.to_string()),
+            (true, "TRIGGER") if self.reminder.is_none() => {
+                // Absolute triggers are not kept, the alarm rings when the event starts
+                self.reminder = Some(match param("VALUE").as_deref() {
+                    Some("DATE-TIME") => Some(0),
+                    _ => duration(value),
+                })
+            }
+            _ => (),
+        }
+    }
+
+    // Alarm ringing at the event reminder, [None] if the event has no reminder or cannot be
+    // mapped
+    fn alarm(self) -> Option<Alarm> {
+        let offset = self.reminder??;
+        let (start, tzid) = self.start?;
+        let (zone, start) = match start.strip_suffix('Z') {
+            Some(start) => (Zone::Named(chrono_tz::UTC), start.to_string()),
+            // Non IANA identifiers (eg: Windows zone names) fall back to the local zone
+            None => (
+                tzid.and_then(|tzid| tzid.parse().ok()).unwrap_or_default(),
+                start,
+            ),
+        };
+        let start = NaiveDateTime::parse_from_str(&start, "%Y%m%dT%H%M%S").ok()?;
+        let ring = start + Duration::seconds(offset);
+        let days = (ring.date() - start.date()).num_days();
+
+        let mut alarm = Alarm {
+            hour: ring.hour() as u8,
+            minute: ring.minute() as u8,
+            seconds: ring.second() as u8,
+            label: self.summary,
+            timezone: zone,
+            valid_from: Some(ring.date()),
+            ..Default::default()
+        };
+
+        let Some(rule) = self.rule else {
+            alarm.date = Some(ring.date());
+            return Some(alarm);
+        };
+
+        // Rule parts out of the alarm model: the end is a validity bound, the rest is dropped
+        let mut parts = Vec::new();
+        for part in rule.split(';') {
+            match part.split_once('=') {
+                Some(("UNTIL", until)) => {
+                    alarm.valid_until = NaiveDate::parse_from_str(until.get(..8)?, "%Y%m%d").ok()
+                }
+                Some(("COUNT" | "WKST", _)) => (),
+                _ => parts.push(part),
+            }
+        }
+        let recurrence = parts.join(";").parse::<Recurrence>().ok()?;
+
+        if recurrence.frequency == Frequency::Weekly
+            && recurrence.interval == 1
+            && recurrence.by_month.is_empty()
+            && recurrence.by_month_day.is_empty()
+            && recurrence.by_day.iter().all(|(nth, _)| nth.is_none())
+        {
+            // Plain weekly rules: the days of the rule (or of the start) are the active days
+            let weekdays = match recurrence.by_day.is_empty() {
+                true => vec![start.weekday()],
+                false => recurrence
+                    .by_day
+                    .iter()
+                    .map(|(_, weekday)| *weekday)
+                    .collect(),
+            };
+            let mask = weekdays.iter().fold(0, |mask, weekday| {
+                mask | 1 << weekday.num_days_from_monday()
+            });
+            alarm.active_days = ActiveDays(mask).shifted(days);
+        } else if days == 0 {
+            alarm.recurrence = Some(Recurrence {
+                start: Some(start.date()),
+                ..recurrence
+            });
+        } else {
+            // A reminder on another day than the event would need another rule
+            return None;
+        }
+
+        Some(alarm)
+    }
+}
+
+// Seconds of a DURATION value (eg: -PT15M, -P1DT2H), see RFC 5545 section 3.3.6
+fn duration(value: &str) -> Option<i64> {
+    let (sign, value) = match value.trim().strip_prefix('-') {
+        Some(value) => (-1, value),
+        None => (1, value.trim().trim_start_matches('+')),
+    };
+    let mut seconds = 0;
+    let mut number = String::new();
+
+    for c in value.strip_prefix('P')?.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => (),
+            unit => {
+                let factor = match unit {
+                    'W' => 604_800,
+                    'D' => 86_400,
+                    'H' => 3_600,
+                    'M' => 60,
+                    'S' => 1,
+                    _ => return None,
+                };
+                seconds += number.parse::<i64>().ok()? * factor;
+                number.clear();
+            }
+        }
+    }
+
+    Some(sign * seconds)
+}
+
+// TEXT value escaping (and unescaping), see RFC 5545 section 3.3.11
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut res = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => res.push('\n'),
+                Some(escaped) => res.push(escaped),
+                None => (),
+            },
+            c => res.push(c),
+        }
+    }
+
+    res
+}
+
+// Content line split in lines of at most LINE_LENGTH bytes (continuations starting with a
+// space), with its CRLF terminator
+fn fold(line: &str) -> String {
+    let mut res = String::new();
+    let mut length = 0;
+
+    for c in line.chars() {
+        if length + c.len_utf8() > LINE_LENGTH {
+            res.push_str("\r\n ");
+            length = 1;
+        }
+        res.push(c);
+        length += c.len_utf8();
+    }
+    res.push_str("\r\n");
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_export() {
+        let from = NaiveDate::from_ymd_opt(2023, 7, 1).unwrap();
+        let stamp = Utc.timestamp_opt(1_688_169_600, 0).unwrap();
+        let alarms = [
+            Alarm {
+                id: Some(1),
+                hour: 9,
+                label: "Dentist, then work".to_string(),
+                date: NaiveDate::from_ymd_opt(2023, 7, 12),
+                timezone: "Europe/Paris".parse().unwrap(),
+                ..Default::default()
+            },
+            Alarm {
+                id: Some(2),
+                hour: 6,
+                recurrence: "FREQ=MONTHLY;BYDAY=1MO".parse().ok(),
+                valid_until: NaiveDate::from_ymd_opt(2023, 12, 31),
+                ..Default::default()
+            },
+            // Never rings
+            Alarm {
+                id: Some(3),
+                ..Default::default()
+            },
+            Alarm {
+                id: Some(4),
+                enabled: false,
+                date: NaiveDate::from_ymd_opt(2023, 7, 12),
+                ..Default::default()
+            },
+        ];
+        let ics = export(&alarms, from, stamp).unwrap();
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+        assert!(ics.contains("DTSTAMP:20230701T000000Z\r\n"));
+
+        assert!(ics.contains("UID:alarm-1@clockrobustus\r\n"));
+        assert!(ics.contains("DTSTART;TZID=Europe/Paris:20230712T090000\r\n"));
+        assert!(ics.contains("SUMMARY:Dentist\\, then work\r\n"));
+
+        assert!(ics.contains("DTSTART:20230703T060000\r\n"));
+        assert!(ics.contains("RRULE:FREQ=MONTHLY;BYDAY=1MO;UNTIL=20231231T235959\r\n"));
+        assert!(!ics.contains("alarm-3@"));
+        assert!(!ics.contains("alarm-4@"));
+    }
+
+    #[test]
+    fn test_folding() {
+        let line = "DESCRIPTION:".to_string() + &"é".repeat(50);
+        let folded = fold(&line);
+
+        assert!(folded.split("\r\n").all(|part| part.len() <= LINE_LENGTH));
+        assert_eq!(folded.replace("\r\n ", ""), line + "\r\n");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let from = NaiveDate::from_ymd_opt(2023, 7, 1).unwrap();
+        let alarms = [
+            Alarm {
+                active_days: ActiveDays(0x41),
+                hour: 7,
+                minute: 45,
+                label: "Gym; then work".to_string(),
+                ..Default::default()
+            },
+            Alarm {
+                hour: 9,
+                date: NaiveDate::from_ymd_opt(2023, 7, 12),
+                timezone: "Asia/Tokyo".parse().unwrap(),
+                ..Default::default()
+            },
+            Alarm {
+                hour: 6,
+                recurrence: "FREQ=MONTHLY;BYDAY=1MO".parse().ok(),
+                valid_until: NaiveDate::from_ymd_opt(2023, 12, 31),
+                ..Default::default()
+            },
+        ];
+        let ics = export(&alarms, from, Utc::now()).unwrap();
+        let parsed = parse(&ics).unwrap();
+
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0].active_days, ActiveDays(0x41));
+        assert_eq!((parsed[0].hour, parsed[0].minute), (7, 45));
+        assert_eq!(parsed[0].label, "Gym; then work");
+        assert_eq!(parsed[1].date, alarms[1].date);
+        assert_eq!(parsed[1].timezone, alarms[1].timezone);
+        assert_eq!(
+            parsed[2].recurrence.as_ref().unwrap().to_string(),
+            "FREQ=MONTHLY;BYDAY=1MO;DTSTART=20230703"
+        );
+        assert_eq!(parsed[2].valid_until, alarms[2].valid_until);
+    }
+
+    #[test]
+    fn test_parsing() {
+        let ics = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+SUMMARY:Early\r
+  train\r
+DTSTART:20230704T003000Z\r
+RRULE:FREQ=WEEKLY;WKST=MO;UNTIL=20231001T000000Z\r
+BEGIN:VALARM\r
+TRIGGER;RELATED=START:-PT45M\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:No reminder\r
+DTSTART:20230704T090000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:All day\r
+DTSTART;VALUE=DATE:20230704\r
+BEGIN:VALARM\r
+TRIGGER:-P1D\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Unsupported\r
+DTSTART:20230704T090000\r
+RRULE:FREQ=MONTHLY;BYSETPOS=-1;BYDAY=MO,TU\r
+BEGIN:VALARM\r
+TRIGGER:PT0S\r
+END:VALARM\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+        let alarms = parse(ics).unwrap();
+
+        assert_eq!(alarms.len(), 1);
+        assert_eq!(alarms[0].label, "Early train");
+        // Tuesday 00:30 UTC, 45 minutes before
+        assert_eq!((alarms[0].hour, alarms[0].minute), (23, 45));
+        assert_eq!(alarms[0].active_days, ActiveDays(0x01));
+        assert_eq!(alarms[0].timezone, Zone::Named(chrono_tz::UTC));
+        assert_eq!(alarms[0].valid_from, NaiveDate::from_ymd_opt(2023, 7, 3));
+        assert_eq!(alarms[0].valid_until, NaiveDate::from_ymd_opt(2023, 10, 1));
+
+        assert!(parse("BEGIN:VCARD\r\nEND:VCARD\r\n").is_err());
+        assert_eq!(duration("-P1DT2H3M4S"), Some(-93_784));
+    }
+}
@@ -0,0 +1,228 @@
+use serde::Deserialize;
+
+use crate::{
+    alarm::{ActiveDays, Alarm},
+    error::ClockError,
+    ical,
+};
+
+/// Metadata key recording where an imported alarm comes from
+pub const IMPORTED_FROM_KEY: &str = "importedFrom";
+
+/// Alarm apps whose exports can be imported.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::import::ImportSource;
+///
+/// let json = r#"[{"name": "Work", "hour": 7, "minute": 15, "days": [0, 1, 2, 3, 4]}]"#;
+/// let alarms = ImportSource::try_from("gnome-clocks").unwrap().parse(json).unwrap();
+///
+/// assert_eq!(alarms[0].label, "Work");
+/// assert_eq!(alarms[0].active_days.0, 0x1F);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportSource {
+    /// GNOME Clocks alarms, as JSON converted from `gsettings get org.gnome.clocks alarms`: a
+    /// list of objects with `name`, `hour`, `minute` and `days` (0 for Monday to 6 for Sunday).
+    GnomeClocks,
+    /// Windows Alarms & Clock export: a list of objects with `Name`, `Time` (`HH:MM` or
+    /// `HH:MM:SS`), `Days` (english day names) and `Enabled`.
+    WindowsAlarms,
+    /// iCalendar document (`.ics` file, CalDAV calendar), see [crate::ical::parse].
+    #[serde(rename = "ical")]
+    ICalendar,
+}
+
+impl ImportSource {
+    /// Database/command line representation
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::GnomeClocks => "gnome-clocks",
+            Self::WindowsAlarms => "windows-alarms",
+            Self::ICalendar => "ical",
+        }
+    }
+
+    /// Maps an export onto unsaved [Alarm]s, tagged with their origin (see [IMPORTED_FROM_KEY]).
+    /// Non repeating GNOME Clocks and Windows alarms are imported without active days: they never
+    /// ring until some days are picked. Disabled Windows alarms are left out, they would ring once
+    /// imported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the export is not valid (JSON of the expected shape, iCalendar document), or
+    /// holds invalid times.
+    pub fn parse(&self, content: &str) -> Result<Vec<Alarm>, ClockError> {
+        let mut alarms = match self {
+            Self::GnomeClocks => serde_json::from_str::<Vec<GnomeAlarm>>(content)?
+                .into_iter()
+                .map(Alarm::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+            Self::WindowsAlarms => serde_json::from_str::<Vec<WindowsAlarm>>(content)?
+                .into_iter()
+                .filter(|alarm| alarm.enabled.unwrap_or(true))
+                .map(Alarm::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+            Self::ICalendar => ical::parse(content)?,
+        };
+
+        for alarm in alarms.iter_mut() {
+            alarm.set_metadata(IMPORTED_FROM_KEY, self.as_str())?;
+        }
+
+        Ok(alarms)
+    }
+}
+
+impl TryFrom<&str> for ImportSource {
+    type Error = ClockError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "gnome-clocks" => Ok(Self::GnomeClocks),
+            "windows-alarms" => Ok(Self::WindowsAlarms),
+            "ical" => Ok(Self::ICalendar),
+            _ => Err(ClockError("Unknown import source")),
+        }
+    }
+}
+
+/// Checks and builds an imported alarm
+fn imported(
+    label: String,
+    active_days: ActiveDays,
+    hour: u8,
+    minute: u8,
+    seconds: u8,
+) -> Result<Alarm, ClockError> {
+    if hour > 23 || minute > 59 || seconds > 59 {
+        return Err(ClockError("Imported alarm has an invalid time"));
+    }
+
+    Ok(Alarm {
+        id: None,
+        active_days,
+        hour,
+        minute,
+        seconds,
+        label,
+        ..Default::default()
+    })
+}
+
+#[derive(Deserialize)]
+struct GnomeAlarm {
+    #[serde(default)]
+    name: String,
+    hour: u8,
+    minute: u8,
+    #[serde(default)]
+    days: Vec<u8>,
+}
+
+impl TryFrom<GnomeAlarm> for Alarm {
+    type Error = ClockError;
+
+    fn try_from(value: GnomeAlarm) -> Result<Self, Self::Error> {
+        let mut days = 0u8;
+
+        for day in value.days {
+            if day > 6 {
+                return Err(ClockError("Imported alarm has an invalid day"));
+            }
+            days |= 1 << day;
+        }
+
+        imported(value.name, ActiveDays(days), value.hour, value.minute, 0)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct WindowsAlarm {
+    #[serde(default)]
+    name: String,
+    time: String,
+    #[serde(default)]
+    days: ActiveDays,
+    /// Enabled if not told otherwise
+    enabled: Option<bool>,
+}
+
+impl TryFrom<WindowsAlarm> for Alarm {
+    type Error = ClockError;
+
+    fn try_from(value: WindowsAlarm) -> Result<Self, Self::Error> {
+        let parts = value
+            .time
+            .split(':')
+            .map(|part| part.trim().parse())
+            .collect::<Result<Vec<u8>, _>>()?;
+        let (hour, minute, seconds) = match parts[..] {
+            [hour, minute] => (hour, minute, 0),
+            [hour, minute, seconds] => (hour, minute, seconds),
+            _ => return Err(ClockError("Imported alarm has an invalid time")),
+        };
+
+        imported(value.name, value.days, hour, minute, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gnome_clocks() {
+        let json = r#"[
+            {"name": "Weekend", "id": "abc", "hour": 9, "minute": 30, "days": [5, 6]},
+            {"name": "Once", "hour": 18, "minute": 0}
+        ]"#;
+        let alarms = ImportSource::GnomeClocks.parse(json).unwrap();
+
+        assert_eq!(alarms.len(), 2);
+        assert_eq!(alarms[0].active_days, ActiveDays(0x60));
+        assert_eq!((alarms[0].hour, alarms[0].minute), (9, 30));
+        assert_eq!(alarms[1].active_days, ActiveDays(0));
+        assert_eq!(
+            alarms[1].metadata::<String>(IMPORTED_FROM_KEY).as_deref(),
+            Some("gnome-clocks")
+        );
+        assert!(ImportSource::GnomeClocks
+            .parse(r#"[{"hour": 25, "minute": 0}]"#)
+            .is_err());
+        assert!(ImportSource::GnomeClocks
+            .parse(r#"[{"hour": 7, "minute": 0, "days": [7]}]"#)
+            .is_err());
+    }
+
+    #[test]
+    fn test_windows_alarms() {
+        let json = r#"[
+            {"Name": "Gym", "Time": "06:45", "Days": ["Monday", "Thursday"], "Enabled": true},
+            {"Name": "Pills", "Time": "21:00:30"}
+        ]"#;
+        let alarms = ImportSource::WindowsAlarms.parse(json).unwrap();
+
+        assert_eq!(alarms[0].label, "Gym");
+        assert_eq!(alarms[0].active_days, ActiveDays(0x09));
+        assert_eq!((alarms[0].hour, alarms[0].minute), (6, 45));
+        assert_eq!(
+            (alarms[1].hour, alarms[1].minute, alarms[1].seconds),
+            (21, 0, 30)
+        );
+        assert!(ImportSource::WindowsAlarms
+            .parse(r#"[{"Name": "Bad", "Time": "noon"}]"#)
+            .is_err());
+        assert!(ImportSource::WindowsAlarms
+            .parse(r#"[{"Name": "Partial", "Time": "06"}]"#)
+            .is_err());
+        assert!(ImportSource::WindowsAlarms
+            .parse(r#"[{"Name": "Off", "Time": "06:00", "Enabled": false}]"#)
+            .unwrap()
+            .is_empty());
+    }
+}
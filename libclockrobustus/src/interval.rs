@@ -0,0 +1,314 @@
+use serde::{Deserialize, Serialize};
+use sqlite::State;
+
+use crate::{
+    codec,
+    error::{ClockError, DecodeError},
+};
+
+const TNAME: &str = "interval_timers";
+
+/// Reminder repeated every few minutes (eg: stretching every 20 minutes), whatever the day and
+/// time, unlike alarms. Reminders are counted from `since`, the daemon publishing an
+/// [IntervalMessage] each time one is due (do-not-disturb windows holding them back).
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::interval::IntervalTimer;
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+/// let stretch = IntervalTimer {
+///     label: "Stretch".to_string(),
+///     every_minutes: 20,
+///     since: 600,
+///     ..Default::default()
+/// };
+///
+/// stretch.save(&conn).unwrap();
+/// let timers = IntervalTimer::all(&conn).unwrap();
+///
+/// assert_eq!(timers[0].label, "Stretch");
+/// // Reminders at 600, 1800, 3000...
+/// assert_eq!(timers[0].due_between(1_000, 2_000), Some(1_800));
+/// assert_eq!(timers[0].due_between(1_800, 2_000), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntervalTimer {
+    pub id: Option<i64>,
+    /// Free text displayed to the user
+    #[serde(default)]
+    pub label: String,
+    pub every_minutes: u32,
+    /// Unix timestamp (seconds) the reminders are counted from
+    #[serde(default)]
+    pub since: i64,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+// Serde default
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl Default for IntervalTimer {
+    fn default() -> Self {
+        Self {
+            id: None,
+            label: String::new(),
+            every_minutes: 0,
+            since: 0,
+            enabled: true,
+        }
+    }
+}
+
+impl IntervalTimer {
+    // Essential db check
+    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER PRIMARY KEY,
+            label TEXT NOT NULL DEFAULT '',
+            every_minutes INTEGER NOT NULL,
+            since INTEGER NOT NULL DEFAULT 0,
+            enabled INTEGER NOT NULL DEFAULT 1
+            )",
+            TNAME
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    /// Saves (creates or updates) the interval timer. Creates the table 'interval_timers' if not
+    /// present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interval is zero or if an SQL error has been encountered
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        if self.every_minutes == 0 {
+            return Err(ClockError("Interval must be positive"));
+        }
+
+        Self::check_table(conn)?;
+        let query = match self.id {
+            Some(_) => format!(
+                "UPDATE {} SET label = :label, every_minutes = :every_minutes, since = :since,
+                enabled = :enabled WHERE id = :id",
+                TNAME
+            ),
+            None => format!(
+                "INSERT INTO {} (label, every_minutes, since, enabled)
+                VALUES (:label, :every_minutes, :since, :enabled)",
+                TNAME
+            ),
+        };
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":label", self.label.as_str()))?;
+        statement.bind((":every_minutes", self.every_minutes as i64))?;
+        statement.bind((":since", self.since))?;
+        statement.bind((":enabled", self.enabled as i64))?;
+        if let Some(id) = self.id {
+            statement.bind((":id", id))?;
+        }
+        statement.next()?;
+
+        Ok(())
+    }
+
+    /// Get every interval timer
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn all(conn: &sqlite::Connection) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("SELECT * FROM {} ORDER BY id", TNAME);
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            res.push(IntervalTimer {
+                id: Some(statement.read::<i64, _>("id")?),
+                label: statement.read::<String, _>("label")?,
+                every_minutes: statement.read::<i64, _>("every_minutes")? as u32,
+                since: statement.read::<i64, _>("since")?,
+                enabled: statement.read::<i64, _>("enabled")? != 0,
+            })
+        }
+
+        Ok(res)
+    }
+
+    /// Removes a saved interval timer
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interval timer has no id (eg: not saved).
+    pub fn remove(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let id = self
+            .id
+            .ok_or(ClockError("Impossible to delete an unsaved interval timer"))?;
+        let query = format!("DELETE FROM {} WHERE id = {}", TNAME, id);
+
+        conn.execute(query)?;
+        Ok(())
+    }
+
+    /// Latest reminder due after `after` and up to `until` (Unix timestamps, seconds), [None] if
+    /// there is none or if the timer is disabled. Reminders missed in between (eg: suspended
+    /// machine) are not repeated.
+    pub fn due_between(&self, after: i64, until: i64) -> Option<i64> {
+        let period = self.every_minutes as i64 * 60;
+
+        if !self.enabled || period == 0 || until < self.since {
+            return None;
+        }
+
+        let latest = until - (until - self.since) % period;
+        (latest > after).then_some(latest)
+    }
+
+    /// Message announcing the reminder due at `due_at`.
+    pub fn message(&self, due_at: i64) -> IntervalMessage {
+        IntervalMessage {
+            id: self.id.unwrap_or_default(),
+            due_at,
+        }
+    }
+}
+
+/// Reminder of an [IntervalTimer], as published by the daemon. Labels are not carried, clients
+/// match interval timers by id.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntervalMessage {
+    pub id: i64,
+    /// Unix timestamp (seconds) the reminder was due at
+    pub due_at: i64,
+}
+
+impl IntervalMessage {
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits)
+    pub const MAX_ENCODED_LEN: usize = 20;
+
+    /// Writes the binary representation at the start of a caller-provided buffer, returning the
+    /// number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is shorter than [IntervalMessage::MAX_ENCODED_LEN].
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        codec::encode(self, buffer)
+    }
+
+    /// Strict counterpart of the [TryFrom] conversion from bytes: truncated messages and
+    /// trailing bytes are rejected (see [codec::decode_strict]).
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        codec::decode_strict(bytes)
+    }
+}
+
+impl TryFrom<Vec<u8>> for IntervalMessage {
+    type Error = ClockError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        codec::decode(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn test_saving() {
+        let conn = Connection::open(":memory:").unwrap();
+        let water = IntervalTimer {
+            label: "Drink water".to_string(),
+            every_minutes: 60,
+            ..Default::default()
+        };
+
+        assert!(IntervalTimer::default().save(&conn).is_err());
+        water.save(&conn).unwrap();
+
+        let mut saved = IntervalTimer::all(&conn).unwrap().remove(0);
+        assert_eq!(
+            saved,
+            IntervalTimer {
+                id: saved.id,
+                ..water
+            }
+        );
+
+        saved.enabled = false;
+        saved.save(&conn).unwrap();
+        assert_eq!(IntervalTimer::all(&conn).unwrap(), vec![saved.clone()]);
+        assert_eq!(saved.due_between(0, 7_200), None);
+
+        saved.remove(&conn).unwrap();
+        assert!(IntervalTimer::all(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_due_between() {
+        let timer = IntervalTimer {
+            id: Some(2),
+            every_minutes: 1,
+            since: 100,
+            ..Default::default()
+        };
+
+        assert_eq!(timer.due_between(0, 99), None);
+        assert_eq!(timer.due_between(0, 100), Some(100));
+        assert_eq!(timer.due_between(100, 159), None);
+        assert_eq!(timer.due_between(159, 160), Some(160));
+        // Only the latest one after a gap
+        assert_eq!(timer.due_between(160, 999), Some(940));
+    }
+
+    #[test]
+    fn test_binary_conversion() {
+        let message = IntervalTimer {
+            id: Some(7),
+            every_minutes: 20,
+            ..Default::default()
+        }
+        .message(1_700_000_400);
+        let longest = IntervalMessage {
+            id: i64::MIN,
+            due_at: i64::MIN,
+        };
+        let mut buffer = [0u8; IntervalMessage::MAX_ENCODED_LEN + 1];
+
+        assert_eq!(
+            longest.encode_into(&mut buffer).unwrap(),
+            IntervalMessage::MAX_ENCODED_LEN
+        );
+
+        let len = message.encode_into(&mut buffer).unwrap();
+
+        assert_eq!(IntervalMessage::try_from(buffer.to_vec()).unwrap(), message);
+        assert!(IntervalMessage::try_from(buffer[..len - 1].to_vec()).is_err());
+        assert_eq!(
+            IntervalMessage::try_from_strict(&buffer[..len]).unwrap(),
+            message
+        );
+        assert_eq!(
+            IntervalMessage::try_from_strict(&buffer[..len + 1])
+                .unwrap_err()
+                .reason,
+            "Trailing bytes"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+use crate::{
+    codec,
+    error::{ClockError, DecodeError},
+};
+
+/// Smallest difference (in milliseconds) between the wall clock and the monotonic clock reported
+/// as a jump, scheduling jitter staying well below.
+pub const JUMP_THRESHOLD_MS: i64 = 1000;
+
+/// Spots changes of the system clock (NTP step, manual change) by comparing, between two
+/// checks, the time elapsed on the wall clock with the time elapsed on the monotonic clock.
+///
+/// Where the monotonic clock stops while the machine is suspended (eg: Linux), a resume looks
+/// like a forward jump as well, which is what clients want to know anyway.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, Instant};
+/// use libclockrobustus::jump::JumpDetector;
+///
+/// let mut detector = JumpDetector::default();
+/// let start = Instant::now();
+///
+/// assert_eq!(detector.check(start, 1_000_000), None);
+/// assert_eq!(detector.check(start + Duration::from_secs(1), 1_001_000), None);
+/// // Set back by an hour
+/// let jump = detector.check(start + Duration::from_secs(2), 1_002_000 - 3_600_000).unwrap();
+///
+/// assert_eq!(jump.jump_ms, -3_600_000);
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct JumpDetector {
+    last: Option<(Instant, i64)>,
+}
+
+impl JumpDetector {
+    /// Compares `wall_ms` (Unix milliseconds) read at `monotonic` with the previous check,
+    /// returning the jump if the wall clock moved by [JUMP_THRESHOLD_MS] or more on its own.
+    pub fn check(&mut self, monotonic: Instant, wall_ms: i64) -> Option<ClockJumpMessage> {
+        let last = self.last.replace((monotonic, wall_ms));
+        let (last_monotonic, last_wall_ms) = last?;
+        let elapsed_ms = monotonic
+            .saturating_duration_since(last_monotonic)
+            .as_millis() as i64;
+        let jump_ms = (wall_ms - last_wall_ms) - elapsed_ms;
+
+        (jump_ms.abs() >= JUMP_THRESHOLD_MS).then_some(ClockJumpMessage { jump_ms, wall_ms })
+    }
+}
+
+/// Change of the system clock, as published by the daemon as soon as it notices it, so that
+/// clients can resync what they display straight away.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockJumpMessage {
+    /// How far the clock jumped, milliseconds (negative when set back)
+    pub jump_ms: i64,
+    /// Wall clock after the jump, Unix milliseconds
+    pub wall_ms: i64,
+}
+
+impl ClockJumpMessage {
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits)
+    pub const MAX_ENCODED_LEN: usize = 20;
+
+    /// Writes the binary representation at the start of a caller-provided buffer, returning the
+    /// number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is shorter than [ClockJumpMessage::MAX_ENCODED_LEN].
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        codec::encode(self, buffer)
+    }
+
+    /// Strict counterpart of the [TryFrom] conversion from bytes: truncated messages and
+    /// trailing bytes are rejected (see [codec::decode_strict]).
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        codec::decode_strict(bytes)
+    }
+}
+
+impl TryFrom<Vec<u8>> for ClockJumpMessage {
+    type Error = ClockError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        codec::decode(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_detector() {
+        let mut detector = JumpDetector::default();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        assert_eq!(detector.check(at(0), 0), None);
+        // Jitter
+        assert_eq!(detector.check(at(1000), 1300), None);
+        assert_eq!(detector.check(at(2000), 2100), None);
+
+        // Stepped forward by NTP
+        assert_eq!(
+            detector.check(at(3000), 4600),
+            Some(ClockJumpMessage {
+                jump_ms: 1500,
+                wall_ms: 4600
+            })
+        );
+        // Compared to the new time from now on
+        assert_eq!(detector.check(at(4000), 5600), None);
+    }
+
+    #[test]
+    fn test_binary_conversion() {
+        let message = ClockJumpMessage {
+            jump_ms: i64::MIN,
+            wall_ms: i64::MIN,
+        };
+        let mut buffer = [0u8; ClockJumpMessage::MAX_ENCODED_LEN + 1];
+
+        let len = message.encode_into(&mut buffer).unwrap();
+
+        assert_eq!(len, ClockJumpMessage::MAX_ENCODED_LEN);
+        assert_eq!(
+            ClockJumpMessage::try_from(buffer.to_vec()).unwrap(),
+            message
+        );
+        assert!(ClockJumpMessage::try_from(buffer[..len - 1].to_vec()).is_err());
+        assert_eq!(
+            ClockJumpMessage::try_from_strict(&buffer[..len]).unwrap(),
+            message
+        );
+        assert_eq!(
+            ClockJumpMessage::try_from_strict(&buffer)
+                .unwrap_err()
+                .reason,
+            "Trailing bytes"
+        );
+    }
+}
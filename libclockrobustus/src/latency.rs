@@ -0,0 +1,194 @@
+use std::{
+    fmt::Display,
+    sync::OnceLock,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::error::ClockError;
+
+/// Size of the timestamp frame closing published messages
+pub const TIMESTAMP_LEN: usize = 8;
+/// Upper bounds (in milliseconds, inclusive) of the histogram buckets. A last bucket holds
+/// anything slower.
+const BUCKET_BOUNDS: [u64; 10] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1000];
+
+/// Wall clock reading taken by the first [stamp_ms] call, with the matching monotonic instant
+static STAMP_ANCHOR: OnceLock<(Instant, u64)> = OnceLock::new();
+
+/// Milliseconds since the Unix epoch, from the wall clock
+pub fn now_ms() -> Result<u64, ClockError> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64)
+}
+
+/// Milliseconds since the Unix epoch for send timestamps. The wall clock (the only one shared
+/// between processes) is read once, then advanced by the monotonic clock, so that adjustments
+/// during a run do not skew latencies. These are only meaningful when the daemon and its clients
+/// run on the same host or on synchronized ones.
+pub fn stamp_ms() -> Result<u64, ClockError> {
+    let wall_ms = now_ms()?;
+    let (anchor, anchor_ms) = STAMP_ANCHOR.get_or_init(|| (Instant::now(), wall_ms));
+
+    Ok(anchor_ms + anchor.elapsed().as_millis() as u64)
+}
+
+/// Binary representation of a send timestamp (big endian)
+pub fn encode_timestamp(ms: u64) -> [u8; TIMESTAMP_LEN] {
+    ms.to_be_bytes()
+}
+
+/// Decodes a send timestamp frame.
+///
+/// # Panics
+///
+/// Panics if the frame does not have the expected size.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::latency::{decode_timestamp, encode_timestamp};
+///
+/// assert_eq!(decode_timestamp(&encode_timestamp(1234)).unwrap(), 1234);
+/// assert!(decode_timestamp(&[0x01]).is_err());
+/// ```
+pub fn decode_timestamp(frame: &[u8]) -> Result<u64, ClockError> {
+    Ok(u64::from_be_bytes(frame.try_into()?))
+}
+
+/// Distribution of end-to-end latencies (publication to reception), with coarse logarithmic
+/// buckets.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::latency::LatencyHistogram;
+///
+/// let mut histogram = LatencyHistogram::default();
+///
+/// for latency in [1, 1, 3, 40] {
+///     histogram.record(latency);
+/// }
+///
+/// assert_eq!(histogram.count(), 4);
+/// assert_eq!(histogram.percentile(50.0), Some(1));
+/// assert_eq!(histogram.percentile(99.0), Some(50));
+/// assert_eq!(histogram.max(), 40);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    buckets: [u64; BUCKET_BOUNDS.len() + 1],
+    max: u64,
+}
+
+impl LatencyHistogram {
+    /// Accounts for one message received `ms` milliseconds after being sent.
+    pub fn record(&mut self, ms: u64) {
+        let bucket = BUCKET_BOUNDS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(BUCKET_BOUNDS.len());
+
+        self.buckets[bucket] += 1;
+        self.max = self.max.max(ms);
+    }
+
+    /// Accounts for a message sent at `sent_at` (see [stamp_ms]) and received now. Both processes
+    /// anchor their stamps on the wall clock at startup, an adjustment between the two startups
+    /// may make it look received before being sent, it then counts as instant.
+    pub fn record_since(&mut self, sent_at: u64) -> Result<(), ClockError> {
+        self.record(stamp_ms()?.saturating_sub(sent_at));
+
+        Ok(())
+    }
+
+    /// Number of recorded messages
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Slowest recorded latency
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// Upper bound of the bucket holding the `p`th percentile ([LatencyHistogram::max] for the
+    /// last, unbounded, bucket), [None] if nothing was recorded.
+    pub fn percentile(&self, p: f64) -> Option<u64> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+
+        let rank = ((p / 100.0) * count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket;
+            if seen >= rank {
+                return Some(BUCKET_BOUNDS.get(index).copied().unwrap_or(self.max));
+            }
+        }
+
+        Some(self.max)
+    }
+}
+
+impl Display for LatencyHistogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = self.count();
+
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            let label = match BUCKET_BOUNDS.get(index) {
+                Some(bound) => format!("<= {} ms", bound),
+                None => format!(" > {} ms", BUCKET_BOUNDS[BUCKET_BOUNDS.len() - 1]),
+            };
+            let share = if count > 0 {
+                *bucket as f64 * 100.0 / count as f64
+            } else {
+                0.0
+            };
+
+            writeln!(
+                f,
+                "{:>11}  {:>8}  {:>5.1}%  {}",
+                label,
+                bucket,
+                share,
+                "#".repeat((share / 2.0).round() as usize)
+            )?;
+        }
+
+        write!(
+            f,
+            "{} messages, p50 {} ms, p99 {} ms, max {} ms",
+            count,
+            self.percentile(50.0).unwrap_or_default(),
+            self.percentile(99.0).unwrap_or_default(),
+            self.max
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let mut histogram = LatencyHistogram::default();
+
+        assert_eq!(histogram.percentile(50.0), None);
+
+        for latency in [0, 2, 7, 7, 3000] {
+            histogram.record(latency);
+        }
+
+        assert_eq!(histogram.count(), 5);
+        assert_eq!(histogram.percentile(0.0), Some(1));
+        assert_eq!(histogram.percentile(60.0), Some(10));
+        assert_eq!(histogram.percentile(100.0), Some(3000));
+        assert!(histogram.to_string().ends_with("max 3000 ms"));
+
+        histogram.record_since(stamp_ms().unwrap() + 1000).unwrap();
+        assert_eq!(histogram.percentile(0.0), Some(1));
+    }
+}
@@ -0,0 +1,506 @@
+use crate::{
+    alarm::{ActiveDays, Alarm},
+    calendar::{CalendarDate, CalendarDates},
+    clock::{ClockMessage, Theme, ThemeHours},
+    error::{ClockError, DecodeError},
+};
+
+// Bodies laid out by hand, as published up to protocol version 1 by the message types that
+// predate [crate::codec] (alarms and clock messages). They are only decoded, for older daemons.
+
+/// Size of an alarm body: days, hour, minute and seconds, then (only if the alarm has an id, is
+/// snoozed or missed) a flags byte followed by the id (0x01), the snooze end (0x02) and the
+/// missed time (0x04) as big-endian 64 bits integers.
+const ALARM_LEN: usize = 4;
+/// Size of a clock message body, optionally followed by calendar dates (a flags byte, then the
+/// Hijri (0x01), Hebrew (0x02) and Chinese (0x04) dates)
+const CLOCK_LEN: usize = 29;
+/// Size of a clock message body before the theme hint was added
+const CLOCK_THEMELESS_LEN: usize = 15;
+/// Size of a clock message body before the date and milliseconds were added
+const CLOCK_UNDATED_LEN: usize = 16;
+/// Size of a clock message body before the ISO week, day of year and DST flag were added
+const CLOCK_DATED_LEN: usize = 25;
+/// Size of a calendar date: year, month, day and leap month flag
+const CALENDAR_DATE_LEN: usize = 7;
+
+/// Decodes a legacy alarm body.
+///
+/// # Panics
+///
+/// Panics if the body is too short, or shorter than what its flags announce.
+pub(crate) fn alarm(bytes: &[u8]) -> Result<Alarm, ClockError> {
+    if bytes.is_empty() {
+        return Err(ClockError("Cannot convert alarm from empty bytes vector"));
+    }
+    if bytes.len() < ALARM_LEN {
+        return Err(ClockError(
+            "binary data is too short to create an alarm message",
+        ));
+    }
+
+    let flags = bytes.get(ALARM_LEN).copied().unwrap_or(0);
+    let mut extensions = bytes.get(ALARM_LEN + 1..).unwrap_or(&[]).chunks(8);
+    let mut extension = |flag: u8| -> Result<Option<i64>, ClockError> {
+        if flags & flag == 0 {
+            return Ok(None);
+        }
+        let bytes = extensions
+            .next()
+            .ok_or(ClockError("Alarm extension too short"))?;
+
+        Ok(Some(i64::from_be_bytes(bytes.try_into()?)))
+    };
+
+    Ok(Alarm {
+        id: extension(0x01)?,
+        active_days: ActiveDays(bytes[0]),
+        hour: bytes[1],
+        minute: bytes[2],
+        seconds: bytes[3],
+        snoozed_until: extension(0x02)?,
+        missed_at: extension(0x04)?,
+        ..Default::default()
+    })
+}
+
+/// Strict counterpart of [alarm]: unknown day bits, out of range times, unknown extensions and
+/// trailing bytes are rejected, the error pointing at the offending byte.
+pub(crate) fn alarm_strict(bytes: &[u8]) -> Result<Alarm, DecodeError> {
+    let mut alarm = Alarm {
+        active_days: ActiveDays(DecodeError::byte(bytes, 0, "active_days", 0..=0x7F)?),
+        hour: DecodeError::byte(bytes, 1, "hour", 0..=23)?,
+        minute: DecodeError::byte(bytes, 2, "minute", 0..=59)?,
+        seconds: DecodeError::byte(bytes, 3, "seconds", 0..=59)?,
+        ..Default::default()
+    };
+    let mut offset = ALARM_LEN;
+
+    if bytes.len() > ALARM_LEN {
+        let flags = DecodeError::byte(bytes, offset, "extension_flags", 0x01..=0x07)?;
+        offset += 1;
+
+        if flags & 0x01 != 0 {
+            alarm.id = Some(i64::from_be_bytes(DecodeError::array(bytes, offset, "id")?));
+            offset += 8;
+        }
+        if flags & 0x02 != 0 {
+            alarm.snoozed_until = Some(i64::from_be_bytes(DecodeError::array(
+                bytes,
+                offset,
+                "snoozed_until",
+            )?));
+            offset += 8;
+        }
+        if flags & 0x04 != 0 {
+            alarm.missed_at = Some(i64::from_be_bytes(DecodeError::array(
+                bytes,
+                offset,
+                "missed_at",
+            )?));
+            offset += 8;
+        }
+    }
+
+    DecodeError::check_end(bytes, offset)?;
+    Ok(alarm)
+}
+
+/// Decodes a legacy clock message body. Older daemons send no theme hint (derived from the
+/// default [ThemeHours]), no date (the local one is used), nor any week (computed from the
+/// date, DST unknown).
+///
+/// # Panics
+///
+/// Panics if the body is too short, or cut within the date or the week.
+pub(crate) fn clock(bytes: &[u8]) -> Result<ClockMessage, ClockError> {
+    if bytes.len() < CLOCK_THEMELESS_LEN
+        || (bytes.len() > CLOCK_UNDATED_LEN && bytes.len() < CLOCK_DATED_LEN)
+        || (bytes.len() > CLOCK_DATED_LEN && bytes.len() < CLOCK_LEN)
+    {
+        return Err(ClockError("Clock message too short"));
+    }
+
+    let (year, month, day, weekday, millis) = match bytes.get(16..CLOCK_DATED_LEN) {
+        Some(date) => (
+            i32::from_be_bytes(date[..4].try_into()?),
+            date[4],
+            date[5],
+            date[6],
+            u16::from_be_bytes(date[7..9].try_into()?),
+        ),
+        None => {
+            let (year, month, day, weekday) = ClockMessage::local_date();
+            (year, month, day, weekday, 0)
+        }
+    };
+    let (iso_week, day_of_year, dst) = match bytes.get(CLOCK_DATED_LEN..CLOCK_LEN) {
+        Some(week) => (
+            week[0],
+            u16::from_be_bytes(week[1..3].try_into()?),
+            week[3] != 0,
+        ),
+        None => {
+            let (iso_week, day_of_year) = ClockMessage::week_of(year, month, day);
+            (iso_week, day_of_year, false)
+        }
+    };
+    let (hours12, pm) = ClockMessage::h24_to_h12(bytes[0]);
+
+    Ok(ClockMessage {
+        hours: bytes[0],
+        hours12,
+        pm,
+        minutes: bytes[1],
+        seconds: bytes[2],
+        hours_angle: f32::from_be_bytes(bytes[3..7].try_into()?),
+        minutes_angle: f32::from_be_bytes(bytes[7..11].try_into()?),
+        seconds_angle: f32::from_be_bytes(bytes[11..15].try_into()?),
+        theme: match bytes.get(15) {
+            Some(byte) => theme(*byte)?,
+            None => ThemeHours::default().theme_at(bytes[0]),
+        },
+        year,
+        month,
+        day,
+        weekday,
+        millis,
+        iso_week,
+        day_of_year,
+        dst,
+        calendars: match bytes.get(CLOCK_LEN..) {
+            Some(extension) if !extension.is_empty() => Some(calendar_dates(extension)?),
+            _ => None,
+        },
+    })
+}
+
+/// Strict counterpart of [clock]: out of range times, angles off the dial, unknown themes, out
+/// of range dates and malformed calendar extensions are rejected, the error pointing at the
+/// offending byte. Bodies without theme hint, date or week are still accepted, but not bodies
+/// cut within the date and milliseconds or within the week.
+pub(crate) fn clock_strict(bytes: &[u8]) -> Result<ClockMessage, DecodeError> {
+    let angle = |offset: usize, field: &'static str| -> Result<f32, DecodeError> {
+        let angle = f32::from_be_bytes(DecodeError::array(bytes, offset, field)?);
+
+        if ClockMessage::is_on_dial(angle) {
+            Ok(angle)
+        } else {
+            Err(DecodeError::new(offset, field, "Value out of range"))
+        }
+    };
+    let hours = DecodeError::byte(bytes, 0, "hours", 0..=23)?;
+    let (hours12, pm) = ClockMessage::h24_to_h12(hours);
+    let (year, month, day, weekday, millis) = if bytes.len() > CLOCK_UNDATED_LEN {
+        (
+            i32::from_be_bytes(DecodeError::array(bytes, 16, "year")?),
+            DecodeError::byte(bytes, 20, "month", 1..=12)?,
+            DecodeError::byte(bytes, 21, "day", 1..=31)?,
+            DecodeError::byte(bytes, 22, "weekday", 0..=6)?,
+            match u16::from_be_bytes(DecodeError::array(bytes, 23, "millis")?) {
+                millis @ 0..=999 => millis,
+                _ => return Err(DecodeError::new(23, "millis", "Value out of range")),
+            },
+        )
+    } else {
+        let (year, month, day, weekday) = ClockMessage::local_date();
+        (year, month, day, weekday, 0)
+    };
+    let (iso_week, day_of_year, dst) = if bytes.len() > CLOCK_DATED_LEN {
+        (
+            DecodeError::byte(bytes, 25, "iso_week", 1..=53)?,
+            match u16::from_be_bytes(DecodeError::array(bytes, 26, "day_of_year")?) {
+                day_of_year @ 1..=366 => day_of_year,
+                _ => return Err(DecodeError::new(26, "day_of_year", "Value out of range")),
+            },
+            DecodeError::byte(bytes, 28, "dst", 0..=1)? == 1,
+        )
+    } else {
+        let (iso_week, day_of_year) = ClockMessage::week_of(year, month, day);
+        (iso_week, day_of_year, false)
+    };
+
+    Ok(ClockMessage {
+        hours,
+        hours12,
+        pm,
+        minutes: DecodeError::byte(bytes, 1, "minutes", 0..=59)?,
+        seconds: DecodeError::byte(bytes, 2, "seconds", 0..=59)?,
+        hours_angle: angle(3, "hours_angle")?,
+        minutes_angle: angle(7, "minutes_angle")?,
+        seconds_angle: angle(11, "seconds_angle")?,
+        theme: match bytes.get(15) {
+            Some(byte) => {
+                theme(*byte).map_err(|_| DecodeError::new(15, "theme", "Value out of range"))?
+            }
+            None => ThemeHours::default().theme_at(hours),
+        },
+        year,
+        month,
+        day,
+        weekday,
+        millis,
+        iso_week,
+        day_of_year,
+        dst,
+        calendars: match bytes.get(CLOCK_LEN..) {
+            Some(extension) if !extension.is_empty() => {
+                Some(calendar_dates_strict(extension).map_err(|error| error.shifted(CLOCK_LEN))?)
+            }
+            _ => None,
+        },
+    })
+}
+
+fn theme(byte: u8) -> Result<Theme, ClockError> {
+    match byte {
+        0 => Ok(Theme::Day),
+        1 => Ok(Theme::Dusk),
+        2 => Ok(Theme::Night),
+        _ => Err(ClockError("Unknown theme")),
+    }
+}
+
+/// Decodes the calendar dates extending a clock message body.
+///
+/// # Panics
+///
+/// Panics if the data is empty or shorter than what its flags announce.
+fn calendar_dates(bytes: &[u8]) -> Result<CalendarDates, ClockError> {
+    let (flags, mut rest) = bytes
+        .split_first()
+        .ok_or(ClockError("Missing calendar dates"))?;
+    let mut dates = [None; 3];
+
+    for (index, date) in dates.iter_mut().enumerate() {
+        if flags & (1 << index) == 0 {
+            continue;
+        }
+        if rest.len() < CALENDAR_DATE_LEN {
+            return Err(ClockError("Calendar dates too short"));
+        }
+
+        *date = Some(CalendarDate {
+            year: i32::from_be_bytes(rest[..4].try_into()?),
+            month: rest[4],
+            day: rest[5],
+            leap_month: rest[6] != 0,
+        });
+        rest = &rest[CALENDAR_DATE_LEN..];
+    }
+
+    let [hijri, hebrew, chinese] = dates;
+    Ok(CalendarDates {
+        hijri,
+        hebrew,
+        chinese,
+    })
+}
+
+/// Strict counterpart of [calendar_dates]: unknown flags, impossible months and days and
+/// trailing bytes are rejected, the error pointing at the offending byte.
+fn calendar_dates_strict(bytes: &[u8]) -> Result<CalendarDates, DecodeError> {
+    let flags = DecodeError::byte(bytes, 0, "calendar_flags", 0..=0x07)?;
+    let mut dates = [None; 3];
+    let mut offset = 1;
+
+    for (index, date) in dates.iter_mut().enumerate() {
+        if flags & (1 << index) == 0 {
+            continue;
+        }
+
+        *date = Some(CalendarDate {
+            year: i32::from_be_bytes(DecodeError::array(bytes, offset, "calendar_year")?),
+            month: DecodeError::byte(bytes, offset + 4, "calendar_month", 1..=13)?,
+            day: DecodeError::byte(bytes, offset + 5, "calendar_day", 1..=31)?,
+            leap_month: DecodeError::byte(bytes, offset + 6, "calendar_leap_month", 0..=1)? != 0,
+        });
+        offset += CALENDAR_DATE_LEN;
+    }
+
+    DecodeError::check_end(bytes, offset)?;
+    let [hijri, hebrew, chinese] = dates;
+    Ok(CalendarDates {
+        hijri,
+        hebrew,
+        chinese,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, Local};
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    /// Version 1 body of a clock message at 13:12:09.500 on Thursday 29th of February 2024,
+    /// week 9 (DST off), dusk theme
+    fn clock_body() -> Vec<u8> {
+        let mut bytes = vec![13, 12, 9];
+
+        for angle in [PI, PI / 2.0, 3.0 * PI / 2.0] {
+            bytes.extend_from_slice(&f32::to_be_bytes(angle));
+        }
+        bytes.push(1);
+        bytes.extend_from_slice(&2024i32.to_be_bytes());
+        bytes.extend_from_slice(&[2, 29, 3]);
+        bytes.extend_from_slice(&500u16.to_be_bytes());
+        bytes.push(9);
+        bytes.extend_from_slice(&60u16.to_be_bytes());
+        bytes.push(0);
+
+        bytes
+    }
+
+    #[test]
+    fn test_alarm() {
+        let body = [0x02, 13, 12, 9];
+        let decoded = alarm(&body).unwrap();
+
+        assert_eq!(decoded.active_days, ActiveDays(0x02));
+        assert_eq!((decoded.hour, decoded.minute, decoded.seconds), (13, 12, 9));
+        assert_eq!(alarm_strict(&body).unwrap(), decoded);
+        assert!(alarm(&body[..3]).is_err());
+        assert!(alarm(&[]).is_err());
+
+        // Saved and snoozed
+        let mut snoozed = body.to_vec();
+        snoozed.push(0x03);
+        snoozed.extend_from_slice(&7i64.to_be_bytes());
+        snoozed.extend_from_slice(&1_700_000_000i64.to_be_bytes());
+        let expected = Alarm {
+            id: Some(7),
+            ..decoded
+        }
+        .snoozed(1_700_000_000);
+
+        assert_eq!(alarm(&snoozed).unwrap(), expected);
+        assert_eq!(alarm_strict(&snoozed).unwrap(), expected);
+        assert!(alarm(&snoozed[..20]).is_err());
+        assert_eq!(
+            alarm_strict(&snoozed[..20]).unwrap_err().field,
+            "snoozed_until"
+        );
+    }
+
+    #[test]
+    fn test_alarm_strict() {
+        let error = alarm_strict(&[0x81, 12, 0, 0]).unwrap_err();
+        assert_eq!((error.offset, error.field), (0, "active_days"));
+        assert_eq!(alarm_strict(&[0x01, 12, 0]).unwrap_err().offset, 3);
+        // Unknown extension
+        assert_eq!(
+            alarm_strict(&[0x01, 12, 0, 0, 0]).unwrap_err().field,
+            "extension_flags"
+        );
+        let error = alarm_strict(&[0x01, 12, 0, 0, 0x01, 0, 0, 0, 0, 0, 0, 0, 1, 0]).unwrap_err();
+        assert_eq!((error.offset, error.reason), (13, "Trailing bytes"));
+    }
+
+    #[test]
+    fn test_clock() {
+        let body = clock_body();
+        let message = clock(&body).unwrap();
+
+        assert_eq!(
+            (message.hours(), message.minutes(), message.seconds()),
+            (13, 12, 9)
+        );
+        assert_eq!((message.hours12(), message.pm()), (1, true));
+        assert_eq!(message.theme(), Theme::Dusk);
+        assert_eq!(
+            (message.year(), message.month(), message.day()),
+            (2024, 2, 29)
+        );
+        assert_eq!((message.weekday(), message.millis()), (3, 500));
+        assert_eq!(
+            (message.iso_week(), message.day_of_year(), message.dst()),
+            (9, 60, false)
+        );
+        assert_eq!(clock_strict(&body).unwrap(), message);
+    }
+
+    #[test]
+    fn test_clock_older_layouts() {
+        let body = clock_body();
+
+        // No week: computed from the date
+        let dated = clock(&body[..CLOCK_DATED_LEN]).unwrap();
+        assert_eq!((dated.iso_week(), dated.day_of_year()), (9, 60));
+        assert!(clock(&body[..27]).is_err());
+
+        // No date: the local one
+        let today = Local::now().date_naive();
+        let undated = clock_strict(&body[..CLOCK_UNDATED_LEN]).unwrap();
+        assert_eq!(undated.year(), today.year());
+        assert!(clock(&body[..23]).is_err());
+        assert_eq!(clock_strict(&body[..20]).unwrap_err().field, "month");
+
+        // No theme hint: from the default theme hours
+        let mut themeless = body[..CLOCK_THEMELESS_LEN].to_vec();
+        themeless[0] = 23;
+        assert_eq!(clock(&themeless).unwrap().theme(), Theme::Night);
+        assert!(clock(&[12, 0]).is_err());
+    }
+
+    #[test]
+    fn test_clock_strict() {
+        let mut body = clock_body();
+
+        body[2] = 60;
+        assert_eq!(clock_strict(&body).unwrap_err().field, "seconds");
+
+        body[2] = 0;
+        body[7..11].copy_from_slice(&f32::NAN.to_be_bytes());
+        assert_eq!(clock_strict(&body).unwrap_err().offset, 7);
+
+        let mut body = clock_body();
+        body[23..25].copy_from_slice(&1000u16.to_be_bytes());
+        assert_eq!(clock_strict(&body).unwrap_err().field, "millis");
+
+        let mut body = clock_body();
+        body[25] = 54;
+        assert_eq!(clock_strict(&body).unwrap_err().field, "iso_week");
+    }
+
+    #[test]
+    fn test_calendar_dates() {
+        let date = |year| CalendarDate {
+            year,
+            month: 6,
+            day: 1,
+            leap_month: true,
+        };
+        let mut body = clock_body();
+        body.push(0x05);
+        for year in [1444i32, 2023] {
+            body.extend_from_slice(&year.to_be_bytes());
+            body.extend_from_slice(&[6, 1, 1]);
+        }
+        let expected = CalendarDates {
+            hijri: Some(date(1444)),
+            hebrew: None,
+            chinese: Some(date(2023)),
+        };
+
+        assert_eq!(clock(&body).unwrap().calendars(), Some(&expected));
+        assert_eq!(clock_strict(&body).unwrap().calendars(), Some(&expected));
+        assert!(clock(&body[..body.len() - 1]).is_err());
+
+        // Trailing bytes
+        body.push(0);
+        let error = clock_strict(&body).unwrap_err();
+        assert_eq!((error.offset, error.field), (CLOCK_LEN + 15, "end"));
+        // Unknown calendar
+        body.pop();
+        body[CLOCK_LEN] = 0x0D;
+        let error = clock_strict(&body).unwrap_err();
+        assert_eq!((error.offset, error.field), (CLOCK_LEN, "calendar_flags"));
+        // Impossible day
+        body[CLOCK_LEN] = 0x05;
+        body[CLOCK_LEN + 6] = 32;
+        let error = clock_strict(&body).unwrap_err();
+        assert_eq!((error.offset, error.field), (CLOCK_LEN + 6, "calendar_day"));
+    }
+}
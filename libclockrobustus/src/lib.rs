@@ -1,21 +1,119 @@
 /// Small, serializable and essential clock representations.
 pub mod alarm;
+pub mod astro;
+pub mod backup;
+pub mod bedtime;
+pub mod bus;
+pub mod calendar;
 pub mod clock;
+pub mod codec;
+pub mod control;
+pub mod countdown;
 pub mod env;
 pub mod error;
+pub mod heartbeat;
+pub mod history;
+pub mod ical;
+pub mod import;
+pub mod interval;
+pub mod jump;
+pub mod latency;
+mod legacy;
 pub mod message;
+pub mod next;
+pub mod ntp;
+pub mod pomodoro;
+pub mod preset;
+pub mod profile;
+#[cfg(feature = "protobuf")]
+pub mod proto;
 pub mod queue;
+pub mod quiet;
+pub mod reminder;
+pub mod schedule;
+pub mod sequence;
+pub mod settings;
+pub mod speak;
+pub mod timer;
+pub mod token;
+pub mod world_clock;
+
+/// Adds the given `(name, definition)` columns to a table when they are missing, so databases
+/// created by older versions keep working after a schema extension.
+pub(crate) fn add_missing_columns(
+    conn: &sqlite::Connection,
+    table: &str,
+    columns: &[(&str, &str)],
+) -> Result<(), error::ClockError> {
+    let mut existing = Vec::new();
+    let mut statement = conn.prepare(format!("PRAGMA table_info({})", table))?;
+
+    while let Ok(sqlite::State::Row) = statement.next() {
+        existing.push(statement.read::<String, _>("name")?);
+    }
+
+    for (name, definition) in columns {
+        if !existing.iter().any(|column| column == name) {
+            conn.execute(format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, name, definition
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `operation` in a transaction, rolled back if anything fails so that nothing is half
+/// done.
+pub(crate) fn in_transaction<T, F>(
+    conn: &sqlite::Connection,
+    operation: F,
+) -> Result<T, error::ClockError>
+where
+    F: FnOnce() -> Result<T, error::ClockError>,
+{
+    conn.execute("BEGIN")?;
+
+    match operation().and_then(|value| {
+        conn.execute("COMMIT")?;
+        Ok(value)
+    }) {
+        Ok(value) => Ok(value),
+        Err(error) => {
+            // Already rolled back when the commit itself failed
+            let _ = conn.execute("ROLLBACK");
+            Err(error)
+        }
+    }
+}
+
+/// Path of the database file, whether it exists or not
+/// Unix version
+#[cfg(target_family = "unix")]
+pub fn database_path() -> Result<String, error::ClockError> {
+    Ok(format!(
+        "{}/.config/clockrobustus/dbase.sqlite",
+        std::env::var("HOME")?
+    ))
+}
+
+/// Version for Windows
+#[cfg(target_family = "windows")]
+pub fn database_path() -> Result<String, error::ClockError> {
+    Ok("C:\\ProgramData\\ClockRobustus\\dbase.sqlite".to_string())
+}
 
 /// Handy function to check if the database file exists (creates it otherwise)
 /// Unix version version
 #[cfg(target_family = "unix")]
 pub fn check_database_directory() -> Result<String, error::ClockError> {
     let home = std::env::var("HOME")?;
-    let dbpath = format!("{}/.config/clockrobustus/dbase.sqlite", home);
+    let dbpath = database_path()?;
     if !std::path::PathBuf::from(dbpath.clone()).exists() {
         std::process::Command::new("mkdir")
             .arg("-p")
-            .arg(&format!("{}/.config/clockrobustus", home))
+            .arg(format!("{}/.config/clockrobustus", home))
             .output()?;
         std::process::Command::new("touch")
             .arg(dbpath.clone())
@@ -27,7 +125,7 @@ pub fn check_database_directory() -> Result<String, error::ClockError> {
 /// Version for Windows
 #[cfg(target_family = "windows")]
 pub fn check_database_directory() -> Result<String, error::ClockError> {
-    let dbpath = "C:\\ProgramData\\ClockRobustus\\dbase.sqlite".to_string();
+    let dbpath = database_path()?;
 
     if !std::path::PathBuf::from(dbpath.clone()).exists() {
         std::fs::create_dir_all("C:\\ProgramData\\ClockRobustus")?;
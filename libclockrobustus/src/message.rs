@@ -1,9 +1,189 @@
-use crate::{alarm::Alarm, clock::ClockMessage, error::ClockError};
+use serde::{ser::Error as _, Deserialize, Serialize, Serializer};
+use std::{ops::RangeInclusive, str::FromStr};
 
-const ALARM_MESSAGE_HEADER: u8 = 0xFF;
-const CLOCK_MESSAGE_HEADER: u8 = 0xFE;
-/// Wrapper enum around [ClockMessage] and [Alarm] to discriminate them as they are passed as binary data through the queues.
-/// Adds a binary header code for each message type and permits conversion in both ways.
+use crate::{
+    alarm::Alarm,
+    astro::SunMessage,
+    bedtime::BedtimeMessage,
+    clock::ClockMessage,
+    codec,
+    countdown::CountdownMessage,
+    error::{ClockError, DecodeError},
+    heartbeat::HeartbeatMessage,
+    interval::IntervalMessage,
+    jump::ClockJumpMessage,
+    legacy,
+    next::NextAlarmMessage,
+    ntp::SyncStatusMessage,
+    pomodoro::PomodoroMessage,
+    reminder::ReminderMessage,
+    speak::SpeakMessage,
+    world_clock::WorldClockMessage,
+};
+
+pub(crate) const ALARM_MESSAGE_HEADER: u8 = 0xFF;
+pub(crate) const CLOCK_MESSAGE_HEADER: u8 = 0xFE;
+pub(crate) const COUNTDOWN_MESSAGE_HEADER: u8 = 0xFD;
+pub(crate) const POMODORO_MESSAGE_HEADER: u8 = 0xFC;
+pub(crate) const INTERVAL_MESSAGE_HEADER: u8 = 0xFB;
+pub(crate) const NEXT_ALARM_MESSAGE_HEADER: u8 = 0xFA;
+pub(crate) const BEDTIME_MESSAGE_HEADER: u8 = 0xF9;
+pub(crate) const REMINDER_MESSAGE_HEADER: u8 = 0xF8;
+pub(crate) const WORLD_CLOCK_MESSAGE_HEADER: u8 = 0xF7;
+pub(crate) const SUN_MESSAGE_HEADER: u8 = 0xF6;
+pub(crate) const SYNC_STATUS_MESSAGE_HEADER: u8 = 0xF5;
+pub(crate) const CLOCK_JUMP_MESSAGE_HEADER: u8 = 0xF4;
+pub(crate) const SPEAK_MESSAGE_HEADER: u8 = 0xF3;
+pub(crate) const HEARTBEAT_MESSAGE_HEADER: u8 = 0xF2;
+pub(crate) const ALARM_BATCH_MESSAGE_HEADER: u8 = 0xF1;
+/// Topic frame of multipart alarm messages (see [Topics] for prefixed ones)
+pub const ALARM_TOPIC: &[u8] = b"alarm";
+/// Topic frame of multipart clock messages
+pub const CLOCK_TOPIC: &[u8] = b"clock";
+/// Topic frame of multipart countdown messages
+pub const COUNTDOWN_TOPIC: &[u8] = b"countdown";
+/// Topic frame of multipart pomodoro messages
+pub const POMODORO_TOPIC: &[u8] = b"pomodoro";
+/// Topic frame of multipart interval timer messages
+pub const INTERVAL_TOPIC: &[u8] = b"interval";
+/// Topic frame of multipart next alarm messages
+pub const NEXT_ALARM_TOPIC: &[u8] = b"next_alarm";
+/// Topic frame of multipart bedtime reminder messages
+pub const BEDTIME_TOPIC: &[u8] = b"bedtime";
+/// Topic frame of multipart reminder messages
+pub const REMINDER_TOPIC: &[u8] = b"reminder";
+/// Topic frame of multipart world clock messages
+pub const WORLD_CLOCK_TOPIC: &[u8] = b"world_clock";
+/// Topic frame of multipart sunrise and sunset messages
+pub const SUN_TOPIC: &[u8] = b"sun";
+/// Topic frame of multipart NTP synchronization status messages
+pub const SYNC_STATUS_TOPIC: &[u8] = b"sync_status";
+/// Topic frame of multipart system clock jump messages
+pub const CLOCK_JUMP_TOPIC: &[u8] = b"jump";
+/// Topic frame of multipart speaking clock announcement messages
+pub const SPEAK_TOPIC: &[u8] = b"speak";
+/// Topic frame of multipart heartbeat messages
+pub const HEARTBEAT_TOPIC: &[u8] = b"heartbeat";
+/// Topic frame of multipart alarm batch messages
+pub const ALARM_BATCH_TOPIC: &[u8] = b"alarm_batch";
+/// Header bytes set aside for the message types to come (timer states, for instance, are to get
+/// 0xF0), allocated downwards. This version decodes them as [Message::Unknown] rather than
+/// failing, so that a newer daemon does not break older clients.
+pub const RESERVED_HEADERS: RangeInclusive<u8> = 0x80..=0xF0;
+/// Registry of the known message types: header byte of their single frames, and topic frame of
+/// their multipart messages.
+const REGISTRY: [(u8, &[u8]); 15] = [
+    (ALARM_MESSAGE_HEADER, ALARM_TOPIC),
+    (CLOCK_MESSAGE_HEADER, CLOCK_TOPIC),
+    (COUNTDOWN_MESSAGE_HEADER, COUNTDOWN_TOPIC),
+    (POMODORO_MESSAGE_HEADER, POMODORO_TOPIC),
+    (INTERVAL_MESSAGE_HEADER, INTERVAL_TOPIC),
+    (NEXT_ALARM_MESSAGE_HEADER, NEXT_ALARM_TOPIC),
+    (BEDTIME_MESSAGE_HEADER, BEDTIME_TOPIC),
+    (REMINDER_MESSAGE_HEADER, REMINDER_TOPIC),
+    (WORLD_CLOCK_MESSAGE_HEADER, WORLD_CLOCK_TOPIC),
+    (SUN_MESSAGE_HEADER, SUN_TOPIC),
+    (SYNC_STATUS_MESSAGE_HEADER, SYNC_STATUS_TOPIC),
+    (CLOCK_JUMP_MESSAGE_HEADER, CLOCK_JUMP_TOPIC),
+    (SPEAK_MESSAGE_HEADER, SPEAK_TOPIC),
+    (HEARTBEAT_MESSAGE_HEADER, HEARTBEAT_TOPIC),
+    (ALARM_BATCH_MESSAGE_HEADER, ALARM_BATCH_TOPIC),
+];
+/// Magic bytes opening versioned frames. Legacy (version 0) frames have none: single frames
+/// start with a header byte, payload frames with a first field never starting with these.
+pub const PROTOCOL_MAGIC: [u8; 2] = [0xC1, 0x0C];
+/// Version of the message layouts, bumped whenever one of them changes. Up to version 1, bodies
+/// were laid out by hand, and only alarms and clock messages are still decoded from these
+/// layouts. From version 2 on, bodies are encoded by [crate::codec].
+pub const PROTOCOL_VERSION: u8 = 2;
+/// Size of the envelope (magic bytes and version) opening versioned frames
+pub const ENVELOPE_LEN: usize = 3;
+
+/// Encoding of the payload frames of multipart messages, daemon and clients must agree on it
+/// (see the `CLOCKROBUSTUS_WIRE_FORMAT` env var).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum WireFormat {
+    /// Envelope and [crate::codec] body (see [Message::encode_payload_into])
+    #[default]
+    Binary,
+    /// JSON representation of the message (see [Message::to_json]), trivial to consume from
+    /// any language
+    Json,
+    /// MessagePack representation of the message (see [Message::to_msgpack]), as
+    /// self-describing as JSON but compact, for embedded subscribers
+    MessagePack,
+}
+
+impl FromStr for WireFormat {
+    type Err = ClockError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "binary" => Ok(Self::Binary),
+            "json" => Ok(Self::Json),
+            "msgpack" | "messagepack" => Ok(Self::MessagePack),
+            _ => Err(ClockError("Unknown wire format")),
+        }
+    }
+}
+
+/// Writes the envelope at the start of `buffer`, returning what follows it.
+fn seal(buffer: &mut [u8]) -> Result<&mut [u8], ClockError> {
+    if buffer.len() < ENVELOPE_LEN {
+        return Err(ClockError("Buffer too small to encode message"));
+    }
+
+    let (envelope, rest) = buffer.split_at_mut(ENVELOPE_LEN);
+    envelope[..2].copy_from_slice(&PROTOCOL_MAGIC);
+    envelope[2] = PROTOCOL_VERSION;
+
+    Ok(rest)
+}
+
+/// Splits the envelope off a frame, returning the protocol version (0 for legacy frames) and
+/// what follows it. Frames of a version newer than [PROTOCOL_VERSION] are rejected, rather than
+/// misread by a client not knowing their layouts.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::message::open_envelope;
+///
+/// assert_eq!(open_envelope(&[0xC1, 0x0C, 1, 0xFE]).unwrap(), (1, &[0xFE][..]));
+/// // Legacy frame
+/// assert_eq!(open_envelope(&[0xFE]).unwrap(), (0, &[0xFE][..]));
+///
+/// let error = open_envelope(&[0xC1, 0x0C, 0x7F, 0xFE]).unwrap_err();
+/// assert_eq!((error.offset, error.reason), (2, "Unsupported protocol version"));
+/// ```
+pub fn open_envelope(frame: &[u8]) -> Result<(u8, &[u8]), DecodeError> {
+    match frame.strip_prefix(&PROTOCOL_MAGIC[..]) {
+        None => Ok((0, frame)),
+        Some(rest) => match rest.split_first() {
+            Some((&version, rest)) if (1..=PROTOCOL_VERSION).contains(&version) => {
+                Ok((version, rest))
+            }
+            Some(_) => Err(DecodeError::new(
+                2,
+                "version",
+                "Unsupported protocol version",
+            )),
+            None => Err(DecodeError::new(2, "version", "Missing field")),
+        },
+    }
+}
+
+/// Wrapper enum around [ClockMessage], [Alarm], [CountdownMessage], [PomodoroMessage],
+/// [IntervalMessage], [NextAlarmMessage], [BedtimeMessage], [ReminderMessage], [WorldClockMessage],
+/// [SunMessage], [SyncStatusMessage], [ClockJumpMessage], [SpeakMessage], [HeartbeatMessage] and
+/// batches of alarms to discriminate them as they are passed as binary data through the queues.
+/// Adds a binary header code for each message type and permits conversion in both ways. Alarm
+/// bodies carry the whole alarm (id, label, snooze end, missed time...).
+///
+/// Binary representations open with an envelope carrying the [PROTOCOL_VERSION] (see
+/// [open_envelope]), legacy ones without an envelope are still understood. The serde
+/// representation is self-describing instead, the wrapped message being tagged with its `type`
+/// (eg: `nextAlarm`), for higher-level transports (REST, WebSocket...).
 ///
 /// # Examples
 /// ```
@@ -16,18 +196,83 @@ const CLOCK_MESSAGE_HEADER: u8 = 0xFE;
 ///     hour: 12,
 ///     minute: 0,
 ///     seconds: 0,
+///     ..Default::default()
 /// };
 ///
 /// let message1 = Message::from(clock_message);
 /// let message2 = Message::from(alarm);
 ///
-/// assert_eq!(message1.as_bytes()[0], 0xFE);
-/// assert_eq!(message2.as_bytes()[0], 0xFF);
+/// assert_eq!(message1.as_bytes().unwrap()[..4], [0xC1, 0x0C, 0x02, 0xFE]);
+/// assert_eq!(message2.as_bytes().unwrap()[..4], [0xC1, 0x0C, 0x02, 0xFF]);
+///
+/// // Saved alarms travel with their id, so clients can snooze or dismiss them
+/// let saved = Message::from(Alarm { id: Some(42), ..Default::default() });
+/// assert_eq!(Message::try_from(saved.as_bytes().unwrap()).unwrap(), saved);
+///
+/// let json = serde_json::to_value(&saved).unwrap();
+/// assert_eq!(json["type"], "alarm");
+/// assert_eq!(json["id"], 42);
+/// assert_eq!(serde_json::from_value::<Message>(json).unwrap(), saved);
 /// ```
-#[derive(Debug, Copy, Clone, PartialEq)]
+// Alarms outweigh the other messages, boxing them would change the public variants
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
 pub enum Message {
     Clock(ClockMessage),
     Alarm(Alarm),
+    Countdown(CountdownMessage),
+    Pomodoro(PomodoroMessage),
+    Interval(IntervalMessage),
+    NextAlarm(NextAlarmMessage),
+    Bedtime(BedtimeMessage),
+    Reminder(ReminderMessage),
+    WorldClock(WorldClockMessage),
+    Sun(SunMessage),
+    SyncStatus(SyncStatusMessage),
+    ClockJump(ClockJumpMessage),
+    Speak(SpeakMessage),
+    Heartbeat(HeartbeatMessage),
+    /// Alarms rung in the same tick, published at once ([crate::queue::listen] hands them out
+    /// one by one as [Message::Alarm]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::{alarm::Alarm, message::{Message, Topics}};
+    ///
+    /// let batch = Message::AlarmBatch(vec![
+    ///     Alarm { id: Some(1), ..Default::default() },
+    ///     Alarm { id: Some(2), ..Default::default() },
+    /// ]);
+    ///
+    /// assert_eq!(Message::try_from(batch.as_bytes().unwrap()).unwrap(), batch);
+    /// assert_eq!(Topics::new("kitchen/").of(&batch), b"kitchen/alarm_batch");
+    /// assert_eq!(serde_json::to_value(&batch).unwrap()["alarms"][1]["id"], 2);
+    /// ```
+    #[serde(with = "alarm_batch_format")]
+    AlarmBatch(Vec<Alarm>),
+    /// Message of a type this version does not know (header in [RESERVED_HEADERS]), eg:
+    /// published by a newer daemon, kept as received: header byte and body. It has no topic,
+    /// nor JSON or MessagePack representation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::message::Message;
+    ///
+    /// let newer = vec![0xC1, 0x0C, 0x02, 0xF0, 0x01, 0x02];
+    /// let message = Message::try_from(newer.clone()).unwrap();
+    ///
+    /// assert_eq!(message, Message::Unknown(0xF0, vec![0x01, 0x02]));
+    /// assert_eq!(message.as_bytes().unwrap(), newer);
+    /// assert!(message.to_json().is_err());
+    /// assert!(serde_json::to_string(&message).is_err());
+    /// // Not a message type to come
+    /// assert!(Message::try_from(vec![0xC1, 0x0C, 0x02, 0x01, 0x01, 0x02]).is_err());
+    /// ```
+    #[serde(skip)]
+    Unknown(u8, Vec<u8>),
 }
 
 impl From<ClockMessage> for Message {
@@ -42,13 +287,86 @@ impl From<Alarm> for Message {
     }
 }
 
+impl From<CountdownMessage> for Message {
+    fn from(value: CountdownMessage) -> Self {
+        Self::Countdown(value)
+    }
+}
+
+impl From<PomodoroMessage> for Message {
+    fn from(value: PomodoroMessage) -> Self {
+        Self::Pomodoro(value)
+    }
+}
+
+impl From<IntervalMessage> for Message {
+    fn from(value: IntervalMessage) -> Self {
+        Self::Interval(value)
+    }
+}
+
+impl From<NextAlarmMessage> for Message {
+    fn from(value: NextAlarmMessage) -> Self {
+        Self::NextAlarm(value)
+    }
+}
+
+impl From<BedtimeMessage> for Message {
+    fn from(value: BedtimeMessage) -> Self {
+        Self::Bedtime(value)
+    }
+}
+
+impl From<ReminderMessage> for Message {
+    fn from(value: ReminderMessage) -> Self {
+        Self::Reminder(value)
+    }
+}
+
+impl From<WorldClockMessage> for Message {
+    fn from(value: WorldClockMessage) -> Self {
+        Self::WorldClock(value)
+    }
+}
+
+impl From<SunMessage> for Message {
+    fn from(value: SunMessage) -> Self {
+        Self::Sun(value)
+    }
+}
+
+impl From<SyncStatusMessage> for Message {
+    fn from(value: SyncStatusMessage) -> Self {
+        Self::SyncStatus(value)
+    }
+}
+
+impl From<ClockJumpMessage> for Message {
+    fn from(value: ClockJumpMessage) -> Self {
+        Self::ClockJump(value)
+    }
+}
+
+impl From<SpeakMessage> for Message {
+    fn from(value: SpeakMessage) -> Self {
+        Self::Speak(value)
+    }
+}
+
+impl From<HeartbeatMessage> for Message {
+    fn from(value: HeartbeatMessage) -> Self {
+        Self::Heartbeat(value)
+    }
+}
+
 impl TryFrom<Vec<u8>> for Message {
     type Error = ClockError;
     /// Try to instantiate a new [Message] using the passed binary vector/
     ///
     /// # Panics
     ///
-    /// Panics if the passed vector is empty, if the header byte value is unknown or if any of the inner conversions fails
+    /// Panics if the passed vector is empty, if the protocol version is not supported, if the
+    /// header byte value is unknown or if any of the inner conversions fails
     ///
     /// # Examples
     /// ```
@@ -58,44 +376,113 @@ impl TryFrom<Vec<u8>> for Message {
     /// let garbage = vec![0x01, 0x02];
     /// let good_header_but_empty_after = vec![0xFF];
     /// let good_header_but_garbage_after = vec![0xFF, 0x01];
-    /// let good = vec![0xFF, 0x01, 12, 0, 0];
+    /// let good = vec![0xC1, 0x0C, 0x01, 0xFF, 0x01, 12, 0, 0];
+    /// let legacy = vec![0xFF, 0x01, 12, 0, 0];
+    /// let newer = vec![0xC1, 0x0C, 0x7F, 0xFF, 0x01, 12, 0, 0];
     ///
     /// let res_empty = Message::try_from(empty);
     /// let res_garbage = Message::try_from(garbage);
     /// let res_good_header_but_empty_after = Message::try_from(good_header_but_empty_after);
     /// let res_good_header_but_garbage_after = Message::try_from(good_header_but_garbage_after);
     /// let res_good = Message::try_from(good);
+    /// let res_legacy = Message::try_from(legacy);
+    /// let res_newer = Message::try_from(newer);
     ///
     /// assert!(res_empty.is_err());
     /// assert!(res_garbage.is_err());
     /// assert!(res_good_header_but_empty_after.is_err());
     /// assert!(res_good_header_but_garbage_after.is_err());
-    /// assert_eq!(res_good.unwrap(), Message::from(Alarm {
+    /// assert!(res_newer.is_err());
+    /// let good = res_good.unwrap();
+    /// assert_eq!(res_legacy.unwrap(), good);
+    /// assert_eq!(good, Message::from(Alarm {
     ///     id: None,
     ///     active_days: ActiveDays(0x01),
     ///     hour: 12,
     ///     minute: 0,
     ///     seconds: 0,
+    ///     ..Default::default()
     /// }));
     /// ```
     fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
-        if value.is_empty() {
-            Err(ClockError("Cannot convert message from empty byte vector"))
-        } else {
-            match value[0] {
-                ALARM_MESSAGE_HEADER => Ok(Self::Alarm(Alarm::try_from(
-                    value[1..value.len()].to_vec(),
-                )?)),
-                CLOCK_MESSAGE_HEADER => Ok(Self::Clock(ClockMessage::try_from(
-                    value[1..value.len()].to_vec(),
-                )?)),
-                _ => Err(ClockError("Unknown message header")),
-            }
+        Self::try_from(value.as_slice())
+    }
+}
+
+impl TryFrom<&[u8]> for Message {
+    type Error = ClockError;
+    /// Borrowing counterpart of the [Vec] conversion, decoding in place (eg: from a receive
+    /// buffer).
+    ///
+    /// # Examples
+    /// ```
+    /// use libclockrobustus::{message::Message, clock::ClockMessage};
+    ///
+    /// let message = Message::from(ClockMessage::default());
+    /// let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+    /// let len = message.encode_into(&mut buffer).unwrap();
+    ///
+    /// assert_eq!(Message::try_from(&buffer[..len]).unwrap(), message);
+    /// assert!(Message::try_from(&buffer[..0]).is_err());
+    /// ```
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let (version, value) = open_envelope(value)?;
+        let (header, body) = value
+            .split_first()
+            .ok_or(ClockError("Cannot convert message from empty byte vector"))?;
+
+        match topic_of(*header) {
+            Some(topic) => Self::decode_body(version, topic, body),
+            None if RESERVED_HEADERS.contains(header) => Ok(Self::Unknown(*header, body.to_vec())),
+            None => Err(ClockError("Unknown message header")),
         }
     }
 }
 
 impl Message {
+    /// Size of a buffer fitting the binary representation (envelope and header included) of any
+    /// message but alarms and alarm batches, whose labels, metadata and rotations have no bound
+    /// (see [Message::payload]).
+    pub const MAX_ENCODED_LEN: usize = ENVELOPE_LEN
+        + 1
+        + max(
+            ClockMessage::MAX_ENCODED_LEN,
+            max(
+                CountdownMessage::MAX_ENCODED_LEN,
+                max(
+                    PomodoroMessage::MAX_ENCODED_LEN,
+                    max(
+                        IntervalMessage::MAX_ENCODED_LEN,
+                        max(
+                            NextAlarmMessage::MAX_ENCODED_LEN,
+                            max(
+                                BedtimeMessage::MAX_ENCODED_LEN,
+                                max(
+                                    ReminderMessage::MAX_ENCODED_LEN,
+                                    max(
+                                        WorldClockMessage::MAX_ENCODED_LEN,
+                                        max(
+                                            SunMessage::MAX_ENCODED_LEN,
+                                            max(
+                                                SyncStatusMessage::MAX_ENCODED_LEN,
+                                                max(
+                                                    ClockJumpMessage::MAX_ENCODED_LEN,
+                                                    max(
+                                                        SpeakMessage::MAX_ENCODED_LEN,
+                                                        HeartbeatMessage::MAX_ENCODED_LEN,
+                                                    ),
+                                                ),
+                                            ),
+                                        ),
+                                    ),
+                                ),
+                            ),
+                        ),
+                    ),
+                ),
+            ),
+        );
+
     /// Convert a [Message] to a vector of bytes
     ///
     /// # Examples
@@ -104,13 +491,895 @@ impl Message {
     /// use libclockrobustus::{message::Message, clock::ClockMessage};
     ///
     /// let message = Message::from(ClockMessage::default());
-    /// let bytes = message.as_bytes();
+    /// let bytes = message.as_bytes().unwrap();
     ///
     /// assert_eq!(Message::try_from(bytes).unwrap(), message);
-    pub fn as_bytes(&self) -> Vec<u8> {
+    /// ```
+    pub fn as_bytes(&self) -> Result<Vec<u8>, ClockError> {
+        Ok(velcro::vec![
+            ..PROTOCOL_MAGIC,
+            PROTOCOL_VERSION,
+            self.header(),
+            ..self.body()?,
+        ])
+    }
+
+    /// Writes the binary representation (envelope and header included) at the start of a
+    /// caller-provided buffer and returns the number of bytes written. A buffer of
+    /// [Message::MAX_ENCODED_LEN] bytes fits any message but alarms and alarm batches, which
+    /// may need [Message::as_bytes] instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is too short for the message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::{message::Message, clock::ClockMessage};
+    ///
+    /// let message = Message::from(ClockMessage::default());
+    /// let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+    ///
+    /// let len = message.encode_into(&mut buffer).unwrap();
+    ///
+    /// assert_eq!(buffer[..len].to_vec(), message.as_bytes().unwrap());
+    /// assert!(message.encode_into(&mut []).is_err());
+    /// ```
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        let (header, body) = seal(buffer)?
+            .split_first_mut()
+            .ok_or(ClockError("Buffer too small to encode message"))?;
+
+        *header = self.header();
+
+        Ok(ENVELOPE_LEN + 1 + self.encode_body_into(body)?)
+    }
+
+    /// Header byte of the message in single frames
+    fn header(&self) -> u8 {
         match self {
-            Self::Alarm(alarm) => velcro::vec![ALARM_MESSAGE_HEADER, ..alarm.as_bytes(),],
-            Self::Clock(clock) => velcro::vec![CLOCK_MESSAGE_HEADER, ..clock.as_bytes(),],
+            Self::Alarm(_) => ALARM_MESSAGE_HEADER,
+            Self::Clock(_) => CLOCK_MESSAGE_HEADER,
+            Self::Countdown(_) => COUNTDOWN_MESSAGE_HEADER,
+            Self::Pomodoro(_) => POMODORO_MESSAGE_HEADER,
+            Self::Interval(_) => INTERVAL_MESSAGE_HEADER,
+            Self::NextAlarm(_) => NEXT_ALARM_MESSAGE_HEADER,
+            Self::Bedtime(_) => BEDTIME_MESSAGE_HEADER,
+            Self::Reminder(_) => REMINDER_MESSAGE_HEADER,
+            Self::WorldClock(_) => WORLD_CLOCK_MESSAGE_HEADER,
+            Self::Sun(_) => SUN_MESSAGE_HEADER,
+            Self::SyncStatus(_) => SYNC_STATUS_MESSAGE_HEADER,
+            Self::ClockJump(_) => CLOCK_JUMP_MESSAGE_HEADER,
+            Self::Speak(_) => SPEAK_MESSAGE_HEADER,
+            Self::Heartbeat(_) => HEARTBEAT_MESSAGE_HEADER,
+            Self::AlarmBatch(_) => ALARM_BATCH_MESSAGE_HEADER,
+            Self::Unknown(header, _) => *header,
         }
     }
+
+    /// Topic frame of the message when published as a multipart (topic, payload) message.
+    /// Subscribers may filter on a topic prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::{message::Message, clock::ClockMessage};
+    ///
+    /// assert_eq!(Message::from(ClockMessage::default()).topic(), b"clock");
+    /// ```
+    pub fn topic(&self) -> &'static [u8] {
+        match self {
+            Self::Alarm(_) => ALARM_TOPIC,
+            Self::Clock(_) => CLOCK_TOPIC,
+            Self::Countdown(_) => COUNTDOWN_TOPIC,
+            Self::Pomodoro(_) => POMODORO_TOPIC,
+            Self::Interval(_) => INTERVAL_TOPIC,
+            Self::NextAlarm(_) => NEXT_ALARM_TOPIC,
+            Self::Bedtime(_) => BEDTIME_TOPIC,
+            Self::Reminder(_) => REMINDER_TOPIC,
+            Self::WorldClock(_) => WORLD_CLOCK_TOPIC,
+            Self::Sun(_) => SUN_TOPIC,
+            Self::SyncStatus(_) => SYNC_STATUS_TOPIC,
+            Self::ClockJump(_) => CLOCK_JUMP_TOPIC,
+            Self::Speak(_) => SPEAK_TOPIC,
+            Self::Heartbeat(_) => HEARTBEAT_TOPIC,
+            Self::AlarmBatch(_) => ALARM_BATCH_TOPIC,
+            Self::Unknown(..) => b"",
+        }
+    }
+
+    /// Writes the payload frame of a multipart message (the envelope, then the binary
+    /// representation without the header byte, the topic frame discriminates the type) and
+    /// returns the number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is too short for the message.
+    pub fn encode_payload_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        Ok(ENVELOPE_LEN + self.encode_body_into(seal(buffer)?)?)
+    }
+
+    /// Allocating counterpart of [Message::encode_payload_into], for the messages not fitting a
+    /// buffer of [Message::MAX_ENCODED_LEN] bytes (alarms and alarm batches with long labels,
+    /// metadata or rotations).
+    pub fn payload(&self) -> Result<Vec<u8>, ClockError> {
+        Ok(velcro::vec![
+            ..PROTOCOL_MAGIC,
+            PROTOCOL_VERSION,
+            ..self.body()?,
+        ])
+    }
+
+    /// Allocating counterpart of [Message::encode_body_into]
+    fn body(&self) -> Result<Vec<u8>, ClockError> {
+        match self {
+            Self::Unknown(_, body) => Ok(body.clone()),
+            _ => codec::to_vec(&Body(self)),
+        }
+    }
+
+    /// Writes the body of the message (the binary representation without the envelope nor the
+    /// header byte, see [crate::codec]) and returns the number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is too short for the message.
+    pub fn encode_body_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        match self {
+            Self::Unknown(_, body) => {
+                buffer
+                    .get_mut(..body.len())
+                    .ok_or(ClockError("Buffer too small to encode message"))?
+                    .copy_from_slice(body);
+
+                Ok(body.len())
+            }
+            _ => codec::encode(&Body(self), buffer),
+        }
+    }
+
+    /// Decodes a multipart message from its topic and payload frames, the payload being legacy
+    /// (no envelope) or of a supported version.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the topic is unknown, if the protocol version is not supported or if the
+    /// payload cannot be decoded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::{message::Message, clock::ClockMessage};
+    ///
+    /// let clock = ClockMessage::default();
+    /// let message = Message::from(clock);
+    /// let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+    /// let len = message.encode_payload_into(&mut buffer).unwrap();
+    ///
+    /// let decoded = Message::try_from_parts(message.topic(), &buffer[..len]).unwrap();
+    ///
+    /// assert_eq!(decoded, message);
+    /// assert!(Message::try_from_parts(b"weather", &buffer[..len]).is_err());
+    /// // Legacy payload
+    /// let legacy = Message::try_from_parts(b"alarm", &[0x01, 12, 0, 0]).unwrap();
+    /// assert_eq!(legacy.topic(), b"alarm");
+    /// ```
+    pub fn try_from_parts(topic: &[u8], payload: &[u8]) -> Result<Self, ClockError> {
+        let (version, body) = open_envelope(payload)?;
+
+        Self::decode_body(version, topic, body)
+    }
+
+    /// Decodes the body of a message, laid out according to the protocol `version`.
+    fn decode_body(version: u8, topic: &[u8], body: &[u8]) -> Result<Self, ClockError> {
+        match (version, topic) {
+            (0 | 1, ALARM_TOPIC) => legacy::alarm(body).map(Self::Alarm),
+            (0 | 1, CLOCK_TOPIC) => legacy::clock(body).map(Self::Clock),
+            // The other message types came with the codec
+            (0 | 1, _) if is_known(topic) => Err(ClockError("No legacy layout for message type")),
+            (_, ALARM_TOPIC) => codec::decode(body).map(Self::Alarm),
+            (_, CLOCK_TOPIC) => codec::decode(body).map(Self::Clock),
+            (_, COUNTDOWN_TOPIC) => codec::decode(body).map(Self::Countdown),
+            (_, POMODORO_TOPIC) => codec::decode(body).map(Self::Pomodoro),
+            (_, INTERVAL_TOPIC) => codec::decode(body).map(Self::Interval),
+            (_, NEXT_ALARM_TOPIC) => codec::decode(body).map(Self::NextAlarm),
+            (_, BEDTIME_TOPIC) => codec::decode(body).map(Self::Bedtime),
+            (_, REMINDER_TOPIC) => codec::decode(body).map(Self::Reminder),
+            (_, WORLD_CLOCK_TOPIC) => codec::decode(body).map(Self::WorldClock),
+            (_, SUN_TOPIC) => codec::decode(body).map(Self::Sun),
+            (_, SYNC_STATUS_TOPIC) => codec::decode(body).map(Self::SyncStatus),
+            (_, CLOCK_JUMP_TOPIC) => codec::decode(body).map(Self::ClockJump),
+            (_, SPEAK_TOPIC) => codec::decode(body).map(Self::Speak),
+            (_, HEARTBEAT_TOPIC) => codec::decode(body).map(Self::Heartbeat),
+            (_, ALARM_BATCH_TOPIC) => codec::decode(body).map(Self::AlarmBatch),
+            _ => Err(ClockError("Unknown message topic")),
+        }
+    }
+
+    /// JSON representation of the message, its serde representation tagged with its `type`
+    /// (redundant with the topic frame, but self-describing).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::{message::Message, jump::ClockJumpMessage};
+    ///
+    /// let message = Message::from(ClockJumpMessage { jump_ms: 1500, wall_ms: 4600 });
+    /// let json = message.to_json().unwrap();
+    ///
+    /// assert_eq!(json, r#"{"type":"clockJump","jumpMs":1500,"wallMs":4600}"#);
+    /// assert_eq!(Message::try_from_json_parts(b"jump", json.as_bytes()).unwrap(), message);
+    /// assert!(Message::try_from_json_parts(b"clock", json.as_bytes()).is_err());
+    /// ```
+    pub fn to_json(&self) -> Result<String, ClockError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Decodes a multipart message from its topic frame and its JSON payload frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the payload is not the JSON representation of a message, or if its type does
+    /// not match the topic.
+    pub fn try_from_json_parts(topic: &[u8], payload: &[u8]) -> Result<Self, ClockError> {
+        serde_json::from_slice::<Self>(payload)?.of_topic(topic)
+    }
+
+    /// MessagePack representation of the message, tagged with its `type` as in JSON.
+    /// Structures are maps keyed by the same field names as in JSON.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::{message::Message, jump::ClockJumpMessage};
+    ///
+    /// let message = Message::from(ClockJumpMessage { jump_ms: 1500, wall_ms: 4600 });
+    /// let bytes = message.to_msgpack().unwrap();
+    ///
+    /// // Map of 3 entries, the first one keyed "type"
+    /// assert_eq!(bytes[..6], [0x83, 0xA4, b't', b'y', b'p', b'e']);
+    /// assert_eq!(Message::try_from_msgpack_parts(b"jump", &bytes).unwrap(), message);
+    /// assert!(Message::try_from_msgpack_parts(b"clock", &bytes).is_err());
+    /// ```
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, ClockError> {
+        Ok(rmp_serde::to_vec_named(self)?)
+    }
+
+    /// Decodes a multipart message from its topic frame and its MessagePack payload frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the payload is not the MessagePack representation of a message, or if its type
+    /// does not match the topic.
+    pub fn try_from_msgpack_parts(topic: &[u8], payload: &[u8]) -> Result<Self, ClockError> {
+        rmp_serde::from_slice::<Self>(payload)?.of_topic(topic)
+    }
+
+    /// The message itself if it was published on `topic`, the type of self-describing payloads
+    /// having to agree with their topic frame.
+    fn of_topic(self, topic: &[u8]) -> Result<Self, ClockError> {
+        if self.topic() == topic {
+            Ok(self)
+        } else {
+            Err(ClockError("Message type does not match its topic"))
+        }
+    }
+
+    /// Protobuf representation of the message (see `proto/message.proto`), type included. Only
+    /// clock and alarm messages have one so far.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::{clock::ClockMessage, message::Message, speak::SpeakMessage};
+    ///
+    /// let message = Message::from(ClockMessage::default());
+    /// let bytes = message.to_protobuf().unwrap();
+    ///
+    /// assert_eq!(Message::try_from_protobuf(&bytes).unwrap(), message);
+    /// assert!(Message::from(SpeakMessage::default()).to_protobuf().is_err());
+    /// ```
+    #[cfg(feature = "protobuf")]
+    pub fn to_protobuf(&self) -> Result<Vec<u8>, ClockError> {
+        use crate::proto::{self, message::Body};
+        use prost::Message as _;
+
+        let body = match self {
+            Self::Clock(clock) => Body::Clock((*clock).into()),
+            Self::Alarm(alarm) => Body::Alarm(alarm.clone().into()),
+            _ => return Err(ClockError("No protobuf representation")),
+        };
+
+        Ok(proto::Message { body: Some(body) }.encode_to_vec())
+    }
+
+    /// Decodes a message from its protobuf representation (see [Message::to_protobuf]).
+    #[cfg(feature = "protobuf")]
+    pub fn try_from_protobuf(bytes: &[u8]) -> Result<Self, ClockError> {
+        use crate::proto::{self, message::Body};
+        use prost::Message as _;
+
+        match proto::Message::decode(bytes)?.body {
+            Some(Body::Clock(tick)) => Ok(Self::Clock(tick.try_into()?)),
+            Some(Body::Alarm(alarm)) => Ok(Self::Alarm(alarm.try_into()?)),
+            None => Err(ClockError("Empty protobuf message")),
+        }
+    }
+
+    /// Strict counterpart of the [TryFrom] conversion from bytes, meant to debug third-party
+    /// publishers: out of range values and trailing bytes are rejected as well, the error
+    /// pointing at the offending byte (envelope and header included). Fields of the bodies of
+    /// version 2 on are not delimited (see [codec::decode_strict]): values out of range are
+    /// reported at the start of the body, naming the field.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::message::Message;
+    ///
+    /// assert!(Message::try_from_strict(&[0xFF, 0x01, 12, 0, 0]).is_ok());
+    ///
+    /// let error = Message::try_from_strict(&[0xFF, 0x01, 12, 61, 0]).unwrap_err();
+    /// assert_eq!((error.offset, error.field), (3, "minute"));
+    ///
+    /// let versioned = [0xC1, 0x0C, 0x01, 0xFF, 0x01, 12, 61, 0];
+    /// let error = Message::try_from_strict(&versioned).unwrap_err();
+    /// assert_eq!((error.offset, error.field), (6, "minute"));
+    ///
+    /// let error = Message::try_from_strict(&[0x01, 0x02]).unwrap_err();
+    /// assert_eq!((error.offset, error.field), (0, "header"));
+    /// ```
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let (version, message) = open_envelope(bytes)?;
+        let offset = bytes.len() - message.len();
+        let (header, body) =
+            message
+                .split_first()
+                .ok_or(DecodeError::new(offset, "header", "Missing field"))?;
+
+        match topic_of(*header) {
+            Some(topic) => Self::decode_body_strict(version, topic, body, offset + 1),
+            None if RESERVED_HEADERS.contains(header) => Ok(Self::Unknown(*header, body.to_vec())),
+            None => Err(DecodeError::new(offset, "header", "Unknown message header")),
+        }
+    }
+
+    /// Strict counterpart of [Message::try_from_parts], offsets being relative to the payload
+    /// (envelope included).
+    pub fn try_from_parts_strict(topic: &[u8], payload: &[u8]) -> Result<Self, DecodeError> {
+        let (version, body) = open_envelope(payload)?;
+
+        Self::decode_body_strict(version, topic, body, payload.len() - body.len())
+    }
+
+    /// Strict counterpart of [Message::decode_body], `offset` being where the body starts.
+    fn decode_body_strict(
+        version: u8,
+        topic: &[u8],
+        body: &[u8],
+        offset: usize,
+    ) -> Result<Self, DecodeError> {
+        let message = match (version, topic) {
+            (0 | 1, ALARM_TOPIC) => legacy::alarm_strict(body).map(Self::Alarm),
+            (0 | 1, CLOCK_TOPIC) => legacy::clock_strict(body).map(Self::Clock),
+            (0 | 1, _) if is_known(topic) => Err(DecodeError::new(
+                0,
+                "version",
+                "No legacy layout for message type",
+            )),
+            (_, ALARM_TOPIC) => Alarm::try_from_strict(body).map(Self::Alarm),
+            (_, CLOCK_TOPIC) => ClockMessage::try_from_strict(body).map(Self::Clock),
+            (_, COUNTDOWN_TOPIC) => CountdownMessage::try_from_strict(body).map(Self::Countdown),
+            (_, POMODORO_TOPIC) => PomodoroMessage::try_from_strict(body).map(Self::Pomodoro),
+            (_, INTERVAL_TOPIC) => IntervalMessage::try_from_strict(body).map(Self::Interval),
+            (_, NEXT_ALARM_TOPIC) => NextAlarmMessage::try_from_strict(body).map(Self::NextAlarm),
+            (_, BEDTIME_TOPIC) => BedtimeMessage::try_from_strict(body).map(Self::Bedtime),
+            (_, REMINDER_TOPIC) => ReminderMessage::try_from_strict(body).map(Self::Reminder),
+            (_, WORLD_CLOCK_TOPIC) => {
+                WorldClockMessage::try_from_strict(body).map(Self::WorldClock)
+            }
+            (_, SUN_TOPIC) => SunMessage::try_from_strict(body).map(Self::Sun),
+            (_, SYNC_STATUS_TOPIC) => {
+                SyncStatusMessage::try_from_strict(body).map(Self::SyncStatus)
+            }
+            (_, CLOCK_JUMP_TOPIC) => ClockJumpMessage::try_from_strict(body).map(Self::ClockJump),
+            (_, SPEAK_TOPIC) => SpeakMessage::try_from_strict(body).map(Self::Speak),
+            (_, HEARTBEAT_TOPIC) => codec::decode_strict(body).map(Self::Heartbeat),
+            (_, ALARM_BATCH_TOPIC) => codec::decode_strict::<Vec<Alarm>>(body).and_then(|alarms| {
+                alarms.iter().try_for_each(Alarm::check_ranges)?;
+                Ok(Self::AlarmBatch(alarms))
+            }),
+            _ => return Err(DecodeError::new(0, "topic", "Unknown message topic")),
+        };
+
+        message.map_err(|error| error.shifted(offset))
+    }
+}
+
+/// Topic of the messages bearing `header` in single frames
+fn topic_of(header: u8) -> Option<&'static [u8]> {
+    REGISTRY
+        .iter()
+        .find(|(known, _)| *known == header)
+        .map(|(_, topic)| *topic)
+}
+
+/// Whether `topic` is the topic frame of a known message type
+fn is_known(topic: &[u8]) -> bool {
+    REGISTRY.iter().any(|(_, known)| *known == topic)
+}
+
+/// Alarms of a batch are tagged as a whole, internally tagged enums cannot carry bare sequences.
+mod alarm_batch_format {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::alarm::Alarm;
+
+    #[derive(Serialize)]
+    struct Batch<'a> {
+        alarms: &'a [Alarm],
+    }
+
+    #[derive(Deserialize)]
+    struct OwnedBatch {
+        alarms: Vec<Alarm>,
+    }
+
+    pub fn serialize<S: Serializer>(alarms: &[Alarm], serializer: S) -> Result<S::Ok, S::Error> {
+        Batch { alarms }.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Alarm>, D::Error> {
+        Ok(OwnedBatch::deserialize(deserializer)?.alarms)
+    }
+}
+
+/// Serde representation of the wrapped message alone, the topic or the header telling its type,
+/// for the binary layouts ([crate::codec] is not self-describing, it cannot decode the tagged
+/// representation of [Message]).
+struct Body<'a>(&'a Message);
+
+impl Serialize for Body<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Message::Alarm(alarm) => alarm.serialize(serializer),
+            Message::Clock(clock) => clock.serialize(serializer),
+            Message::Countdown(countdown) => countdown.serialize(serializer),
+            Message::Pomodoro(pomodoro) => pomodoro.serialize(serializer),
+            Message::Interval(interval) => interval.serialize(serializer),
+            Message::NextAlarm(next_alarm) => next_alarm.serialize(serializer),
+            Message::Bedtime(bedtime) => bedtime.serialize(serializer),
+            Message::Reminder(reminder) => reminder.serialize(serializer),
+            Message::WorldClock(world_clock) => world_clock.serialize(serializer),
+            Message::Sun(sun) => sun.serialize(serializer),
+            Message::SyncStatus(sync_status) => sync_status.serialize(serializer),
+            Message::ClockJump(clock_jump) => clock_jump.serialize(serializer),
+            Message::Speak(speak) => speak.serialize(serializer),
+            Message::Heartbeat(heartbeat) => heartbeat.serialize(serializer),
+            Message::AlarmBatch(alarms) => alarms.serialize(serializer),
+            Message::Unknown(..) => Err(S::Error::custom("Unknown message type")),
+        }
+    }
+}
+
+/// Topic frames actually used on the wire: [ALARM_TOPIC], [CLOCK_TOPIC], [COUNTDOWN_TOPIC],
+/// [POMODORO_TOPIC], [INTERVAL_TOPIC], [NEXT_ALARM_TOPIC], [BEDTIME_TOPIC], [REMINDER_TOPIC],
+/// [WORLD_CLOCK_TOPIC], [SUN_TOPIC], [SYNC_STATUS_TOPIC], [CLOCK_JUMP_TOPIC], [SPEAK_TOPIC],
+/// [HEARTBEAT_TOPIC] and [ALARM_BATCH_TOPIC] behind an optional prefix, so independent instances
+/// can share a broker/port without their clients cross-subscribing (see the
+/// `CLOCKROBUSTUS_TOPIC_PREFIX` env var), along with the [WireFormat] of the payload frames
+/// following them.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::{
+///     clock::ClockMessage,
+///     message::{Message, Topics, WireFormat},
+/// };
+///
+/// let topics = Topics::new("kitchen/");
+/// let message = Message::from(ClockMessage::default());
+/// let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+/// let len = message.encode_payload_into(&mut buffer).unwrap();
+///
+/// assert_eq!(topics.of(&message), b"kitchen/clock");
+/// assert_eq!(topics.decode(b"kitchen/clock", &buffer[..len]).unwrap(), message);
+/// assert!(Topics::new("bedroom/").decode(b"kitchen/clock", &buffer[..len]).is_err());
+///
+/// let json = Topics::new("").with_format(WireFormat::Json);
+/// let payload = message.to_json().unwrap();
+/// assert_eq!(json.decode(b"clock", payload.as_bytes()).unwrap(), message);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Topics {
+    prefix: Vec<u8>,
+    format: WireFormat,
+    alarm: Vec<u8>,
+    clock: Vec<u8>,
+    countdown: Vec<u8>,
+    pomodoro: Vec<u8>,
+    interval: Vec<u8>,
+    next_alarm: Vec<u8>,
+    bedtime: Vec<u8>,
+    reminder: Vec<u8>,
+    world_clock: Vec<u8>,
+    sun: Vec<u8>,
+    sync_status: Vec<u8>,
+    clock_jump: Vec<u8>,
+    speak: Vec<u8>,
+    heartbeat: Vec<u8>,
+    alarm_batch: Vec<u8>,
+}
+
+impl Topics {
+    pub fn new(prefix: &str) -> Self {
+        let topic = |name: &[u8]| [prefix.as_bytes(), name].concat();
+
+        Self {
+            prefix: prefix.as_bytes().to_vec(),
+            format: WireFormat::default(),
+            alarm: topic(ALARM_TOPIC),
+            clock: topic(CLOCK_TOPIC),
+            countdown: topic(COUNTDOWN_TOPIC),
+            pomodoro: topic(POMODORO_TOPIC),
+            interval: topic(INTERVAL_TOPIC),
+            next_alarm: topic(NEXT_ALARM_TOPIC),
+            bedtime: topic(BEDTIME_TOPIC),
+            reminder: topic(REMINDER_TOPIC),
+            world_clock: topic(WORLD_CLOCK_TOPIC),
+            sun: topic(SUN_TOPIC),
+            sync_status: topic(SYNC_STATUS_TOPIC),
+            clock_jump: topic(CLOCK_JUMP_TOPIC),
+            speak: topic(SPEAK_TOPIC),
+            heartbeat: topic(HEARTBEAT_TOPIC),
+            alarm_batch: topic(ALARM_BATCH_TOPIC),
+        }
+    }
+
+    /// Same topics, their payload frames being encoded in `format`
+    pub fn with_format(self, format: WireFormat) -> Self {
+        Self { format, ..self }
+    }
+
+    /// True if no prefix is used (legacy single frame messages are only understood then).
+    pub fn is_unprefixed(&self) -> bool {
+        self.prefix.is_empty()
+    }
+
+    /// Encoding of the payload frames
+    pub fn format(&self) -> WireFormat {
+        self.format
+    }
+
+    /// Topic frame of alarm messages
+    pub fn alarm(&self) -> &[u8] {
+        &self.alarm
+    }
+
+    /// Topic frame of clock messages
+    pub fn clock(&self) -> &[u8] {
+        &self.clock
+    }
+
+    /// Topic frame of countdown messages
+    pub fn countdown(&self) -> &[u8] {
+        &self.countdown
+    }
+
+    /// Topic frame of pomodoro messages
+    pub fn pomodoro(&self) -> &[u8] {
+        &self.pomodoro
+    }
+
+    /// Topic frame of interval timer messages
+    pub fn interval(&self) -> &[u8] {
+        &self.interval
+    }
+
+    /// Topic frame of next alarm messages
+    pub fn next_alarm(&self) -> &[u8] {
+        &self.next_alarm
+    }
+
+    /// Topic frame of bedtime reminder messages
+    pub fn bedtime(&self) -> &[u8] {
+        &self.bedtime
+    }
+
+    /// Topic frame of reminder messages
+    pub fn reminder(&self) -> &[u8] {
+        &self.reminder
+    }
+
+    /// Topic frame of world clock messages
+    pub fn world_clock(&self) -> &[u8] {
+        &self.world_clock
+    }
+
+    /// Topic frame of sunrise and sunset messages
+    pub fn sun(&self) -> &[u8] {
+        &self.sun
+    }
+
+    /// Topic frame of NTP synchronization status messages
+    pub fn sync_status(&self) -> &[u8] {
+        &self.sync_status
+    }
+
+    /// Topic frame of system clock jump messages
+    pub fn clock_jump(&self) -> &[u8] {
+        &self.clock_jump
+    }
+
+    /// Topic frame of speaking clock announcement messages
+    pub fn speak(&self) -> &[u8] {
+        &self.speak
+    }
+
+    /// Topic frame of heartbeat messages
+    pub fn heartbeat(&self) -> &[u8] {
+        &self.heartbeat
+    }
+
+    /// Topic frame of alarm batch messages
+    pub fn alarm_batch(&self) -> &[u8] {
+        &self.alarm_batch
+    }
+
+    /// Topic frame of the given message
+    pub fn of(&self, message: &Message) -> &[u8] {
+        match message {
+            Message::Alarm(_) => &self.alarm,
+            Message::Clock(_) => &self.clock,
+            Message::Countdown(_) => &self.countdown,
+            Message::Pomodoro(_) => &self.pomodoro,
+            Message::Interval(_) => &self.interval,
+            Message::NextAlarm(_) => &self.next_alarm,
+            Message::Bedtime(_) => &self.bedtime,
+            Message::Reminder(_) => &self.reminder,
+            Message::WorldClock(_) => &self.world_clock,
+            Message::Sun(_) => &self.sun,
+            Message::SyncStatus(_) => &self.sync_status,
+            Message::ClockJump(_) => &self.clock_jump,
+            Message::Speak(_) => &self.speak,
+            Message::Heartbeat(_) => &self.heartbeat,
+            Message::AlarmBatch(_) => &self.alarm_batch,
+            Message::Unknown(..) => &self.prefix,
+        }
+    }
+
+    /// True if `topic` is the (prefixed) topic frame of a known message type. Subscriptions being
+    /// prefixes, topics of types to come may match them (eg: `alarm` matching `alarm_group`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::message::Topics;
+    ///
+    /// let topics = Topics::new("kitchen/");
+    ///
+    /// assert!(topics.knows(b"kitchen/alarm"));
+    /// assert!(!topics.knows(b"kitchen/alarm_group"));
+    /// assert!(!topics.knows(b"alarm"));
+    /// ```
+    pub fn knows(&self, topic: &[u8]) -> bool {
+        topic
+            .strip_prefix(self.prefix.as_slice())
+            .is_some_and(|topic| REGISTRY.iter().any(|(_, known)| *known == topic))
+    }
+
+    /// Decodes a multipart message from its (prefixed) topic and payload frames.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the topic does not bear the prefix, or for the same reasons as
+    /// [Message::try_from_parts] (or [Message::try_from_json_parts] and
+    /// [Message::try_from_msgpack_parts]).
+    pub fn decode(&self, topic: &[u8], payload: &[u8]) -> Result<Message, ClockError> {
+        let topic = topic
+            .strip_prefix(self.prefix.as_slice())
+            .ok_or(ClockError("Unknown message topic"))?;
+
+        match self.format {
+            WireFormat::Binary => Message::try_from_parts(topic, payload),
+            WireFormat::Json => Message::try_from_json_parts(topic, payload),
+            WireFormat::MessagePack => Message::try_from_msgpack_parts(topic, payload),
+        }
+    }
+
+    /// Strict counterpart of [Topics::decode] (see [Message::try_from_strict]). JSON and
+    /// MessagePack payloads are always decoded strictly, errors only telling the payload is
+    /// wrong.
+    pub fn decode_strict(&self, topic: &[u8], payload: &[u8]) -> Result<Message, DecodeError> {
+        let topic = topic
+            .strip_prefix(self.prefix.as_slice())
+            .ok_or(DecodeError::new(0, "topic", "Unknown message topic"))?;
+
+        match self.format {
+            WireFormat::Binary => Message::try_from_parts_strict(topic, payload),
+            WireFormat::Json => Message::try_from_json_parts(topic, payload)
+                .map_err(|_| DecodeError::new(0, "payload", "Invalid JSON message")),
+            WireFormat::MessagePack => Message::try_from_msgpack_parts(topic, payload)
+                .map_err(|_| DecodeError::new(0, "payload", "Invalid MessagePack message")),
+        }
+    }
+}
+
+impl Default for Topics {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+// Const context maximum, used for buffer sizes
+const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::alarm::{ActiveDays, Rotation, RotationWeek};
+
+    fn labelled_alarm(id: i64) -> Alarm {
+        let week = |hour, active_days| {
+            Some(RotationWeek {
+                hour,
+                minute: 0,
+                seconds: 0,
+                active_days,
+            })
+        };
+        let mut alarm = Alarm {
+            id: Some(id),
+            label: "Wake up".to_string(),
+            active_days: ActiveDays(0x1F),
+            rotation: Some(Rotation {
+                start: NaiveDate::from_ymd_opt(2023, 7, 3).unwrap(),
+                weeks: vec![week(5, None), week(9, Some(ActiveDays(0x23))), None],
+            }),
+            ..Default::default()
+        };
+        alarm
+            .metadata
+            .insert("snoozeSound".to_string(), "birds".into());
+
+        alarm
+    }
+
+    #[test]
+    fn test_unknown_not_serializable() {
+        let unknown = Message::Unknown(0xF0, vec![0x01, 0x02]);
+
+        assert!(serde_json::to_string(&unknown).is_err());
+        assert!(rmp_serde::to_vec_named(&unknown).is_err());
+        assert!(unknown.to_json().is_err());
+        assert!(unknown.to_msgpack().is_err());
+    }
+
+    #[test]
+    fn test_tagged_round_trips() {
+        let alarm = Message::from(labelled_alarm(1));
+        let batch = Message::AlarmBatch(vec![labelled_alarm(2), labelled_alarm(3)]);
+
+        for message in [alarm, batch] {
+            let json = message.to_json().unwrap();
+            let msgpack = message.to_msgpack().unwrap();
+
+            assert_eq!(
+                Message::try_from_json_parts(message.topic(), json.as_bytes()).unwrap(),
+                message
+            );
+            assert_eq!(
+                Message::try_from_msgpack_parts(message.topic(), &msgpack).unwrap(),
+                message
+            );
+            // Deserializers lending no strings
+            let value = serde_json::to_value(&message).unwrap();
+            assert_eq!(serde_json::from_value::<Message>(value).unwrap(), message);
+            assert_eq!(
+                rmp_serde::from_read::<_, Message>(msgpack.as_slice()).unwrap(),
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_buffer_fallback() {
+        let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+        let longest = Message::from(ReminderMessage {
+            id: i64::MIN,
+            due_at: i64::MIN,
+            text: "a".repeat(ReminderMessage::MAX_TEXT_LEN),
+        });
+        let alarm = Message::from(Alarm {
+            label: "a".repeat(Message::MAX_ENCODED_LEN),
+            ..labelled_alarm(1)
+        });
+
+        assert_eq!(
+            longest.encode_payload_into(&mut buffer).unwrap(),
+            Message::MAX_ENCODED_LEN - 1
+        );
+        assert!(alarm.encode_payload_into(&mut buffer).is_err());
+        assert_eq!(
+            Message::try_from_parts(ALARM_TOPIC, &alarm.payload().unwrap()).unwrap(),
+            alarm
+        );
+    }
+
+    #[test]
+    fn test_strict_ranges() {
+        let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+        let mut clock = ClockMessage::new(12, 0, 0).unwrap();
+        clock.month = 13;
+        let alarm = Alarm {
+            hour: 99,
+            ..Default::default()
+        };
+
+        for (message, field) in [
+            (Message::from(clock), "month"),
+            (Message::from(alarm.clone()), "hour"),
+            (Message::AlarmBatch(vec![labelled_alarm(1), alarm]), "hour"),
+        ] {
+            let len = message.encode_into(&mut buffer).unwrap();
+
+            assert_eq!(Message::try_from(&buffer[..len]).unwrap(), message);
+            let error = Message::try_from_strict(&buffer[..len]).unwrap_err();
+            assert_eq!((error.offset, error.field), (ENVELOPE_LEN + 1, field));
+        }
+
+        let len = Message::from(Alarm {
+            minute: 60,
+            ..Default::default()
+        })
+        .encode_payload_into(&mut buffer)
+        .unwrap();
+        let error = Message::try_from_parts_strict(ALARM_TOPIC, &buffer[..len]).unwrap_err();
+        assert_eq!(
+            (error.offset, error.field, error.reason),
+            (ENVELOPE_LEN, "minute", "Value out of range")
+        );
+    }
+
+    #[test]
+    fn test_legacy_layouts() {
+        let mut buffer = [0u8; Message::MAX_ENCODED_LEN];
+        let len = Message::from(CountdownMessage::default())
+            .encode_payload_into(&mut buffer)
+            .unwrap();
+        buffer[2] = 1;
+
+        assert!(Message::try_from_parts(COUNTDOWN_TOPIC, &buffer[..len]).is_err());
+        let error = Message::try_from_parts_strict(COUNTDOWN_TOPIC, &buffer[..len]).unwrap_err();
+        assert_eq!(
+            (error.offset, error.reason),
+            (ENVELOPE_LEN, "No legacy layout for message type")
+        );
+        let error = Message::try_from_parts_strict(b"weather", &buffer[..len]).unwrap_err();
+        assert_eq!(error.reason, "Unknown message topic");
+
+        assert_eq!(
+            Message::try_from_parts(ALARM_TOPIC, &[0xC1, 0x0C, 0x01, 0x01, 12, 0, 0]).unwrap(),
+            Message::try_from_parts(ALARM_TOPIC, &[0x01, 12, 0, 0]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_tag_matches_topic() {
+        let json = Message::from(labelled_alarm(1)).to_json().unwrap();
+
+        assert!(json.starts_with(r#"{"type":"alarm","#));
+        assert!(Message::try_from_json_parts(ALARM_BATCH_TOPIC, json.as_bytes()).is_err());
+        assert!(Message::try_from_json_parts(b"weather", json.as_bytes()).is_err());
+    }
 }
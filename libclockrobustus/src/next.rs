@@ -0,0 +1,185 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alarm::Alarm,
+    codec,
+    error::{ClockError, DecodeError},
+};
+
+/// Next alarm to ring, as published by the daemon whenever it changes (at least every minute, as
+/// the time left decreases), so clients can display "next alarm in 6h 12m" without scheduling
+/// anything themselves. Labels are not carried, clients match alarms by id.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::{Local, TimeZone};
+/// use libclockrobustus::{
+///     alarm::{ActiveDays, Alarm},
+///     next::NextAlarmMessage,
+/// };
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+/// // 2023-07-03 was a Monday
+/// let now = Local.with_ymd_and_hms(2023, 7, 3, 1, 0, 0).unwrap();
+///
+/// assert_eq!(NextAlarmMessage::find(&conn, now).unwrap().id, None);
+///
+/// Alarm { active_days: ActiveDays(0x01), hour: 7, minute: 12, ..Default::default() }
+///     .save(&conn)
+///     .unwrap();
+/// let next = NextAlarmMessage::find(&conn, now).unwrap();
+///
+/// assert_eq!(next.id, Some(1));
+/// assert_eq!(next.minutes_left, 6 * 60 + 12);
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NextAlarmMessage {
+    /// Id of the next alarm, [None] if no alarm is upcoming
+    pub id: Option<i64>,
+    /// Unix timestamp (seconds) the alarm rings at (jitter included), 0 if none
+    pub rings_at: i64,
+    /// Minutes left before the alarm rings, rounded up
+    pub minutes_left: u32,
+}
+
+impl NextAlarmMessage {
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits)
+    pub const MAX_ENCODED_LEN: usize = 26;
+
+    /// Finds the earliest enabled alarm ringing after `now`, skipped occurrences excluded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn find(conn: &sqlite::Connection, now: DateTime<Local>) -> Result<Self, ClockError> {
+        let mut next: Option<(DateTime<Local>, i64)> = None;
+
+        for alarm in Alarm::all(conn)? {
+            let (Some(id), Some(ring)) = (alarm.id, alarm.next_ring(conn, now)?) else {
+                continue;
+            };
+            if next.is_none_or(|(earliest, _)| ring < earliest) {
+                next = Some((ring, id));
+            }
+        }
+
+        Ok(match next {
+            Some((ring, id)) => Self {
+                id: Some(id),
+                rings_at: ring.timestamp(),
+                minutes_left: ((ring.timestamp() - now.timestamp()).max(0) as u32).div_ceil(60),
+            },
+            None => Self::default(),
+        })
+    }
+
+    /// Writes the binary representation at the start of a caller-provided buffer, returning the
+    /// number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is shorter than [NextAlarmMessage::MAX_ENCODED_LEN].
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        codec::encode(self, buffer)
+    }
+
+    /// Strict counterpart of the [TryFrom] conversion from bytes: truncated messages and
+    /// trailing bytes are rejected (see [codec::decode_strict]).
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        codec::decode_strict(bytes)
+    }
+}
+
+impl TryFrom<Vec<u8>> for NextAlarmMessage {
+    type Error = ClockError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        codec::decode(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use sqlite::Connection;
+
+    use super::*;
+    use crate::alarm::ActiveDays;
+
+    #[test]
+    fn test_find() {
+        let conn = Connection::open(":memory:").unwrap();
+        let now = Local.with_ymd_and_hms(2023, 7, 3, 6, 59, 30).unwrap();
+        let alarm = |hour: u8, enabled: bool| Alarm {
+            active_days: ActiveDays::EVERYDAY,
+            hour,
+            enabled,
+            ..Default::default()
+        };
+
+        alarm(9, true).save(&conn).unwrap();
+        alarm(7, false).save(&conn).unwrap();
+        alarm(8, true).save(&conn).unwrap();
+        assert_eq!(
+            NextAlarmMessage::find(&conn, now).unwrap(),
+            NextAlarmMessage {
+                id: Some(3),
+                rings_at: Local
+                    .with_ymd_and_hms(2023, 7, 3, 8, 0, 0)
+                    .unwrap()
+                    .timestamp(),
+                minutes_left: 61,
+            }
+        );
+
+        // Skipped occurrences do not count
+        let eight = Alarm::by_id(&conn, 3).unwrap().unwrap();
+        eight.skip(&conn, now.date_naive()).unwrap();
+        assert_eq!(NextAlarmMessage::find(&conn, now).unwrap().id, Some(1));
+    }
+
+    #[test]
+    fn test_binary_conversion() {
+        let none = NextAlarmMessage::default();
+        let next = NextAlarmMessage {
+            id: Some(4),
+            rings_at: 1_700_000_000,
+            minutes_left: 372,
+        };
+        let longest = NextAlarmMessage {
+            id: Some(i64::MIN),
+            rings_at: i64::MIN,
+            minutes_left: u32::MAX,
+        };
+        let mut buffer = [0u8; NextAlarmMessage::MAX_ENCODED_LEN + 1];
+
+        for message in [none, next, longest] {
+            let len = message.encode_into(&mut buffer).unwrap();
+            assert_eq!(
+                NextAlarmMessage::try_from(buffer.to_vec()).unwrap(),
+                message
+            );
+            assert_eq!(
+                NextAlarmMessage::try_from_strict(&buffer[..len]).unwrap(),
+                message
+            );
+            assert!(NextAlarmMessage::try_from(buffer[..len - 1].to_vec()).is_err());
+        }
+
+        assert_eq!(
+            longest.encode_into(&mut buffer).unwrap(),
+            NextAlarmMessage::MAX_ENCODED_LEN
+        );
+        buffer[0] = 2;
+        assert_eq!(
+            NextAlarmMessage::try_from_strict(&buffer[..NextAlarmMessage::MAX_ENCODED_LEN])
+                .unwrap_err()
+                .reason,
+            "Invalid message body"
+        );
+    }
+}
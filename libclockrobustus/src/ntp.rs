@@ -0,0 +1,343 @@
+use serde::{Deserialize, Serialize};
+use std::{net::UdpSocket, time::Duration};
+
+use crate::{
+    codec,
+    error::{ClockError, DecodeError},
+    latency,
+};
+
+/// Seconds between the NTP epoch (1900-01-01) and the Unix epoch
+const NTP_UNIX_OFFSET: i64 = 2_208_988_800;
+/// Size of an NTP packet without extensions
+const PACKET_LEN: usize = 48;
+/// First byte of a request: no leap second warning, version 4, client mode
+const CLIENT_HEADER: u8 = 0b00_100_011;
+/// Mode of a server reply
+const SERVER_MODE: u8 = 4;
+/// Time given to the server to answer
+pub const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Unix milliseconds from an NTP timestamp (seconds since 1900 and a 32 bits fraction)
+fn ntp_to_unix_ms(bytes: &[u8]) -> i64 {
+    let seconds = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64;
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as i64;
+
+    // Rounded, so that converting back and forth is lossless
+    (seconds - NTP_UNIX_OFFSET) * 1000 + ((fraction * 1000 + (1 << 31)) >> 32)
+}
+
+/// NTP timestamp from Unix milliseconds
+fn unix_ms_to_ntp(ms: i64) -> [u8; 8] {
+    let seconds = (ms.div_euclid(1000) + NTP_UNIX_OFFSET) as u32;
+    let fraction = ((ms.rem_euclid(1000) << 32) / 1000) as u32;
+    let mut bytes = [0u8; 8];
+
+    bytes[..4].copy_from_slice(&seconds.to_be_bytes());
+    bytes[4..].copy_from_slice(&fraction.to_be_bytes());
+    bytes
+}
+
+/// Outcome of one exchange with an NTP server.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct NtpSample {
+    /// Milliseconds to add to the local clock to get the server time
+    pub offset_ms: i64,
+    /// Round trip time of the exchange, milliseconds
+    pub delay_ms: u32,
+    /// Distance of the server to a reference clock (1 for a reference clock itself)
+    pub stratum: u8,
+}
+
+impl NtpSample {
+    /// Reads the reply of a server to a request sent at `sent_ms` and received at
+    /// `received_ms` (local Unix milliseconds).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reply is truncated, is not an answer to the request or comes from an
+    /// unsynchronized server.
+    pub fn from_reply(reply: &[u8], sent_ms: i64, received_ms: i64) -> Result<Self, ClockError> {
+        if reply.len() < PACKET_LEN {
+            return Err(ClockError("NTP reply too short"));
+        }
+        if reply[0] & 0b111 != SERVER_MODE {
+            return Err(ClockError("Not an NTP server reply"));
+        }
+        // The server echoes the transmit timestamp of the request, which rules out stray replies
+        if reply[24..32] != unix_ms_to_ntp(sent_ms) {
+            return Err(ClockError("NTP reply to another request"));
+        }
+        // 0 is a "kiss-o'-death" (eg: rate limiting), 16 an unsynchronized server
+        if !(1..=15).contains(&reply[1]) {
+            return Err(ClockError("NTP server not synchronized"));
+        }
+
+        let server_received_ms = ntp_to_unix_ms(&reply[32..40]);
+        let server_sent_ms = ntp_to_unix_ms(&reply[40..48]);
+
+        Ok(Self {
+            offset_ms: ((server_received_ms - sent_ms) + (server_sent_ms - received_ms)) / 2,
+            delay_ms: ((received_ms - sent_ms) - (server_sent_ms - server_received_ms)).max(0)
+                as u32,
+            stratum: reply[1],
+        })
+    }
+
+    /// Queries `server` (`host:port`) once, waiting at most [QUERY_TIMEOUT] for the reply.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the server cannot be reached or if its reply is invalid.
+    pub fn query(server: &str) -> Result<Self, ClockError> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        let mut request = [0u8; PACKET_LEN];
+        let mut reply = [0u8; PACKET_LEN];
+
+        socket.set_read_timeout(Some(QUERY_TIMEOUT))?;
+        socket.connect(server)?;
+
+        let sent_ms = latency::now_ms()? as i64;
+        request[0] = CLIENT_HEADER;
+        request[40..48].copy_from_slice(&unix_ms_to_ntp(sent_ms));
+        socket.send(&request)?;
+
+        let len = socket.recv(&mut reply)?;
+
+        Self::from_reply(&reply[..len], sent_ms, latency::now_ms()? as i64)
+    }
+}
+
+/// How far the daemon clock is from an NTP server, as published after each query, so that users
+/// know how trustworthy the displayed time is.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::ntp::{NtpSample, SyncStatusMessage};
+///
+/// let first = SyncStatusMessage::default().next(
+///     Ok(NtpSample { offset_ms: 100, delay_ms: 20, stratum: 2 }),
+///     0,
+/// );
+/// // 60 ms more behind after 10 minutes
+/// let second = first.next(
+///     Ok(NtpSample { offset_ms: 160, delay_ms: 20, stratum: 2 }),
+///     600_000,
+/// );
+///
+/// assert!(second.reachable);
+/// assert_eq!(second.drift_ppm, 100);
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatusMessage {
+    /// Whether the server answered the last query. If not, the other values are the last known
+    pub reachable: bool,
+    pub stratum: u8,
+    /// Milliseconds to add to the daemon clock to get the server time
+    pub offset_ms: i64,
+    /// Round trip time of the query, milliseconds
+    pub delay_ms: u32,
+    /// Change of the offset over time, in parts per million (0 until two queries succeeded)
+    pub drift_ppm: i32,
+    /// Unix timestamp (milliseconds) of the last query
+    pub checked_at: i64,
+}
+
+impl SyncStatusMessage {
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits)
+    pub const MAX_ENCODED_LEN: usize = 32;
+
+    /// Status following this one after a query made at `checked_at` (Unix milliseconds), the
+    /// drift being measured against the last successful query.
+    pub fn next(&self, sample: Result<NtpSample, ClockError>, checked_at: i64) -> Self {
+        let sample = match sample {
+            Ok(sample) => sample,
+            Err(_) => {
+                return Self {
+                    reachable: false,
+                    checked_at,
+                    ..*self
+                }
+            }
+        };
+        let elapsed = checked_at - self.checked_at;
+        let drift_ppm = if self.reachable && elapsed > 0 {
+            ((sample.offset_ms - self.offset_ms) * 1_000_000 / elapsed) as i32
+        } else {
+            self.drift_ppm
+        };
+
+        Self {
+            reachable: true,
+            stratum: sample.stratum,
+            offset_ms: sample.offset_ms,
+            delay_ms: sample.delay_ms,
+            drift_ppm,
+            checked_at,
+        }
+    }
+
+    /// Writes the binary representation at the start of a caller-provided buffer, returning the
+    /// number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is shorter than [SyncStatusMessage::MAX_ENCODED_LEN].
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        codec::encode(self, buffer)
+    }
+
+    /// Strict counterpart of the [TryFrom] conversion from bytes: truncated messages, trailing
+    /// bytes (see [codec::decode_strict]) and an out of range stratum are rejected.
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let message: Self = codec::decode_strict(bytes)?;
+
+        DecodeError::check_range(message.stratum, "stratum", 0..=15)?;
+        Ok(message)
+    }
+}
+
+impl TryFrom<Vec<u8>> for SyncStatusMessage {
+    type Error = ClockError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        codec::decode(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(sent_ms: i64, server_received_ms: i64, server_sent_ms: i64) -> [u8; PACKET_LEN] {
+        let mut reply = [0u8; PACKET_LEN];
+
+        reply[0] = 0b00_100_100;
+        reply[1] = 2;
+        reply[24..32].copy_from_slice(&unix_ms_to_ntp(sent_ms));
+        reply[32..40].copy_from_slice(&unix_ms_to_ntp(server_received_ms));
+        reply[40..48].copy_from_slice(&unix_ms_to_ntp(server_sent_ms));
+        reply
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let ms = 1_700_000_000_123;
+
+        assert_eq!(ntp_to_unix_ms(&unix_ms_to_ntp(ms)), ms);
+        assert_eq!(ntp_to_unix_ms(&unix_ms_to_ntp(0)), 0);
+    }
+
+    #[test]
+    fn test_from_reply() {
+        let sent_ms = 1_700_000_000_000;
+        // Local clock 500 ms behind, 40 ms round trip of which 10 ms spent in the server
+        let reply = reply(sent_ms, sent_ms + 515, sent_ms + 525);
+
+        assert_eq!(
+            NtpSample::from_reply(&reply, sent_ms, sent_ms + 40).unwrap(),
+            NtpSample {
+                offset_ms: 500,
+                delay_ms: 30,
+                stratum: 2
+            }
+        );
+        assert!(NtpSample::from_reply(&reply[..47], sent_ms, sent_ms + 40).is_err());
+        // Answer to another request
+        assert!(NtpSample::from_reply(&reply, sent_ms + 1, sent_ms + 40).is_err());
+
+        let mut kiss_of_death = reply;
+        kiss_of_death[1] = 0;
+        assert!(NtpSample::from_reply(&kiss_of_death, sent_ms, sent_ms + 40).is_err());
+    }
+
+    #[test]
+    fn test_next() {
+        let sample = NtpSample {
+            offset_ms: -20,
+            delay_ms: 15,
+            stratum: 3,
+        };
+        let synced = SyncStatusMessage::default().next(Ok(sample), 1_000);
+        assert!(synced.reachable);
+        assert_eq!(synced.drift_ppm, 0);
+
+        // Unreachable servers keep the last known values
+        let lost = synced.next(Err(ClockError("timeout")), 2_000);
+        assert_eq!(
+            lost,
+            SyncStatusMessage {
+                reachable: false,
+                checked_at: 2_000,
+                ..synced
+            }
+        );
+
+        // Drift is only measured between two successful queries
+        let back = lost.next(Ok(sample), 3_000);
+        assert_eq!(back.drift_ppm, 0);
+        let drifting = back.next(
+            Ok(NtpSample {
+                offset_ms: -80,
+                ..sample
+            }),
+            603_000,
+        );
+        assert_eq!(drifting.drift_ppm, -100);
+    }
+
+    #[test]
+    fn test_binary_conversion() {
+        let message = SyncStatusMessage {
+            reachable: true,
+            stratum: 2,
+            offset_ms: -1234,
+            delay_ms: 42,
+            drift_ppm: -15,
+            checked_at: 1_700_000_000_000,
+        };
+        let longest = SyncStatusMessage {
+            reachable: true,
+            stratum: u8::MAX,
+            offset_ms: i64::MIN,
+            delay_ms: u32::MAX,
+            drift_ppm: i32::MIN,
+            checked_at: i64::MIN,
+        };
+        let mut buffer = [0u8; SyncStatusMessage::MAX_ENCODED_LEN + 1];
+
+        assert_eq!(
+            longest.encode_into(&mut buffer).unwrap(),
+            SyncStatusMessage::MAX_ENCODED_LEN
+        );
+
+        let len = message.encode_into(&mut buffer).unwrap();
+
+        assert_eq!(
+            SyncStatusMessage::try_from(buffer.to_vec()).unwrap(),
+            message
+        );
+        assert!(SyncStatusMessage::try_from(buffer[..len - 1].to_vec()).is_err());
+        assert_eq!(
+            SyncStatusMessage::try_from_strict(&buffer[..len]).unwrap(),
+            message
+        );
+        assert_eq!(
+            SyncStatusMessage::try_from_strict(&buffer[..len + 1])
+                .unwrap_err()
+                .reason,
+            "Trailing bytes"
+        );
+
+        buffer[0] = 2;
+        assert_eq!(
+            SyncStatusMessage::try_from_strict(&buffer[..len])
+                .unwrap_err()
+                .reason,
+            "Invalid message body"
+        );
+    }
+}
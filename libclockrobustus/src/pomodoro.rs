@@ -0,0 +1,428 @@
+use serde::{Deserialize, Serialize};
+use sqlite::State;
+
+use crate::{
+    codec,
+    error::{ClockError, DecodeError},
+};
+
+const TNAME: &str = "pomodoro";
+
+/// Durations of the pomodoro phases.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PomodoroSettings {
+    pub work_minutes: u16,
+    pub short_break_minutes: u16,
+    pub long_break_minutes: u16,
+    /// Number of work sessions between two long breaks
+    pub long_break_every: u8,
+}
+
+impl Default for PomodoroSettings {
+    fn default() -> Self {
+        Self {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            long_break_every: 4,
+        }
+    }
+}
+
+impl PomodoroSettings {
+    /// The settings themselves if usable.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a duration or the number of sessions between long breaks is zero.
+    pub fn validate(self) -> Result<Self, ClockError> {
+        if self.work_minutes == 0
+            || self.short_break_minutes == 0
+            || self.long_break_minutes == 0
+            || self.long_break_every == 0
+        {
+            return Err(ClockError("Invalid pomodoro settings"));
+        }
+
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PomodoroPhase {
+    /// Not started, or stopped
+    #[default]
+    Idle,
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl PomodoroPhase {
+    fn as_byte(self) -> u8 {
+        match self {
+            Self::Idle => 0,
+            Self::Work => 1,
+            Self::ShortBreak => 2,
+            Self::LongBreak => 3,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, ClockError> {
+        match byte {
+            0 => Ok(Self::Idle),
+            1 => Ok(Self::Work),
+            2 => Ok(Self::ShortBreak),
+            3 => Ok(Self::LongBreak),
+            _ => Err(ClockError("Unknown pomodoro phase")),
+        }
+    }
+}
+
+/// Work/break cycles kept by the daemon: work sessions alternate with short breaks, a long break
+/// coming after every [PomodoroSettings::long_break_every] sessions. Times are milliseconds since
+/// the Unix epoch, like [crate::timer::Timer]. The phases follow each other on their own, the
+/// daemon publishing a [PomodoroMessage] on every change.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::pomodoro::{Pomodoro, PomodoroPhase, PomodoroSettings};
+///
+/// let mut pomodoro = Pomodoro::default();
+///
+/// pomodoro.start(Some(PomodoroSettings::default()), 0).unwrap();
+/// assert_eq!(pomodoro.message().phase, PomodoroPhase::Work);
+/// assert!(!pomodoro.advance(60_000));
+///
+/// // 25 minutes later
+/// assert!(pomodoro.advance(25 * 60_000));
+/// assert_eq!(pomodoro.message().phase, PomodoroPhase::ShortBreak);
+/// assert_eq!(pomodoro.message().ends_at_ms, 30 * 60_000);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Pomodoro {
+    settings: PomodoroSettings,
+    phase: PomodoroPhase,
+    /// Start of the current phase
+    started_at: u64,
+    /// Work sessions completed since the start
+    completed: u32,
+}
+
+impl Pomodoro {
+    // Essential db check
+    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER PRIMARY KEY CHECK (id = 0),
+            settings TEXT NOT NULL,
+            phase INTEGER NOT NULL,
+            started_at INTEGER NOT NULL,
+            completed INTEGER NOT NULL
+            )",
+            TNAME
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    /// Starts a new cycle with a work session, with new settings if given.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a duration or the number of sessions between long breaks is zero.
+    pub fn start(
+        &mut self,
+        settings: Option<PomodoroSettings>,
+        now: u64,
+    ) -> Result<(), ClockError> {
+        let settings = settings.unwrap_or(self.settings).validate()?;
+
+        *self = Self {
+            settings,
+            phase: PomodoroPhase::Work,
+            started_at: now,
+            completed: 0,
+        };
+        Ok(())
+    }
+
+    /// Stops the cycle, the settings are kept.
+    pub fn stop(&mut self) {
+        *self = Self {
+            settings: self.settings,
+            ..Default::default()
+        };
+    }
+
+    /// Ends the current phase early, moving on to the next one.
+    pub fn skip(&mut self, now: u64) {
+        if self.phase != PomodoroPhase::Idle {
+            self.next_phase(now);
+        }
+    }
+
+    fn duration_ms(&self) -> u64 {
+        let minutes = match self.phase {
+            PomodoroPhase::Idle => 0,
+            PomodoroPhase::Work => self.settings.work_minutes,
+            PomodoroPhase::ShortBreak => self.settings.short_break_minutes,
+            PomodoroPhase::LongBreak => self.settings.long_break_minutes,
+        };
+
+        minutes as u64 * 60_000
+    }
+
+    fn next_phase(&mut self, started_at: u64) {
+        self.phase = match self.phase {
+            PomodoroPhase::Work => {
+                self.completed += 1;
+                if self
+                    .completed
+                    .is_multiple_of(self.settings.long_break_every as u32)
+                {
+                    PomodoroPhase::LongBreak
+                } else {
+                    PomodoroPhase::ShortBreak
+                }
+            }
+            PomodoroPhase::Idle => PomodoroPhase::Idle,
+            PomodoroPhase::ShortBreak | PomodoroPhase::LongBreak => PomodoroPhase::Work,
+        };
+        self.started_at = started_at;
+    }
+
+    /// Moves on to the phase running at `now`, returning true if the phase changed. Phases
+    /// elapsed in the meantime (eg: while the daemon was stopped) are gone through, keeping the
+    /// rhythm.
+    pub fn advance(&mut self, now: u64) -> bool {
+        let mut changed = false;
+
+        while self.phase != PomodoroPhase::Idle && now >= self.started_at + self.duration_ms() {
+            self.next_phase(self.started_at + self.duration_ms());
+            changed = true;
+        }
+
+        changed
+    }
+
+    /// What clients get to display the cycle.
+    pub fn message(&self) -> PomodoroMessage {
+        PomodoroMessage {
+            phase: self.phase,
+            completed: self.completed,
+            ends_at_ms: match self.phase {
+                PomodoroPhase::Idle => 0,
+                _ => self.started_at + self.duration_ms(),
+            },
+        }
+    }
+
+    /// Stores the cycle, so a restarted daemon resumes it (see [Pomodoro::load]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered.
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "INSERT OR REPLACE INTO {} (id, settings, phase, started_at, completed)
+            VALUES (0, :settings, :phase, :started_at, :completed)",
+            TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":settings", serde_json::to_string(&self.settings)?.as_str()))?;
+        statement.bind((":phase", self.phase.as_byte() as i64))?;
+        statement.bind((":started_at", self.started_at as i64))?;
+        statement.bind((":completed", self.completed as i64))?;
+        statement.next()?;
+
+        Ok(())
+    }
+
+    /// Restores the stored cycle (an idle one if none was stored).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered or if the stored settings are invalid.
+    pub fn load(conn: &sqlite::Connection) -> Result<Self, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("SELECT * FROM {} WHERE id = 0", TNAME);
+        let mut statement = conn.prepare(query)?;
+
+        if let Ok(State::Row) = statement.next() {
+            Ok(Self {
+                settings: serde_json::from_str::<PomodoroSettings>(
+                    &statement.read::<String, _>("settings")?,
+                )?
+                .validate()?,
+                phase: PomodoroPhase::from_byte(statement.read::<i64, _>("phase")? as u8)?,
+                started_at: statement.read::<i64, _>("started_at")? as u64,
+                completed: statement.read::<i64, _>("completed")? as u32,
+            })
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+/// Current pomodoro phase, as published by the daemon when it changes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PomodoroMessage {
+    pub phase: PomodoroPhase,
+    /// Work sessions completed since the start
+    pub completed: u32,
+    /// End of the phase (milliseconds since the Unix epoch), 0 while idle
+    pub ends_at_ms: u64,
+}
+
+impl PomodoroMessage {
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits)
+    pub const MAX_ENCODED_LEN: usize = 16;
+
+    /// Writes the binary representation at the start of a caller-provided buffer, returning the
+    /// number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is shorter than [PomodoroMessage::MAX_ENCODED_LEN].
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        codec::encode(self, buffer)
+    }
+
+    /// Strict counterpart of the [TryFrom] conversion from bytes: truncated messages and
+    /// trailing bytes are rejected (see [codec::decode_strict]).
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        codec::decode_strict(bytes)
+    }
+}
+
+impl TryFrom<Vec<u8>> for PomodoroMessage {
+    type Error = ClockError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        codec::decode(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn test_cycles() {
+        let settings = PomodoroSettings {
+            work_minutes: 2,
+            short_break_minutes: 1,
+            long_break_minutes: 3,
+            long_break_every: 2,
+        };
+        let mut pomodoro = Pomodoro::default();
+
+        assert!(!pomodoro.advance(1_000_000));
+        assert!(pomodoro
+            .start(
+                Some(PomodoroSettings {
+                    long_break_every: 0,
+                    ..settings
+                }),
+                0
+            )
+            .is_err());
+
+        pomodoro.start(Some(settings), 0).unwrap();
+        // Work, short break, work, then a long break
+        assert!(pomodoro.advance(5 * 60_000));
+        assert_eq!(
+            pomodoro.message(),
+            PomodoroMessage {
+                phase: PomodoroPhase::LongBreak,
+                completed: 2,
+                ends_at_ms: 8 * 60_000,
+            }
+        );
+
+        pomodoro.skip(6 * 60_000);
+        assert_eq!(pomodoro.message().phase, PomodoroPhase::Work);
+        assert_eq!(pomodoro.message().ends_at_ms, 8 * 60_000);
+
+        pomodoro.stop();
+        assert_eq!(pomodoro.message().phase, PomodoroPhase::Idle);
+        assert!(!pomodoro.advance(10 * 60_000));
+
+        // Settings are kept
+        pomodoro.start(None, 0).unwrap();
+        assert_eq!(pomodoro.message().ends_at_ms, 2 * 60_000);
+    }
+
+    #[test]
+    fn test_persistence() {
+        let conn = Connection::open(":memory:").unwrap();
+        let mut pomodoro = Pomodoro::default();
+
+        assert_eq!(Pomodoro::load(&conn).unwrap(), pomodoro);
+
+        pomodoro.start(None, 1_000).unwrap();
+        pomodoro.save(&conn).unwrap();
+        assert_eq!(Pomodoro::load(&conn).unwrap(), pomodoro);
+
+        // Would never get a long break
+        Pomodoro {
+            settings: PomodoroSettings {
+                long_break_every: 0,
+                ..Default::default()
+            },
+            ..pomodoro
+        }
+        .save(&conn)
+        .unwrap();
+        assert!(Pomodoro::load(&conn).is_err());
+    }
+
+    #[test]
+    fn test_binary_conversion() {
+        let message = PomodoroMessage {
+            phase: PomodoroPhase::ShortBreak,
+            completed: 3,
+            ends_at_ms: 1_700_000_000_000,
+        };
+        let longest = PomodoroMessage {
+            phase: PomodoroPhase::LongBreak,
+            completed: u32::MAX,
+            ends_at_ms: u64::MAX,
+        };
+        let mut buffer = [0u8; PomodoroMessage::MAX_ENCODED_LEN];
+
+        assert_eq!(
+            longest.encode_into(&mut buffer).unwrap(),
+            PomodoroMessage::MAX_ENCODED_LEN
+        );
+
+        let len = message.encode_into(&mut buffer).unwrap();
+
+        assert_eq!(PomodoroMessage::try_from(buffer.to_vec()).unwrap(), message);
+        assert!(PomodoroMessage::try_from(buffer[..len - 1].to_vec()).is_err());
+        assert_eq!(
+            PomodoroMessage::try_from_strict(&buffer[..len]).unwrap(),
+            message
+        );
+
+        buffer[0] = 4;
+        assert!(PomodoroMessage::try_from(buffer.to_vec()).is_err());
+        assert_eq!(
+            PomodoroMessage::try_from_strict(&buffer[..len])
+                .unwrap_err()
+                .reason,
+            "Invalid message body"
+        );
+    }
+}
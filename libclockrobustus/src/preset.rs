@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use sqlite::State;
+
+use crate::error::ClockError;
+
+const TNAME: &str = "timer_presets";
+
+/// Named timer duration (eg: "Tea" for 3 minutes), offered by clients as a one-tap timer.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::preset::TimerPreset;
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+/// let tea = TimerPreset { id: None, name: "Tea".to_string(), duration_ms: 180_000 };
+///
+/// tea.save(&conn).unwrap();
+/// let presets = TimerPreset::all(&conn).unwrap();
+///
+/// assert_eq!(presets[0].name, "Tea");
+/// assert!(presets[0].remove(&conn).is_ok());
+/// assert!(TimerPreset::all(&conn).unwrap().is_empty());
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerPreset {
+    pub id: Option<i64>,
+    pub name: String,
+    pub duration_ms: u64,
+}
+
+impl TimerPreset {
+    // Essential db check
+    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            duration_ms INTEGER NOT NULL
+            )",
+            TNAME
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    /// Saves (creates or updates) the preset. Creates the table 'timer_presets' if not present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name is blank, if the duration is zero or if an SQL error has been
+    /// encountered
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        if self.name.trim().is_empty() {
+            return Err(ClockError("Timer preset must be named"));
+        }
+        if self.duration_ms == 0 {
+            return Err(ClockError("Timer preset duration must be positive"));
+        }
+
+        Self::check_table(conn)?;
+        let query = match self.id {
+            Some(_) => format!(
+                "UPDATE {} SET name = :name, duration_ms = :duration_ms WHERE id = :id",
+                TNAME
+            ),
+            None => format!(
+                "INSERT INTO {} (name, duration_ms) VALUES (:name, :duration_ms)",
+                TNAME
+            ),
+        };
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":name", self.name.trim()))?;
+        statement.bind((":duration_ms", self.duration_ms as i64))?;
+        if let Some(id) = self.id {
+            statement.bind((":id", id))?;
+        }
+        statement.next()?;
+
+        Ok(())
+    }
+
+    /// Get every timer preset, shortest first
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn all(conn: &sqlite::Connection) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("SELECT * FROM {} ORDER BY duration_ms, name", TNAME);
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            res.push(TimerPreset {
+                id: Some(statement.read::<i64, _>("id")?),
+                name: statement.read::<String, _>("name")?,
+                duration_ms: statement.read::<i64, _>("duration_ms")? as u64,
+            })
+        }
+
+        Ok(res)
+    }
+
+    /// Removes a saved timer preset
+    ///
+    /// # Panics
+    ///
+    /// Panics if the preset has no id (eg: not saved).
+    pub fn remove(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let id = self
+            .id
+            .ok_or(ClockError("Impossible to delete an unsaved timer preset"))?;
+        let query = format!("DELETE FROM {} WHERE id = {}", TNAME, id);
+
+        conn.execute(query)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn test_saving() {
+        let conn = Connection::open(":memory:").unwrap();
+        let laundry = TimerPreset {
+            id: None,
+            name: " Laundry ".to_string(),
+            duration_ms: 2_700_000,
+        };
+        let tea = TimerPreset {
+            id: None,
+            name: "Tea".to_string(),
+            duration_ms: 180_000,
+        };
+
+        assert!(TimerPreset::default().save(&conn).is_err());
+        assert!(TimerPreset {
+            duration_ms: 0,
+            ..tea.clone()
+        }
+        .save(&conn)
+        .is_err());
+        laundry.save(&conn).unwrap();
+        tea.save(&conn).unwrap();
+
+        let mut presets = TimerPreset::all(&conn).unwrap();
+        assert_eq!(presets[0].name, "Tea");
+        assert_eq!(presets[1].name, "Laundry");
+
+        presets[0].duration_ms = 240_000;
+        presets[0].save(&conn).unwrap();
+        assert_eq!(TimerPreset::all(&conn).unwrap()[0].duration_ms, 240_000);
+    }
+}
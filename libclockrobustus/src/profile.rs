@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+use sqlite::State;
+
+use crate::{alarm::Alarm, error::ClockError};
+
+const TNAME: &str = "profiles";
+
+/// A named set of alarm groups (eg: "workweek", "vacation"). Switching to a profile enables the
+/// alarms of its groups and disables the alarms of the groups only other profiles list, alarms
+/// without a group being left untouched.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::{alarm::Alarm, profile::Profile};
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+/// let group = |name: &str| Some(name.to_string());
+/// Alarm { hour: 7, group: group("work"), ..Default::default() }.save(&conn).unwrap();
+/// Alarm { hour: 10, group: group("holidays"), ..Default::default() }.save(&conn).unwrap();
+///
+/// Profile::new("workweek", &["work"]).save(&conn).unwrap();
+/// Profile::new("vacation", &["holidays"]).save(&conn).unwrap();
+///
+/// Profile::activate(&conn, "vacation").unwrap();
+///
+/// let enabled = Alarm::all(&conn).unwrap().iter().map(|alarm| alarm.enabled).collect::<Vec<bool>>();
+/// assert_eq!(enabled, vec![false, true]);
+/// assert_eq!(Profile::active(&conn).unwrap().unwrap().name, "vacation");
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub id: Option<i64>,
+    /// Unique name of the profile
+    pub name: String,
+    /// Alarm groups (see [Alarm::group]) enabled by the profile
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Last profile switched to
+    #[serde(default)]
+    pub active: bool,
+}
+
+impl Profile {
+    /// A new (unsaved and inactive) profile
+    pub fn new(name: &str, groups: &[&str]) -> Self {
+        Self {
+            id: None,
+            name: name.to_string(),
+            groups: groups.iter().map(|group| group.to_string()).collect(),
+            active: false,
+        }
+    }
+
+    // Essential db check
+    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            groups TEXT NOT NULL DEFAULT '[]',
+            active INTEGER NOT NULL DEFAULT 0
+            )",
+            TNAME
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    /// Saves (creates or updates) the profile. Creates the table 'profiles' if not present.
+    /// Activity is only changed through [Profile::activate].
+    ///
+    /// # Panics
+    ///
+    /// Panics if an SQL error has been encountered (eg: another profile has the same name)
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let query = match self.id {
+            Some(_) => format!(
+                "UPDATE {} SET name = :name, groups = :groups WHERE id = :id",
+                TNAME
+            ),
+            None => format!(
+                "INSERT INTO {} (name, groups) VALUES (:name, :groups)",
+                TNAME
+            ),
+        };
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":name", self.name.as_str()))?;
+        statement.bind((":groups", serde_json::to_string(&self.groups)?.as_str()))?;
+        if let Some(id) = self.id {
+            statement.bind((":id", id))?;
+        }
+        statement.next()?;
+
+        Ok(())
+    }
+
+    /// Get every profile, by name
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn all(conn: &sqlite::Connection) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("SELECT * FROM {} ORDER BY name", TNAME);
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            res.push(Profile {
+                id: Some(statement.read::<i64, _>("id")?),
+                name: statement.read::<String, _>("name")?,
+                groups: serde_json::from_str(&statement.read::<String, _>("groups")?)?,
+                active: statement.read::<i64, _>("active")? != 0,
+            })
+        }
+
+        Ok(res)
+    }
+
+    /// The profile last switched to, if any
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn active(conn: &sqlite::Connection) -> Result<Option<Self>, ClockError> {
+        Ok(Self::all(conn)?.into_iter().find(|profile| profile.active))
+    }
+
+    /// Removes a saved profile, its alarms being left as they are
+    ///
+    /// # Panics
+    ///
+    /// Panics if the profile has no id (eg: not saved).
+    pub fn remove(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let id = self
+            .id
+            .ok_or(ClockError("Impossible to delete an unsaved profile"))?;
+        let query = format!("DELETE FROM {} WHERE id = {}", TNAME, id);
+
+        conn.execute(query)?;
+        Ok(())
+    }
+
+    /// Switches to the profile `name`: the alarms of its groups are enabled, the alarms of the
+    /// groups listed by other profiles only are disabled. Returns the number of alarms updated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no profile has this name or if a SQL error is encountered
+    pub fn activate(conn: &sqlite::Connection, name: &str) -> Result<usize, ClockError> {
+        let profiles = Self::all(conn)?;
+        let profile = profiles
+            .iter()
+            .find(|profile| profile.name == name)
+            .ok_or(ClockError("No such profile"))?;
+        let mut updated = 0;
+
+        for group in profiles.iter().flat_map(|other| &other.groups) {
+            if !profile.groups.contains(group) {
+                updated += Alarm::set_enabled_by_group(conn, group, false)?;
+            }
+        }
+        for group in &profile.groups {
+            updated += Alarm::set_enabled_by_group(conn, group, true)?;
+        }
+
+        let mut statement =
+            conn.prepare(format!("UPDATE {} SET active = (name = :name)", TNAME))?;
+        statement.bind((":name", name))?;
+        statement.next()?;
+
+        Ok(updated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn test_switching() {
+        let conn = Connection::open(":memory:").unwrap();
+        let alarm = |hour, group: Option<&str>| Alarm {
+            hour,
+            group: group.map(|group| group.to_string()),
+            ..Default::default()
+        };
+
+        for alarm in [
+            alarm(7, Some("work")),
+            alarm(8, Some("shared")),
+            alarm(10, Some("holidays")),
+            alarm(12, None),
+        ] {
+            alarm.save(&conn).unwrap();
+        }
+        Profile::new("workweek", &["work", "shared"])
+            .save(&conn)
+            .unwrap();
+        Profile::new("vacation", &["holidays", "shared"])
+            .save(&conn)
+            .unwrap();
+
+        let enabled = |conn: &Connection| {
+            Alarm::all(conn)
+                .unwrap()
+                .iter()
+                .map(|alarm| alarm.enabled)
+                .collect::<Vec<bool>>()
+        };
+
+        Profile::activate(&conn, "workweek").unwrap();
+        assert_eq!(enabled(&conn), vec![true, true, false, true]);
+
+        Profile::activate(&conn, "vacation").unwrap();
+        assert_eq!(enabled(&conn), vec![false, true, true, true]);
+        assert_eq!(
+            Profile::all(&conn)
+                .unwrap()
+                .iter()
+                .filter(|profile| profile.active)
+                .count(),
+            1
+        );
+
+        assert!(Profile::activate(&conn, "weekend").is_err());
+        // Unique names
+        assert!(Profile::new("vacation", &[]).save(&conn).is_err());
+    }
+}
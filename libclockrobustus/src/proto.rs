@@ -0,0 +1,3 @@
+// Code generated from `proto/message.proto` by the build script. Conversions from and to the
+// library types live along with these.
+include!(concat!(env!("OUT_DIR"), "/clockrobustus.rs"));
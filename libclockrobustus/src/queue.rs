@@ -1,21 +1,211 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
-use crate::{env::ClockEnv, error::ClockError, message::Message};
+use serde::Serialize;
+
+use crate::{
+    control::{self, Request, Response},
+    env::ClockEnv,
+    error::ClockError,
+    latency::decode_timestamp,
+    message::{
+        Message, Topics, WireFormat, ALARM_BATCH_MESSAGE_HEADER, ALARM_MESSAGE_HEADER, ALARM_TOPIC,
+        BEDTIME_MESSAGE_HEADER, BEDTIME_TOPIC, CLOCK_JUMP_MESSAGE_HEADER, CLOCK_JUMP_TOPIC,
+        CLOCK_MESSAGE_HEADER, CLOCK_TOPIC, COUNTDOWN_MESSAGE_HEADER, COUNTDOWN_TOPIC,
+        HEARTBEAT_MESSAGE_HEADER, HEARTBEAT_TOPIC, INTERVAL_MESSAGE_HEADER, INTERVAL_TOPIC,
+        NEXT_ALARM_MESSAGE_HEADER, NEXT_ALARM_TOPIC, POMODORO_MESSAGE_HEADER, POMODORO_TOPIC,
+        PROTOCOL_MAGIC, PROTOCOL_VERSION, REMINDER_MESSAGE_HEADER, REMINDER_TOPIC,
+        SPEAK_MESSAGE_HEADER, SPEAK_TOPIC, SUN_MESSAGE_HEADER, SUN_TOPIC,
+        SYNC_STATUS_MESSAGE_HEADER, SYNC_STATUS_TOPIC, WORLD_CLOCK_MESSAGE_HEADER,
+        WORLD_CLOCK_TOPIC,
+    },
+    sequence::{decode_sequence, SequenceTracker},
+};
+
+/// Maximum time (in milliseconds) a listener blocks on the socket before checking its flags again.
+const RECV_TIMEOUT_MS: i32 = 500;
+
+/// First delay before a listener retries after an error (see [crate::error::ErrorCategory::retry_delay]).
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+
 /// Zmq listener. Handling incoming binary messages on client side,
 /// converts them to [Message] items and passes them to a callback.
-pub fn listen<F>(running_flag: Arc<AtomicBool>, callback: F) -> Result<(), ClockError>
+/// Both multipart (topic, payload) messages and single frame messages (see [Message::as_bytes])
+/// are understood, the latter only when no topic prefix is configured.
+/// Errors are retried according to their [crate::error::ErrorCategory], the listener only
+/// returns on fatal ones.
+/// Only the messages of the `topics` given (unprefixed, eg: [ALARM_TOPIC]) are subscribed to,
+/// every message is when none is given.
+/// Batches of alarms rung together are handed out one by one, as alarm messages.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::{atomic::AtomicBool, Arc};
+/// use libclockrobustus::{message::ALARM_TOPIC, queue::listen};
+///
+/// listen(Arc::new(AtomicBool::new(true)), &[ALARM_TOPIC], |alarm| {
+///     println!("{:?}", alarm);
+/// })
+/// .unwrap();
+/// ```
+pub fn listen<F>(
+    running_flag: Arc<AtomicBool>,
+    topics: &[&[u8]],
+    callback: F,
+) -> Result<(), ClockError>
 where
     F: Fn(Message),
+{
+    listen_filtered(
+        running_flag,
+        Arc::new(AtomicBool::new(true)),
+        topics,
+        |message, _| callback(message),
+    )
+}
+
+/// Same as [listen] with every topic, but clock messages are only subscribed to while
+/// `clock_flag` is set.
+/// Clearing the flag unsubscribes from the clock topic (the daemon stops sending those messages
+/// to this client, which saves wakeups when nothing is displayed), setting it back resubscribes.
+/// Every other message (alarms, countdowns, pomodoro phases...) is always received.
+pub fn listen_pausable<F>(
+    running_flag: Arc<AtomicBool>,
+    clock_flag: Arc<AtomicBool>,
+    callback: F,
+) -> Result<(), ClockError>
+where
+    F: Fn(Message),
+{
+    listen_timed(running_flag, clock_flag, |message, _| callback(message))
+}
+
+/// Same as [listen_pausable], the callback also gets the time the message was sent at (see
+/// [crate::latency]), when the publisher provided it.
+pub fn listen_timed<F>(
+    running_flag: Arc<AtomicBool>,
+    clock_flag: Arc<AtomicBool>,
+    callback: F,
+) -> Result<(), ClockError>
+where
+    F: Fn(Message, Option<u64>),
+{
+    listen_tracked(running_flag, clock_flag, |message, received| {
+        callback(message, received.sent_at)
+    })
+}
+
+/// What is known of a received message besides its content.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Received {
+    /// Send time (see [crate::latency]), when the publisher provided it
+    pub sent_at: Option<u64>,
+    /// Number in its topic (see [crate::sequence]), when the publisher provided it
+    pub sequence: Option<u64>,
+    /// Messages of the same topic dropped on the way just before this one
+    pub missed: u64,
+}
+
+/// Same as [listen_pausable], the callback also gets the send time and the sequence number of
+/// the message, along with the number of messages missed before it.
+pub fn listen_tracked<F>(
+    running_flag: Arc<AtomicBool>,
+    clock_flag: Arc<AtomicBool>,
+    callback: F,
+) -> Result<(), ClockError>
+where
+    F: Fn(Message, Received),
+{
+    listen_filtered(running_flag, clock_flag, &[], callback)
+}
+
+/// Same as [listen_tracked], only subscribing to the given (unprefixed) topics, to all of them
+/// if none is given.
+fn listen_filtered<F>(
+    running_flag: Arc<AtomicBool>,
+    clock_flag: Arc<AtomicBool>,
+    filter: &[&[u8]],
+    callback: F,
+) -> Result<(), ClockError>
+where
+    F: Fn(Message, Received),
 {
     let env = ClockEnv::new()?;
     let ctx = zmq::Context::new();
     let socket = ctx.socket(zmq::SUB)?;
-    let mut msg = zmq::Message::new();
+    let mut frames = Vec::new();
+    let mut clock_subscribed = false;
+    let mut failures = 0;
+    let mut tracker = SequenceTracker::default();
+    let topics = env.queue().topics();
+    let strict = env.queue().strict_decode();
+    // Legacy frames carry no topic, they cannot be told apart between instances, and are binary
+    let legacy = topics.is_unprefixed() && topics.format() == WireFormat::Binary;
+    let wanted = |name: &[u8]| filter.is_empty() || filter.contains(&name);
 
-    socket.set_subscribe(b"")?;
+    socket.set_rcvtimeo(RECV_TIMEOUT_MS)?;
+    for (topic, name, header) in [
+        (topics.alarm(), ALARM_TOPIC, ALARM_MESSAGE_HEADER),
+        (
+            topics.countdown(),
+            COUNTDOWN_TOPIC,
+            COUNTDOWN_MESSAGE_HEADER,
+        ),
+        (topics.pomodoro(), POMODORO_TOPIC, POMODORO_MESSAGE_HEADER),
+        (topics.interval(), INTERVAL_TOPIC, INTERVAL_MESSAGE_HEADER),
+        (
+            topics.next_alarm(),
+            NEXT_ALARM_TOPIC,
+            NEXT_ALARM_MESSAGE_HEADER,
+        ),
+        (topics.bedtime(), BEDTIME_TOPIC, BEDTIME_MESSAGE_HEADER),
+        (topics.reminder(), REMINDER_TOPIC, REMINDER_MESSAGE_HEADER),
+        (
+            topics.world_clock(),
+            WORLD_CLOCK_TOPIC,
+            WORLD_CLOCK_MESSAGE_HEADER,
+        ),
+        (topics.sun(), SUN_TOPIC, SUN_MESSAGE_HEADER),
+        (
+            topics.sync_status(),
+            SYNC_STATUS_TOPIC,
+            SYNC_STATUS_MESSAGE_HEADER,
+        ),
+        (
+            topics.clock_jump(),
+            CLOCK_JUMP_TOPIC,
+            CLOCK_JUMP_MESSAGE_HEADER,
+        ),
+        (topics.speak(), SPEAK_TOPIC, SPEAK_MESSAGE_HEADER),
+        (
+            topics.heartbeat(),
+            HEARTBEAT_TOPIC,
+            HEARTBEAT_MESSAGE_HEADER,
+        ),
+        // Batches are alarms rung together
+        (
+            topics.alarm_batch(),
+            ALARM_TOPIC,
+            ALARM_BATCH_MESSAGE_HEADER,
+        ),
+    ] {
+        if !wanted(name) {
+            continue;
+        }
+        socket.set_subscribe(topic)?;
+        if legacy {
+            for prefix in single_frame_prefixes(header) {
+                socket.set_subscribe(&prefix)?;
+            }
+        }
+    }
     socket.connect(&format!(
         "tcp://{}:{}",
         env.queue().host(),
@@ -27,13 +217,215 @@ where
             break;
         }
 
-        socket.recv(&mut msg, 0)?;
+        let clock_wanted = clock_flag.load(Ordering::SeqCst) && wanted(CLOCK_TOPIC);
+        if clock_wanted != clock_subscribed {
+            let single_frame = single_frame_prefixes(CLOCK_MESSAGE_HEADER);
+            let mut prefixes = vec![topics.clock()];
+            if legacy {
+                prefixes.extend(single_frame.iter().map(Vec::as_slice));
+            }
 
-        let bytes = msg.iter().copied().collect::<Vec<u8>>();
-        let message = Message::try_from(bytes)?;
+            for prefix in prefixes {
+                if clock_wanted {
+                    socket.set_subscribe(prefix)?;
+                } else {
+                    socket.set_unsubscribe(prefix)?;
+                }
+            }
+            clock_subscribed = clock_wanted;
+            // Clock messages published while paused were not dropped
+            tracker.forget(CLOCK_TOPIC);
+        }
 
-        callback(message);
+        match receive(&socket, &topics, strict, &mut frames) {
+            // Subscriptions only match topic prefixes, longer topics may slip through
+            Ok(Some((message, _))) if !wanted(subscription_of(&message)) => failures = 0,
+            Ok(Some((message, mut received))) => {
+                failures = 0;
+                if let Some(sequence) = received.sequence {
+                    received.missed = tracker.record(&message, sequence);
+                }
+                match message {
+                    // Alarms rung in the same tick, handed out one by one
+                    Message::AlarmBatch(alarms) => {
+                        for alarm in alarms {
+                            callback(Message::Alarm(alarm), received);
+                        }
+                    }
+                    message => callback(message, received),
+                }
+            }
+            // Timeout, skipped message or message of a newer type, time to check the flags again
+            Ok(None) => (),
+            // Lost connection: back off (zeromq reconnects by itself), unless it cannot get better
+            Err(error) => match error.category().retry_delay(RETRY_BASE_DELAY, failures) {
+                Some(delay) => {
+                    println!("Listener error : {}, retrying in {:?}", error, delay);
+                    failures += 1;
+                    thread::sleep(delay);
+                }
+                None => return Err(error),
+            },
+        }
     }
 
     Ok(())
 }
+
+/// Subscription prefixes of the single frame messages bearing `header`: with an envelope of a
+/// supported protocol version, or legacy ones without.
+fn single_frame_prefixes(header: u8) -> Vec<Vec<u8>> {
+    (1..=PROTOCOL_VERSION)
+        .map(|version| [&PROTOCOL_MAGIC[..], &[version, header]].concat())
+        .chain([vec![header]])
+        .collect()
+}
+
+/// Unprefixed topic a message is filtered on, batches of alarms being alarms.
+fn subscription_of(message: &Message) -> &'static [u8] {
+    match message {
+        Message::AlarmBatch(_) => ALARM_TOPIC,
+        message => message.topic(),
+    }
+}
+
+/// Receives and decodes (strictly if asked to) the next message with its send time and sequence
+/// number, [None] on timeout, when the message cannot be decoded (it is skipped) or for multipart
+/// messages of types unknown to this version. Every frame of the message is read before decoding,
+/// so a bad message does not spill over the next one. Only socket errors are returned. Frames are
+/// received in (and decoded from) the reused `frames` buffers.
+fn receive(
+    socket: &zmq::Socket,
+    topics: &Topics,
+    strict: bool,
+    frames: &mut Vec<zmq::Message>,
+) -> Result<Option<(Message, Received)>, ClockError> {
+    let mut count = 0;
+    loop {
+        if count == frames.len() {
+            frames.push(zmq::Message::new());
+        }
+        match socket.recv(&mut frames[count], 0) {
+            Err(zmq::Error::EAGAIN) if count == 0 => return Ok(None),
+            Err(error) => return Err(error.into()),
+            Ok(()) => count += 1,
+        }
+        if !frames[count - 1].get_more() {
+            break;
+        }
+    }
+
+    let decoded = match (&frames[..count], strict) {
+        ([bytes], false) => {
+            Message::try_from(&bytes[..]).map(|message| (message, Received::default()))
+        }
+        ([bytes], true) => Message::try_from_strict(bytes)
+            .map(|message| (message, Received::default()))
+            .map_err(ClockError::from),
+        // Subscriptions being prefixes, a newer daemon may publish types unknown to this version
+        // under matching topics (eg: `alarm_batch`)
+        ([topic, _, ..], _) if !topics.knows(topic) => return Ok(None),
+        // Optional send timestamp and sequence number frames, extra frames are not part of the
+        // protocol (yet)
+        ([topic, payload, rest @ ..], _) => if strict {
+            topics
+                .decode_strict(topic, payload)
+                .map_err(ClockError::from)
+        } else {
+            topics.decode(topic, payload)
+        }
+        .map(|message| {
+            (
+                message,
+                Received {
+                    sent_at: rest.first().and_then(|frame| decode_timestamp(frame).ok()),
+                    sequence: rest.get(1).and_then(|frame| decode_sequence(frame).ok()),
+                    missed: 0,
+                },
+            )
+        }),
+        ([], _) => return Ok(None),
+    };
+
+    // A bad message says nothing about the connection, the next one is read right away
+    match decoded {
+        Ok(decoded) => Ok(Some(decoded)),
+        Err(error) => {
+            println!("Skipping undecodable message : {}", error);
+            Ok(None)
+        }
+    }
+}
+
+/// Round-trip times measured by [measure_latency], in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundTrip {
+    pub samples: usize,
+    pub min_ms: f64,
+    pub average_ms: f64,
+    pub max_ms: f64,
+}
+
+impl RoundTrip {
+    /// Summarizes measured round trips, [None] if there is none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use libclockrobustus::queue::RoundTrip;
+    ///
+    /// let round_trip = RoundTrip::from_durations(&[
+    ///     Duration::from_millis(2),
+    ///     Duration::from_millis(4),
+    /// ]).unwrap();
+    ///
+    /// assert_eq!(round_trip.min_ms, 2.0);
+    /// assert_eq!(round_trip.average_ms, 3.0);
+    /// assert_eq!(round_trip.max_ms, 4.0);
+    /// assert!(RoundTrip::from_durations(&[]).is_none());
+    /// ```
+    pub fn from_durations(durations: &[Duration]) -> Option<Self> {
+        let ms = durations
+            .iter()
+            .map(|duration| duration.as_secs_f64() * 1000.0)
+            .collect::<Vec<f64>>();
+
+        if ms.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            samples: ms.len(),
+            min_ms: ms.iter().copied().fold(f64::INFINITY, f64::min),
+            average_ms: ms.iter().sum::<f64>() / ms.len() as f64,
+            max_ms: ms.iter().copied().fold(0.0, f64::max),
+        })
+    }
+}
+
+/// Measures the round-trip time to the daemon over its control channel, sending `samples` echo
+/// requests (see [Request::Echo]) on a single connection. Connection setup is not accounted for.
+///
+/// # Panics
+///
+/// Panics if the daemon is unreachable, does not answer within the control channel timeout, or
+/// answers with the wrong nonce.
+pub fn measure_latency(samples: usize) -> Result<RoundTrip, ClockError> {
+    let env = ClockEnv::new()?;
+    let ctx = zmq::Context::new();
+    let socket = control::connect(&ctx, &env)?;
+    let mut durations = Vec::with_capacity(samples);
+
+    for nonce in 0..samples as u64 {
+        let start = Instant::now();
+
+        match control::exchange(&socket, &env, &Request::Echo { nonce })? {
+            Response::Echo { nonce: echoed } if echoed == nonce => durations.push(start.elapsed()),
+            _ => return Err(ClockError("Unexpected reply to an echo request")),
+        }
+    }
+
+    RoundTrip::from_durations(&durations).ok_or(ClockError("No latency sample requested"))
+}
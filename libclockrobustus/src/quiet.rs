@@ -0,0 +1,280 @@
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+use sqlite::State;
+
+use crate::{alarm::ActiveDays, error::ClockError};
+
+const TNAME: &str = "quiet_windows";
+
+/// Do-not-disturb window (eg: 22:00 to 07:00 on weekends) during which the daemon does not
+/// publish alarms, the silenced occurrences being recorded in the history. A window ending
+/// before it starts runs past midnight, `days` being the days it starts on. Equal bounds cover
+/// the whole day.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use libclockrobustus::{alarm::ActiveDays, quiet::QuietWindow};
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+/// let nights = QuietWindow {
+///     days: ActiveDays::WEEKEND,
+///     start_hour: 22,
+///     end_hour: 7,
+///     ..Default::default()
+/// };
+///
+/// nights.save(&conn).unwrap();
+///
+/// // Sunday morning
+/// let at = NaiveDate::from_ymd_opt(2023, 7, 9).unwrap().and_hms_opt(6, 30, 0).unwrap();
+/// assert!(QuietWindow::is_quiet(&conn, at).unwrap());
+/// // Monday morning, after the sunday night
+/// assert!(QuietWindow::is_quiet(&conn, at + chrono::Duration::days(1)).unwrap());
+/// // Tuesday morning
+/// assert!(!QuietWindow::is_quiet(&conn, at + chrono::Duration::days(2)).unwrap());
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietWindow {
+    pub id: Option<i64>,
+    /// Free text displayed to the user
+    #[serde(default)]
+    pub label: String,
+    /// Days the window starts on
+    pub days: ActiveDays,
+    pub start_hour: u8,
+    pub start_minute: u8,
+    pub end_hour: u8,
+    pub end_minute: u8,
+}
+
+impl QuietWindow {
+    // Essential db check
+    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER PRIMARY KEY,
+            label TEXT NOT NULL DEFAULT '',
+            days INTEGER NOT NULL,
+            start_hour INTEGER NOT NULL,
+            start_minute INTEGER NOT NULL,
+            end_hour INTEGER NOT NULL,
+            end_minute INTEGER NOT NULL
+            )",
+            TNAME
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    /// Saves (creates or updates) the window. Creates the table 'quiet_windows' if not present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bounds are not valid times of day or if an SQL error has been encountered
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        if self.start_hour > 23
+            || self.end_hour > 23
+            || self.start_minute > 59
+            || self.end_minute > 59
+        {
+            return Err(ClockError("Invalid quiet window bounds"));
+        }
+
+        Self::check_table(conn)?;
+        let query = match self.id {
+            Some(_) => format!(
+                "UPDATE {} SET label = :label, days = :days, start_hour = :start_hour,
+                start_minute = :start_minute, end_hour = :end_hour, end_minute = :end_minute
+                WHERE id = :id",
+                TNAME
+            ),
+            None => format!(
+                "INSERT INTO {} (label, days, start_hour, start_minute, end_hour, end_minute)
+                VALUES (:label, :days, :start_hour, :start_minute, :end_hour, :end_minute)",
+                TNAME
+            ),
+        };
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":label", self.label.as_str()))?;
+        statement.bind((":days", self.days.0 as i64))?;
+        statement.bind((":start_hour", self.start_hour as i64))?;
+        statement.bind((":start_minute", self.start_minute as i64))?;
+        statement.bind((":end_hour", self.end_hour as i64))?;
+        statement.bind((":end_minute", self.end_minute as i64))?;
+        if let Some(id) = self.id {
+            statement.bind((":id", id))?;
+        }
+        statement.next()?;
+
+        Ok(())
+    }
+
+    /// Get every window
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn all(conn: &sqlite::Connection) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("SELECT * FROM {} ORDER BY id", TNAME);
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            res.push(QuietWindow {
+                id: Some(statement.read::<i64, _>("id")?),
+                label: statement.read::<String, _>("label")?,
+                days: ActiveDays(statement.read::<i64, _>("days")? as u8),
+                start_hour: statement.read::<i64, _>("start_hour")? as u8,
+                start_minute: statement.read::<i64, _>("start_minute")? as u8,
+                end_hour: statement.read::<i64, _>("end_hour")? as u8,
+                end_minute: statement.read::<i64, _>("end_minute")? as u8,
+            })
+        }
+
+        Ok(res)
+    }
+
+    /// Removes a saved window
+    ///
+    /// # Panics
+    ///
+    /// Panics if the window has no id (eg: not saved).
+    pub fn remove(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let id = self
+            .id
+            .ok_or(ClockError("Impossible to delete an unsaved quiet window"))?;
+        let query = format!("DELETE FROM {} WHERE id = {}", TNAME, id);
+
+        conn.execute(query)?;
+        Ok(())
+    }
+
+    /// True if the (wall clock) time `at` falls within the window, start included and end
+    /// excluded.
+    pub fn contains(&self, at: NaiveDateTime) -> bool {
+        let start = self.start_hour as u32 * 60 + self.start_minute as u32;
+        let end = self.end_hour as u32 * 60 + self.end_minute as u32;
+        let minute = at.hour() * 60 + at.minute();
+        let weekdays = self.days.to_weekdays();
+        let today = weekdays.contains(&at.weekday());
+
+        if start < end {
+            today && (start..end).contains(&minute)
+        } else if start == end {
+            today
+        } else {
+            // Either the evening of a starting day or the morning after it
+            (today && minute >= start) || (weekdays.contains(&at.weekday().pred()) && minute < end)
+        }
+    }
+
+    /// True if any stored window contains the (wall clock) time `at`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn is_quiet(conn: &sqlite::Connection, at: NaiveDateTime) -> Result<bool, ClockError> {
+        Ok(Self::all(conn)?.iter().any(|window| window.contains(at)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use sqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn test_contains() {
+        // Monday
+        let at = |day, hour, minute| {
+            NaiveDate::from_ymd_opt(2023, 7, day)
+                .unwrap()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap()
+        };
+        let lunch = QuietWindow {
+            days: ActiveDays::WEEKDAYS,
+            start_hour: 12,
+            end_hour: 13,
+            end_minute: 30,
+            ..Default::default()
+        };
+
+        assert!(lunch.contains(at(3, 12, 0)));
+        assert!(lunch.contains(at(3, 13, 29)));
+        assert!(!lunch.contains(at(3, 13, 30)));
+        assert!(!lunch.contains(at(3, 11, 59)));
+        // Saturday
+        assert!(!lunch.contains(at(8, 12, 15)));
+
+        let sunday = QuietWindow {
+            days: ActiveDays(0x40),
+            ..Default::default()
+        };
+
+        assert!(sunday.contains(at(9, 0, 0)));
+        assert!(sunday.contains(at(9, 23, 59)));
+        assert!(!sunday.contains(at(10, 0, 0)));
+
+        // Friday night only
+        let night = QuietWindow {
+            days: ActiveDays(0x10),
+            start_hour: 23,
+            end_hour: 6,
+            ..Default::default()
+        };
+
+        assert!(night.contains(at(7, 23, 0)));
+        assert!(night.contains(at(8, 5, 59)));
+        assert!(!night.contains(at(8, 6, 0)));
+        assert!(!night.contains(at(8, 23, 0)));
+        assert!(!night.contains(at(7, 5, 0)));
+    }
+
+    #[test]
+    fn test_saving() {
+        let conn = Connection::open(":memory:").unwrap();
+        let window = QuietWindow {
+            label: "Nights".to_string(),
+            days: ActiveDays::EVERYDAY,
+            start_hour: 22,
+            start_minute: 30,
+            end_hour: 7,
+            end_minute: 15,
+            ..Default::default()
+        };
+
+        window.save(&conn).unwrap();
+        let mut saved = QuietWindow::all(&conn).unwrap().remove(0);
+        assert_eq!(
+            saved,
+            QuietWindow {
+                id: saved.id,
+                ..window
+            }
+        );
+
+        saved.end_hour = 8;
+        saved.save(&conn).unwrap();
+        assert_eq!(QuietWindow::all(&conn).unwrap(), vec![saved.clone()]);
+
+        assert!(QuietWindow {
+            end_hour: 24,
+            ..Default::default()
+        }
+        .save(&conn)
+        .is_err());
+
+        saved.remove(&conn).unwrap();
+        assert!(QuietWindow::all(&conn).unwrap().is_empty());
+    }
+}
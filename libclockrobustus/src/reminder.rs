@@ -0,0 +1,319 @@
+use serde::{Deserialize, Serialize};
+use sqlite::State;
+
+use crate::{
+    codec,
+    error::{ClockError, DecodeError},
+};
+
+const TNAME: &str = "reminders";
+
+/// One-shot notification (eg: "Call the plumber" tomorrow at 10:00). Unlike alarms nothing rings:
+/// the daemon publishes a [ReminderMessage] carrying the text when it is due, once.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::reminder::Reminder;
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+/// let plumber = Reminder {
+///     text: "Call the plumber".to_string(),
+///     due_at: 1_700_000_000,
+///     ..Default::default()
+/// };
+///
+/// plumber.save(&conn).unwrap();
+///
+/// assert!(Reminder::take_due(&conn, 1_699_999_999).unwrap().is_empty());
+/// let due = Reminder::take_due(&conn, 1_700_000_000).unwrap();
+/// assert_eq!(due[0].text, "Call the plumber");
+/// // Published once and for all
+/// assert!(Reminder::take_due(&conn, 1_700_000_000).unwrap().is_empty());
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reminder {
+    pub id: Option<i64>,
+    /// Unix timestamp (seconds)
+    pub due_at: i64,
+    /// Text displayed to the user, at most [ReminderMessage::MAX_TEXT_LEN] bytes
+    pub text: String,
+    /// True once published
+    #[serde(default)]
+    pub done: bool,
+}
+
+impl Reminder {
+    // Essential db check
+    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER PRIMARY KEY,
+            due_at INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            done INTEGER NOT NULL DEFAULT 0
+            )",
+            TNAME
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    /// Saves (creates or updates) the reminder. Creates the table 'reminders' if not present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the text is blank or too long, or if an SQL error has been encountered
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        if self.text.trim().is_empty() {
+            return Err(ClockError("Reminder text must not be empty"));
+        }
+        if self.text.len() > ReminderMessage::MAX_TEXT_LEN {
+            return Err(ClockError("Reminder text too long"));
+        }
+
+        Self::check_table(conn)?;
+        let query = match self.id {
+            Some(_) => format!(
+                "UPDATE {} SET due_at = :due_at, text = :text, done = :done WHERE id = :id",
+                TNAME
+            ),
+            None => format!(
+                "INSERT INTO {} (due_at, text, done) VALUES (:due_at, :text, :done)",
+                TNAME
+            ),
+        };
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":due_at", self.due_at))?;
+        statement.bind((":text", self.text.as_str()))?;
+        statement.bind((":done", self.done as i64))?;
+        if let Some(id) = self.id {
+            statement.bind((":id", id))?;
+        }
+        statement.next()?;
+
+        Ok(())
+    }
+
+    // Row to reminder
+    fn from_row(statement: &sqlite::Statement<'_>) -> Result<Self, ClockError> {
+        Ok(Reminder {
+            id: Some(statement.read::<i64, _>("id")?),
+            due_at: statement.read::<i64, _>("due_at")?,
+            text: statement.read::<String, _>("text")?,
+            done: statement.read::<i64, _>("done")? != 0,
+        })
+    }
+
+    /// Get every reminder, published ones included, soonest first
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn all(conn: &sqlite::Connection) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("SELECT * FROM {} ORDER BY due_at, id", TNAME);
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            res.push(Self::from_row(&statement)?)
+        }
+
+        Ok(res)
+    }
+
+    /// Reminders due at `now` (Unix timestamp, seconds) and not published yet, flagged as done.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn take_due(conn: &sqlite::Connection, now: i64) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "SELECT * FROM {} WHERE done = 0 AND due_at <= :now ORDER BY due_at, id",
+            TNAME
+        );
+        let mut due = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":now", now))?;
+        while let Ok(State::Row) = statement.next() {
+            due.push(Self::from_row(&statement)?)
+        }
+
+        for reminder in &due {
+            conn.execute(format!(
+                "UPDATE {} SET done = 1 WHERE id = {}",
+                TNAME,
+                reminder.id.unwrap_or_default()
+            ))?;
+        }
+
+        Ok(due)
+    }
+
+    /// Removes a saved reminder
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reminder has no id (eg: not saved).
+    pub fn remove(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let id = self
+            .id
+            .ok_or(ClockError("Impossible to delete an unsaved reminder"))?;
+        let query = format!("DELETE FROM {} WHERE id = {}", TNAME, id);
+
+        conn.execute(query)?;
+        Ok(())
+    }
+
+    /// Message carrying the reminder text.
+    pub fn message(&self) -> ReminderMessage {
+        ReminderMessage {
+            id: self.id.unwrap_or_default(),
+            due_at: self.due_at,
+            text: self.text.clone(),
+        }
+    }
+}
+
+/// Due [Reminder], as published by the daemon. The text is carried so that clients can show a
+/// notification straight away.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderMessage {
+    pub id: i64,
+    /// Unix timestamp (seconds) the reminder was due at
+    pub due_at: i64,
+    pub text: String,
+}
+
+impl ReminderMessage {
+    /// Longest text (in bytes) a message can carry
+    pub const MAX_TEXT_LEN: usize = 255;
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits)
+    pub const MAX_ENCODED_LEN: usize = 22 + Self::MAX_TEXT_LEN;
+
+    /// Writes the binary representation at the start of a caller-provided buffer, returning the
+    /// number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the text is too long or if the buffer is too small for it.
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        if self.text.len() > Self::MAX_TEXT_LEN {
+            return Err(ClockError("Reminder text too long"));
+        }
+
+        codec::encode(self, buffer)
+    }
+
+    /// Strict counterpart of the [TryFrom] conversion from bytes: truncated messages, trailing
+    /// bytes (see [codec::decode_strict]) and a text too long are rejected.
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let message: Self = codec::decode_strict(bytes)?;
+
+        DecodeError::check_range(message.text.len(), "text", 0..=Self::MAX_TEXT_LEN)?;
+        Ok(message)
+    }
+}
+
+impl TryFrom<Vec<u8>> for ReminderMessage {
+    type Error = ClockError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        codec::decode(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn test_saving() {
+        let conn = Connection::open(":memory:").unwrap();
+        let reminder = Reminder {
+            text: "Water the plants".to_string(),
+            due_at: 100,
+            ..Default::default()
+        };
+
+        assert!(Reminder::default().save(&conn).is_err());
+        assert!(Reminder {
+            text: "a".repeat(256),
+            ..reminder.clone()
+        }
+        .save(&conn)
+        .is_err());
+        reminder.save(&conn).unwrap();
+        Reminder {
+            due_at: 50,
+            ..reminder.clone()
+        }
+        .save(&conn)
+        .unwrap();
+
+        let all = Reminder::all(&conn).unwrap();
+        assert_eq!(
+            all.iter().map(|r| r.due_at).collect::<Vec<_>>(),
+            vec![50, 100]
+        );
+
+        assert_eq!(Reminder::take_due(&conn, 60).unwrap().len(), 1);
+        assert!(Reminder::all(&conn).unwrap()[0].done);
+        all[1].remove(&conn).unwrap();
+        assert_eq!(Reminder::all(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_binary_conversion() {
+        let message = Reminder {
+            id: Some(5),
+            due_at: 1_700_000_000,
+            text: "Café ☕".to_string(),
+            done: false,
+        }
+        .message();
+        let longest = ReminderMessage {
+            id: i64::MIN,
+            due_at: i64::MIN,
+            text: "a".repeat(ReminderMessage::MAX_TEXT_LEN),
+        };
+        let mut buffer = [0u8; ReminderMessage::MAX_ENCODED_LEN + 1];
+
+        assert_eq!(
+            longest.encode_into(&mut buffer).unwrap(),
+            ReminderMessage::MAX_ENCODED_LEN
+        );
+
+        let len = message.encode_into(&mut buffer).unwrap();
+
+        assert_eq!(ReminderMessage::try_from(buffer.to_vec()).unwrap(), message);
+        assert_eq!(
+            ReminderMessage::try_from_strict(&buffer[..len]).unwrap(),
+            message
+        );
+        assert!(ReminderMessage::try_from(buffer[..len - 1].to_vec()).is_err());
+        assert_eq!(
+            ReminderMessage::try_from_strict(&buffer[..len - 1])
+                .unwrap_err()
+                .reason,
+            "Missing field"
+        );
+        assert_eq!(
+            ReminderMessage::try_from_strict(&buffer[..len + 1])
+                .unwrap_err()
+                .reason,
+            "Trailing bytes"
+        );
+    }
+}
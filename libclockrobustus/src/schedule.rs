@@ -0,0 +1,421 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt::Display, str::FromStr};
+
+use crate::error::ClockError;
+
+/// Years scanned (times the interval) when looking for the next occurrence of a rule, enough
+/// for a yearly rule on February 29th.
+const SCAN_YEARS: i64 = 8;
+
+/// Period a [Recurrence] repeats over.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Daily => "DAILY",
+            Self::Weekly => "WEEKLY",
+            Self::Monthly => "MONTHLY",
+            Self::Yearly => "YEARLY",
+        }
+    }
+}
+
+/// Recurrence rule of an alarm, a subset of the iCalendar RRULE syntax: `FREQ` (`DAILY`,
+/// `WEEKLY`, `MONTHLY` or `YEARLY`), `INTERVAL`, `BYDAY` (eg: `MO`, `1MO` for the first Monday of
+/// the month, `-1FR` for the last Friday), `BYMONTHDAY` (negative values count from the end of
+/// the month), `BYMONTH`, and `DTSTART` (`YYYYMMDD`), which anchors intervals and defaults the
+/// day when no `BY` part is given. Serialized as its rule string.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use libclockrobustus::schedule::Recurrence;
+///
+/// let first_monday = "FREQ=MONTHLY;BYDAY=1MO".parse::<Recurrence>().unwrap();
+/// let every_2_days = "FREQ=DAILY;INTERVAL=2;DTSTART=20230703".parse::<Recurrence>().unwrap();
+/// let date = |day| NaiveDate::from_ymd_opt(2023, 7, day).unwrap();
+///
+/// assert!(first_monday.matches(date(3)));
+/// assert!(!first_monday.matches(date(10)));
+/// assert!(every_2_days.matches(date(5)));
+/// assert!(!every_2_days.matches(date(6)));
+/// assert_eq!(first_monday.next_date(date(4)), NaiveDate::from_ymd_opt(2023, 8, 7));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// Repeats every `interval` periods (1 by default)
+    pub interval: u32,
+    /// Weekdays, optionally restricted to their nth (negative: from the end) occurrence in the
+    /// month
+    pub by_day: Vec<(Option<i8>, Weekday)>,
+    pub by_month_day: Vec<i8>,
+    pub by_month: Vec<u8>,
+    pub start: Option<NaiveDate>,
+}
+
+impl Recurrence {
+    /// A rule repeating every period, anchored at no date.
+    pub fn new(frequency: Frequency) -> Self {
+        Self {
+            frequency,
+            interval: 1,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+            start: None,
+        }
+    }
+
+    /// Same rule, starting on `date` unless it has a start already. Saved alarms anchor their
+    /// rule on the day it is set (see [crate::alarm::Alarm::save]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use libclockrobustus::schedule::Recurrence;
+    ///
+    /// let weekly = "FREQ=WEEKLY;INTERVAL=2".parse::<Recurrence>().unwrap();
+    /// let monday = NaiveDate::from_ymd_opt(2023, 7, 3).unwrap();
+    /// let anchored = weekly.anchored(monday);
+    ///
+    /// assert_eq!(anchored.start, Some(monday));
+    /// assert_eq!(anchored.anchored(monday + chrono::Duration::days(1)), anchored);
+    /// assert!(anchored.matches(NaiveDate::from_ymd_opt(2023, 7, 17).unwrap()));
+    /// assert!(!anchored.matches(NaiveDate::from_ymd_opt(2023, 7, 10).unwrap()));
+    /// ```
+    pub fn anchored(&self, date: NaiveDate) -> Self {
+        Self {
+            start: self.start.or(Some(date)),
+            ..self.clone()
+        }
+    }
+
+    // Anchor of the intervals, arbitrary (but fixed) for a rule not anchored yet
+    fn anchor(&self) -> NaiveDate {
+        self.start.unwrap_or_default()
+    }
+
+    /// True if the rule has an occurrence on `date`.
+    pub fn matches(&self, date: NaiveDate) -> bool {
+        let anchor = self.anchor();
+
+        if self.start.is_some_and(|start| date < start) {
+            return false;
+        }
+
+        // Periods elapsed since the anchor
+        let period = match self.frequency {
+            Frequency::Daily => (date - anchor).num_days(),
+            Frequency::Weekly => {
+                let monday = |date: NaiveDate| {
+                    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+                };
+                (monday(date) - monday(anchor)).num_weeks()
+            }
+            Frequency::Monthly => {
+                (date.year() - anchor.year()) as i64 * 12 + date.month() as i64
+                    - anchor.month() as i64
+            }
+            Frequency::Yearly => (date.year() - anchor.year()) as i64,
+        };
+        if period.rem_euclid(self.interval.max(1) as i64) != 0 {
+            return false;
+        }
+
+        if !self.by_month.is_empty() && !self.by_month.contains(&(date.month() as u8)) {
+            return false;
+        }
+
+        let days_in_month = days_in_month(date);
+        let day = date.day() as i64;
+
+        if !self.by_month_day.is_empty()
+            && !self.by_month_day.iter().any(|month_day| {
+                let month_day = *month_day as i64;
+                month_day == day || month_day == day - days_in_month - 1
+            })
+        {
+            return false;
+        }
+
+        if !self.by_day.is_empty() {
+            let nth_in_month = |nth: i8| match nth {
+                nth if nth > 0 => (day - 1) / 7 + 1 == nth as i64,
+                nth => (days_in_month - day) / 7 + 1 == -nth as i64,
+            };
+
+            return self.by_day.iter().any(|(nth, weekday)| {
+                *weekday == date.weekday()
+                    && match (nth, self.frequency) {
+                        (Some(nth), Frequency::Monthly | Frequency::Yearly) => nth_in_month(*nth),
+                        _ => true,
+                    }
+            });
+        }
+
+        // Without any day selector, the day of the anchor is repeated
+        match self.frequency {
+            Frequency::Daily => true,
+            Frequency::Weekly => date.weekday() == anchor.weekday(),
+            Frequency::Monthly => !self.by_month_day.is_empty() || date.day() == anchor.day(),
+            Frequency::Yearly => {
+                !self.by_month_day.is_empty()
+                    || (date.day() == anchor.day()
+                        && (!self.by_month.is_empty() || date.month() == anchor.month()))
+            }
+        }
+    }
+
+    /// First date from `from` (included) the rule has an occurrence on, [None] if there is none
+    /// within a few years.
+    pub fn next_date(&self, from: NaiveDate) -> Option<NaiveDate> {
+        let from = self.start.map_or(from, |start| start.max(from));
+        let horizon = 366 * SCAN_YEARS * self.interval.clamp(1, 100) as i64;
+
+        (0..horizon)
+            .map(|offset| from + Duration::days(offset))
+            .find(|date| self.matches(*date))
+    }
+}
+
+// Number of days of the month of `date`
+fn days_in_month(date: NaiveDate) -> i64 {
+    let (year, month) = match date.month() {
+        12 => (date.year() + 1, 1),
+        month => (date.year(), month + 1),
+    };
+
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .map_or(31, |next| (next - Duration::days(1)).day() as i64)
+}
+
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = ClockError;
+
+    /// Parses a rule such as `FREQ=MONTHLY;BYDAY=1MO` (an `RRULE:` prefix is accepted).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rule = s.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+        let mut frequency = None;
+        let mut res = Self::new(Frequency::Daily);
+
+        fn list<T: FromStr>(value: &str) -> Result<Vec<T>, ClockError> {
+            value
+                .split(',')
+                .map(|item| {
+                    item.trim()
+                        .parse()
+                        .map_err(|_| ClockError("Invalid recurrence rule value"))
+                })
+                .collect()
+        }
+
+        for part in rule.split(';').filter(|part| !part.trim().is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or(ClockError("Invalid recurrence rule part"))?;
+
+            match key.trim().to_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.trim().to_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(ClockError("Unsupported recurrence frequency")),
+                    })
+                }
+                "INTERVAL" => res.interval = value.trim().parse()?,
+                "BYMONTHDAY" => res.by_month_day = list(value)?,
+                "BYMONTH" => res.by_month = list(value)?,
+                "BYDAY" => {
+                    res.by_day = value
+                        .split(',')
+                        .map(|day| {
+                            let day = day.trim().to_uppercase();
+                            if !day.is_ascii() {
+                                return Err(ClockError("Invalid recurrence weekday"));
+                            }
+                            let split = day.len().saturating_sub(2);
+                            let weekday = [
+                                Weekday::Mon,
+                                Weekday::Tue,
+                                Weekday::Wed,
+                                Weekday::Thu,
+                                Weekday::Fri,
+                                Weekday::Sat,
+                                Weekday::Sun,
+                            ]
+                            .into_iter()
+                            .find(|weekday| weekday_name(*weekday) == &day[split..])
+                            .ok_or(ClockError("Invalid recurrence weekday"))?;
+                            let nth = match &day[..split] {
+                                "" => None,
+                                nth => Some(nth.trim_start_matches('+').parse()?),
+                            };
+
+                            Ok((nth, weekday))
+                        })
+                        .collect::<Result<Vec<_>, ClockError>>()?
+                }
+                "DTSTART" => {
+                    let value = value.trim().replace('-', "");
+                    res.start = Some(
+                        NaiveDate::parse_from_str(&value, "%Y%m%d")
+                            .map_err(|_| ClockError("Invalid recurrence start date"))?,
+                    );
+                }
+                _ => return Err(ClockError("Unsupported recurrence rule part")),
+            }
+        }
+
+        if res.interval == 0 {
+            return Err(ClockError("Recurrence interval must be positive"));
+        }
+        if res.by_month.iter().any(|month| !(1..=12).contains(month))
+            || res
+                .by_month_day
+                .iter()
+                .any(|day| *day == 0 || !(-31..=31).contains(day))
+            || res
+                .by_day
+                .iter()
+                .any(|(nth, _)| matches!(nth, Some(nth) if *nth == 0 || !(-5..=5).contains(nth)))
+        {
+            return Err(ClockError("Recurrence rule value out of range"));
+        }
+
+        res.frequency = frequency.ok_or(ClockError("Missing recurrence frequency"))?;
+        Ok(res)
+    }
+}
+
+impl Display for Recurrence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |items: Vec<String>| items.join(",");
+
+        write!(f, "FREQ={}", self.frequency.name())?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_month.is_empty() {
+            let months = self.by_month.iter().map(|month| month.to_string());
+            write!(f, ";BYMONTH={}", join(months.collect()))?;
+        }
+        if !self.by_month_day.is_empty() {
+            let days = self.by_month_day.iter().map(|day| day.to_string());
+            write!(f, ";BYMONTHDAY={}", join(days.collect()))?;
+        }
+        if !self.by_day.is_empty() {
+            let days = self.by_day.iter().map(|(nth, weekday)| {
+                format!(
+                    "{}{}",
+                    nth.map(|nth| nth.to_string()).unwrap_or_default(),
+                    weekday_name(*weekday)
+                )
+            });
+            write!(f, ";BYDAY={}", join(days.collect()))?;
+        }
+        if let Some(start) = self.start {
+            write!(f, ";DTSTART={}", start.format("%Y%m%d"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Serialize for Recurrence {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Recurrence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(|error: ClockError| serde::de::Error::custom(error.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_parsing() {
+        let rule = "RRULE:FREQ=MONTHLY;INTERVAL=2;BYDAY=-1FR,1MO;DTSTART=2023-07-03"
+            .parse::<Recurrence>()
+            .unwrap();
+
+        assert_eq!(rule.frequency, Frequency::Monthly);
+        assert_eq!(rule.interval, 2);
+        assert_eq!(
+            rule.by_day,
+            vec![(Some(-1), Weekday::Fri), (Some(1), Weekday::Mon)]
+        );
+        assert_eq!(
+            rule.to_string(),
+            "FREQ=MONTHLY;INTERVAL=2;BYDAY=-1FR,1MO;DTSTART=20230703"
+        );
+        assert_eq!(rule.to_string().parse::<Recurrence>().unwrap(), rule);
+
+        assert!("BYDAY=MO".parse::<Recurrence>().is_err());
+        assert!("FREQ=HOURLY".parse::<Recurrence>().is_err());
+        assert!("FREQ=DAILY;INTERVAL=0".parse::<Recurrence>().is_err());
+        assert!("FREQ=MONTHLY;BYDAY=6MO".parse::<Recurrence>().is_err());
+        assert!("FREQ=MONTHLY;BYMONTHDAY=32".parse::<Recurrence>().is_err());
+    }
+
+    #[test]
+    fn test_matching() {
+        let last_friday = "FREQ=MONTHLY;BYDAY=-1FR".parse::<Recurrence>().unwrap();
+        let last_day = "FREQ=MONTHLY;BYMONTHDAY=-1".parse::<Recurrence>().unwrap();
+        let every_other_week = "FREQ=WEEKLY;INTERVAL=2;DTSTART=20230703"
+            .parse::<Recurrence>()
+            .unwrap();
+        let leap_day = "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=29"
+            .parse::<Recurrence>()
+            .unwrap();
+
+        assert!(last_friday.matches(date(2023, 6, 30)));
+        assert!(!last_friday.matches(date(2023, 6, 23)));
+        assert!(last_day.matches(date(2024, 2, 29)));
+        assert!(!last_day.matches(date(2024, 2, 28)));
+        // Mondays of every other week, not before the start
+        assert!(every_other_week.matches(date(2023, 7, 17)));
+        assert!(!every_other_week.matches(date(2023, 7, 10)));
+        assert!(!every_other_week.matches(date(2023, 6, 19)));
+        assert_eq!(
+            leap_day.next_date(date(2023, 3, 1)),
+            Some(date(2024, 2, 29))
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use crate::{error::ClockError, message::Message};
+
+/// Size of the sequence number frame closing published messages
+pub const SEQUENCE_LEN: usize = 8;
+
+/// Binary representation of a sequence number (big endian)
+pub fn encode_sequence(sequence: u64) -> [u8; SEQUENCE_LEN] {
+    sequence.to_be_bytes()
+}
+
+/// Decodes a sequence number frame.
+///
+/// # Panics
+///
+/// Panics if the frame does not have the expected size.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::sequence::{decode_sequence, encode_sequence};
+///
+/// assert_eq!(decode_sequence(&encode_sequence(42)).unwrap(), 42);
+/// assert!(decode_sequence(&[0x01]).is_err());
+/// ```
+pub fn decode_sequence(frame: &[u8]) -> Result<u64, ClockError> {
+    Ok(u64::from_be_bytes(frame.try_into()?))
+}
+
+/// Publisher side numbering: each topic has its own sequence, starting at 1 when the daemon
+/// starts, so that clients subscribed to some topics only (or pausing the clock) do not mistake
+/// the messages they filtered out for dropped ones.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::{
+///     alarm::Alarm, clock::ClockMessage, message::Message, sequence::Sequencer,
+/// };
+///
+/// let sequencer = Sequencer::new();
+/// let clock = Message::from(ClockMessage::default());
+///
+/// assert_eq!(sequencer.next(&clock), 1);
+/// assert_eq!(sequencer.next(&clock), 2);
+/// assert_eq!(sequencer.next(&Message::from(Alarm::default())), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct Sequencer {
+    last: Mutex<BTreeMap<&'static [u8], u64>>,
+}
+
+impl Sequencer {
+    pub const fn new() -> Self {
+        Self {
+            last: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Sequence number of `message`, the next one in its topic.
+    pub fn next(&self, message: &Message) -> u64 {
+        let mut last = self
+            .last
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let sequence = last.entry(message.topic()).or_default();
+
+        *sequence += 1;
+        *sequence
+    }
+}
+
+/// Subscriber side check of the sequence numbers, counting the messages dropped on the way (a
+/// zeromq publisher silently discards messages under pressure).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SequenceTracker {
+    last: BTreeMap<&'static [u8], u64>,
+    missed: u64,
+}
+
+impl SequenceTracker {
+    /// Accounts for `message` received with `sequence`, returning the number of messages of its
+    /// topic missed just before it. Nothing is reported missed before the first message of a
+    /// topic, nor when the numbering starts over (the daemon restarted).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::{clock::ClockMessage, message::Message, sequence::SequenceTracker};
+    ///
+    /// let mut tracker = SequenceTracker::default();
+    /// let clock = Message::from(ClockMessage::default());
+    ///
+    /// assert_eq!(tracker.record(&clock, 10), 0);
+    /// assert_eq!(tracker.record(&clock, 13), 2);
+    /// assert_eq!(tracker.missed(), 2);
+    /// ```
+    pub fn record(&mut self, message: &Message, sequence: u64) -> u64 {
+        let missed = match self.last.insert(message.topic(), sequence) {
+            Some(last) if sequence > last => sequence - last - 1,
+            _ => 0,
+        };
+
+        self.missed += missed;
+        missed
+    }
+
+    /// Forgets where `topic` was, eg: when resubscribing to it after a while.
+    pub fn forget(&mut self, topic: &[u8]) {
+        self.last.remove(topic);
+    }
+
+    /// Number of messages missed since the tracker was created
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{alarm::Alarm, clock::ClockMessage, message::CLOCK_TOPIC};
+
+    use super::*;
+
+    #[test]
+    fn test_tracker() {
+        let mut tracker = SequenceTracker::default();
+        let clock = Message::from(ClockMessage::default());
+        let alarm = Message::from(Alarm::default());
+
+        assert_eq!(tracker.record(&clock, 1), 0);
+        assert_eq!(tracker.record(&clock, 2), 0);
+        // Topics are numbered independently
+        assert_eq!(tracker.record(&alarm, 7), 0);
+        assert_eq!(tracker.record(&clock, 5), 2);
+        assert_eq!(tracker.record(&alarm, 8), 0);
+
+        // Daemon restart
+        assert_eq!(tracker.record(&clock, 1), 0);
+        assert_eq!(tracker.record(&clock, 2), 0);
+
+        // Clock paused then resumed
+        tracker.forget(CLOCK_TOPIC);
+        assert_eq!(tracker.record(&clock, 60), 0);
+
+        assert_eq!(tracker.missed(), 2);
+    }
+
+    #[test]
+    fn test_sequencer() {
+        let sequencer = Sequencer::new();
+        let clock = Message::from(ClockMessage::default());
+        let mut tracker = SequenceTracker::default();
+
+        for _ in 0..3 {
+            assert_eq!(tracker.record(&clock, sequencer.next(&clock)), 0);
+        }
+        sequencer.next(&clock);
+        assert_eq!(tracker.record(&clock, sequencer.next(&clock)), 1);
+    }
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use sqlite::State;
+
+use crate::{clock::HourFormat, error::ClockError};
+
+const TNAME: &str = "settings";
+
+/// Display preferences of the user, shared by every frontend using the same database. Stored as
+/// a single row, so that new preferences can be added without migrations.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::{clock::HourFormat, settings::Settings};
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+///
+/// assert_eq!(Settings::load(&conn).unwrap().hour_format, HourFormat::H24);
+///
+/// Settings {
+///     hour_format: HourFormat::H12,
+/// }
+/// .save(&conn)
+/// .unwrap();
+///
+/// assert_eq!(Settings::load(&conn).unwrap().hour_format, HourFormat::H12);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    #[serde(default)]
+    pub hour_format: HourFormat,
+}
+
+impl Settings {
+    // Essential db check
+    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER PRIMARY KEY CHECK (id = 0),
+            value TEXT NOT NULL
+            )",
+            TNAME
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    /// Stores the settings, replacing the previous ones. Creates the table 'settings' if not
+    /// present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered.
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "INSERT OR REPLACE INTO {} (id, value) VALUES (0, :value)",
+            TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":value", serde_json::to_string(self)?.as_str()))?;
+        statement.next()?;
+
+        Ok(())
+    }
+
+    /// Restores the stored settings (the default ones if none were stored).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered.
+    pub fn load(conn: &sqlite::Connection) -> Result<Self, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("SELECT * FROM {} WHERE id = 0", TNAME);
+        let mut statement = conn.prepare(query)?;
+
+        if let Ok(State::Row) = statement.next() {
+            Ok(serde_json::from_str(
+                &statement.read::<String, _>("value")?,
+            )?)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
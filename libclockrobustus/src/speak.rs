@@ -0,0 +1,365 @@
+use chrono::{NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::{
+    codec,
+    error::{ClockError, DecodeError},
+};
+
+const EN_NUMBERS: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+const FR_NUMBERS: [&str; 17] = [
+    "zéro", "une", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf", "dix", "onze",
+    "douze", "treize", "quatorze", "quinze", "seize",
+];
+const FR_TENS: [&str; 6] = ["", "dix", "vingt", "trente", "quarante", "cinquante"];
+
+/// Language of the announcements.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SpeakLocale {
+    /// 12-hour phrasing, eg: "It is twenty to eight"
+    #[default]
+    En,
+    /// 24-hour phrasing, eg: "Il est dix-neuf heures quarante"
+    Fr,
+}
+
+impl FromStr for SpeakLocale {
+    type Err = ClockError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "en" => Ok(Self::En),
+            "fr" => Ok(Self::Fr),
+            _ => Err(ClockError("Unknown locale")),
+        }
+    }
+}
+
+/// English words of 1 to 29
+fn en_number(n: u8) -> String {
+    match n {
+        0..=19 => EN_NUMBERS[n as usize].to_string(),
+        20 => "twenty".to_string(),
+        _ => format!("twenty-{}", EN_NUMBERS[n as usize - 20]),
+    }
+}
+
+/// French words of 1 to 59, feminine (as "heure" and "minute" are)
+fn fr_number(n: u8) -> String {
+    match (n / 10, n % 10) {
+        _ if n <= 16 => FR_NUMBERS[n as usize].to_string(),
+        (1, units) => format!("dix-{}", FR_NUMBERS[units as usize]),
+        (tens, 0) => FR_TENS[tens as usize].to_string(),
+        (tens, 1) => format!("{} et une", FR_TENS[tens as usize]),
+        (tens, units) => format!("{}-{}", FR_TENS[tens as usize], FR_NUMBERS[units as usize]),
+    }
+}
+
+fn en_phrase(hours: u8, minutes: u8) -> String {
+    // Past the hour up to half past, to the next one after
+    let (relative, hour) = match minutes {
+        0..=30 => (minutes, hours),
+        _ => (60 - minutes, (hours + 1) % 24),
+    };
+    let hour = match hour {
+        0 => "midnight".to_string(),
+        12 => "noon".to_string(),
+        _ => en_number((hour + 11) % 12 + 1),
+    };
+    let relative = match relative {
+        15 => "quarter".to_string(),
+        30 => "half".to_string(),
+        1 => "one minute".to_string(),
+        _ if relative % 5 == 0 => en_number(relative),
+        _ => format!("{} minutes", en_number(relative)),
+    };
+
+    match (minutes, hours) {
+        (0, 0) | (0, 12) => format!("It is {}", hour),
+        (0, _) => format!("It is {} o'clock", hour),
+        (1..=30, _) => format!("It is {} past {}", relative, hour),
+        _ => format!("It is {} to {}", relative, hour),
+    }
+}
+
+fn fr_phrase(hours: u8, minutes: u8) -> String {
+    let hour = match hours {
+        0 => "minuit".to_string(),
+        12 => "midi".to_string(),
+        1 => "une heure".to_string(),
+        _ => format!("{} heures", fr_number(hours)),
+    };
+
+    match minutes {
+        0 => format!("Il est {}", hour),
+        _ => format!("Il est {} {}", hour, fr_number(minutes)),
+    }
+}
+
+/// Spoken form of a time of day.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::speak::{phrase, SpeakLocale};
+///
+/// assert_eq!(phrase(19, 30, SpeakLocale::En), "It is half past seven");
+/// assert_eq!(phrase(7, 40, SpeakLocale::En), "It is twenty to eight");
+/// assert_eq!(phrase(19, 31, SpeakLocale::Fr), "Il est dix-neuf heures trente et une");
+/// ```
+pub fn phrase(hours: u8, minutes: u8, locale: SpeakLocale) -> String {
+    match locale {
+        SpeakLocale::En => en_phrase(hours, minutes),
+        SpeakLocale::Fr => fr_phrase(hours, minutes),
+    }
+}
+
+/// When the speaking clock announces the time: every `every_minutes` minutes from midnight.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveTime;
+/// use libclockrobustus::speak::{SpeakLocale, SpeakSchedule};
+///
+/// let schedule: SpeakSchedule = "15,fr".parse().unwrap();
+/// let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+///
+/// assert_eq!(schedule.locale, SpeakLocale::Fr);
+/// assert_eq!(schedule.slot(at(7, 14)), schedule.slot(at(7, 0)));
+/// assert_ne!(schedule.slot(at(7, 15)), schedule.slot(at(7, 14)));
+/// assert!("0".parse::<SpeakSchedule>().is_err());
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SpeakSchedule {
+    pub every_minutes: u16,
+    pub locale: SpeakLocale,
+}
+
+impl FromStr for SpeakSchedule {
+    type Err = ClockError;
+
+    /// Parses `minutes[,locale]` (eg: `30` or `15,fr`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (every_minutes, locale) = s.split_once(',').unwrap_or((s, "en"));
+        let every_minutes = every_minutes
+            .trim()
+            .parse::<u16>()
+            .map_err(|_| ClockError("Invalid speaking clock interval"))?;
+
+        if !(1..=1440).contains(&every_minutes) {
+            return Err(ClockError(
+                "Speaking clock interval must be 1 to 1440 minutes",
+            ));
+        }
+
+        Ok(Self {
+            every_minutes,
+            locale: locale.parse()?,
+        })
+    }
+}
+
+impl SpeakSchedule {
+    /// Index of the announcement period `time` falls in. The time is announced whenever the
+    /// slot changes.
+    pub fn slot(&self, time: NaiveTime) -> u32 {
+        (time.hour() * 60 + time.minute()) / self.every_minutes as u32
+    }
+
+    /// Announcement of `time`, to the minute.
+    pub fn announce(&self, time: NaiveTime) -> SpeakMessage {
+        let (hours, minutes) = (time.hour() as u8, time.minute() as u8);
+
+        SpeakMessage {
+            hours,
+            minutes,
+            locale: self.locale,
+            text: phrase(hours, minutes, self.locale),
+        }
+    }
+}
+
+/// Time to announce, as published by the speaking clock of the daemon. The text is meant to be
+/// fed to a text-to-speech engine of the given locale.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeakMessage {
+    pub hours: u8,
+    pub minutes: u8,
+    pub locale: SpeakLocale,
+    /// At most [SpeakMessage::MAX_TEXT_LEN] bytes
+    pub text: String,
+}
+
+impl SpeakMessage {
+    /// Longest text (in bytes) a message can carry
+    pub const MAX_TEXT_LEN: usize = 128;
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits)
+    pub const MAX_ENCODED_LEN: usize = 5 + Self::MAX_TEXT_LEN;
+
+    /// Writes the binary representation at the start of a caller-provided buffer, returning the
+    /// number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the text is too long, or if the buffer is too small for it.
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        if self.text.len() > Self::MAX_TEXT_LEN {
+            return Err(ClockError("Speak message text too long"));
+        }
+
+        codec::encode(self, buffer)
+    }
+
+    /// Strict counterpart of the [TryFrom] conversion from bytes: truncated messages, trailing
+    /// bytes (see [codec::decode_strict]), out of range times and texts too long are rejected.
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let message: Self = codec::decode_strict(bytes)?;
+
+        DecodeError::check_range(message.hours, "hours", 0..=23)?;
+        DecodeError::check_range(message.minutes, "minutes", 0..=59)?;
+        DecodeError::check_range(message.text.len(), "text", 0..=Self::MAX_TEXT_LEN)?;
+        Ok(message)
+    }
+}
+
+impl TryFrom<Vec<u8>> for SpeakMessage {
+    type Error = ClockError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        codec::decode(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_en_phrase() {
+        let cases = [
+            (0, 0, "It is midnight"),
+            (0, 15, "It is quarter past midnight"),
+            (7, 0, "It is seven o'clock"),
+            (7, 1, "It is one minute past seven"),
+            (7, 5, "It is five past seven"),
+            (7, 23, "It is twenty-three minutes past seven"),
+            (11, 45, "It is quarter to noon"),
+            (12, 0, "It is noon"),
+            (13, 0, "It is one o'clock"),
+            (19, 30, "It is half past seven"),
+            (23, 57, "It is three minutes to midnight"),
+        ];
+
+        for (hours, minutes, expected) in cases {
+            assert_eq!(phrase(hours, minutes, SpeakLocale::En), expected);
+        }
+    }
+
+    #[test]
+    fn test_fr_phrase() {
+        let cases = [
+            (0, 0, "Il est minuit"),
+            (0, 5, "Il est minuit cinq"),
+            (1, 0, "Il est une heure"),
+            (12, 17, "Il est midi dix-sept"),
+            (21, 0, "Il est vingt et une heures"),
+            (8, 45, "Il est huit heures quarante-cinq"),
+            (19, 59, "Il est dix-neuf heures cinquante-neuf"),
+        ];
+
+        for (hours, minutes, expected) in cases {
+            assert_eq!(phrase(hours, minutes, SpeakLocale::Fr), expected);
+        }
+    }
+
+    #[test]
+    fn test_schedule() {
+        assert_eq!(
+            "30".parse::<SpeakSchedule>().unwrap(),
+            SpeakSchedule {
+                every_minutes: 30,
+                locale: SpeakLocale::En
+            }
+        );
+        assert!("1441".parse::<SpeakSchedule>().is_err());
+        assert!("30,de".parse::<SpeakSchedule>().is_err());
+
+        let hourly: SpeakSchedule = "60".parse().unwrap();
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert_eq!(hourly.slot(at(23, 59)), 23);
+        assert_eq!(hourly.slot(at(0, 0)), 0);
+        assert_eq!(hourly.announce(at(7, 0)).text, "It is seven o'clock");
+    }
+
+    #[test]
+    fn test_binary_conversion() {
+        let message = "30,fr"
+            .parse::<SpeakSchedule>()
+            .unwrap()
+            .announce(NaiveTime::from_hms_opt(7, 30, 12).unwrap());
+        let longest = SpeakMessage {
+            text: "a".repeat(SpeakMessage::MAX_TEXT_LEN),
+            ..message.clone()
+        };
+        let mut buffer = [0u8; SpeakMessage::MAX_ENCODED_LEN + 1];
+
+        assert_eq!(
+            longest.encode_into(&mut buffer).unwrap(),
+            SpeakMessage::MAX_ENCODED_LEN
+        );
+
+        let len = message.encode_into(&mut buffer).unwrap();
+
+        assert_eq!(SpeakMessage::try_from(buffer.to_vec()).unwrap(), message);
+        assert_eq!(
+            SpeakMessage::try_from_strict(&buffer[..len]).unwrap(),
+            message
+        );
+        assert_eq!(
+            SpeakMessage::try_from_strict(&buffer[..len - 1])
+                .unwrap_err()
+                .reason,
+            "Missing field"
+        );
+        assert_eq!(
+            SpeakMessage::try_from_strict(&buffer[..len + 1])
+                .unwrap_err()
+                .reason,
+            "Trailing bytes"
+        );
+
+        buffer[2] = 2;
+        assert_eq!(
+            SpeakMessage::try_from_strict(&buffer[..len])
+                .unwrap_err()
+                .reason,
+            "Invalid message body"
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use serde::{Deserialize, Serialize};
+use sqlite::State;
+
+use crate::error::ClockError;
+
+const TNAME: &str = "timekeeping";
+/// Row keys of the timekeeping table
+const TIMER_KEY: &str = "timer";
+const STOPWATCH_KEY: &str = "stopwatch";
+
+/// Raw row of the timekeeping table, shared by [Timer] and [Stopwatch]. Start times being wall
+/// clock times, a restored running countdown keeps counting through the downtime.
+#[derive(Default)]
+struct Row {
+    duration_ms: u64,
+    elapsed_ms: u64,
+    started_at: Option<u64>,
+    notified: bool,
+    laps: Vec<u64>,
+}
+
+impl Row {
+    // Essential db check
+    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            kind TEXT PRIMARY KEY,
+            duration_ms INTEGER NOT NULL DEFAULT 0,
+            elapsed_ms INTEGER NOT NULL DEFAULT 0,
+            started_at INTEGER,
+            notified INTEGER NOT NULL DEFAULT 0,
+            laps TEXT NOT NULL DEFAULT '[]'
+            )",
+            TNAME
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    fn save(&self, conn: &sqlite::Connection, kind: &str) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "INSERT OR REPLACE INTO {} (kind, duration_ms, elapsed_ms, started_at, notified, laps)
+            VALUES (:kind, :duration_ms, :elapsed_ms, :started_at, :notified, :laps)",
+            TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+        statement.bind((":kind", kind))?;
+        statement.bind((":duration_ms", self.duration_ms as i64))?;
+        statement.bind((":elapsed_ms", self.elapsed_ms as i64))?;
+        statement.bind((
+            ":started_at",
+            self.started_at.map(|started_at| started_at as i64),
+        ))?;
+        statement.bind((":notified", self.notified as i64))?;
+        statement.bind((":laps", serde_json::to_string(&self.laps)?.as_str()))?;
+        statement.next()?;
+
+        Ok(())
+    }
+
+    fn load(conn: &sqlite::Connection, kind: &str) -> Result<Self, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("SELECT * FROM {} WHERE kind = :kind", TNAME);
+        let mut statement = conn.prepare(query)?;
+        statement.bind((":kind", kind))?;
+
+        if let Ok(State::Row) = statement.next() {
+            Ok(Self {
+                duration_ms: statement.read::<i64, _>("duration_ms")? as u64,
+                elapsed_ms: statement.read::<i64, _>("elapsed_ms")? as u64,
+                started_at: statement
+                    .read::<Option<i64>, _>("started_at")?
+                    .map(|started_at| started_at as u64),
+                notified: statement.read::<i64, _>("notified")? != 0,
+                laps: serde_json::from_str(&statement.read::<String, _>("laps")?)?,
+            })
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+/// Countdown kept by the daemon. Times are milliseconds since the Unix epoch (see
+/// [crate::latency::now_ms]), every method takes the current time so the state is easy to
+/// snapshot and to test.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::timer::Timer;
+///
+/// let mut timer = Timer::default();
+///
+/// timer.start(Some(60_000), 1_000);
+/// timer.pause(11_000);
+///
+/// assert_eq!(timer.state(50_000).remaining_ms, 50_000);
+///
+/// timer.start(None, 50_000);
+///
+/// assert!(!timer.state(99_000).finished);
+/// assert!(timer.state(100_000).finished);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Timer {
+    duration_ms: u64,
+    /// Time elapsed before the current run
+    elapsed_ms: u64,
+    /// Start of the current run, [None] while paused
+    started_at: Option<u64>,
+    /// The end of the countdown has been notified
+    notified: bool,
+}
+
+/// What clients get to display a [Timer].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerState {
+    pub duration_ms: u64,
+    pub remaining_ms: u64,
+    pub running: bool,
+    pub finished: bool,
+}
+
+impl Timer {
+    fn elapsed(&self, now: u64) -> u64 {
+        self.elapsed_ms
+            + self
+                .started_at
+                .map(|started_at| now.saturating_sub(started_at))
+                .unwrap_or(0)
+    }
+
+    /// Starts a new countdown of `duration_ms`, or resumes the paused one if no duration is
+    /// given.
+    pub fn start(&mut self, duration_ms: Option<u64>, now: u64) {
+        if let Some(duration_ms) = duration_ms {
+            *self = Self {
+                duration_ms,
+                ..Default::default()
+            };
+        }
+        if self.started_at.is_none() {
+            self.started_at = Some(now);
+        }
+    }
+
+    /// Pauses the countdown, [Timer::start] resumes it.
+    pub fn pause(&mut self, now: u64) {
+        self.elapsed_ms = self.elapsed(now);
+        self.started_at = None;
+    }
+
+    /// Stops the countdown and brings it back to its full duration.
+    pub fn reset(&mut self) {
+        *self = Self {
+            duration_ms: self.duration_ms,
+            ..Default::default()
+        };
+    }
+
+    pub fn state(&self, now: u64) -> TimerState {
+        let remaining_ms = self.duration_ms.saturating_sub(self.elapsed(now));
+
+        TimerState {
+            duration_ms: self.duration_ms,
+            remaining_ms,
+            running: self.started_at.is_some() && remaining_ms > 0,
+            finished: self.duration_ms > 0 && remaining_ms == 0,
+        }
+    }
+
+    /// Stores the timer, so a restarted daemon resumes it (see [Timer::load]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered.
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Row {
+            duration_ms: self.duration_ms,
+            elapsed_ms: self.elapsed_ms,
+            started_at: self.started_at,
+            notified: self.notified,
+            laps: vec![],
+        }
+        .save(conn, TIMER_KEY)
+    }
+
+    /// Restores the stored timer (a fresh one if none was stored).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered.
+    pub fn load(conn: &sqlite::Connection) -> Result<Self, ClockError> {
+        let row = Row::load(conn, TIMER_KEY)?;
+
+        Ok(Self {
+            duration_ms: row.duration_ms,
+            elapsed_ms: row.elapsed_ms,
+            started_at: row.started_at,
+            notified: row.notified,
+        })
+    }
+
+    /// True the first time it is called after the countdown reached zero, so its end is
+    /// notified once.
+    pub fn take_finished(&mut self, now: u64) -> bool {
+        if self.state(now).finished && !self.notified {
+            self.notified = true;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Stopwatch kept by the daemon, with lap times. Times are milliseconds since the Unix epoch,
+/// like [Timer].
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::timer::Stopwatch;
+///
+/// let mut stopwatch = Stopwatch::default();
+///
+/// stopwatch.start(0);
+/// stopwatch.lap(1_500);
+/// stopwatch.pause(2_000);
+///
+/// let state = stopwatch.state(9_000);
+///
+/// assert_eq!(state.elapsed_ms, 2_000);
+/// assert_eq!(state.laps, vec![1_500]);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Stopwatch {
+    /// Time elapsed before the current run
+    elapsed_ms: u64,
+    /// Start of the current run, [None] while paused
+    started_at: Option<u64>,
+    /// Total elapsed time at each lap
+    laps: Vec<u64>,
+}
+
+/// What clients get to display a [Stopwatch].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StopwatchState {
+    pub elapsed_ms: u64,
+    pub running: bool,
+    /// Total elapsed time at each lap
+    pub laps: Vec<u64>,
+}
+
+impl Stopwatch {
+    fn elapsed(&self, now: u64) -> u64 {
+        self.elapsed_ms
+            + self
+                .started_at
+                .map(|started_at| now.saturating_sub(started_at))
+                .unwrap_or(0)
+    }
+
+    /// Starts (or resumes) the stopwatch.
+    pub fn start(&mut self, now: u64) {
+        if self.started_at.is_none() {
+            self.started_at = Some(now);
+        }
+    }
+
+    pub fn pause(&mut self, now: u64) {
+        self.elapsed_ms = self.elapsed(now);
+        self.started_at = None;
+    }
+
+    /// Stops the stopwatch and clears its laps.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Records a lap (only while running).
+    pub fn lap(&mut self, now: u64) {
+        if self.started_at.is_some() {
+            self.laps.push(self.elapsed(now));
+        }
+    }
+
+    /// Stores the stopwatch, so a restarted daemon resumes it (see [Stopwatch::load]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered.
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Row {
+            elapsed_ms: self.elapsed_ms,
+            started_at: self.started_at,
+            laps: self.laps.clone(),
+            ..Default::default()
+        }
+        .save(conn, STOPWATCH_KEY)
+    }
+
+    /// Restores the stored stopwatch (a fresh one if none was stored).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered.
+    pub fn load(conn: &sqlite::Connection) -> Result<Self, ClockError> {
+        let row = Row::load(conn, STOPWATCH_KEY)?;
+
+        Ok(Self {
+            elapsed_ms: row.elapsed_ms,
+            started_at: row.started_at,
+            laps: row.laps,
+        })
+    }
+
+    pub fn state(&self, now: u64) -> StopwatchState {
+        StopwatchState {
+            elapsed_ms: self.elapsed(now),
+            running: self.started_at.is_some(),
+            laps: self.laps.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn test_timer() {
+        let mut timer = Timer::default();
+
+        assert!(!timer.state(0).finished);
+
+        timer.start(Some(10_000), 0);
+        assert!(timer.state(5_000).running);
+        assert!(!timer.take_finished(5_000));
+        assert!(timer.take_finished(12_000));
+        // Notified once
+        assert!(!timer.take_finished(13_000));
+        assert!(!timer.state(13_000).running);
+
+        timer.reset();
+        assert_eq!(timer.state(20_000).remaining_ms, 10_000);
+        assert!(!timer.state(20_000).running);
+
+        timer.start(None, 20_000);
+        assert_eq!(timer.state(21_000).remaining_ms, 9_000);
+    }
+
+    #[test]
+    fn test_stopwatch() {
+        let mut stopwatch = Stopwatch::default();
+
+        // Laps are only recorded while running
+        stopwatch.lap(0);
+        stopwatch.start(1_000);
+        stopwatch.start(2_000);
+        stopwatch.lap(3_000);
+        stopwatch.pause(4_000);
+        stopwatch.start(10_000);
+
+        let state = stopwatch.state(11_000);
+
+        assert_eq!(state.elapsed_ms, 4_000);
+        assert_eq!(state.laps, vec![2_000]);
+        assert!(state.running);
+
+        stopwatch.reset();
+        assert_eq!(
+            stopwatch.state(12_000),
+            StopwatchState {
+                elapsed_ms: 0,
+                running: false,
+                laps: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_persistence() {
+        let conn = Connection::open(":memory:").unwrap();
+        let mut timer = Timer::default();
+        let mut stopwatch = Stopwatch::default();
+
+        assert_eq!(Timer::load(&conn).unwrap(), timer);
+        assert_eq!(Stopwatch::load(&conn).unwrap(), stopwatch);
+
+        timer.start(Some(60_000), 1_000);
+        stopwatch.start(500);
+        stopwatch.lap(700);
+        timer.save(&conn).unwrap();
+        stopwatch.save(&conn).unwrap();
+
+        // Still running after a "restart"
+        assert_eq!(
+            Timer::load(&conn).unwrap().state(31_000).remaining_ms,
+            30_000
+        );
+        assert_eq!(Stopwatch::load(&conn).unwrap(), stopwatch);
+    }
+}
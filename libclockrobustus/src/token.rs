@@ -0,0 +1,295 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlite::State;
+
+use crate::{add_missing_columns, error::ClockError};
+
+const TNAME: &str = "api_tokens";
+/// Number of random bytes in a token secret
+const SECRET_LEN: usize = 32;
+/// Columns added after the first release of the table. Tokens issued before roles existed keep
+/// full access.
+const LATE_COLUMNS: &[(&str, &str)] = &[("role", "TEXT NOT NULL DEFAULT 'admin'")];
+
+/// What the bearer of a token is allowed to do. Roles are ordered: an admin may do anything a
+/// read-only client may.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::token::Role;
+///
+/// assert!(Role::Admin > Role::ReadOnly);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Role {
+    /// Subscribe to events and list alarms.
+    ReadOnly,
+    /// Anything, including alarm changes and daemon shutdown.
+    Admin,
+}
+
+impl Role {
+    /// Database representation
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::ReadOnly => "read-only",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl TryFrom<&str> for Role {
+    type Error = ClockError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "read-only" => Ok(Self::ReadOnly),
+            "admin" => Ok(Self::Admin),
+            _ => Err(ClockError("Unknown token role")),
+        }
+    }
+}
+
+/// A credential for the remote surfaces of the daemon (control socket, HTTP, gRPC). Only a hash
+/// of the secret is stored: the secret itself is shown once, when issued.
+///
+/// As long as no active token exists, remote surfaces stay open (local, single user setups).
+/// Issuing the first token locks them.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::token::{ApiToken, Role};
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+///
+/// // Nothing issued yet, anyone is authorized
+/// assert!(ApiToken::authorize(&conn, None).unwrap());
+///
+/// let (token, secret) = ApiToken::issue(&conn, "dashboard", Role::Admin).unwrap();
+///
+/// assert!(!ApiToken::authorize(&conn, None).unwrap());
+/// assert!(!ApiToken::authorize(&conn, Some("guess")).unwrap());
+/// assert!(ApiToken::authorize(&conn, Some(&secret)).unwrap());
+///
+/// ApiToken::revoke(&conn, token.id.unwrap()).unwrap();
+///
+/// assert!(ApiToken::authorize(&conn, None).unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    pub id: Option<i64>,
+    /// What the token is used for
+    pub name: String,
+    /// Unix timestamp (seconds)
+    pub created_at: i64,
+    pub revoked: bool,
+    pub role: Role,
+}
+
+/// Hex encoded SHA-256 of a secret
+fn hash(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+impl ApiToken {
+    // Essential db check
+    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            hash TEXT NOT NULL UNIQUE,
+            created_at INTEGER NOT NULL,
+            revoked INTEGER NOT NULL DEFAULT 0
+            )",
+            TNAME
+        );
+        conn.execute(query)?;
+
+        add_missing_columns(conn, TNAME, LATE_COLUMNS)
+    }
+
+    /// Creates a new token, returning it along with its secret (the only time the secret is
+    /// available).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the system random generator fails or if an SQL error is encountered.
+    pub fn issue(
+        conn: &sqlite::Connection,
+        name: &str,
+        role: Role,
+    ) -> Result<(Self, String), ClockError> {
+        Self::check_table(conn)?;
+        let mut bytes = [0u8; SECRET_LEN];
+        getrandom::getrandom(&mut bytes)
+            .map_err(|_| ClockError("Unable to generate a random token"))?;
+        let secret = bytes
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        let created_at = Local::now().timestamp();
+
+        let query = format!(
+            "INSERT INTO {} (name, hash, created_at, role) VALUES (:name, :hash, :created_at, :role)",
+            TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+        statement.bind((":name", name))?;
+        statement.bind((":hash", hash(&secret).as_str()))?;
+        statement.bind((":created_at", created_at))?;
+        statement.bind((":role", role.as_str()))?;
+        statement.next()?;
+
+        let mut statement = conn.prepare("SELECT last_insert_rowid() AS id")?;
+        statement.next()?;
+
+        Ok((
+            Self {
+                id: Some(statement.read::<i64, _>("id")?),
+                name: name.to_string(),
+                created_at,
+                revoked: false,
+                role,
+            },
+            secret,
+        ))
+    }
+
+    /// Revokes a token: its secret is not accepted anymore.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no token has this id or if an SQL error is encountered.
+    pub fn revoke(conn: &sqlite::Connection, id: i64) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("UPDATE {} SET revoked = 1 WHERE id = {}", TNAME, id);
+        conn.execute(query)?;
+
+        let mut statement = conn.prepare("SELECT changes() AS count")?;
+        statement.next()?;
+
+        if statement.read::<i64, _>("count")? == 0 {
+            Err(ClockError("No such token"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get every token ever issued (revoked ones included)
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn all(conn: &sqlite::Connection) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("SELECT * FROM {} ORDER BY id", TNAME);
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            res.push(Self {
+                id: Some(statement.read::<i64, _>("id")?),
+                name: statement.read::<String, _>("name")?,
+                created_at: statement.read::<i64, _>("created_at")?,
+                revoked: statement.read::<i64, _>("revoked")? != 0,
+                role: Role::try_from(statement.read::<String, _>("role")?.as_str())?,
+            })
+        }
+
+        Ok(res)
+    }
+
+    /// Tells whether a client presenting `secret` may use the remote surfaces: true if the
+    /// secret matches an active token, or if no active token exists at all.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    pub fn authorize(conn: &sqlite::Connection, secret: Option<&str>) -> Result<bool, ClockError> {
+        Ok(Self::role(conn, secret)?.is_some())
+    }
+
+    /// Gives the [Role] granted to a client presenting `secret`: the role of the matching active
+    /// token, [Role::Admin] if no active token exists at all, [None] if the client is not
+    /// authorized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libclockrobustus::token::{ApiToken, Role};
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    ///
+    /// assert_eq!(ApiToken::role(&conn, None).unwrap(), Some(Role::Admin));
+    ///
+    /// let (_, secret) = ApiToken::issue(&conn, "wall display", Role::ReadOnly).unwrap();
+    ///
+    /// assert_eq!(ApiToken::role(&conn, None).unwrap(), None);
+    /// assert_eq!(ApiToken::role(&conn, Some(&secret)).unwrap(), Some(Role::ReadOnly));
+    /// ```
+    pub fn role(
+        conn: &sqlite::Connection,
+        secret: Option<&str>,
+    ) -> Result<Option<Role>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!(
+            "SELECT COUNT(*) AS active, MAX(CASE WHEN hash = :hash THEN role END) AS matching
+            FROM {} WHERE revoked = 0",
+            TNAME
+        );
+        let mut statement = conn.prepare(query)?;
+        statement.bind((":hash", hash(secret.unwrap_or_default()).as_str()))?;
+        statement.next()?;
+
+        if statement.read::<i64, _>("active")? == 0 {
+            return Ok(Some(Role::Admin));
+        }
+
+        match (secret, statement.read::<Option<String>, _>("matching")?) {
+            (Some(_), Some(role)) => Ok(Some(Role::try_from(role.as_str())?)),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn test_issue_and_list() {
+        let conn = Connection::open(":memory:").unwrap();
+        let (first, first_secret) = ApiToken::issue(&conn, "first", Role::Admin).unwrap();
+        let (second, second_secret) = ApiToken::issue(&conn, "second", Role::ReadOnly).unwrap();
+
+        assert_ne!(first_secret, second_secret);
+        assert_eq!(first_secret.len(), SECRET_LEN * 2);
+        assert_eq!(ApiToken::all(&conn).unwrap(), vec![first.clone(), second]);
+
+        ApiToken::revoke(&conn, first.id.unwrap()).unwrap();
+
+        assert!(ApiToken::all(&conn).unwrap()[0].revoked);
+        assert!(!ApiToken::authorize(&conn, Some(&first_secret)).unwrap());
+        assert!(ApiToken::authorize(&conn, Some(&second_secret)).unwrap());
+        assert_eq!(
+            ApiToken::role(&conn, Some(&second_secret)).unwrap(),
+            Some(Role::ReadOnly)
+        );
+        assert!(ApiToken::revoke(&conn, 42).is_err());
+    }
+}
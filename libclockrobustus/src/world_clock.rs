@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+use sqlite::State;
+
+use crate::{
+    clock::{ClockMessage, ThemeHours, Zone},
+    codec,
+    error::{ClockError, DecodeError},
+};
+
+const TNAME: &str = "world_clocks";
+
+/// Extra timezone displayed next to the main clock (eg: "Office" in `America/New_York`). Every
+/// tick the daemon publishes a [WorldClockMessage] for each of them.
+///
+/// # Examples
+///
+/// ```
+/// use libclockrobustus::{clock::Zone, world_clock::WorldClock};
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+/// let office = WorldClock {
+///     label: "Office".to_string(),
+///     zone: "America/New_York".parse::<Zone>().unwrap(),
+///     ..Default::default()
+/// };
+///
+/// office.save(&conn).unwrap();
+/// let clocks = WorldClock::all(&conn).unwrap();
+///
+/// assert_eq!(clocks[0].zone.to_string(), "America/New_York");
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldClock {
+    pub id: Option<i64>,
+    /// Text displayed to the user, at most [WorldClockMessage::MAX_LABEL_LEN] bytes
+    pub label: String,
+    pub zone: Zone,
+}
+
+impl WorldClock {
+    // Essential db check
+    fn check_table(conn: &sqlite::Connection) -> Result<(), ClockError> {
+        let query = format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER PRIMARY KEY,
+            label TEXT NOT NULL,
+            zone TEXT NOT NULL
+            )",
+            TNAME
+        );
+        conn.execute(query)?;
+
+        Ok(())
+    }
+
+    /// Saves (creates or updates) the world clock. Creates the table 'world_clocks' if not
+    /// present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the label is blank or too long, or if an SQL error has been encountered
+    pub fn save(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        if self.label.trim().is_empty() {
+            return Err(ClockError("World clock label must not be empty"));
+        }
+        if self.label.len() > WorldClockMessage::MAX_LABEL_LEN {
+            return Err(ClockError("World clock label too long"));
+        }
+
+        Self::check_table(conn)?;
+        let query = match self.id {
+            Some(_) => format!(
+                "UPDATE {} SET label = :label, zone = :zone WHERE id = :id",
+                TNAME
+            ),
+            None => format!("INSERT INTO {} (label, zone) VALUES (:label, :zone)", TNAME),
+        };
+        let mut statement = conn.prepare(query)?;
+
+        statement.bind((":label", self.label.as_str()))?;
+        statement.bind((":zone", self.zone.to_string().as_str()))?;
+        if let Some(id) = self.id {
+            statement.bind((":id", id))?;
+        }
+        statement.next()?;
+
+        Ok(())
+    }
+
+    /// Get every world clock, in the order they were added
+    ///
+    /// # Panics
+    ///
+    /// Panics if a SQL error is encountered or if a stored zone is unknown
+    pub fn all(conn: &sqlite::Connection) -> Result<Vec<Self>, ClockError> {
+        Self::check_table(conn)?;
+        let query = format!("SELECT * FROM {} ORDER BY id", TNAME);
+        let mut res = Vec::new();
+        let mut statement = conn.prepare(query)?;
+
+        while let Ok(State::Row) = statement.next() {
+            res.push(WorldClock {
+                id: Some(statement.read::<i64, _>("id")?),
+                label: statement.read::<String, _>("label")?,
+                zone: statement.read::<String, _>("zone")?.parse()?,
+            })
+        }
+
+        Ok(res)
+    }
+
+    /// Removes a saved world clock
+    ///
+    /// # Panics
+    ///
+    /// Panics if the world clock has no id (eg: not saved).
+    pub fn remove(&self, conn: &sqlite::Connection) -> Result<(), ClockError> {
+        Self::check_table(conn)?;
+        let id = self
+            .id
+            .ok_or(ClockError("Impossible to delete an unsaved world clock"))?;
+        let query = format!("DELETE FROM {} WHERE id = {}", TNAME, id);
+
+        conn.execute(query)?;
+        Ok(())
+    }
+
+    /// Message carrying the current time in the zone of the world clock, its theme hint
+    /// following `theme_hours` there.
+    pub fn message(&self, theme_hours: &ThemeHours) -> WorldClockMessage {
+        WorldClockMessage {
+            id: self.id.unwrap_or_default(),
+            zone: self.zone,
+            label: self.label.clone(),
+            clock: ClockMessage::now_in(&self.zone).with_theme_hours(theme_hours),
+        }
+    }
+}
+
+/// Time of a [WorldClock], as published by the daemon. The zone and the label are carried so
+/// that clients can render one labelled dial per zone straight away.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldClockMessage {
+    pub id: i64,
+    pub zone: Zone,
+    pub label: String,
+    pub clock: ClockMessage,
+}
+
+impl WorldClockMessage {
+    /// Longest label (in bytes) a message can carry
+    pub const MAX_LABEL_LEN: usize = 64;
+    /// Longest zone name (in bytes) a message can carry
+    pub const MAX_ZONE_LEN: usize = 64;
+    /// Size of the largest binary representation (see [crate::codec], integers take up to one
+    /// more byte per 7 bits)
+    pub const MAX_ENCODED_LEN: usize =
+        12 + Self::MAX_ZONE_LEN + Self::MAX_LABEL_LEN + ClockMessage::MAX_ENCODED_LEN;
+
+    /// Writes the binary representation at the start of a caller-provided buffer, returning the
+    /// number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the zone name or the label is too long, or if the buffer is too small for them.
+    pub fn encode_into(&self, buffer: &mut [u8]) -> Result<usize, ClockError> {
+        if self.zone.to_string().len() > Self::MAX_ZONE_LEN
+            || self.label.len() > Self::MAX_LABEL_LEN
+        {
+            return Err(ClockError("World clock zone or label too long"));
+        }
+
+        codec::encode(self, buffer)
+    }
+
+    /// Strict counterpart of the [TryFrom] conversion from bytes: truncated messages, trailing
+    /// bytes (see [codec::decode_strict]), zone names or labels too long and clock messages out
+    /// of range (see [ClockMessage::try_from_strict]) are rejected.
+    pub fn try_from_strict(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let message: Self = codec::decode_strict(bytes)?;
+
+        DecodeError::check_range(
+            message.zone.to_string().len(),
+            "zone",
+            0..=Self::MAX_ZONE_LEN,
+        )?;
+        DecodeError::check_range(message.label.len(), "label", 0..=Self::MAX_LABEL_LEN)?;
+        message.clock.check_ranges()?;
+        Ok(message)
+    }
+}
+
+impl TryFrom<Vec<u8>> for WorldClockMessage {
+    type Error = ClockError;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        codec::decode(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlite::Connection;
+
+    use super::*;
+
+    #[test]
+    fn test_saving() {
+        let conn = Connection::open(":memory:").unwrap();
+        let home = WorldClock {
+            label: "Home".to_string(),
+            zone: Zone::Named(chrono_tz::Europe::Paris),
+            ..Default::default()
+        };
+
+        assert!(WorldClock::default().save(&conn).is_err());
+        assert!(WorldClock {
+            label: "a".repeat(65),
+            ..home.clone()
+        }
+        .save(&conn)
+        .is_err());
+        home.save(&conn).unwrap();
+
+        let mut saved = WorldClock::all(&conn).unwrap().remove(0);
+        assert_eq!(
+            saved,
+            WorldClock {
+                id: saved.id,
+                ..home
+            }
+        );
+
+        saved.zone = Zone::Local;
+        saved.save(&conn).unwrap();
+        assert_eq!(WorldClock::all(&conn).unwrap(), vec![saved.clone()]);
+
+        saved.remove(&conn).unwrap();
+        assert!(WorldClock::all(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_binary_conversion() {
+        let message = WorldClock {
+            id: Some(3),
+            label: "Tōkyō".to_string(),
+            zone: Zone::Named(chrono_tz::Asia::Tokyo),
+        }
+        .message(&ThemeHours::default());
+        let mut buffer = [0u8; WorldClockMessage::MAX_ENCODED_LEN + 1];
+
+        let len = message.encode_into(&mut buffer).unwrap();
+
+        assert_eq!(
+            WorldClockMessage::try_from(buffer.to_vec()).unwrap(),
+            message
+        );
+        assert_eq!(
+            WorldClockMessage::try_from_strict(&buffer[..len]).unwrap(),
+            message
+        );
+        assert!(WorldClockMessage::try_from(buffer[..len - 1].to_vec()).is_err());
+        assert_eq!(
+            WorldClockMessage::try_from_strict(&buffer[..len - 1])
+                .unwrap_err()
+                .reason,
+            "Missing field"
+        );
+        assert_eq!(
+            WorldClockMessage::try_from_strict(&buffer[..len + 1])
+                .unwrap_err()
+                .reason,
+            "Trailing bytes"
+        );
+
+        // Unknown zone, right after the id and the zone name length
+        assert_eq!(&buffer[2..6], b"Asia");
+        buffer[2..6].copy_from_slice(b"Mars");
+        assert!(WorldClockMessage::try_from_strict(&buffer[..len]).is_err());
+
+        assert!(WorldClockMessage {
+            label: "a".repeat(WorldClockMessage::MAX_LABEL_LEN + 1),
+            ..message
+        }
+        .encode_into(&mut buffer)
+        .is_err());
+    }
+}